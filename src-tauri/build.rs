@@ -1,3 +1,17 @@
 fn main() {
-  tauri_build::build()
+  #[cfg(feature = "app")]
+  tauri_build::build();
+
+  // tauri-build sets these for the app; the command-line tool alone sets
+  // them the same way
+  #[cfg(not(feature = "app"))]
+  {
+    println!("cargo:rustc-check-cfg=cfg(desktop, mobile)");
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    if target_os == "android" || target_os == "ios" {
+      println!("cargo:rustc-cfg=mobile");
+    } else {
+      println!("cargo:rustc-cfg=desktop");
+    }
+  }
 }
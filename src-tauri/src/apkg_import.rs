@@ -0,0 +1,303 @@
+//! Anki .apkg decks as custom packs.
+//!
+//! An .apkg is a ZIP holding the deck's collection as SQLite: `collection.anki21b`
+//! (zstd-compressed, Anki ≥ 2.1.50), `collection.anki21` or the legacy
+//! `collection.anki2`. Notes keep their fields in `flds`, separated by 0x1f.
+//! The user picks which field is the Tibetan term and which the definition,
+//! then the notes are turned into a .tibdict and installed like any other.
+//! Same cleanup as build/lib/apkg-reader.js + normalize-entries.js.
+
+use crate::custom_packs::{copy_within_limits, InstallError, InstallLimits};
+use crate::markup::html_to_text;
+use crate::tibdict_builder::{build_tibdict_bytes, slug_id, TibdictEntry, TibdictMetadata};
+use crate::tibetan::tibetan_runs;
+use rusqlite::{params, Connection, OpenFlags};
+use ruzstd::StreamingDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Seek};
+#[cfg(feature = "app")]
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::ZipArchive;
+
+/// Collection files by preference: older ones are placeholders in recent exports
+const COLLECTION_NAMES: [&str; 3] = ["collection.anki21b", "collection.anki21", "collection.anki2"];
+
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+const SAMPLE_NOTES: usize = 5;
+
+/// Fields chosen by the user, plus optional pack metadata
+/// (defaults come from the file name).
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApkgImportOptions {
+    /// Name of the field holding the Tibetan term
+    pub term_field: String,
+    /// Name of the field holding the definition
+    pub definition_field: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApkgNoteType {
+    pub name: String,
+    pub fields: Vec<String>,
+    pub notes_count: usize,
+    /// A few notes' fields, cleaned up, to help choosing
+    pub samples: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApkgInfo {
+    pub note_types: Vec<ApkgNoteType>,
+}
+
+struct NoteType {
+    id: i64,
+    name: String,
+    fields: Vec<String>,
+}
+
+/// Whether a ZIP is an Anki package rather than a .tibdict
+pub(crate) fn is_apkg_archive<R: Read + Seek>(archive: &ZipArchive<R>) -> bool {
+    archive.file_names().any(|name| COLLECTION_NAMES.contains(&name))
+}
+
+/// Write the collection to `dest`, held to `limits` like a pack's data.sqlite
+fn extract_collection<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    dest: &Path,
+    limits: &InstallLimits,
+) -> Result<(), InstallError> {
+    for name in COLLECTION_NAMES {
+        let Ok(mut entry) = archive.by_name(name) else {
+            continue;
+        };
+        let compressed = entry.compressed_size();
+        let mut file = File::create(dest)
+            .map_err(|e| InstallError::new("path", &format!("write collection: {e}")))?;
+        if name.ends_with("anki21b") {
+            let decoder = StreamingDecoder::new(&mut entry)
+                .map_err(|e| InstallError::new("corrupt", &format!("zstd: {e}")))?;
+            copy_within_limits(decoder, compressed, limits, &mut file)?;
+        } else {
+            copy_within_limits(entry, compressed, limits, &mut file)?;
+        }
+        return Ok(());
+    }
+    Err(InstallError::new("format", "not an Anki package"))
+}
+
+/// rusqlite opens files only: park the collection in the temp dir while reading it
+fn temp_collection_path() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    std::env::temp_dir().join(format!("tibdict-apkg-{}-{}.anki2", std::process::id(), nanos))
+}
+
+fn read_note_types(conn: &Connection) -> rusqlite::Result<Vec<NoteType>> {
+    let has_notetypes_table = conn
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'notetypes'")?
+        .exists([])?;
+
+    if has_notetypes_table {
+        // Schema 18: one row per note type and per field
+        let mut note_types: Vec<NoteType> = conn
+            .prepare("SELECT id, name FROM notetypes")?
+            .query_map([], |row| {
+                Ok(NoteType { id: row.get(0)?, name: row.get(1)?, fields: Vec::new() })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+        let mut stmt = conn.prepare("SELECT name FROM fields WHERE ntid = ? ORDER BY ord")?;
+        for note_type in &mut note_types {
+            note_type.fields = stmt
+                .query_map(params![note_type.id], |row| row.get(0))?
+                .filter_map(|r| r.ok())
+                .collect();
+        }
+        return Ok(note_types);
+    }
+
+    // Schema 11: note types are a JSON object in col.models
+    let models: String = conn.query_row("SELECT models FROM col", [], |row| row.get(0))?;
+    let models: HashMap<String, serde_json::Value> = serde_json::from_str(&models).unwrap_or_default();
+    Ok(models
+        .into_iter()
+        .filter_map(|(id, model)| {
+            let mut fields: Vec<(i64, String)> = model["flds"]
+                .as_array()?
+                .iter()
+                .filter_map(|f| Some((f["ord"].as_i64()?, f["name"].as_str()?.to_string())))
+                .collect();
+            fields.sort();
+            Some(NoteType {
+                id: id.parse().ok()?,
+                name: model["name"].as_str().unwrap_or_default().to_string(),
+                fields: fields.into_iter().map(|(_, name)| name).collect(),
+            })
+        })
+        .collect())
+}
+
+fn read_notes(conn: &Connection) -> rusqlite::Result<Vec<(i64, Vec<String>)>> {
+    let notes = conn
+        .prepare("SELECT mid, flds FROM notes WHERE flds IS NOT NULL")?
+        .query_map([], |row| {
+            let flds: String = row.get(1)?;
+            Ok((row.get(0)?, flds.split(FIELD_SEPARATOR).map(String::from).collect()))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(notes)
+}
+
+fn with_collection<R: Read + Seek, T>(
+    archive: &mut ZipArchive<R>,
+    limits: &InstallLimits,
+    read: impl FnOnce(&Connection) -> rusqlite::Result<T>,
+) -> Result<T, InstallError> {
+    let path = temp_collection_path();
+    let result = extract_collection(archive, &path, limits).and_then(|()| {
+        Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .and_then(|conn| read(&conn))
+            .map_err(|e| InstallError::new("corrupt", &format!("bad collection: {e}")))
+    });
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// List an .apkg's note types and their fields, so the user can pick which
+/// field holds the term and which the definition before installing.
+pub(crate) fn inspect_apkg_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    limits: &InstallLimits,
+) -> Result<ApkgInfo, InstallError> {
+    let (note_types, notes) =
+        with_collection(archive, limits, |conn| Ok((read_note_types(conn)?, read_notes(conn)?)))?;
+
+    let mut note_types: Vec<ApkgNoteType> = note_types
+        .into_iter()
+        .map(|note_type| {
+            let notes: Vec<&Vec<String>> = notes.iter().filter(|(mid, _)| *mid == note_type.id).map(|(_, f)| f).collect();
+            ApkgNoteType {
+                name: note_type.name,
+                fields: note_type.fields,
+                notes_count: notes.len(),
+                samples: notes
+                    .iter()
+                    .take(SAMPLE_NOTES)
+                    .map(|fields| fields.iter().map(|f| html_to_text(f)).collect())
+                    .collect(),
+            }
+        })
+        .filter(|note_type| note_type.notes_count > 0)
+        .collect();
+    note_types.sort_by_key(|note_type| std::cmp::Reverse(note_type.notes_count));
+
+    Ok(ApkgInfo { note_types })
+}
+
+/// Convert an .apkg to .tibdict bytes, ready for the regular installer.
+/// `file_stem` provides the default id and name.
+pub(crate) fn convert_apkg_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    file_stem: Option<&str>,
+    options: ApkgImportOptions,
+    limits: &InstallLimits,
+) -> Result<Vec<u8>, InstallError> {
+    let (note_types, notes) =
+        with_collection(archive, limits, |conn| Ok((read_note_types(conn)?, read_notes(conn)?)))?;
+
+    // Where the chosen fields sit in each note type that has both
+    let columns: HashMap<i64, (usize, usize)> = note_types
+        .iter()
+        .filter_map(|note_type| {
+            let term = note_type.fields.iter().position(|f| *f == options.term_field)?;
+            let definition = note_type.fields.iter().position(|f| *f == options.definition_field)?;
+            Some((note_type.id, (term, definition)))
+        })
+        .collect();
+    if columns.is_empty() {
+        return Err(InstallError::new("fields", "no note type has the chosen fields"));
+    }
+
+    let mut seen = HashSet::new();
+    let entries: Vec<TibdictEntry> = notes
+        .iter()
+        .filter_map(|(mid, fields)| {
+            let (term_index, definition_index) = columns.get(mid)?;
+            let term = html_to_text(fields.get(*term_index)?);
+            let definition = html_to_text(fields.get(*definition_index)?);
+            if definition.is_empty() || tibetan_runs(&term).is_empty() {
+                return None;
+            }
+            seen.insert((term.clone(), definition.clone())).then_some(TibdictEntry {
+                term,
+                definition,
+                dictionary: None,
+                term_phonetics_strict: None,
+                term_phonetics_loose: None,
+                definition_phonetics_words_strict: None,
+                definition_phonetics_words_loose: None,
+            })
+        })
+        .collect();
+
+    let file_stem = file_stem.unwrap_or("Anki deck");
+    let name = options.name.unwrap_or_else(|| file_stem.to_string());
+    let metadata = TibdictMetadata {
+        id: options.id.unwrap_or_else(|| slug_id(&[&name, file_stem])),
+        name,
+        description: options.description.unwrap_or_else(|| "Imported from Anki".to_string()),
+        author: None,
+        version: None,
+        icon: None,
+        update_url: None,
+    };
+
+    build_tibdict_bytes(metadata, &entries)
+}
+
+#[cfg(feature = "app")]
+async fn inspect<R: Read + Seek + Send + 'static>(
+    mut archive: ZipArchive<R>,
+    limits: Option<InstallLimits>,
+) -> Result<ApkgInfo, InstallError> {
+    tauri::async_runtime::spawn_blocking(move || inspect_apkg_archive(&mut archive, &limits.unwrap_or_default()))
+        .await
+        .map_err(|e| InstallError::new("path", &format!("inspect task: {e}")))?
+}
+
+/// Inspect an .apkg file before installing it with install_custom_pack.
+/// The collection is read straight from the file, within `limits`.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn inspect_apkg(file_path: String, limits: Option<InstallLimits>) -> Result<ApkgInfo, InstallError> {
+    let file = File::open(Path::new(&file_path))
+        .map_err(|e| InstallError::new("path", &format!("read file: {e}")))?;
+    let archive = ZipArchive::new(BufReader::new(file))
+        .map_err(|e| InstallError::new("corrupt", &format!("read zip: {e}")))?;
+    inspect(archive, limits).await
+}
+
+/// Same as inspect_apkg but takes raw bytes, for the drag-drop path
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn inspect_apkg_from_bytes(data: Vec<u8>, limits: Option<InstallLimits>) -> Result<ApkgInfo, InstallError> {
+    let archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| InstallError::new("corrupt", &format!("read zip: {e}")))?;
+    inspect(archive, limits).await
+}
@@ -0,0 +1,249 @@
+//! The Tauri app: the one builder with every plugin, state and command.
+//! Mobile builds start from `run`, desktop builds from main.rs, which adds
+//! nothing but the run event handling.
+
+use crate::apkg_import::{inspect_apkg, inspect_apkg_from_bytes};
+use crate::browse::browse_terms;
+use crate::custom_packs::{install_custom_pack, install_custom_pack_from_bytes, list_custom_packs, remove_custom_pack};
+use crate::database::{init_database, reload_database, DatabasePool};
+#[cfg(desktop)]
+use crate::deep_links::focus_running_instance;
+use crate::deep_links::{listen_for_deep_links, take_deep_link, DeepLinkState};
+use crate::dictionary_export::export_dictionary;
+use crate::dictionary_settings::{
+    get_dictionary_settings, reset_dictionary_order, save_dictionary_settings, set_dictionary_enabled,
+    set_dictionary_order, set_pack_enabled,
+};
+use crate::global_lookup::{
+    get_lookup_popup_pinned, hide_lookup_panel, set_global_lookup_hotkey, set_lookup_popup_pinned, GlobalLookupState,
+};
+#[cfg(desktop)]
+use crate::app_menu::{handle_menu_event, setup_menu};
+use crate::importers::{import_dictionary, import_dictionary_from_bytes};
+use crate::lookup_panel::configure_window_for_fullscreen;
+use crate::lookup_server::{
+    get_lookup_server, regenerate_lookup_server_token, set_lookup_server,
+    start_lookup_server_in_background, LookupServerState,
+};
+use crate::pack_migrations::{migrate_packs, migrate_packs_in_background};
+use crate::pack_phonetics::{list_entries_missing_phonetics, save_entry_phonetics};
+use crate::pack_signing::{
+    add_trusted_key, generate_signing_key, get_trust_settings, remove_trusted_key,
+    set_strict_signatures, sign_custom_pack,
+};
+use crate::pack_updates::{check_custom_pack_updates, restore_custom_pack_backup, set_custom_pack_feed, update_custom_pack};
+use crate::packs::{
+    download_pack, ensure_pack_available, fetch_pack_manifest, get_installed_packs,
+    get_pack_database_size, get_pack_path, read_pack_database, read_pack_database_chunk,
+    remove_pack, supports_modular_packs, update_pack,
+};
+use crate::repository::{
+    execute_query, get_all_terms, get_dictionaries, get_entries_for_term, pack_execute_query,
+    pack_get_all_terms, pack_get_dictionaries, pack_get_entries_for_term, pack_search_entries,
+    search_entries,
+};
+use crate::reverse_index::reverse_lookup;
+use crate::sanskrit_index::search_by_sanskrit;
+use crate::scans::{check_scan_downloaded, delete_scan, download_scan_images, get_scan_image_data};
+use crate::term_links::get_related_terms;
+use crate::tibdict_builder::{create_custom_pack, parse_glossary};
+use crate::user_pack::{
+    add_user_entry, delete_user_entry, get_entry_note, list_entry_notes, set_entry_note,
+    update_user_entry,
+};
+use crate::verb_index::lookup_verb;
+
+#[cfg(desktop)]
+use crate::tray;
+#[cfg(desktop)]
+use tauri::Manager;
+use tauri::{AppHandle, RunEvent, Wry};
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    builder()
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+pub fn builder() -> tauri::Builder<Wry> {
+    let mut builder = tauri::Builder::default();
+
+    // First plugin, so that a second launch hands its arguments (and deep
+    // links) to the running app and exits before anything else starts
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(focus_running_instance));
+    }
+
+    builder = builder
+        .manage(DatabasePool::default())
+        .manage(LookupServerState::default())
+        .manage(DeepLinkState::default())
+        .manage(GlobalLookupState::default())
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init());
+
+    // Add desktop-only plugins
+    #[cfg(desktop)]
+    {
+        builder = builder
+            .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+            .plugin(tauri_plugin_updater::Builder::new().build())
+            .plugin(tauri_plugin_process::init());
+    }
+
+    // Add NSPanel plugin for macOS fullscreen overlay support, and the
+    // macOS permissions plugin
+    #[cfg(target_os = "macos")]
+    {
+        builder = builder
+            .plugin(tauri_nspanel::init())
+            .plugin(tauri_plugin_macos_permissions::init());
+    }
+
+    #[cfg(desktop)]
+    {
+        builder = builder.on_menu_event(handle_menu_event);
+    }
+
+    builder
+        .setup(|app| {
+            #[cfg(desktop)]
+            {
+                setup_menu(app)?;
+                if let Err(e) = tray::setup_tray(app) {
+                    eprintln!("[tray] Failed to set up the tray icon: {}", e);
+                }
+            }
+            migrate_packs_in_background(app.handle());
+            start_lookup_server_in_background(app.handle());
+            listen_for_deep_links(app.handle());
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            // Database commands
+            init_database,
+            reload_database,
+            get_all_terms,
+            get_dictionaries,
+            get_entries_for_term,
+            search_entries,
+            execute_query,
+            // Scan commands
+            check_scan_downloaded,
+            get_scan_image_data,
+            download_scan_images,
+            delete_scan,
+            // Pack commands
+            fetch_pack_manifest,
+            get_installed_packs,
+            download_pack,
+            update_pack,
+            remove_pack,
+            get_pack_path,
+            read_pack_database,
+            read_pack_database_chunk,
+            get_pack_database_size,
+            ensure_pack_available,
+            supports_modular_packs,
+            // Native SQLite pack queries (for popup and mobile)
+            pack_get_all_terms,
+            pack_get_entries_for_term,
+            pack_search_entries,
+            pack_get_dictionaries,
+            pack_execute_query,
+            browse_terms,
+            reverse_lookup,
+            get_related_terms,
+            search_by_sanskrit,
+            lookup_verb,
+            create_custom_pack,
+            parse_glossary,
+            list_entries_missing_phonetics,
+            save_entry_phonetics,
+            export_dictionary,
+            // Dictionary settings commands
+            get_dictionary_settings,
+            set_dictionary_enabled,
+            set_dictionary_order,
+            reset_dictionary_order,
+            set_pack_enabled,
+            save_dictionary_settings,
+            // User pack commands
+            add_user_entry,
+            update_user_entry,
+            delete_user_entry,
+            set_entry_note,
+            get_entry_note,
+            list_entry_notes,
+            // Custom pack commands
+            install_custom_pack,
+            install_custom_pack_from_bytes,
+            inspect_apkg,
+            inspect_apkg_from_bytes,
+            import_dictionary,
+            import_dictionary_from_bytes,
+            list_custom_packs,
+            remove_custom_pack,
+            migrate_packs,
+            check_custom_pack_updates,
+            update_custom_pack,
+            restore_custom_pack_backup,
+            set_custom_pack_feed,
+            // Pack signing commands
+            generate_signing_key,
+            sign_custom_pack,
+            get_trust_settings,
+            add_trusted_key,
+            remove_trusted_key,
+            set_strict_signatures,
+            // Local lookup server commands
+            get_lookup_server,
+            set_lookup_server,
+            regenerate_lookup_server_token,
+            // Deep link commands
+            take_deep_link,
+            // Global lookup commands
+            set_global_lookup_hotkey,
+            hide_lookup_panel,
+            get_lookup_popup_pinned,
+            set_lookup_popup_pinned,
+            // macOS fullscreen support
+            configure_window_for_fullscreen,
+        ])
+}
+
+pub fn handle_run_event(app_handle: &AppHandle, event: RunEvent) {
+    match event {
+        #[cfg(target_os = "macos")]
+        RunEvent::Reopen { has_visible_windows, .. } => {
+            // When clicking the dock icon, show the main window
+            if !has_visible_windows {
+                if let Some(window) = app_handle.get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+        }
+        #[cfg(desktop)]
+        RunEvent::WindowEvent {
+            label,
+            event: tauri::WindowEvent::CloseRequested { api, .. },
+            ..
+        } => {
+            // Hide the main window instead of closing it, so the app
+            // stays running in the tray (and the Dock on macOS).
+            // Without a tray it closes, or the app couldn't be reached
+            if label == "main" && tray::has_tray(app_handle) {
+                api.prevent_close();
+                if let Some(window) = app_handle.get_webview_window("main") {
+                    let _ = window.hide();
+                }
+            }
+        }
+        _ => {}
+    }
+}
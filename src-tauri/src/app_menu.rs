@@ -0,0 +1,116 @@
+//! The desktop app menu: the usual App and Edit menus, plus links to the
+//! project website, the issue tracker and the author.
+
+use tauri::menu::{AboutMetadata, Menu, MenuItem, MenuItemBuilder, Submenu, SubmenuBuilder};
+
+pub(crate) fn setup_menu(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
+    let app_handle = app.handle();
+
+    let app_menu = Menu::new(app_handle)?;
+
+    let feature_menu = MenuItem::with_id(
+        app_handle,
+        "feature",
+        "Request a feature",
+        true,
+        None::<&str>
+    )?;
+
+    let bug_menu = MenuItem::with_id(
+        app_handle,
+        "bug",
+        "Report a bug",
+        true,
+        None::<&str>
+    )?;
+
+    let contact_menu = MenuItem::with_id(
+        app_handle,
+        "contact",
+        "Contact the author",
+        true,
+        None::<&str>
+    )?;
+
+    let website = MenuItemBuilder::new("Project website")
+        .id("website")
+        .build(app)?;
+
+    let github = MenuItemBuilder::new("Source code on Github")
+        .id("github")
+        .build(app)?;
+
+    let support = MenuItemBuilder::new("Support the author")
+        .id("support")
+        .build(app)?;
+
+    let app_submenu = SubmenuBuilder::new(app, "App")
+        .about(Some(AboutMetadata {
+            ..Default::default()
+        }))
+        .separator()
+        .item(&website)
+        .item(&github)
+        .item(&support)
+        .separator()
+        .services()
+        .separator()
+        .hide()
+        .hide_others()
+        .quit()
+        .build()?;
+
+    let edit_submenu = SubmenuBuilder::new(app, "Edit")
+        .undo()
+        .redo()
+        .separator()
+        .cut()
+        .copy()
+        .paste()
+        .select_all()
+        .build()?;
+
+    let help_submenu = Submenu::new(
+        app_handle,
+        "Help",
+        true
+    )?;
+
+    help_submenu.append(&feature_menu)?;
+    help_submenu.append(&bug_menu)?;
+    help_submenu.append(&contact_menu)?;
+
+    let menu = app_menu;
+    menu.append(&app_submenu)?;
+    menu.append(&edit_submenu)?;
+    menu.append(&help_submenu)?;
+
+    app.set_menu(menu)?;
+
+    Ok(())
+}
+
+#[cfg(desktop)]
+pub(crate) fn handle_menu_event(_app_handle: &tauri::AppHandle, event: tauri::menu::MenuEvent) {
+    match event.id().0.as_str() {
+        "website" => {
+            let _ = open::that("https://jerefrer.github.io/tibetan-translator/");
+        },
+        "github" => {
+            let _ = open::that("https://github.com/jerefrer/tibetan-translator/");
+        },
+        "bug" => {
+            let _ = open::that("https://github.com/jerefrer/tibetan-translator/issues/new");
+        },
+        "feature" => {
+            let _ = open::that("https://github.com/jerefrer/tibetan-translator/issues/new");
+        },
+        "contact" => {
+            let _ = open::that("https://frerejeremy.me");
+        },
+        "support" => {
+            let _ = open::that("https://frerejeremy.me");
+        },
+        _ => {}
+    }
+}
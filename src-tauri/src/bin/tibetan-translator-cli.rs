@@ -0,0 +1,5 @@
+//! Command-line entry point, see src/cli.rs
+
+fn main() {
+    std::process::exit(app_lib::cli::run(std::env::args().skip(1).collect()));
+}
@@ -0,0 +1,199 @@
+use crate::collation::{tibetan_sort_key, SyllableKey};
+use crate::pack_indexes::pack_signature;
+use crate::packs::{ensure_pack_available, get_all_pack_db_paths};
+use once_cell::sync::Lazy;
+use rusqlite::{params_from_iter, Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::AppHandle;
+
+/// Sorted headword lists, keyed by installed-packs signature + dictionary filter.
+/// Building one means reading and collating every term of every pack, so we
+/// keep them around until a pack is added, removed or replaced.
+static BROWSE_INDEXES: Lazy<Mutex<HashMap<String, Arc<Vec<SortedTerm>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct SortedTerm {
+    key: Vec<SyllableKey>,
+    term: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowseResult {
+    pub anchor: String,
+    /// Whether the anchor itself is a headword in the browsed dictionaries
+    pub anchor_found: bool,
+    /// Headwords preceding the anchor, in alphabetical order
+    pub before: Vec<String>,
+    /// Headwords following the anchor, in alphabetical order
+    pub after: Vec<String>,
+    pub has_more_before: bool,
+    pub has_more_after: bool,
+}
+
+/// Identifies the current set of installed packs, so cached indexes are
+/// dropped whenever a pack file changes.
+fn packs_signature(pack_paths: &[(String, PathBuf)]) -> String {
+    pack_paths
+        .iter()
+        .map(|(pack_id, path)| format!("{}@{}", pack_id, pack_signature(path)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Group compound dictionary IDs ("pack_id:dictionary_id") by pack
+fn dictionary_ids_by_pack(dictionaries: &[String]) -> HashMap<String, Vec<i64>> {
+    let mut by_pack: HashMap<String, Vec<i64>> = HashMap::new();
+    for compound_id in dictionaries {
+        if let Some((pack_id, raw_id)) = compound_id.rsplit_once(':') {
+            if let Ok(raw_id) = raw_id.parse::<i64>() {
+                by_pack.entry(pack_id.to_string()).or_default().push(raw_id);
+            }
+        }
+    }
+    by_pack
+}
+
+fn build_index(
+    pack_paths: &[(String, PathBuf)],
+    dictionaries: Option<&HashMap<String, Vec<i64>>>,
+) -> Vec<SortedTerm> {
+    let mut terms = BTreeSet::new();
+
+    for (pack_id, db_path) in pack_paths {
+        let dictionary_ids = match dictionaries {
+            Some(filter) => match filter.get(pack_id) {
+                Some(ids) => Some(ids),
+                None => continue,
+            },
+            None => None,
+        };
+
+        let conn = match Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("Warning: Failed to open pack {} at {:?}: {}", pack_id, db_path, e);
+                continue;
+            }
+        };
+
+        let sql = match dictionary_ids {
+            Some(ids) => format!(
+                "SELECT DISTINCT term FROM entries WHERE dictionaryId IN ({})",
+                vec!["?"; ids.len()].join(",")
+            ),
+            None => "SELECT DISTINCT term FROM entries".to_string(),
+        };
+
+        let mut stmt = match conn.prepare(&sql) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Warning: Failed to prepare browse query for pack {}: {}", pack_id, e);
+                continue;
+            }
+        };
+
+        let params: Vec<i64> = dictionary_ids.cloned().unwrap_or_default();
+        match stmt.query_map(params_from_iter(params.iter()), |row| row.get::<_, String>(0)) {
+            Ok(rows) => terms.extend(rows.filter_map(|r| r.ok())),
+            Err(e) => eprintln!("Warning: Browse query failed for pack {}: {}", pack_id, e),
+        }
+    }
+
+    let mut sorted: Vec<SortedTerm> = terms
+        .into_iter()
+        .map(|term| SortedTerm { key: tibetan_sort_key(&term), term })
+        .collect();
+    sorted.sort_by(|a, b| a.key.cmp(&b.key).then_with(|| a.term.cmp(&b.term)));
+    sorted
+}
+
+/// Cache key of the index for `pack_paths` filtered by `dictionaries`, and
+/// the prefix shared by every key of the same set of packs
+fn cache_key(pack_paths: &[(String, PathBuf)], dictionaries: Option<&[String]>) -> (String, String) {
+    let prefix = format!("{}|", packs_signature(pack_paths));
+    let filter_key = match dictionaries {
+        Some(ids) => {
+            let mut ids = ids.to_vec();
+            ids.sort();
+            ids.join(",")
+        }
+        None => "*".to_string(),
+    };
+    (format!("{}{}", prefix, filter_key), prefix)
+}
+
+fn cached_index(
+    pack_paths: &[(String, PathBuf)],
+    dictionaries: Option<&[String]>,
+) -> Result<Option<Arc<Vec<SortedTerm>>>, String> {
+    let (key, _) = cache_key(pack_paths, dictionaries);
+    let cache = BROWSE_INDEXES
+        .lock()
+        .map_err(|e| format!("Failed to lock browse cache: {}", e))?;
+    Ok(cache.get(&key).cloned())
+}
+
+fn build_and_cache_index(
+    pack_paths: &[(String, PathBuf)],
+    dictionaries: Option<&[String]>,
+) -> Result<Arc<Vec<SortedTerm>>, String> {
+    let by_pack = dictionaries.map(dictionary_ids_by_pack);
+    let index = Arc::new(build_index(pack_paths, by_pack.as_ref()));
+
+    let (key, prefix) = cache_key(pack_paths, dictionaries);
+    let mut cache = BROWSE_INDEXES
+        .lock()
+        .map_err(|e| format!("Failed to lock browse cache: {}", e))?;
+    // Indexes built for a previous set of packs can never be hit again
+    cache.retain(|cached, _| cached.starts_with(&prefix));
+    cache.insert(key, index.clone());
+
+    Ok(index)
+}
+
+/// Get the headwords around `anchor` in Tibetan alphabetical order, like a
+/// column of a paper dictionary. `dictionaries` restricts browsing to the
+/// given compound IDs (e.g. ["core:3"] to page through a single dictionary).
+/// The anchor doesn't need to exist: neighbours are found by its sort position.
+#[tauri::command]
+pub async fn browse_terms(
+    app: AppHandle,
+    anchor: String,
+    before: usize,
+    after: usize,
+    dictionaries: Option<Vec<String>>,
+) -> Result<BrowseResult, String> {
+    let pack_paths = get_all_pack_db_paths(&app)?;
+    let index = match cached_index(&pack_paths, dictionaries.as_deref())? {
+        Some(index) => index,
+        None => {
+            // Only checked when building: the core pack may be missing or stale
+            ensure_pack_available(app.clone(), "core".to_string()).await?;
+            let pack_paths = get_all_pack_db_paths(&app)?;
+            build_and_cache_index(&pack_paths, dictionaries.as_deref())?
+        }
+    };
+
+    let anchor_key = tibetan_sort_key(&anchor);
+    let start = index.partition_point(|t| {
+        (&t.key, t.term.as_str()) < (&anchor_key, anchor.as_str())
+    });
+    let anchor_found = index.get(start).map_or(false, |t| t.term == anchor);
+    let after_start = if anchor_found { start + 1 } else { start };
+
+    let before_start = start.saturating_sub(before);
+    let after_end = (after_start + after).min(index.len());
+
+    Ok(BrowseResult {
+        anchor,
+        anchor_found,
+        before: index[before_start..start].iter().map(|t| t.term.clone()).collect(),
+        after: index[after_start..after_end].iter().map(|t| t.term.clone()).collect(),
+        has_more_before: before_start > 0,
+        has_more_after: after_end < index.len(),
+    })
+}
@@ -0,0 +1,333 @@
+//! `tibetan-translator-cli`: dictionary lookups and pack management without
+//! the app, for scripts, translation pipelines and servers.
+//!
+//! It reads the same data directory as the desktop app (or `--data-dir`),
+//! so packs downloaded or installed in the app are available here and the
+//! other way round. The bundled core pack is found once the app has copied
+//! it to `<data-dir>/packs/core.sqlite` on first launch.
+//!
+//! Every command prints JSON on stdout. Errors go to stderr, with exit
+//! code 1 (2 for usage errors).
+
+use crate::custom_packs::{
+    install_from_archive, installed_custom_packs, uninstall_custom_pack, InstallError, InstallLimits,
+};
+use crate::data_host::DataHost;
+use crate::dictionary_export::{export_from_pack, parse_export_request};
+use crate::lookup_server::{bind, load_settings, serve};
+use crate::repository::{PackSet, Repository};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Product identifier from tauri.conf.json; names the app data directory
+const APP_IDENTIFIER: &str = "com.jerefrer.TibetanTranslator";
+
+const USAGE: &str = "\
+Usage: tibetan-translator-cli [--data-dir <dir>] [--pretty] <command>
+
+Commands:
+  define <term>                         Entries for an exact term
+  search <query> [--type <type>]        Full-text search; type is regular
+                                        (default), phonetics_strict or
+                                        phonetics_loose
+  segment <text>                        Split Tibetan text into dictionary words
+  dictionaries                          Installed dictionaries and their ids
+  packs list                            Installed packs
+  packs install <file.tibdict> [--force]
+  packs remove <custom-pack-id>
+  export <pack:dictionary> --format <csv|jsonl|tei|stardict|tibdict>
+         --output <path>
+  serve [--port <port>] [--origin <origin>]...
+                                        Run the local lookup server (see the
+                                        app settings); prints its URL and
+                                        token, then serves until stopped
+
+Options:
+  --data-dir <dir>   Data directory (default: the desktop app's)
+  --pretty           Indent the JSON output
+";
+
+/// Options followed by a value
+const VALUE_OPTIONS: &[&str] = &["data-dir", "type", "format", "output", "port", "origin"];
+
+/// Options on their own
+const FLAGS: &[&str] = &["force", "pretty", "help"];
+
+enum CliError {
+    Usage(String),
+    Failed(String),
+}
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError::Failed(message)
+    }
+}
+
+impl From<InstallError> for CliError {
+    fn from(error: InstallError) -> Self {
+        CliError::Failed(format!("{} ({})", error.message, error.code))
+    }
+}
+
+/// A bare data directory. Nothing is bundled, progress isn't reported, and
+/// derived indexes catch up the next time the app uses them.
+struct DataDir {
+    root: PathBuf,
+}
+
+impl DataHost for DataDir {
+    fn app_data_dir(&self) -> Result<PathBuf, String> {
+        Ok(self.root.clone())
+    }
+
+    fn resource(&self, _name: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn emit(&self, _event: &str, _payload: serde_json::Value) {}
+
+    fn packs_changed(&self) {}
+}
+
+/// The directory the desktop app uses on this platform
+fn default_data_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(target_os = "macos") {
+        home.map(|home| home.join("Library").join("Application Support"))
+    } else if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.map(|home| home.join(".local").join("share")))
+    };
+    base.map(|base| base.join(APP_IDENTIFIER))
+}
+
+#[derive(Default)]
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+    flags: HashSet<String>,
+}
+
+impl Args {
+    fn parse(raw: Vec<String>) -> Result<Self, CliError> {
+        let mut args = Args::default();
+        let mut raw = raw.into_iter();
+        while let Some(arg) = raw.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                args.positional.push(arg);
+                continue;
+            };
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (name.to_string(), None),
+            };
+            if VALUE_OPTIONS.contains(&name.as_str()) {
+                let value = inline_value
+                    .or_else(|| raw.next())
+                    .ok_or_else(|| CliError::Usage(format!("--{} needs a value", name)))?;
+                args.options.push((name, value));
+            } else if FLAGS.contains(&name.as_str()) && inline_value.is_none() {
+                args.flags.insert(name);
+            } else {
+                return Err(CliError::Usage(format!("Unknown option: --{}", name)));
+            }
+        }
+        Ok(args)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every value of a repeatable option, in order
+    fn option_values(&self, name: &str) -> Vec<String> {
+        self.options
+            .iter()
+            .filter(|(option, _)| option == name)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    /// Positional argument `index`, after the command
+    fn arg(&self, index: usize, what: &str) -> Result<&str, CliError> {
+        self.positional
+            .get(index)
+            .map(|s| s.as_str())
+            .ok_or_else(|| CliError::Usage(format!("Missing {}", what)))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InstalledPack {
+    id: String,
+    path: String,
+    /// False when the user turned the pack off in the app
+    enabled: bool,
+}
+
+fn install_pack(host: &dyn DataHost, path: &Path, force: bool) -> Result<serde_json::Value, CliError> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let archive = ZipArchive::new(file).map_err(|e| format!("Not a .tibdict archive: {}", e))?;
+    let installed = install_from_archive(host, archive, force, &InstallLimits::default())?;
+    to_json(installed)
+}
+
+fn print_json(output: &serde_json::Value, pretty: bool) -> Result<(), CliError> {
+    let json = if pretty {
+        serde_json::to_string_pretty(output)
+    } else {
+        serde_json::to_string(output)
+    };
+    let json = json.map_err(|e| CliError::Failed(format!("Failed to serialize output: {}", e)))?;
+    println!("{}", json);
+    // `serve` keeps running, so whoever reads the URL and token from a pipe
+    // needs them now
+    let _ = std::io::stdout().flush();
+    Ok(())
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<serde_json::Value, CliError> {
+    serde_json::to_value(value).map_err(|e| CliError::Failed(format!("Failed to serialize output: {}", e)))
+}
+
+fn execute(args: &Args, host: &dyn DataHost) -> Result<serde_json::Value, CliError> {
+    let command = args.arg(0, "command")?;
+    match command {
+        "define" => {
+            let term = args.arg(1, "term")?;
+            to_json(PackSet::load(host)?.entries_for_term(term)?)
+        }
+        "search" => {
+            let query = args.arg(1, "query")?;
+            let search_type = args.option("type").unwrap_or("regular");
+            if !["regular", "phonetics_strict", "phonetics_loose"].contains(&search_type) {
+                return Err(CliError::Usage(format!("Unknown search type: {}", search_type)));
+            }
+            to_json(PackSet::load(host)?.search_entries(query, search_type)?)
+        }
+        "segment" => {
+            let text = args.arg(1, "text")?;
+            to_json(PackSet::load(host)?.segment(text)?)
+        }
+        "dictionaries" => to_json(PackSet::load(host)?.dictionaries()?),
+        "packs" => match args.arg(1, "packs command (list, install or remove)")? {
+            "list" => {
+                let pack_set = PackSet::load(host)?;
+                let packs: Vec<InstalledPack> = pack_set
+                    .packs
+                    .iter()
+                    .map(|(id, path)| InstalledPack {
+                        id: id.clone(),
+                        path: path.display().to_string(),
+                        enabled: pack_set.settings.pack_enabled(id),
+                    })
+                    .collect();
+                Ok(serde_json::json!({
+                    "packs": to_json(packs)?,
+                    "custom": to_json(installed_custom_packs(host)?)?,
+                }))
+            }
+            "install" => install_pack(host, Path::new(args.arg(2, "pack file")?), args.flag("force")),
+            "remove" => {
+                let pack_id = args.arg(2, "pack id")?;
+                uninstall_custom_pack(host, pack_id)?;
+                Ok(serde_json::json!({ "removed": pack_id }))
+            }
+            other => Err(CliError::Usage(format!("Unknown packs command: {}", other))),
+        },
+        "export" => {
+            let compound_id = args.arg(1, "dictionary id (pack:dictionary)")?;
+            let format = args
+                .option("format")
+                .ok_or_else(|| CliError::Usage("Missing --format".to_string()))?;
+            let output = args
+                .option("output")
+                .ok_or_else(|| CliError::Usage("Missing --output".to_string()))?;
+            let (pack_id, dictionary_id, format) = parse_export_request(compound_id, format)?;
+            let pack_set = PackSet::load(host)?;
+            let (_, db_path) = pack_set
+                .packs
+                .iter()
+                .find(|(id, _)| *id == pack_id)
+                .ok_or_else(|| format!("Pack not installed: {}", pack_id))?;
+            to_json(export_from_pack(db_path, &pack_id, dictionary_id, &format, output, &|_| {})?)
+        }
+        "serve" => {
+            let mut settings = load_settings(host)?;
+            if let Some(port) = args.option("port") {
+                settings.port = port
+                    .parse()
+                    .map_err(|_| CliError::Usage(format!("Invalid port: {}", port)))?;
+            }
+            let origins = args.option_values("origin");
+            if !origins.is_empty() {
+                settings.allowed_origins = origins;
+            }
+            let server = bind(settings.port)?;
+            print_json(
+                &serde_json::json!({
+                    "url": format!("http://127.0.0.1:{}", settings.port),
+                    "token": settings.token,
+                }),
+                args.flag("pretty"),
+            )?;
+            serve(&server, host, &settings);
+            Err(CliError::Failed("Lookup server stopped".to_string()))
+        }
+        other => Err(CliError::Usage(format!("Unknown command: {}", other))),
+    }
+}
+
+/// Run the command line in `raw_args` (without the program name) and
+/// return the process exit code
+pub fn run(raw_args: Vec<String>) -> i32 {
+    let args = match Args::parse(raw_args) {
+        Ok(args) => args,
+        Err(CliError::Usage(message)) | Err(CliError::Failed(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return 2;
+        }
+    };
+    if args.flag("help") || args.positional.is_empty() {
+        print!("{}", USAGE);
+        return if args.flag("help") { 0 } else { 2 };
+    }
+
+    let root = match args.option("data-dir").map(PathBuf::from).or_else(default_data_dir) {
+        Some(root) => root,
+        None => {
+            eprintln!("Can't find the app data directory, use --data-dir");
+            return 2;
+        }
+    };
+    let host = DataDir { root };
+
+    match execute(&args, &host).and_then(|output| print_json(&output, args.flag("pretty"))) {
+        Ok(()) => 0,
+        Err(CliError::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            2
+        }
+        Err(CliError::Failed(message)) => {
+            eprintln!("{}", message);
+            1
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Tibetan alphabetical ordering.
+//!
+//! Plain codepoint order puts ཀྱ before ཀི and scatters prefixed syllables
+//! (དཀ, བཀ) far away from their root letter. Paper dictionaries sort each
+//! syllable by its root letter first, then superscript, prefix, subscript,
+//! vowel, suffix and post-suffix, which is what the keys below reproduce.
+
+use std::cmp::Ordering;
+
+/// Sort key for a single syllable:
+/// [root, superscript, prefix, subscript, vowel, suffix, post-suffix]
+pub type SyllableKey = [u8; 7];
+
+/// Syllables that are not Tibetan (Latin glosses, digits...) sort after all
+/// Tibetan syllables.
+const NON_TIBETAN_RANK: u8 = 250;
+
+const ROOT_LETTERS: [char; 30] = [
+    'ཀ', 'ཁ', 'ག', 'ང', 'ཅ', 'ཆ', 'ཇ', 'ཉ', 'ཏ', 'ཐ', 'ད', 'ན', 'པ', 'ཕ', 'བ', 'མ', 'ཙ', 'ཚ',
+    'ཛ', 'ཝ', 'ཞ', 'ཟ', 'འ', 'ཡ', 'ར', 'ལ', 'ཤ', 'ས', 'ཧ', 'ཨ',
+];
+
+const PREFIXES: [char; 5] = ['ག', 'ད', 'བ', 'མ', 'འ'];
+const SUFFIXES: [char; 10] = ['ག', 'ང', 'ད', 'ན', 'བ', 'མ', 'འ', 'ར', 'ལ', 'ས'];
+const SUPERSCRIPTS: [char; 3] = ['ར', 'ལ', 'ས'];
+
+#[derive(Debug, Default)]
+struct Stack {
+    base: char,
+    subjoined: Vec<char>,
+    vowels: Vec<char>,
+}
+
+fn is_base_letter(c: char) -> bool {
+    ('\u{0F40}'..='\u{0F6C}').contains(&c)
+}
+
+fn is_subjoined_letter(c: char) -> bool {
+    ('\u{0F8D}'..='\u{0FBC}').contains(&c)
+}
+
+fn is_vowel_sign(c: char) -> bool {
+    ('\u{0F71}'..='\u{0F84}').contains(&c)
+}
+
+fn is_syllable_char(c: char) -> bool {
+    is_base_letter(c) || is_subjoined_letter(c) || is_vowel_sign(c)
+}
+
+/// Map a subjoined letter (U+0F90..) back to its base form (U+0F40..)
+fn to_base(c: char) -> char {
+    match c {
+        '\u{0FBA}' => 'ཝ',
+        '\u{0FBB}' => 'ཡ',
+        '\u{0FBC}' => 'ར',
+        '\u{0F90}'..='\u{0FB9}' => char::from_u32(c as u32 - 0x50).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Rank of a letter in the traditional 30-letter order. Sanskrit-only letters
+/// are slotted right after the Tibetan letter they derive from.
+fn letter_rank(c: char) -> u8 {
+    let c = to_base(c);
+    if let Some(i) = ROOT_LETTERS.iter().position(|l| *l == c) {
+        return (i as u8 + 1) * 4;
+    }
+    let (parent, offset) = match c {
+        '\u{0F43}' => ('ག', 1), // gha
+        '\u{0F4A}' => ('ཏ', 1), // retroflex ta
+        '\u{0F4B}' => ('ཐ', 1), // retroflex tha
+        '\u{0F4C}' => ('ད', 1), // retroflex da
+        '\u{0F4D}' => ('ད', 2), // retroflex dha
+        '\u{0F52}' => ('ད', 3), // dha
+        '\u{0F4E}' => ('ན', 1), // retroflex na
+        '\u{0F57}' => ('བ', 1), // bha
+        '\u{0F5C}' => ('ཛ', 1), // dzha
+        '\u{0F69}' => ('ཀ', 1), // kssa
+        '\u{0F65}' => ('ཤ', 1), // retroflex sha
+        '\u{0F6A}' => ('ར', 1), // fixed-form ra
+        _ => ('ཨ', 3),
+    };
+    letter_rank(parent) + offset
+}
+
+fn vowel_rank(vowels: &[char]) -> u8 {
+    vowels
+        .iter()
+        .map(|v| match v {
+            '\u{0F71}' => 1,              // a-chung (long a)
+            '\u{0F72}' | '\u{0F80}' => 2, // i, reversed i
+            '\u{0F73}' | '\u{0F81}' => 3, // long i, long reversed i
+            '\u{0F74}' => 4,              // u
+            '\u{0F75}' => 5,              // long u
+            '\u{0F7A}' | '\u{0F7B}' => 6, // e, ai
+            '\u{0F7C}' | '\u{0F7D}' => 8, // o, au
+            _ => 10,
+        })
+        .fold(0, u8::saturating_add)
+}
+
+fn subscript_rank(c: char) -> u8 {
+    match to_base(c) {
+        'ཡ' => 1,
+        'ར' => 2,
+        'ལ' => 3,
+        'ཝ' => 4,
+        'ཧ' => 5,
+        other => 5 + letter_rank(other),
+    }
+}
+
+fn is_subscript(c: char) -> bool {
+    matches!(to_base(c), 'ཡ' | 'ར' | 'ལ' | 'ཝ')
+}
+
+fn split_stacks(syllable: &str) -> Vec<Stack> {
+    let mut stacks: Vec<Stack> = Vec::new();
+    for c in syllable.chars() {
+        if is_base_letter(c) {
+            stacks.push(Stack { base: c, ..Default::default() });
+        } else if is_subjoined_letter(c) {
+            match stacks.last_mut() {
+                Some(stack) => stack.subjoined.push(c),
+                None => stacks.push(Stack { base: to_base(c), ..Default::default() }),
+            }
+        } else if let Some(stack) = stacks.last_mut() {
+            stack.vowels.push(c);
+        }
+    }
+    stacks
+}
+
+/// Guess the root stack of a syllable spelled without vowel or subjoined
+/// letters, e.g. དག (root ད) vs. དགའ (root ག).
+fn root_index_by_letter_count(stacks: &[Stack]) -> usize {
+    match stacks.len() {
+        0..=2 => 0,
+        3 => {
+            let first = stacks[0].base;
+            let last = stacks[2].base;
+            if PREFIXES.contains(&first) && SUFFIXES.contains(&last) { 1 } else { 0 }
+        }
+        _ => 1,
+    }
+}
+
+fn root_index(stacks: &[Stack]) -> usize {
+    match stacks
+        .iter()
+        .position(|s| !s.subjoined.is_empty() || !s.vowels.is_empty())
+    {
+        // A vowel on a trailing འ is the genitive/terminative particle (ཀའི),
+        // not the root: work out the root from the letters before it.
+        Some(i) if i > 0 && stacks[i].base == 'འ' && stacks[i].subjoined.is_empty() => {
+            root_index_by_letter_count(&stacks[..=i])
+        }
+        Some(i) => i,
+        None => root_index_by_letter_count(stacks),
+    }
+}
+
+fn syllable_key(syllable: &str) -> SyllableKey {
+    let stacks = split_stacks(syllable);
+    if stacks.is_empty() {
+        return [NON_TIBETAN_RANK, 0, 0, 0, 0, 0, 0];
+    }
+
+    let main = root_index(&stacks);
+    let stack = &stacks[main];
+
+    let (root, superscript, subscripts) = match stack.subjoined.first() {
+        Some(first)
+            if SUPERSCRIPTS.contains(&stack.base)
+                && !is_subscript(*first)
+                && !(stack.base == 'ལ' && to_base(*first) == 'ཧ') =>
+        {
+            let sup = SUPERSCRIPTS.iter().position(|s| *s == stack.base).unwrap_or(0) as u8 + 1;
+            (*first, sup, &stack.subjoined[1..])
+        }
+        _ => (stack.base, 0, &stack.subjoined[..]),
+    };
+
+    let prefix = if main > 0 {
+        let p = stacks[main - 1].base;
+        PREFIXES.iter().position(|x| *x == p).map_or(6, |i| i as u8 + 1)
+    } else {
+        0
+    };
+    let subscript = subscripts.first().map_or(0, |c| subscript_rank(*c));
+    let suffix = stacks.get(main + 1).map_or(0, |s| letter_rank(s.base));
+    let post_suffix = stacks.get(main + 2).map_or(0, |s| letter_rank(s.base));
+
+    [
+        letter_rank(root),
+        superscript,
+        prefix,
+        subscript,
+        vowel_rank(&stack.vowels),
+        suffix,
+        post_suffix,
+    ]
+}
+
+fn push_syllable(current: &mut String, in_tibetan: bool, keys: &mut Vec<SyllableKey>) {
+    if current.is_empty() {
+        return;
+    }
+    keys.push(if in_tibetan {
+        syllable_key(current)
+    } else {
+        [NON_TIBETAN_RANK, 0, 0, 0, 0, 0, 0]
+    });
+    current.clear();
+}
+
+/// Build the collation key for a whole term, one entry per syllable.
+/// Tshegs, shads and whitespace only separate syllables.
+pub fn tibetan_sort_key(term: &str) -> Vec<SyllableKey> {
+    let mut keys = Vec::new();
+    let mut current = String::new();
+    let mut in_tibetan = false;
+
+    for c in term.chars() {
+        if is_syllable_char(c) {
+            if !in_tibetan {
+                push_syllable(&mut current, in_tibetan, &mut keys);
+                in_tibetan = true;
+            }
+            current.push(c);
+        } else if c.is_alphanumeric() {
+            if in_tibetan {
+                push_syllable(&mut current, in_tibetan, &mut keys);
+                in_tibetan = false;
+            }
+            current.push(c);
+        } else {
+            push_syllable(&mut current, in_tibetan, &mut keys);
+        }
+    }
+    push_syllable(&mut current, in_tibetan, &mut keys);
+    keys
+}
+
+/// Compare two terms in Tibetan dictionary order, falling back to codepoint
+/// order so that distinct spellings never compare equal.
+pub fn compare_tibetan(a: &str, b: &str) -> Ordering {
+    tibetan_sort_key(a)
+        .cmp(&tibetan_sort_key(b))
+        .then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_sorted(terms: &[&str]) {
+        let mut sorted = terms.to_vec();
+        sorted.sort_by(|a, b| compare_tibetan(a, b));
+        assert_eq!(sorted, terms);
+    }
+
+    #[test]
+    fn vowels_follow_suffixes() {
+        assert_sorted(&["ཀ", "ཀག", "ཀང", "ཀས", "ཀི", "ཀུ", "ཀེ", "ཀོ"]);
+    }
+
+    #[test]
+    fn root_then_superscript_prefix_and_subscript() {
+        assert_sorted(&["ཀ", "ཀོ", "ཀྱ", "ཀྲ", "ཀླ", "དཀར", "བཀའ", "བཀྲ", "རྐ", "བརྐ", "སྐ", "ཁ"]);
+    }
+
+    #[test]
+    fn finds_the_root_after_a_prefix() {
+        // དགའ is under ག, the genitive འི doesn't move ཀ
+        assert_sorted(&["ཀ", "ཀའི", "ཀི", "ཁ", "ག", "དགའ", "ང"]);
+    }
+
+    #[test]
+    fn compares_syllable_by_syllable() {
+        assert_sorted(&["ཀ་བ", "ཀ་མ", "ཀ་མ་ལ", "ཀག", "ཀི་ཀ"]);
+    }
+
+    #[test]
+    fn sanskrit_letters_follow_their_parent() {
+        // Precomposed gha, retroflex da, retroflex dha and dha
+        assert_sorted(&["ག", "\u{0F43}", "ང"]);
+        assert_sorted(&["ད", "\u{0F4C}", "\u{0F4D}", "\u{0F52}", "ན"]);
+        // Spelled with a subjoined ཧ, gha is ག with a subscript
+        assert_sorted(&["ག", "གྱ", "ག\u{0FB7}", "ང"]);
+    }
+
+    #[test]
+    fn other_scripts_sort_last() {
+        assert_sorted(&["ཨ", "ཨོཾ", "abc"]);
+    }
+
+    #[test]
+    fn distinct_spellings_never_compare_equal() {
+        assert_eq!(compare_tibetan("ཀ", "ཀ"), Ordering::Equal);
+        assert_ne!(compare_tibetan("ཀ།", "ཀ"), Ordering::Equal);
+        assert_ne!(compare_tibetan("ཀ་", "ཀ"), Ordering::Equal);
+    }
+}
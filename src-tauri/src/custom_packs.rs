@@ -1,9 +1,22 @@
+#[cfg(feature = "app")]
+use crate::apkg_import::{convert_apkg_archive, is_apkg_archive, ApkgImportOptions};
+use crate::data_host::DataHost;
+use crate::pack_migrations::migrate_custom_pack;
+use crate::pack_signing::{
+    check_provenance, installed_provenance, load_trust_settings, ContentHasher, Provenance,
+    SIGNATURE_FILE,
+};
+use crate::pack_validation::{validate_pack, SchemaReport};
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::io::{Cursor, Read};
-use std::path::PathBuf;
-use tauri::{AppHandle, Manager};
+use std::fs::{self, File};
+#[cfg(feature = "app")]
+use std::io::Cursor;
+use std::io::{self, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+use zip::result::ZipError;
 use zip::ZipArchive;
 
 /// Format version this app supports for .tibdict envelopes
@@ -11,9 +24,9 @@ const MAX_SUPPORTED_FORMAT_VERSION: u32 = 1;
 
 /// SQLite schema version this app supports
 /// MUST match SUPPORTED_SCHEMA_VERSION in src/config/pack-definitions.js
-const SUPPORTED_SCHEMA_VERSION: u32 = 3;
+pub(crate) const SUPPORTED_SCHEMA_VERSION: u32 = 3;
 
-const CUSTOM_ID_PREFIX: &str = "custom-";
+pub(crate) const CUSTOM_ID_PREFIX: &str = "custom-";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -32,6 +45,10 @@ pub struct TibdictManifest {
     pub created_at: Option<String>,
     #[serde(default)]
     pub icon: Option<String>,
+    /// Where to look for newer versions: an http(s) URL or a local path to a
+    /// JSON feed, see pack_updates.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_url: Option<String>,
     pub dictionaries: Vec<TibdictManifestDictionary>,
 }
 
@@ -48,6 +65,8 @@ pub struct TibdictManifestDictionary {
 pub struct InstalledCustomPack {
     pub id: String,
     pub manifest: TibdictManifest,
+    /// Signature status, see pack_signing.rs
+    pub provenance: Provenance,
 }
 
 #[derive(Debug, Serialize)]
@@ -58,32 +77,40 @@ pub struct InstallError {
     /// Populated on "conflict" so the frontend can show the real pack name
     /// in its confirmation modal without re-reading the .tibdict itself.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub incoming_manifest: Option<TibdictManifest>,
+    pub incoming_manifest: Option<Box<TibdictManifest>>,
     /// Populated on "conflict" so the frontend can show the already-installed version.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub existing_manifest: Option<TibdictManifest>,
+    pub existing_manifest: Option<Box<TibdictManifest>>,
+    /// Populated on "tampered", "untrusted" and "conflict" so the frontend
+    /// can say who signed the refused pack.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Box<Provenance>>,
+    /// Populated when the pack's SQLite fails validation, see pack_validation.rs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_report: Option<Box<SchemaReport>>,
 }
 
 impl InstallError {
-    fn new(code: &str, message: &str) -> Self {
+    pub(crate) fn new(code: &str, message: &str) -> Self {
         Self {
             code: code.into(),
             message: message.into(),
             incoming_manifest: None,
             existing_manifest: None,
+            provenance: None,
+            schema_report: None,
         }
     }
 }
 
-fn custom_packs_dir(app: &AppHandle) -> Result<PathBuf, InstallError> {
-    let base = app
-        .path()
+pub(crate) fn custom_packs_dir(host: &dyn DataHost) -> Result<PathBuf, InstallError> {
+    let base = host
         .app_data_dir()
-        .map_err(|e| InstallError::new("path", &format!("app_data_dir: {e}")))?;
+        .map_err(|e| InstallError::new("path", &e))?;
     Ok(base.join("packs").join("custom"))
 }
 
-fn is_valid_id(id: &str) -> bool {
+pub(crate) fn is_valid_id(id: &str) -> bool {
     if id.is_empty() { return false; }
     let bytes = id.as_bytes();
     let valid_char = |b: u8| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-';
@@ -95,72 +122,373 @@ fn is_valid_id(id: &str) -> bool {
         && (last.is_ascii_lowercase() || last.is_ascii_digit())
 }
 
-/// Install a .tibdict file.
+/// Caps applied while extracting a .tibdict, against oversized packs and zip
+/// bombs. They are enforced on the bytes actually extracted, not only on the
+/// sizes declared in the ZIP headers.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InstallLimits {
+    /// Uncompressed size of data.sqlite
+    pub max_sqlite_bytes: u64,
+    /// Uncompressed size of manifest.json and signature.json
+    pub max_metadata_bytes: u64,
+    /// Files in the archive
+    pub max_entries: usize,
+    /// Uncompressed / compressed size of any file
+    pub max_compression_ratio: u64,
+}
+
+impl Default for InstallLimits {
+    fn default() -> Self {
+        InstallLimits {
+            max_sqlite_bytes: 2 * 1024 * 1024 * 1024,
+            max_metadata_bytes: 1024 * 1024,
+            max_entries: 16,
+            max_compression_ratio: 100,
+        }
+    }
+}
+
+/// Files smaller than this are not checked for their compression ratio
+const RATIO_CHECK_FLOOR: u64 = 1024 * 1024;
+
+/// Copy all of a decompressing `reader` to `out`, held to max_sqlite_bytes
+/// and to max_compression_ratio times its `compressed` size. Returns the
+/// bytes copied.
+pub(crate) fn copy_within_limits(
+    reader: impl Read,
+    compressed: u64,
+    limits: &InstallLimits,
+    out: &mut impl Write,
+) -> Result<u64, InstallError> {
+    let ratio_cap = RATIO_CHECK_FLOOR.max(compressed.saturating_mul(limits.max_compression_ratio));
+    let cap = limits.max_sqlite_bytes.min(ratio_cap);
+    let copied = io::copy(&mut reader.take(cap.saturating_add(1)), out)
+        .map_err(|e| InstallError::new("corrupt", &format!("decompress: {e}")))?;
+    if copied > cap {
+        return Err(InstallError::new(
+            "limits",
+            "decompressed data is larger than allowed or has a suspicious compression ratio",
+        ));
+    }
+    Ok(copied)
+}
+
+/// Same as copy_within_limits, into memory, for the importers
+pub(crate) fn read_within_limits(
+    reader: impl Read,
+    compressed: u64,
+    limits: &InstallLimits,
+) -> Result<Vec<u8>, InstallError> {
+    let mut data = Vec::new();
+    copy_within_limits(reader, compressed, limits, &mut data)?;
+    Ok(data)
+}
+
+/// Emit an install progress event every this many extracted bytes
+const PROGRESS_STEP: u64 = 1024 * 1024;
+
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallProgress {
+    pub pack_id: String,
+    pub extracted: u64,
+    pub total: u64,
+    pub percentage: f32,
+    pub status: String,
+}
+
+fn emit_progress(host: &dyn DataHost, pack_id: &str, extracted: u64, total: u64, status: &str) {
+    let progress = InstallProgress {
+        pack_id: pack_id.to_string(),
+        extracted,
+        total,
+        percentage: if total > 0 {
+            (extracted as f32 / total as f32) * 100.0
+        } else {
+            0.0
+        },
+        status: status.to_string(),
+    };
+    if let Ok(payload) = serde_json::to_value(progress) {
+        host.emit("custom-pack-install-progress", payload);
+    }
+}
+
+/// Install a .tibdict file, or an Anki .apkg converted on the fly.
+/// The pack is streamed from disk to the packs dir, within `limits`.
 /// Returns the installed pack info, or an error with a structured code:
 ///   - "format"    : not a tibdict / bad envelope
-///   - "schema"    : schema version mismatch
+///   - "schema"    : pack needs a newer app, or its migration failed
 ///   - "corrupt"   : unreadable ZIP / SQLite
 ///   - "conflict"  : id already installed (use force=true to override)
+///   - "fields"    : .apkg given without `apkg` options (see inspect_apkg)
+///   - "tampered"  : signature.json doesn't match the pack
+///   - "untrusted" : strict mode and not signed by a trusted key
+///   - "limits"    : too large, too many files or suspicious compression ratio
 ///   - "path"      : filesystem error
+/// Emits `custom-pack-install-progress` events while extracting.
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn install_custom_pack(
     app: AppHandle,
     file_path: String,
     force: Option<bool>,
+    apkg: Option<ApkgImportOptions>,
+    limits: Option<InstallLimits>,
 ) -> Result<InstalledCustomPack, InstallError> {
     let src = PathBuf::from(&file_path);
     if !src.exists() {
         return Err(InstallError::new("path", &format!("File not found: {file_path}")));
     }
-    let bytes = fs::read(&src)
+    let file = File::open(&src)
         .map_err(|e| InstallError::new("corrupt", &format!("read file: {e}")))?;
-    install_from_bytes(app, bytes, force).await
+    let archive = ZipArchive::new(file)
+        .map_err(|e| InstallError::new("corrupt", &format!("read zip: {e}")))?;
+    let limits = limits.unwrap_or_default();
+
+    if is_apkg_archive(&archive) {
+        // The deck's collection is extracted within `limits`; the pack built
+        // from it is small and converted in memory
+        let file_stem = src.file_stem().and_then(|s| s.to_str()).map(String::from);
+        let bytes = convert_apkg(archive, file_stem, apkg, limits.clone()).await?;
+        return install_from_bytes(app, bytes, force, limits).await;
+    }
+    install_archive(app, archive, force.unwrap_or(false), limits).await
 }
 
 /// Same as install_custom_pack but takes raw ZIP bytes. Used by the frontend
 /// HTML5 drag-drop path where we only have the file's content (not an OS path).
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn install_custom_pack_from_bytes(
     app: AppHandle,
     data: Vec<u8>,
     force: Option<bool>,
+    apkg: Option<ApkgImportOptions>,
+    limits: Option<InstallLimits>,
 ) -> Result<InstalledCustomPack, InstallError> {
-    install_from_bytes(app, data, force).await
+    let archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| InstallError::new("corrupt", &format!("read zip: {e}")))?;
+    let limits = limits.unwrap_or_default();
+    if is_apkg_archive(&archive) {
+        let bytes = convert_apkg(archive, None, apkg, limits.clone()).await?;
+        return install_from_bytes(app, bytes, force, limits).await;
+    }
+    install_archive(app, archive, force.unwrap_or(false), limits).await
+}
+
+#[cfg(feature = "app")]
+/// Turn an Anki package into .tibdict bytes
+async fn convert_apkg<R: Read + Seek + Send + 'static>(
+    mut archive: ZipArchive<R>,
+    file_stem: Option<String>,
+    apkg: Option<ApkgImportOptions>,
+    limits: InstallLimits,
+) -> Result<Vec<u8>, InstallError> {
+    let options = apkg.ok_or_else(|| {
+        InstallError::new("fields", "choose the term and definition fields of this Anki deck")
+    })?;
+    tauri::async_runtime::spawn_blocking(move || {
+        convert_apkg_archive(&mut archive, file_stem.as_deref(), options, &limits)
+    })
+        .await
+        .map_err(|e| InstallError::new("path", &format!("convert task: {e}")))?
 }
 
-async fn install_from_bytes(
+#[cfg(feature = "app")]
+/// Install a .tibdict built in memory (importers, Anki decks)
+pub(crate) async fn install_from_bytes(
     app: AppHandle,
     bytes: Vec<u8>,
     force: Option<bool>,
+    limits: InstallLimits,
 ) -> Result<InstalledCustomPack, InstallError> {
-    let force = force.unwrap_or(false);
-
-    // 1. Open ZIP from the in-memory bytes
-    let cursor = Cursor::new(&bytes);
-    let mut archive = ZipArchive::new(cursor)
+    let archive = ZipArchive::new(Cursor::new(bytes))
         .map_err(|e| InstallError::new("corrupt", &format!("read zip: {e}")))?;
+    install_archive(app, archive, force.unwrap_or(false), limits).await
+}
+
+#[cfg(feature = "app")]
+async fn install_archive<R: Read + Seek + Send + 'static>(
+    app: AppHandle,
+    archive: ZipArchive<R>,
+    force: bool,
+    limits: InstallLimits,
+) -> Result<InstalledCustomPack, InstallError> {
+    tauri::async_runtime::spawn_blocking(move || install_from_archive(&app, archive, force, &limits))
+        .await
+        .map_err(|e| InstallError::new("path", &format!("install task: {e}")))?
+}
 
-    // 2. Extract entries into memory
-    let mut manifest_bytes: Option<Vec<u8>> = None;
-    let mut sqlite_bytes: Option<Vec<u8>> = None;
+/// Refuse archives whose declared layout exceeds the limits, before
+/// extracting anything
+fn check_declared_sizes<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    limits: &InstallLimits,
+) -> Result<(), InstallError> {
+    if archive.len() > limits.max_entries {
+        return Err(InstallError::new(
+            "limits",
+            &format!("too many files in archive ({} > {})", archive.len(), limits.max_entries),
+        ));
+    }
     for i in 0..archive.len() {
-        let mut entry = archive.by_index(i)
+        let entry = archive.by_index_raw(i)
             .map_err(|e| InstallError::new("corrupt", &format!("zip entry: {e}")))?;
-        let name = entry.name().to_string();
-        let mut buf = Vec::new();
-        entry.read_to_end(&mut buf)
+        let max = match entry.name() {
+            "data.sqlite" => limits.max_sqlite_bytes,
+            "manifest.json" => limits.max_metadata_bytes,
+            name if name == SIGNATURE_FILE => limits.max_metadata_bytes,
+            // Anything else is never extracted
+            _ => continue,
+        };
+        if entry.size() > max {
+            return Err(InstallError::new(
+                "limits",
+                &format!("{} is too large ({} bytes, at most {})", entry.name(), entry.size(), max),
+            ));
+        }
+        if entry.size() > RATIO_CHECK_FLOOR
+            && entry.size() / entry.compressed_size().max(1) > limits.max_compression_ratio
+        {
+            return Err(InstallError::new(
+                "limits",
+                &format!("{} has a suspicious compression ratio", entry.name()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Read a small archive file into memory, None when absent
+fn read_metadata_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+    limits: &InstallLimits,
+) -> Result<Option<Vec<u8>>, InstallError> {
+    let entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(InstallError::new("corrupt", &format!("zip entry: {e}"))),
+    };
+    let mut buf = Vec::new();
+    entry.take(limits.max_metadata_bytes + 1).read_to_end(&mut buf)
+        .map_err(|e| InstallError::new("corrupt", &format!("zip read: {e}")))?;
+    if buf.len() as u64 > limits.max_metadata_bytes {
+        return Err(InstallError::new("limits", &format!("{name} is too large")));
+    }
+    Ok(Some(buf))
+}
+
+/// Stream data.sqlite to `dest`, returning its SHA-256 for the signature check
+fn stream_sqlite<R: Read + Seek>(
+    host: &dyn DataHost,
+    archive: &mut ZipArchive<R>,
+    dest: &Path,
+    pack_id: &str,
+    limits: &InstallLimits,
+) -> Result<String, InstallError> {
+    let mut entry = match archive.by_name("data.sqlite") {
+        Ok(entry) => entry,
+        Err(ZipError::FileNotFound) => return Err(InstallError::new("format", "missing data.sqlite")),
+        Err(e) => return Err(InstallError::new("corrupt", &format!("zip entry: {e}"))),
+    };
+    let declared = entry.size();
+    let compressed = entry.compressed_size().max(1);
+
+    let file = File::create(dest)
+        .map_err(|e| InstallError::new("path", &format!("write sqlite: {e}")))?;
+    let mut out = BufWriter::new(file);
+    let mut hasher = ContentHasher::new();
+    let mut buf = vec![0u8; COPY_BUFFER_SIZE];
+    let mut extracted: u64 = 0;
+    let mut next_progress: u64 = 0;
+
+    loop {
+        let n = entry.read(&mut buf)
             .map_err(|e| InstallError::new("corrupt", &format!("zip read: {e}")))?;
-        if name == "manifest.json" { manifest_bytes = Some(buf); }
-        else if name == "data.sqlite" { sqlite_bytes = Some(buf); }
+        if n == 0 {
+            break;
+        }
+        extracted += n as u64;
+
+        // Headers can lie: hold the extracted bytes to the same caps
+        if extracted > declared || extracted > limits.max_sqlite_bytes {
+            return Err(InstallError::new("limits", "data.sqlite is larger than declared or allowed"));
+        }
+        if extracted > RATIO_CHECK_FLOOR && extracted / compressed > limits.max_compression_ratio {
+            return Err(InstallError::new("limits", "data.sqlite has a suspicious compression ratio"));
+        }
+
+        hasher.update(&buf[..n]);
+        out.write_all(&buf[..n])
+            .map_err(|e| InstallError::new("path", &format!("write sqlite: {e}")))?;
+        if extracted >= next_progress {
+            emit_progress(host, pack_id, extracted, declared, "extracting");
+            next_progress = extracted + PROGRESS_STEP;
+        }
+    }
+    out.flush()
+        .map_err(|e| InstallError::new("path", &format!("write sqlite: {e}")))?;
+    Ok(hasher.finish())
+}
+
+/// Fill the temp dir: data.sqlite streamed and checked against the
+/// signature, then manifest and signature, migrate an older schema, then
+/// validate the SQLite (see pack_validation.rs).
+fn extract_into<R: Read + Seek>(
+    host: &dyn DataHost,
+    archive: &mut ZipArchive<R>,
+    temp_dir: &Path,
+    manifest: &TibdictManifest,
+    manifest_bytes: &[u8],
+    signature_bytes: Option<&[u8]>,
+    limits: &InstallLimits,
+) -> Result<Provenance, InstallError> {
+    let pack_id = &format!("{CUSTOM_ID_PREFIX}{}", manifest.id);
+    let sqlite_path = temp_dir.join("data.sqlite");
+    let sqlite_sha256 = stream_sqlite(host, archive, &sqlite_path, pack_id, limits)?;
+    let mut provenance = check_provenance(host, manifest_bytes, &sqlite_sha256, signature_bytes)?;
+
+    fs::write(temp_dir.join("manifest.json"), manifest_bytes)
+        .map_err(|e| InstallError::new("path", &format!("write manifest: {e}")))?;
+    if let Some(signature_bytes) = signature_bytes {
+        fs::write(temp_dir.join(SIGNATURE_FILE), signature_bytes)
+            .map_err(|e| InstallError::new("path", &format!("write signature: {e}")))?;
+    }
+
+    let size = fs::metadata(&sqlite_path).map_or(0, |m| m.len());
+    if manifest.schema_version < SUPPORTED_SCHEMA_VERSION {
+        emit_progress(host, pack_id, size, size, "migrating");
+        let report = migrate_custom_pack(temp_dir, pack_id, false).map_err(|e| InstallError::new("schema", &e))?;
+        provenance = provenance.migrated(report.from_version);
     }
 
-    let manifest_bytes = manifest_bytes
+    // Validate the SQLite schema and contents against the manifest
+    emit_progress(host, pack_id, size, size, "validating");
+    let conn = Connection::open(&sqlite_path)
+        .map_err(|e| InstallError::new("corrupt", &format!("bad sqlite: {e}")))?;
+    validate_pack(&conn, manifest)?;
+    Ok(provenance)
+}
+
+pub(crate) fn install_from_archive<R: Read + Seek>(
+    host: &dyn DataHost,
+    mut archive: ZipArchive<R>,
+    force: bool,
+    limits: &InstallLimits,
+) -> Result<InstalledCustomPack, InstallError> {
+    // 1. Check the declared sizes, then read the small files into memory
+    check_declared_sizes(&mut archive, limits)?;
+    let manifest_bytes = read_metadata_entry(&mut archive, "manifest.json", limits)?
         .ok_or_else(|| InstallError::new("format", "missing manifest.json"))?;
-    let sqlite_bytes = sqlite_bytes
-        .ok_or_else(|| InstallError::new("format", "missing data.sqlite"))?;
+    let signature_bytes = read_metadata_entry(&mut archive, SIGNATURE_FILE, limits)?;
 
-    // 3. Parse and validate manifest
-    let manifest: TibdictManifest = serde_json::from_slice(&manifest_bytes)
+    // 2. Parse and validate manifest
+    let mut manifest: TibdictManifest = serde_json::from_slice(&manifest_bytes)
         .map_err(|e| InstallError::new("format", &format!("bad manifest json: {e}")))?;
 
     if manifest.format != "tibdict" {
@@ -169,15 +497,16 @@ async fn install_from_bytes(
     if manifest.format_version > MAX_SUPPORTED_FORMAT_VERSION {
         return Err(InstallError::new("schema", "format version not supported"));
     }
-    if manifest.schema_version != SUPPORTED_SCHEMA_VERSION {
-        return Err(InstallError::new("schema", "schema version mismatch"));
+    // Older schemas are migrated once extracted, see pack_migrations.rs
+    if manifest.schema_version > SUPPORTED_SCHEMA_VERSION {
+        return Err(InstallError::new("schema", "pack needs a newer version of the app"));
     }
     if !is_valid_id(&manifest.id) {
         return Err(InstallError::new("format", "invalid id"));
     }
 
-    // 4. Write temp dir and validate SQLite
-    let packs_dir = custom_packs_dir(&app)?;
+    // 3. Stream into a temp dir, verify the signature and validate SQLite
+    let packs_dir = custom_packs_dir(host)?;
     fs::create_dir_all(&packs_dir)
         .map_err(|e| InstallError::new("path", &format!("mkdir: {e}")))?;
 
@@ -191,34 +520,25 @@ async fn install_from_bytes(
     fs::create_dir_all(&temp_dir)
         .map_err(|e| InstallError::new("path", &format!("mkdir tmp: {e}")))?;
 
-    fs::write(temp_dir.join("data.sqlite"), &sqlite_bytes)
-        .map_err(|e| InstallError::new("path", &format!("write sqlite: {e}")))?;
-    fs::write(temp_dir.join("manifest.json"), &manifest_bytes)
-        .map_err(|e| InstallError::new("path", &format!("write manifest: {e}")))?;
-
-    // Validate SQLite opens and has expected tables
-    match Connection::open(temp_dir.join("data.sqlite")) {
-        Ok(conn) => {
-            let check = conn.query_row::<i64, _, _>(
-                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name IN ('dictionaries','entries')",
-                [],
-                |r| r.get(0),
-            );
-            match check {
-                Ok(2) => {}
-                _ => {
-                    let _ = fs::remove_dir_all(&temp_dir);
-                    return Err(InstallError::new("format", "sqlite missing expected tables"));
-                }
-            }
-        }
+    let extracted = extract_into(
+        host,
+        &mut archive,
+        &temp_dir,
+        &manifest,
+        &manifest_bytes,
+        signature_bytes.as_deref(),
+        limits,
+    );
+    let provenance = match extracted {
+        Ok(provenance) => provenance,
         Err(e) => {
             let _ = fs::remove_dir_all(&temp_dir);
-            return Err(InstallError::new("corrupt", &format!("bad sqlite: {e}")));
+            return Err(e);
         }
-    }
+    };
+    manifest.schema_version = SUPPORTED_SCHEMA_VERSION;
 
-    // 5. Conflict handling
+    // 4. Conflict handling
     if final_dir.exists() {
         if !force {
             // Read the existing manifest so the frontend can show the installed version.
@@ -227,25 +547,34 @@ async fn install_from_bytes(
                 .and_then(|s| serde_json::from_str::<TibdictManifest>(&s).ok());
             let _ = fs::remove_dir_all(&temp_dir);
             let mut err = InstallError::new("conflict", "pack already installed");
-            err.incoming_manifest = Some(manifest.clone());
-            err.existing_manifest = existing;
+            err.incoming_manifest = Some(Box::new(manifest.clone()));
+            err.existing_manifest = existing.map(Box::new);
+            err.provenance = Some(Box::new(provenance));
             return Err(err);
         }
         fs::remove_dir_all(&final_dir)
             .map_err(|e| InstallError::new("path", &format!("replace existing: {e}")))?;
     }
 
-    // 6. Atomic-ish move
+    // 5. Atomic-ish move
     fs::rename(&temp_dir, &final_dir)
         .map_err(|e| InstallError::new("path", &format!("move into place: {e}")))?;
 
-    Ok(InstalledCustomPack { id: prefixed_id, manifest })
+    host.packs_changed();
+    emit_progress(host, &prefixed_id, 1, 1, "complete");
+
+    Ok(InstalledCustomPack { id: prefixed_id, manifest, provenance })
 }
 
 /// List all installed custom packs
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn list_custom_packs(app: AppHandle) -> Result<Vec<InstalledCustomPack>, String> {
-    let packs_dir = match custom_packs_dir(&app) {
+    installed_custom_packs(&app)
+}
+
+pub(crate) fn installed_custom_packs(host: &dyn DataHost) -> Result<Vec<InstalledCustomPack>, String> {
+    let packs_dir = match custom_packs_dir(host) {
         Ok(p) => p,
         Err(e) => return Err(e.message),
     };
@@ -254,6 +583,8 @@ pub async fn list_custom_packs(app: AppHandle) -> Result<Vec<InstalledCustomPack
         return Ok(Vec::new());
     }
 
+    // For display only: with no readable store, no pack shows as verified
+    let trust = load_trust_settings(host).unwrap_or_default();
     let mut out = Vec::new();
     let entries = fs::read_dir(&packs_dir).map_err(|e| format!("read_dir: {e}"))?;
     for entry in entries.flatten() {
@@ -275,7 +606,10 @@ pub async fn list_custom_packs(app: AppHandle) -> Result<Vec<InstalledCustomPack
 
         match fs::read_to_string(&manifest_path) {
             Ok(contents) => match serde_json::from_str::<TibdictManifest>(&contents) {
-                Ok(manifest) => out.push(InstalledCustomPack { id: name, manifest }),
+                Ok(manifest) => {
+                    let provenance = installed_provenance(&path, &trust);
+                    out.push(InstalledCustomPack { id: name, manifest, provenance })
+                }
                 Err(e) => eprintln!("[list_custom_packs] bad manifest in {name}: {e}"),
             },
             Err(e) => eprintln!("[list_custom_packs] cannot read manifest in {name}: {e}"),
@@ -287,22 +621,28 @@ pub async fn list_custom_packs(app: AppHandle) -> Result<Vec<InstalledCustomPack
 }
 
 /// Remove an installed custom pack
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn remove_custom_pack(app: AppHandle, pack_id: String) -> Result<(), String> {
+    uninstall_custom_pack(&app, &pack_id)
+}
+
+pub(crate) fn uninstall_custom_pack(host: &dyn DataHost, pack_id: &str) -> Result<(), String> {
     if !pack_id.starts_with(CUSTOM_ID_PREFIX) {
         return Err("pack_id must start with 'custom-'".into());
     }
-    let packs_dir = custom_packs_dir(&app).map_err(|e| e.message)?;
-    let target = packs_dir.join(&pack_id);
+    let packs_dir = custom_packs_dir(host).map_err(|e| e.message)?;
+    let target = packs_dir.join(pack_id);
     if target.exists() {
         fs::remove_dir_all(&target).map_err(|e| format!("remove: {e}"))?;
     }
+    host.packs_changed();
     Ok(())
 }
 
 /// Public helper used by packs.rs to discover custom pack paths
-pub fn get_custom_pack_paths(app: &AppHandle) -> Vec<(String, PathBuf)> {
-    let packs_dir = match custom_packs_dir(app) {
+pub fn get_custom_pack_paths(host: &dyn DataHost) -> Vec<(String, PathBuf)> {
+    let packs_dir = match custom_packs_dir(host) {
         Ok(p) => p,
         Err(_) => return Vec::new(),
     };
@@ -326,3 +666,150 @@ pub fn get_custom_pack_paths(app: &AppHandle) -> Vec<(String, PathBuf)> {
     }
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_host::TestDataDir;
+    use std::io::Cursor;
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    const MANIFEST: &str = r#"{"format":"tibdict","formatVersion":1,"schemaVersion":3,"id":"limits",
+        "name":"Limits","description":"","dictionaries":[]}"#;
+
+    fn small_limits() -> InstallLimits {
+        InstallLimits {
+            max_sqlite_bytes: 8 * 1024 * 1024,
+            max_metadata_bytes: 4096,
+            max_entries: 3,
+            max_compression_ratio: 100,
+        }
+    }
+
+    fn zip(files: &[(&str, &[u8], CompressionMethod)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data, method) in files {
+            zip.start_file(*name, SimpleFileOptions::default().compression_method(*method))
+                .unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// Rewrite the uncompressed size of `name` in its local and central
+    /// directory headers
+    fn declare_size(bytes: &mut [u8], name: &str, size: u32) {
+        let patch = |bytes: &mut [u8], signature: &[u8; 4], name_at: usize, size_at: usize| {
+            let mut at = 0;
+            while let Some(found) = bytes[at..].windows(4).position(|w| w == signature) {
+                let header = at + found;
+                if bytes[header + name_at..].starts_with(name.as_bytes()) {
+                    bytes[header + size_at..header + size_at + 4].copy_from_slice(&size.to_le_bytes());
+                }
+                at = header + 4;
+            }
+        };
+        patch(bytes, b"PK\x03\x04", 30, 22);
+        patch(bytes, b"PK\x01\x02", 46, 24);
+    }
+
+    fn install(dir: &TestDataDir, bytes: Vec<u8>, limits: &InstallLimits) -> Result<InstalledCustomPack, InstallError> {
+        install_from_archive(dir, ZipArchive::new(Cursor::new(bytes)).unwrap(), false, limits)
+    }
+
+    fn assert_limits(result: Result<InstalledCustomPack, InstallError>, message: &str) {
+        match result {
+            Ok(_) => panic!("installed despite the limits"),
+            Err(e) => {
+                assert_eq!(e.code, "limits", "{}", e.message);
+                assert!(e.message.contains(message), "{}", e.message);
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_data_larger_than_declared() {
+        let dir = TestDataDir::new("limits-declared");
+        let data = vec![7u8; 64 * 1024];
+        let mut bytes = zip(&[
+            ("manifest.json", MANIFEST.as_bytes(), CompressionMethod::Stored),
+            ("data.sqlite", &data, CompressionMethod::Stored),
+        ]);
+        declare_size(&mut bytes, "data.sqlite", 1024);
+        assert_limits(install(&dir, bytes, &small_limits()), "larger than declared");
+        // Nothing is left behind
+        let leftovers = fs::read_dir(custom_packs_dir(&dir).unwrap()).unwrap().count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn refuses_suspicious_compression_ratios() {
+        let dir = TestDataDir::new("limits-ratio");
+        let zeros = vec![0u8; 2 * RATIO_CHECK_FLOOR as usize];
+        let bytes = zip(&[
+            ("manifest.json", MANIFEST.as_bytes(), CompressionMethod::Stored),
+            ("data.sqlite", &zeros, CompressionMethod::Deflated),
+        ]);
+        assert_limits(install(&dir, bytes, &small_limits()), "suspicious compression ratio");
+
+        // The same checks on the bytes the importers decompress
+        let compressed = {
+            let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(&zeros).unwrap();
+            encoder.finish().unwrap()
+        };
+        let inflate = |data: &[u8]| flate2::read::DeflateDecoder::new(Cursor::new(data.to_vec()));
+        let err = read_within_limits(inflate(&compressed), compressed.len() as u64, &small_limits()).unwrap_err();
+        assert_eq!(err.code, "limits");
+        // Under the floor, small files may compress as well as they like
+        let small = &zeros[..RATIO_CHECK_FLOOR as usize / 2];
+        let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(small).unwrap();
+        let compressed_small = encoder.finish().unwrap();
+        let data = read_within_limits(inflate(&compressed_small), compressed_small.len() as u64, &small_limits()).unwrap();
+        assert_eq!(data.len(), small.len());
+    }
+
+    #[test]
+    fn refuses_archives_with_too_many_files() {
+        let dir = TestDataDir::new("limits-entries");
+        let bytes = zip(&[
+            ("manifest.json", MANIFEST.as_bytes(), CompressionMethod::Stored),
+            ("data.sqlite", b"", CompressionMethod::Stored),
+            ("a.txt", b"a", CompressionMethod::Stored),
+            ("b.txt", b"b", CompressionMethod::Stored),
+        ]);
+        assert_limits(install(&dir, bytes, &small_limits()), "too many files in archive (4 > 3)");
+    }
+
+    #[test]
+    fn refuses_files_over_their_size_cap() {
+        let dir = TestDataDir::new("limits-size");
+        let limits = InstallLimits {
+            max_sqlite_bytes: 1024,
+            ..small_limits()
+        };
+        let data = vec![7u8; 2048];
+        let bytes = zip(&[
+            ("manifest.json", MANIFEST.as_bytes(), CompressionMethod::Stored),
+            ("data.sqlite", &data, CompressionMethod::Stored),
+        ]);
+        assert_limits(install(&dir, bytes, &limits), "data.sqlite is too large");
+
+        let manifest = format!("{}{}", MANIFEST, " ".repeat(8192));
+        let bytes = zip(&[
+            ("manifest.json", manifest.as_bytes(), CompressionMethod::Stored),
+            ("data.sqlite", b"", CompressionMethod::Stored),
+        ]);
+        assert_limits(install(&dir, bytes, &small_limits()), "manifest.json is too large");
+
+        // A manifest whose header understates its size
+        let mut bytes = zip(&[
+            ("manifest.json", manifest.as_bytes(), CompressionMethod::Stored),
+            ("data.sqlite", b"", CompressionMethod::Stored),
+        ]);
+        declare_size(&mut bytes, "manifest.json", 100);
+        assert!(install(&dir, bytes, &small_limits()).is_err());
+    }
+}
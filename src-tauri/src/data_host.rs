@@ -0,0 +1,89 @@
+//! Where pack code finds the app's data, so that the same discovery, search
+//! and install logic runs inside the Tauri app and in the command-line tool
+//! (see cli.rs).
+//!
+//! Functions that only need paths take `&dyn DataHost`; Tauri commands pass
+//! their `&AppHandle` unchanged, the command-line tool a bare directory.
+//! Hosts are shared between the lookup server's workers, hence `Sync`.
+
+#[cfg(feature = "app")]
+use crate::pack_indexes::refresh_indexes_in_background;
+use std::path::PathBuf;
+#[cfg(feature = "app")]
+use tauri::{AppHandle, Emitter, Manager};
+
+pub(crate) trait DataHost: Sync {
+    /// `<app_data>`, holding packs, settings and indexes
+    fn app_data_dir(&self) -> Result<PathBuf, String>;
+
+    /// A file bundled with the app (e.g. "core.sqlite"), if there is one
+    fn resource(&self, name: &str) -> Option<PathBuf>;
+
+    /// Report progress to whoever is watching
+    fn emit(&self, event: &str, payload: serde_json::Value);
+
+    /// Packs were installed, replaced or removed
+    fn packs_changed(&self);
+}
+
+#[cfg(feature = "app")]
+impl DataHost for AppHandle {
+    fn app_data_dir(&self) -> Result<PathBuf, String> {
+        self.path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to get app data dir: {}", e))
+    }
+
+    fn resource(&self, name: &str) -> Option<PathBuf> {
+        self.path()
+            .resolve(name, tauri::path::BaseDirectory::Resource)
+            .ok()
+    }
+
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        let _ = Emitter::emit(self, event, payload);
+    }
+
+    fn packs_changed(&self) {
+        refresh_indexes_in_background(self);
+    }
+}
+
+/// A fresh data directory under the system temp dir, removed when dropped
+#[cfg(test)]
+pub(crate) struct TestDataDir {
+    pub(crate) root: PathBuf,
+}
+
+#[cfg(test)]
+impl TestDataDir {
+    /// `name` tells apart the tests running at the same time
+    pub(crate) fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("tibetan-translator-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        TestDataDir { root }
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDataDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+#[cfg(test)]
+impl DataHost for TestDataDir {
+    fn app_data_dir(&self) -> Result<PathBuf, String> {
+        Ok(self.root.clone())
+    }
+
+    fn resource(&self, _name: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn emit(&self, _event: &str, _payload: serde_json::Value) {}
+
+    fn packs_changed(&self) {}
+}
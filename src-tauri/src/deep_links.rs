@@ -0,0 +1,169 @@
+//! `tibetan-translator://` links, so that other apps, PDFs and notes can
+//! open a lookup:
+//!
+//!   tibetan-translator://define/<term>
+//!   tibetan-translator://search?q=<query>&type=<regular|phonetics_strict|phonetics_loose>
+//!
+//! With the main window on screen, links open there. While the app runs in
+//! the background, definitions and plain searches open in the lookup popup
+//! instead, like the global hotkey does; phonetic searches always need the
+//! main window.
+//!
+//! The link is kept for the chosen window until its page takes it (see
+//! services/deep-links.js), so links that launch the app aren't lost while
+//! the frontend loads. On desktop, the single-instance plugin hands links
+//! opened while the app runs to this process instead of starting another.
+
+#[cfg(desktop)]
+use crate::global_lookup::open_popup;
+use crate::lookup_server::decode_component;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Url, WebviewWindow};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+const SCHEME: &str = "tibetan-translator";
+
+const MAIN_WINDOW: &str = "main";
+const POPUP_WINDOW: &str = "global-lookup-popup";
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DeepLink {
+    Define {
+        term: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Search {
+        query: String,
+        search_type: String,
+    },
+}
+
+/// Links waiting for their window's page, by window label
+#[derive(Default)]
+pub struct DeepLinkState {
+    pending: Mutex<HashMap<String, DeepLink>>,
+}
+
+fn parse_deep_link(url: &Url) -> Option<DeepLink> {
+    if url.scheme() != SCHEME {
+        return None;
+    }
+    match url.host_str()? {
+        "define" => {
+            // A "+" in a path is a plus sign (Wylie uses it), not a space
+            let encoded = url.path().trim_start_matches('/').replace('+', "%2B");
+            let term = decode_component(&encoded)?.trim().to_string();
+            (!term.is_empty()).then_some(DeepLink::Define { term })
+        }
+        "search" => {
+            let mut query = String::new();
+            let mut search_type = "regular".to_string();
+            for (key, value) in url.query_pairs() {
+                match key.as_ref() {
+                    "q" => query = value.trim().to_string(),
+                    "type" => search_type = value.into_owned(),
+                    _ => {}
+                }
+            }
+            let known_type = ["regular", "phonetics_strict", "phonetics_loose"].contains(&search_type.as_str());
+            (known_type && !query.is_empty()).then_some(DeepLink::Search { query, search_type })
+        }
+        _ => None,
+    }
+}
+
+fn is_on_screen(window: &WebviewWindow) -> bool {
+    window.is_visible().unwrap_or(false) && !window.is_minimized().unwrap_or(false)
+}
+
+/// See the module doc for which window a link goes to
+fn target_window(app: &AppHandle, link: &DeepLink) -> &'static str {
+    let popup_can_show = match link {
+        DeepLink::Define { .. } => true,
+        DeepLink::Search { search_type, .. } => search_type == "regular",
+    };
+    let main_on_screen = app.get_webview_window(MAIN_WINDOW).map_or(false, |window| is_on_screen(&window));
+    if popup_can_show && !main_on_screen && app.get_webview_window(POPUP_WINDOW).is_some() {
+        POPUP_WINDOW
+    } else {
+        MAIN_WINDOW
+    }
+}
+
+fn bring_to_front(app: &AppHandle, label: &str) {
+    // Placed by the cursor and shown without activating the app, like a
+    // hotkey lookup
+    #[cfg(desktop)]
+    if label == POPUP_WINDOW {
+        if let Err(e) = open_popup(app, None) {
+            eprintln!("[deep_links] Failed to show the popup: {}", e);
+        }
+        return;
+    }
+    if let Some(window) = app.get_webview_window(label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Open `link` in the window target_window picks for it
+pub(crate) fn open_link(app: &AppHandle, link: DeepLink) {
+    let label = target_window(app, &link);
+    app.state::<DeepLinkState>()
+        .pending
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(label.to_string(), link);
+    let _ = app.emit_to(label, "deep-link", ());
+    bring_to_front(app, label);
+}
+
+fn open_urls(app: &AppHandle, urls: Vec<Url>) {
+    for url in urls {
+        match parse_deep_link(&url) {
+            Some(link) => open_link(app, link),
+            None => eprintln!("[deep_links] Ignoring unsupported link: {}", url),
+        }
+    }
+}
+
+/// Handle the link that launched the app, and the ones opened afterwards
+pub(crate) fn listen_for_deep_links(app: &AppHandle) {
+    // Installers register the scheme; this also covers dev builds and
+    // AppImages that weren't integrated with the desktop
+    #[cfg(any(windows, target_os = "linux"))]
+    if let Err(e) = app.deep_link().register_all() {
+        eprintln!("[deep_links] Failed to register the {} scheme: {}", SCHEME, e);
+    }
+
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| open_urls(&handle, event.urls()));
+    if let Ok(Some(urls)) = app.deep_link().get_current() {
+        open_urls(app, urls);
+    }
+}
+
+/// Called in the running app when it is launched again. Links in `args`
+/// were already routed by the deep-link plugin; a plain launch brings the
+/// main window forward.
+#[cfg(desktop)]
+pub(crate) fn focus_running_instance(app: &AppHandle, args: Vec<String>, _cwd: String) {
+    let prefix = format!("{}://", SCHEME);
+    if !args.iter().any(|arg| arg.starts_with(&prefix)) {
+        bring_to_front(app, MAIN_WINDOW);
+    }
+}
+
+/// The link waiting for the calling window, if any
+#[tauri::command]
+pub fn take_deep_link(window: WebviewWindow, state: State<'_, DeepLinkState>) -> Option<DeepLink> {
+    state
+        .pending
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(window.label())
+}
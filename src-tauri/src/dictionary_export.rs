@@ -0,0 +1,510 @@
+//! Export of one installed dictionary to open formats.
+//!
+//! Dictionaries are addressed like PackDictionary ("pack_id:dictionary_id"),
+//! so anything listed by pack_get_dictionaries can be exported: the core
+//! pack, downloaded packs and custom packs. Rows are streamed out of the
+//! pack database; only StarDict (sorted index) and .tibdict (built from the
+//! collected rows) hold the whole dictionary in memory.
+//!
+//! Formats:
+//!   - "csv"      : header row with the columns create_custom_pack reads
+//!   - "jsonl"    : one create_custom_pack entry object per line
+//!   - "tei"      : TEI-lite XML, title and source in the teiHeader
+//!   - "stardict" : .ifo/.idx/.dict next to `output_path`
+//!   - "tibdict"  : a one-dictionary custom pack
+
+use crate::custom_packs::TibdictManifest;
+#[cfg(feature = "app")]
+use crate::packs::{ensure_pack_available, get_all_pack_db_paths};
+use crate::tibdict_builder::{build_tibdict, now_iso8601, slugify, TibdictEntry, TibdictMetadata};
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "app")]
+use tauri::{AppHandle, Emitter, Window};
+
+/// Emit a progress event every this many entries
+const PROGRESS_EVERY: usize = 1_000;
+
+const CSV_COLUMNS: [&str; 7] = [
+    "term",
+    "definition",
+    "dictionary",
+    "termPhoneticsStrict",
+    "termPhoneticsLoose",
+    "definitionPhoneticsWordsStrict",
+    "definitionPhoneticsWordsLoose",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProgress {
+    pub compound_id: String,
+    pub exported: usize,
+    pub total: usize,
+    pub percentage: f32,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DictionaryExport {
+    pub compound_id: String,
+    pub format: String,
+    pub name: String,
+    pub entries_count: usize,
+    /// Files written; StarDict gives three
+    pub files: Vec<String>,
+}
+
+/// What is known about the exported dictionary and its pack
+struct ExportSource {
+    pack_id: String,
+    name: String,
+    description: String,
+    author: Option<String>,
+    version: Option<String>,
+}
+
+struct Progress<'a> {
+    report: &'a dyn Fn(ExportProgress),
+    compound_id: &'a str,
+    total: usize,
+    exported: usize,
+}
+
+impl Progress<'_> {
+    fn emit(&self, status: &str) {
+        (self.report)(ExportProgress {
+            compound_id: self.compound_id.to_string(),
+            exported: self.exported,
+            total: self.total,
+            percentage: if self.total > 0 {
+                (self.exported as f32 / self.total as f32) * 100.0
+            } else {
+                0.0
+            },
+            status: status.to_string(),
+        });
+    }
+
+    fn tick(&mut self) {
+        self.exported += 1;
+        if self.exported % PROGRESS_EVERY == 0 || self.exported == self.total {
+            self.emit("exporting");
+        }
+    }
+}
+
+/// Custom packs carry their author and description in manifest.json
+fn read_source(
+    conn: &Connection,
+    db_path: &Path,
+    pack_id: &str,
+    dictionary_id: i64,
+) -> Result<ExportSource, String> {
+    let name: String = conn
+        .query_row(
+            "SELECT name FROM dictionaries WHERE id = ?",
+            params![dictionary_id],
+            |row| row.get(0),
+        )
+        .map_err(|_| format!("Dictionary {}:{} not found", pack_id, dictionary_id))?;
+
+    let manifest = db_path
+        .parent()
+        .map(|dir| dir.join("manifest.json"))
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str::<TibdictManifest>(&contents).ok());
+
+    Ok(match manifest {
+        Some(manifest) => ExportSource {
+            pack_id: pack_id.to_string(),
+            name,
+            description: if manifest.description.is_empty() {
+                format!("Exported from {}", manifest.name)
+            } else {
+                manifest.description
+            },
+            author: manifest.author,
+            version: manifest.version,
+        },
+        None => ExportSource {
+            pack_id: pack_id.to_string(),
+            name,
+            description: format!("Exported from the {} pack", pack_id),
+            author: None,
+            version: None,
+        },
+    })
+}
+
+/// Call `f` with every entry of the dictionary, in pack order
+fn for_each_entry(
+    conn: &Connection,
+    dictionary_id: i64,
+    dictionary_name: &str,
+    mut f: impl FnMut(TibdictEntry) -> io::Result<()>,
+) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT term, definition, termPhoneticsStrict, termPhoneticsLoose,
+                    definitionPhoneticsWordsStrict, definitionPhoneticsWordsLoose
+             FROM entries WHERE dictionaryId = ? ORDER BY id",
+        )
+        .map_err(|e| format!("Failed to prepare export query: {}", e))?;
+    let mut rows = stmt
+        .query(params![dictionary_id])
+        .map_err(|e| format!("Failed to query entries: {}", e))?;
+
+    // Empty phonetics are the same as none for create_custom_pack
+    let optional = |value: Option<String>| value.filter(|v| !v.is_empty());
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| format!("Failed to read entry: {}", e))?
+    {
+        let entry = TibdictEntry {
+            term: row.get(0).unwrap_or_default(),
+            definition: row.get(1).unwrap_or_default(),
+            dictionary: Some(dictionary_name.to_string()),
+            term_phonetics_strict: optional(row.get(2).ok()),
+            term_phonetics_loose: optional(row.get(3).ok()),
+            definition_phonetics_words_strict: optional(row.get(4).ok()),
+            definition_phonetics_words_loose: optional(row.get(5).ok()),
+        };
+        f(entry).map_err(|e| format!("Write error: {}", e))?;
+    }
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn export_csv(
+    conn: &Connection,
+    dictionary_id: i64,
+    source: &ExportSource,
+    output: &Path,
+    progress: &mut Progress,
+) -> Result<(), String> {
+    let mut out =
+        BufWriter::new(File::create(output).map_err(|e| format!("Failed to create file: {}", e))?);
+    writeln!(out, "{}", CSV_COLUMNS.join(",")).map_err(|e| format!("Write error: {}", e))?;
+    for_each_entry(conn, dictionary_id, &source.name, |entry| {
+        let fields = [
+            Some(entry.term.as_str()),
+            Some(entry.definition.as_str()),
+            entry.dictionary.as_deref(),
+            entry.term_phonetics_strict.as_deref(),
+            entry.term_phonetics_loose.as_deref(),
+            entry.definition_phonetics_words_strict.as_deref(),
+            entry.definition_phonetics_words_loose.as_deref(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f.unwrap_or(""))).collect();
+        progress.tick();
+        writeln!(out, "{}", line.join(","))
+    })?;
+    out.flush().map_err(|e| format!("Write error: {}", e))
+}
+
+fn export_jsonl(
+    conn: &Connection,
+    dictionary_id: i64,
+    source: &ExportSource,
+    output: &Path,
+    progress: &mut Progress,
+) -> Result<(), String> {
+    let mut out =
+        BufWriter::new(File::create(output).map_err(|e| format!("Failed to create file: {}", e))?);
+    for_each_entry(conn, dictionary_id, &source.name, |entry| {
+        serde_json::to_writer(&mut out, &entry)?;
+        progress.tick();
+        writeln!(out)
+    })?;
+    out.flush().map_err(|e| format!("Write error: {}", e))
+}
+
+fn export_tei(
+    conn: &Connection,
+    dictionary_id: i64,
+    source: &ExportSource,
+    output: &Path,
+    progress: &mut Progress,
+) -> Result<(), String> {
+    let mut out =
+        BufWriter::new(File::create(output).map_err(|e| format!("Failed to create file: {}", e))?);
+    let author = source
+        .author
+        .as_deref()
+        .map(|author| format!("<author>{}</author>", xml_escape(author)))
+        .unwrap_or_default();
+    let edition = source
+        .version
+        .as_deref()
+        .map(|version| {
+            format!(
+                "\n      <editionStmt><edition>{}</edition></editionStmt>",
+                xml_escape(version)
+            )
+        })
+        .unwrap_or_default();
+    write!(
+        out,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<TEI xmlns="http://www.tei-c.org/ns/1.0">
+  <teiHeader>
+    <fileDesc>
+      <titleStmt><title>{title}</title>{author}</titleStmt>{edition}
+      <extent>{total} entries</extent>
+      <publicationStmt><p>Exported from the {pack} pack on {date}</p></publicationStmt>
+      <sourceDesc><p>{description}</p></sourceDesc>
+    </fileDesc>
+  </teiHeader>
+  <text>
+    <body>
+"#,
+        title = xml_escape(&source.name),
+        author = author,
+        edition = edition,
+        total = progress.total,
+        pack = xml_escape(&source.pack_id),
+        date = &now_iso8601()[..10],
+        description = xml_escape(&source.description),
+    )
+    .map_err(|e| format!("Write error: {}", e))?;
+
+    for_each_entry(conn, dictionary_id, &source.name, |entry| {
+        // Line breaks in definitions become <lb/>, as read back by the TEI importer
+        let definition = xml_escape(entry.definition.trim()).replace('\n', "<lb/>");
+        progress.tick();
+        writeln!(
+            out,
+            "      <entry><form><orth>{}</orth></form><sense><def>{}</def></sense></entry>",
+            xml_escape(&entry.term),
+            definition
+        )
+    })?;
+
+    writeln!(out, "    </body>\n  </text>\n</TEI>").map_err(|e| format!("Write error: {}", e))?;
+    out.flush().map_err(|e| format!("Write error: {}", e))
+}
+
+/// StarDict index order: ASCII case-insensitive first, then bytewise
+fn stardict_order(a: &str, b: &str) -> std::cmp::Ordering {
+    let folded = |s: &str| {
+        s.bytes()
+            .map(|b| b.to_ascii_lowercase())
+            .collect::<Vec<u8>>()
+    };
+    folded(a).cmp(&folded(b)).then_with(|| a.cmp(b))
+}
+
+/// .ifo values are single lines
+fn ifo_value(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn export_stardict(
+    conn: &Connection,
+    dictionary_id: i64,
+    source: &ExportSource,
+    output: &Path,
+    progress: &mut Progress,
+) -> Result<Vec<PathBuf>, String> {
+    // One article per headword: a term's entries are joined
+    let mut articles: HashMap<String, Vec<String>> = HashMap::new();
+    for_each_entry(conn, dictionary_id, &source.name, |entry| {
+        progress.tick();
+        articles
+            .entry(entry.term)
+            .or_default()
+            .push(entry.definition);
+        Ok(())
+    })?;
+    let mut headwords: Vec<&String> = articles.keys().collect();
+    headwords.sort_by(|a, b| stardict_order(a, b));
+
+    progress.emit("writing");
+    let mut idx = Vec::new();
+    let mut dict = Vec::new();
+    for headword in &headwords {
+        let article = articles[*headword].join("\n");
+        idx.extend_from_slice(headword.as_bytes());
+        idx.push(0);
+        idx.extend_from_slice(&(dict.len() as u32).to_be_bytes());
+        idx.extend_from_slice(&(article.len() as u32).to_be_bytes());
+        dict.extend_from_slice(article.as_bytes());
+    }
+    if dict.len() > u32::MAX as usize {
+        return Err("Dictionary too large for a 32-bit StarDict index".to_string());
+    }
+
+    let ifo = format!(
+        "StarDict's dict ifo file\nversion=2.4.2\nwordcount={}\nidxfilesize={}\nbookname={}\n{}description={}\ndate={}\nsametypesequence=m\n",
+        headwords.len(),
+        idx.len(),
+        ifo_value(&source.name),
+        source.author.as_deref().map(|a| format!("author={}\n", ifo_value(a))).unwrap_or_default(),
+        ifo_value(&source.description),
+        now_iso8601()[..10].replace('-', "."),
+    );
+
+    let files = [
+        (output.with_extension("ifo"), ifo.into_bytes()),
+        (output.with_extension("idx"), idx),
+        (output.with_extension("dict"), dict),
+    ];
+    for (path, data) in &files {
+        fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+fn export_tibdict(
+    conn: &Connection,
+    dictionary_id: i64,
+    source: &ExportSource,
+    output: &Path,
+    progress: &mut Progress,
+) -> Result<(), String> {
+    let mut entries = Vec::with_capacity(progress.total);
+    for_each_entry(conn, dictionary_id, &source.name, |entry| {
+        progress.tick();
+        entries.push(entry);
+        Ok(())
+    })?;
+
+    // Tibetan names slugify to nothing: fall back to the compound id
+    let id = Some(slugify(&source.name))
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| slugify(&format!("{}-{}", source.pack_id, dictionary_id)));
+    let metadata = TibdictMetadata {
+        id,
+        name: source.name.clone(),
+        description: source.description.clone(),
+        author: source.author.clone(),
+        version: source.version.clone(),
+        icon: None,
+        update_url: None,
+    };
+
+    progress.emit("writing");
+    build_tibdict(output, metadata, &entries)
+        .map(|_| ())
+        .map_err(|e| e.message)
+}
+
+/// Split "pack_id:dictionary_id" and check `format`
+pub(crate) fn parse_export_request(compound_id: &str, format: &str) -> Result<(String, i64, String), String> {
+    let (pack_id, dictionary_id) = compound_id
+        .split_once(':')
+        .and_then(|(pack_id, id)| Some((pack_id.to_string(), id.parse::<i64>().ok()?)))
+        .ok_or_else(|| format!("Invalid dictionary id: {}", compound_id))?;
+    let format = format.to_lowercase();
+    if !["csv", "jsonl", "tei", "stardict", "tibdict"].contains(&format.as_str()) {
+        return Err(format!("Unsupported export format: {}", format));
+    }
+    Ok((pack_id, dictionary_id, format))
+}
+
+/// Export one dictionary of the pack at `db_path` on the calling thread,
+/// passing progress to `report`
+pub(crate) fn export_from_pack(
+    db_path: &Path,
+    pack_id: &str,
+    dictionary_id: i64,
+    format: &str,
+    output_path: &str,
+    report: &dyn Fn(ExportProgress),
+) -> Result<DictionaryExport, String> {
+    let compound_id = format!("{}:{}", pack_id, dictionary_id);
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open pack {}: {}", pack_id, e))?;
+    let source = read_source(&conn, db_path, pack_id, dictionary_id)?;
+    let total: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM entries WHERE dictionaryId = ?",
+            params![dictionary_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count entries: {}", e))?;
+
+    let mut progress = Progress {
+        report,
+        compound_id: &compound_id,
+        total: total as usize,
+        exported: 0,
+    };
+    progress.emit("starting");
+
+    let output = PathBuf::from(output_path);
+    let files = match format {
+        "csv" => export_csv(&conn, dictionary_id, &source, &output, &mut progress)
+            .map(|_| vec![output]),
+        "jsonl" => export_jsonl(&conn, dictionary_id, &source, &output, &mut progress)
+            .map(|_| vec![output]),
+        "tei" => export_tei(&conn, dictionary_id, &source, &output, &mut progress)
+            .map(|_| vec![output]),
+        "stardict" => export_stardict(&conn, dictionary_id, &source, &output, &mut progress),
+        _ => export_tibdict(&conn, dictionary_id, &source, &output, &mut progress)
+            .map(|_| vec![output]),
+    }?;
+    progress.emit("complete");
+
+    Ok(DictionaryExport {
+        compound_id: compound_id.clone(),
+        format: format.to_string(),
+        name: source.name,
+        entries_count: progress.exported,
+        files: files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+    })
+}
+
+/// Export the entries of one dictionary ("pack_id:dictionary_id", as in
+/// PackDictionary) to `output_path` in "csv", "jsonl", "tei", "stardict" or
+/// "tibdict" format. Emits `dictionary-export-progress` events.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn export_dictionary(
+    app: AppHandle,
+    window: Window,
+    compound_id: String,
+    format: String,
+    output_path: String,
+) -> Result<DictionaryExport, String> {
+    let (pack_id, dictionary_id, format) = parse_export_request(&compound_id, &format)?;
+
+    ensure_pack_available(app.clone(), "core".to_string()).await?;
+    let db_path = get_all_pack_db_paths(&app)?
+        .into_iter()
+        .find(|(id, _)| *id == pack_id)
+        .map(|(_, path)| path)
+        .ok_or_else(|| format!("Pack not installed: {}", pack_id))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = |progress: ExportProgress| {
+            let _ = window.emit("dictionary-export-progress", progress);
+        };
+        export_from_pack(&db_path, &pack_id, dictionary_id, &format, &output_path, &report)
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))?
+}
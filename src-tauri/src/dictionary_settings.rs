@@ -0,0 +1,334 @@
+//! Which dictionaries and packs are enabled, and in what order.
+//!
+//! Kept by the Rust side in `<app_data>/settings.sqlite` so the main window,
+//! the lookup popup and the native mobile path all see the same choices.
+//! Dictionaries are keyed by compound id ("pack_id:dictionary_id"). Nothing
+//! is stored until the user changes something: a dictionary without a row
+//! is enabled and keeps the position from its pack.
+
+use crate::data_host::DataHost;
+#[cfg(feature = "app")]
+use rusqlite::params;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+
+const SETTINGS_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS dictionary_settings (
+      dictionaryId  text primary key,
+      enabled       boolean,
+      position      integer
+    );
+    CREATE TABLE IF NOT EXISTS pack_settings (
+      packId   text primary key,
+      enabled  boolean not null
+    );
+";
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DictionarySetting {
+    /// Compound id: "pack_id:dictionary_id" (e.g., "core:1")
+    pub dictionary_id: String,
+    pub enabled: Option<bool>,
+    pub position: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PackSetting {
+    pub pack_id: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DictionarySettings {
+    pub dictionaries: Vec<DictionarySetting>,
+    pub packs: Vec<PackSetting>,
+}
+
+/// Settings loaded once per command, for filtering and sorting results
+#[derive(Debug, Default)]
+pub(crate) struct AppliedSettings {
+    enabled: HashMap<String, bool>,
+    positions: HashMap<String, i64>,
+    disabled_packs: HashSet<String>,
+}
+
+impl AppliedSettings {
+    pub(crate) fn pack_enabled(&self, pack_id: &str) -> bool {
+        !self.disabled_packs.contains(pack_id)
+    }
+
+    pub(crate) fn dictionary_enabled(&self, dictionary_id: &str) -> bool {
+        let pack_id = dictionary_id.split(':').next().unwrap_or_default();
+        self.pack_enabled(pack_id) && self.enabled.get(dictionary_id).copied().unwrap_or(true)
+    }
+
+    /// SQL condition on `entries` leaving out the dictionaries the user
+    /// turned off in a pack
+    pub(crate) fn entries_filter(&self, pack_id: &str) -> String {
+        let disabled = self.disabled_in_pack(pack_id);
+        if disabled.is_empty() {
+            return "1".to_string();
+        }
+        let ids: Vec<String> = disabled.iter().map(|id| id.to_string()).collect();
+        format!("entries.dictionaryId NOT IN ({})", ids.join(", "))
+    }
+
+    /// `default` is the pack's own position
+    pub(crate) fn position(&self, dictionary_id: &str, default: i64) -> i64 {
+        self.positions.get(dictionary_id).copied().unwrap_or(default)
+    }
+
+    /// Raw dictionary ids of a pack the user turned off
+    fn disabled_in_pack(&self, pack_id: &str) -> Vec<i64> {
+        let prefix = format!("{}:", pack_id);
+        self.enabled
+            .iter()
+            .filter(|(_, enabled)| !**enabled)
+            .filter_map(|(id, _)| id.strip_prefix(&prefix)?.parse().ok())
+            .collect()
+    }
+}
+
+fn open_settings(host: &dyn DataHost) -> Result<Connection, String> {
+    let app_data = host.app_data_dir()?;
+    fs::create_dir_all(&app_data).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    let conn = Connection::open(app_data.join("settings.sqlite"))
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    conn.execute_batch(SETTINGS_SCHEMA)
+        .map_err(|e| format!("Failed to create settings tables: {}", e))?;
+    Ok(conn)
+}
+
+fn read_settings(conn: &Connection) -> rusqlite::Result<DictionarySettings> {
+    let dictionaries = conn
+        .prepare("SELECT dictionaryId, enabled, position FROM dictionary_settings ORDER BY dictionaryId")?
+        .query_map([], |row| {
+            Ok(DictionarySetting {
+                dictionary_id: row.get(0)?,
+                enabled: row.get(1)?,
+                position: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let packs = conn
+        .prepare("SELECT packId, enabled FROM pack_settings ORDER BY packId")?
+        .query_map([], |row| {
+            Ok(PackSetting {
+                pack_id: row.get(0)?,
+                enabled: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(DictionarySettings { dictionaries, packs })
+}
+
+/// Settings to apply in pack queries. Unreadable settings apply nothing
+/// rather than failing every lookup.
+pub(crate) fn load_applied_settings(host: &dyn DataHost) -> AppliedSettings {
+    let settings = match open_settings(host).and_then(|conn| read_settings(&conn).map_err(|e| e.to_string())) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("[dictionary_settings] {}", e);
+            return AppliedSettings::default();
+        }
+    };
+
+    let mut applied = AppliedSettings::default();
+    for dictionary in settings.dictionaries {
+        if let Some(enabled) = dictionary.enabled {
+            applied.enabled.insert(dictionary.dictionary_id.clone(), enabled);
+        }
+        if let Some(position) = dictionary.position {
+            applied.positions.insert(dictionary.dictionary_id, position);
+        }
+    }
+    applied.disabled_packs = settings
+        .packs
+        .into_iter()
+        .filter(|pack| !pack.enabled)
+        .map(|pack| pack.pack_id)
+        .collect();
+    applied
+}
+
+fn check_dictionary_id(dictionary_id: &str) -> Result<(), String> {
+    match dictionary_id.split_once(':') {
+        Some((pack_id, id)) if !pack_id.is_empty() && id.parse::<i64>().is_ok() => Ok(()),
+        _ => Err(format!("Invalid dictionary id: {}", dictionary_id)),
+    }
+}
+
+/// Everything the user changed, per dictionary and per pack
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn get_dictionary_settings(app: AppHandle) -> Result<DictionarySettings, String> {
+    let conn = open_settings(&app)?;
+    read_settings(&conn).map_err(|e| format!("Failed to read settings: {}", e))
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn set_dictionary_enabled(app: AppHandle, dictionary_id: String, enabled: bool) -> Result<(), String> {
+    check_dictionary_id(&dictionary_id)?;
+    let conn = open_settings(&app)?;
+    conn.execute(
+        "INSERT INTO dictionary_settings (dictionaryId, enabled) VALUES (?, ?)
+         ON CONFLICT(dictionaryId) DO UPDATE SET enabled = excluded.enabled",
+        params![dictionary_id, enabled],
+    )
+    .map_err(|e| format!("Failed to save setting: {}", e))?;
+    Ok(())
+}
+
+/// Set the order of dictionaries, first to last. Dictionaries left out keep
+/// their position.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn set_dictionary_order(app: AppHandle, dictionary_ids: Vec<String>) -> Result<(), String> {
+    for dictionary_id in &dictionary_ids {
+        check_dictionary_id(dictionary_id)?;
+    }
+    let mut conn = open_settings(&app)?;
+    let tx = conn.transaction().map_err(|e| format!("Failed to save order: {}", e))?;
+    for (index, dictionary_id) in dictionary_ids.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO dictionary_settings (dictionaryId, position) VALUES (?, ?)
+             ON CONFLICT(dictionaryId) DO UPDATE SET position = excluded.position",
+            params![dictionary_id, index as i64 + 1],
+        )
+        .map_err(|e| format!("Failed to save order: {}", e))?;
+    }
+    tx.commit().map_err(|e| format!("Failed to save order: {}", e))
+}
+
+/// Forget the custom order, going back to each pack's positions
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn reset_dictionary_order(app: AppHandle) -> Result<(), String> {
+    let conn = open_settings(&app)?;
+    conn.execute_batch(
+        "UPDATE dictionary_settings SET position = NULL;
+         DELETE FROM dictionary_settings WHERE enabled IS NULL AND position IS NULL;",
+    )
+    .map_err(|e| format!("Failed to reset order: {}", e))
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn set_pack_enabled(app: AppHandle, pack_id: String, enabled: bool) -> Result<(), String> {
+    let conn = open_settings(&app)?;
+    conn.execute(
+        "INSERT INTO pack_settings (packId, enabled) VALUES (?, ?)
+         ON CONFLICT(packId) DO UPDATE SET enabled = excluded.enabled",
+        params![pack_id, enabled],
+    )
+    .map_err(|e| format!("Failed to save setting: {}", e))?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DictionarySettingInput {
+    pub dictionary_id: String,
+    pub enabled: bool,
+    pub position: i64,
+}
+
+/// Save the enabled flag and position of many dictionaries at once, as the
+/// settings page does after every change
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn save_dictionary_settings(app: AppHandle, dictionaries: Vec<DictionarySettingInput>) -> Result<(), String> {
+    for dictionary in &dictionaries {
+        check_dictionary_id(&dictionary.dictionary_id)?;
+    }
+    let mut conn = open_settings(&app)?;
+    let tx = conn.transaction().map_err(|e| format!("Failed to save settings: {}", e))?;
+    for dictionary in &dictionaries {
+        tx.execute(
+            "INSERT OR REPLACE INTO dictionary_settings (dictionaryId, enabled, position) VALUES (?, ?, ?)",
+            params![dictionary.dictionary_id, dictionary.enabled, dictionary.position],
+        )
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    }
+    tx.commit().map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_host::TestDataDir;
+    use crate::packs::get_packs_dir;
+    use crate::repository::{PackSet, Repository};
+    use crate::tibdict_builder::{build_pack_sqlite, TibdictEntry};
+
+    fn entry(dictionary: &str, definition: &str) -> TibdictEntry {
+        TibdictEntry {
+            term: "ཀ་".to_string(),
+            definition: definition.to_string(),
+            dictionary: Some(dictionary.to_string()),
+            term_phonetics_strict: None,
+            term_phonetics_loose: None,
+            definition_phonetics_words_strict: None,
+            definition_phonetics_words_loose: None,
+        }
+    }
+
+    #[test]
+    fn pack_queries_follow_the_settings() {
+        let dir = TestDataDir::new("dictionary-settings");
+        let pack = get_packs_dir(&dir).unwrap().join("core.sqlite");
+        build_pack_sqlite(
+            &pack,
+            &[entry("First", "first letter"), entry("Second", "a letter"), entry("Third", "the letter")],
+            "First",
+        )
+        .unwrap();
+        open_settings(&dir)
+            .unwrap()
+            .execute_batch(
+                "INSERT INTO dictionary_settings (dictionaryId, enabled, position) VALUES
+                   ('core:1', 0, NULL), ('core:2', NULL, 9), ('core:3', NULL, 4);",
+            )
+            .unwrap();
+
+        let packs = PackSet::load(&dir).unwrap();
+        let definitions = |entries: Vec<crate::packs::PackEntry>| -> Vec<String> {
+            entries.into_iter().map(|entry| entry.definition).collect()
+        };
+        assert_eq!(definitions(packs.entries_for_term("ཀ་").unwrap()), ["the letter", "a letter"]);
+        assert_eq!(definitions(packs.search_entries("letter", "regular").unwrap()).len(), 2);
+        assert_eq!(packs.all_terms().unwrap(), ["ཀ་"]);
+
+        // Disabled dictionaries are still listed, to be turned back on
+        let dictionaries: Vec<(String, i64, bool)> = packs
+            .dictionaries()
+            .unwrap()
+            .into_iter()
+            .map(|dictionary| (dictionary.name, dictionary.position, dictionary.enabled))
+            .collect();
+        assert_eq!(
+            dictionaries,
+            [
+                ("First".to_string(), 1, false),
+                ("Third".to_string(), 4, true),
+                ("Second".to_string(), 9, true)
+            ]
+        );
+
+        open_settings(&dir)
+            .unwrap()
+            .execute("INSERT INTO pack_settings (packId, enabled) VALUES ('core', 0)", [])
+            .unwrap();
+        let packs = PackSet::load(&dir).unwrap();
+        assert!(packs.entries_for_term("ཀ་").unwrap().is_empty());
+        assert!(packs.all_terms().unwrap().is_empty());
+    }
+}
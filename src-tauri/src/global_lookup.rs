@@ -0,0 +1,415 @@
+//! The global lookup hotkey, from key press to popup.
+//!
+//! Pressing the hotkey copies the selection of the frontmost app (by
+//! sending it the copy shortcut), then puts back what the clipboard held
+//! before, so a lookup doesn't cost the user their clipboard. Plain text,
+//! HTML with its plain text, images and file lists are restored; formats
+//! the clipboard library can't read (RTF on its own, app-private data) are
+//! lost. Without a selection, the text already on the clipboard is looked
+//! up, as before.
+//!
+//! The first Tibetan phrase of the text is split into words
+//! (tibetan::segment) and the first known word looked up. The popup gets
+//! all of it with its "panel-shown" event instead of reading the clipboard.
+//!
+//! Where the popup opens is up to popup_placement.
+//!
+//! On macOS the popup is an NSPanel (see lookup_panel.rs), so that it shows
+//! over fullscreen apps without activating ours.
+
+use crate::popup_placement::{self, PopupSettings};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+#[cfg(target_os = "macos")]
+use tauri_nspanel::ManagerExt;
+
+#[cfg(desktop)]
+use crate::packs::PackEntry;
+#[cfg(desktop)]
+use crate::repository::{PackSet, Repository};
+#[cfg(desktop)]
+use crate::tibetan::{tibetan_runs, Segment};
+#[cfg(desktop)]
+use arboard::{Clipboard, ImageData};
+#[cfg(desktop)]
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+#[cfg(desktop)]
+use serde::Serialize;
+#[cfg(desktop)]
+use std::path::PathBuf;
+#[cfg(desktop)]
+use std::time::{Duration, Instant};
+#[cfg(desktop)]
+use tauri_plugin_clipboard_manager::ClipboardExt;
+#[cfg(desktop)]
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+const POPUP_WINDOW: &str = "global-lookup-popup";
+
+/// How long the other app gets to put its selection on the clipboard
+#[cfg(desktop)]
+const COPY_TIMEOUT: Duration = Duration::from_millis(400);
+
+#[cfg(desktop)]
+const COPY_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How much longer to keep checking, when nothing was copied in time,
+/// before the clipboard is restored: a slow app's copy landing after the
+/// restore would replace it for good
+#[cfg(desktop)]
+const LATE_COPY_GRACE: Duration = Duration::from_millis(250);
+
+/// The registered hotkey, as given by the frontend, and the popup settings
+#[derive(Default)]
+pub struct GlobalLookupState {
+    hotkey: Mutex<Option<String>>,
+    popup: Mutex<Option<PopupSettings>>,
+}
+
+/// What the popup shows for a hotkey press
+#[cfg(desktop)]
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SelectionLookup {
+    /// The selected text, or the clipboard's without a selection
+    text: String,
+    /// The first Tibetan phrase in `text`
+    phrase: Option<String>,
+    segments: Vec<Segment>,
+    /// First dictionary word of the phrase, and its entries
+    term: Option<String>,
+    entries: Vec<PackEntry>,
+}
+
+/// Clipboard contents saved while the selection is copied
+#[cfg(desktop)]
+enum ClipboardContents {
+    Files(Vec<PathBuf>),
+    Html { html: String, text: Option<String> },
+    Text(String),
+    Image(ImageData<'static>),
+    Empty,
+}
+
+#[cfg(desktop)]
+impl ClipboardContents {
+    /// The richest format on the clipboard: a copied file list or web page
+    /// usually comes with plain text too
+    fn read() -> Self {
+        let Ok(mut clipboard) = Clipboard::new() else {
+            return ClipboardContents::Empty;
+        };
+        if let Ok(files) = clipboard.get().file_list() {
+            if !files.is_empty() {
+                return ClipboardContents::Files(files);
+            }
+        }
+        let text = clipboard.get_text().ok().filter(|text| !text.is_empty());
+        if let Ok(html) = clipboard.get().html() {
+            if !html.is_empty() {
+                return ClipboardContents::Html { html, text };
+            }
+        }
+        if let Some(text) = text {
+            return ClipboardContents::Text(text);
+        }
+        match clipboard.get_image() {
+            Ok(image) => ClipboardContents::Image(image),
+            Err(_) => ClipboardContents::Empty,
+        }
+    }
+
+    /// Plain text to look up when nothing is selected
+    fn text(self) -> Option<String> {
+        match self {
+            ClipboardContents::Html { text, .. } => text,
+            ClipboardContents::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// On Linux, what this sets stays served after `clipboard` is dropped
+    /// because the clipboard plugin keeps its own instance open
+    fn restore(&self) {
+        let result = Clipboard::new().and_then(|mut clipboard| match self {
+            ClipboardContents::Files(files) => clipboard.set().file_list(files.as_slice()),
+            ClipboardContents::Html { html, text } => clipboard.set_html(html.as_str(), text.as_deref()),
+            ClipboardContents::Text(text) => clipboard.set_text(text.as_str()),
+            ClipboardContents::Image(image) => clipboard.set_image(image.clone()),
+            ClipboardContents::Empty => clipboard.clear(),
+        });
+        if let Err(e) = result {
+            eprintln!("[global_lookup] Failed to restore the clipboard: {}", e);
+        }
+    }
+}
+
+/// Send Cmd+C (Ctrl+C elsewhere) to the frontmost app
+#[cfg(desktop)]
+fn send_copy_shortcut() -> Result<(), String> {
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| format!("Failed to simulate keys: {}", e))?;
+    // The hotkey's own modifiers may still be down and would turn Cmd+C
+    // into, say, Cmd+Shift+C
+    for key in [Key::Shift, Key::Alt, Key::Control, Key::Meta] {
+        let _ = enigo.key(key, Direction::Release);
+    }
+    let modifier = if cfg!(target_os = "macos") { Key::Meta } else { Key::Control };
+    let result = enigo
+        .key(modifier, Direction::Press)
+        .and_then(|()| enigo.key(Key::Unicode('c'), Direction::Click));
+    let _ = enigo.key(modifier, Direction::Release);
+    result.map_err(|e| format!("Failed to send the copy shortcut: {}", e))
+}
+
+/// Text the other app copied, checked until COPY_TIMEOUT and then, as long
+/// as nothing landed, LATE_COPY_GRACE. Without a selection nothing ever
+/// does, so such a press waits both (650 ms) before the popup opens.
+#[cfg(desktop)]
+fn wait_for_copied_text(app: &AppHandle) -> Option<String> {
+    let deadline = Instant::now() + COPY_TIMEOUT + LATE_COPY_GRACE;
+    loop {
+        if let Ok(text) = app.clipboard().read_text() {
+            if !text.is_empty() {
+                return Some(text);
+            }
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        std::thread::sleep(COPY_POLL_INTERVAL);
+    }
+}
+
+/// The selected text, or the clipboard's when nothing is selected, leaving
+/// the clipboard as it was
+#[cfg(desktop)]
+fn capture_selection(app: &AppHandle) -> Option<String> {
+    let previous = ClipboardContents::read();
+    // Emptied first, so that anything on it afterwards is the selection
+    let _ = app.clipboard().clear();
+    let copied = match send_copy_shortcut() {
+        Ok(()) => wait_for_copied_text(app),
+        Err(e) => {
+            eprintln!("[global_lookup] {}", e);
+            None
+        }
+    };
+    previous.restore();
+
+    let text = copied.or_else(|| previous.text())?;
+    let text = text.trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(desktop)]
+fn look_up_text(app: &AppHandle, text: String) -> SelectionLookup {
+    let phrase = tibetan_runs(&text)
+        .first()
+        .map(|&(start, end)| text[start..end].to_string());
+    let mut lookup = SelectionLookup {
+        text,
+        phrase,
+        ..Default::default()
+    };
+    let Some(phrase) = &lookup.phrase else {
+        return lookup;
+    };
+
+    let result = PackSet::load(app).and_then(|packs| {
+        let segments = packs.segment(phrase)?;
+        let term = segments.iter().find(|segment| segment.known).map(|segment| segment.text.clone());
+        let entries = match &term {
+            Some(term) => packs.entries_for_term(term)?,
+            None => Vec::new(),
+        };
+        Ok((segments, term, entries))
+    });
+    match result {
+        Ok((segments, term, entries)) => {
+            lookup.segments = segments;
+            lookup.term = term;
+            lookup.entries = entries;
+        }
+        Err(e) => eprintln!("[global_lookup] Lookup failed: {}", e),
+    }
+    lookup
+}
+
+#[cfg(desktop)]
+fn on_hotkey(app: &AppHandle) {
+    let app = app.clone();
+    // Off the event loop: copying waits on the other app
+    std::thread::spawn(move || {
+        let lookup = capture_selection(&app).map(|text| look_up_text(&app, text));
+        let handle = app.clone();
+        let shown = app.run_on_main_thread(move || {
+            if let Err(e) = open_popup(&handle, lookup.as_ref()) {
+                eprintln!("[global_lookup] Failed to show the popup: {}", e);
+            }
+        });
+        if let Err(e) = shown {
+            eprintln!("[global_lookup] Failed to show the popup: {}", e);
+        }
+    });
+}
+
+/// The popup settings, loaded on first use
+fn with_popup_settings<T>(app: &AppHandle, f: impl FnOnce(&mut PopupSettings) -> T) -> T {
+    let state = app.state::<GlobalLookupState>();
+    let mut popup = state.popup.lock().unwrap_or_else(|e| e.into_inner());
+    f(popup.get_or_insert_with(|| popup_placement::load_settings(app)))
+}
+
+/// Save where the popup is before it moves or hides
+fn remember_popup_placement(app: &AppHandle, window: &WebviewWindow) {
+    with_popup_settings(app, |settings| {
+        if popup_placement::remember(window, settings) {
+            if let Err(e) = popup_placement::save_settings(app, settings) {
+                eprintln!("[global_lookup] {}", e);
+            }
+        }
+    });
+}
+
+pub(crate) fn popup_pinned(app: &AppHandle) -> bool {
+    with_popup_settings(app, |settings| settings.pinned)
+}
+
+/// Place the popup by the cursor (see popup_placement), show it and send it
+/// `lookup` (None when there was nothing to look up; the popup then keeps
+/// what it showed last)
+#[cfg(desktop)]
+pub(crate) fn open_popup(app: &AppHandle, lookup: Option<&SelectionLookup>) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(POPUP_WINDOW) {
+        remember_popup_placement(app, &window);
+        with_popup_settings(app, |settings| popup_placement::place(app, &window, settings));
+    }
+    show_window(app)?;
+    let _ = app.emit_to(POPUP_WINDOW, "panel-shown", lookup);
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn show_window(app: &AppHandle) -> Result<(), String> {
+    // Use the panel's show method which properly handles key window
+    if let Ok(panel) = app.get_webview_panel(POPUP_WINDOW) {
+        panel.show();
+        // Make sure it becomes the key window to receive keyboard input
+        panel.make_key_window();
+    } else if let Some(window) = app.get_webview_window(POPUP_WINDOW) {
+        // Fallback to regular window show if panel not found (first time before conversion)
+        window.show().map_err(|e| e.to_string())?;
+        window.set_focus().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(all(desktop, not(target_os = "macos")))]
+fn show_window(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(POPUP_WINDOW) {
+        window.show().map_err(|e| e.to_string())?;
+        window.set_focus().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(desktop)]
+fn register_hotkey(app: &AppHandle, previous: Option<&str>, hotkey: Option<&str>) -> Result<(), String> {
+    let shortcuts = app.global_shortcut();
+    if let Some(previous) = previous {
+        let _ = shortcuts.unregister(previous);
+    }
+    let Some(hotkey) = hotkey else {
+        return Ok(());
+    };
+
+    let shortcut: Shortcut = hotkey
+        .parse()
+        .map_err(|e| format!("Invalid hotkey {}: {}", hotkey, e))?;
+    // Still registered if the frontend reloaded without turning it off
+    if shortcuts.is_registered(hotkey) {
+        let _ = shortcuts.unregister(hotkey);
+    }
+    shortcuts
+        .on_shortcut(shortcut, |app, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                on_hotkey(app);
+            }
+        })
+        .map_err(|e| format!("Failed to register hotkey {}: {}", hotkey, e))
+}
+
+#[cfg(mobile)]
+fn register_hotkey(_app: &AppHandle, _previous: Option<&str>, _hotkey: Option<&str>) -> Result<(), String> {
+    Err("Global lookup is only available on desktop".to_string())
+}
+
+/// Use `hotkey` (e.g. "CommandOrControl+Shift+C") for global lookup in
+/// place of the previous one; None turns global lookup off. Sends
+/// "global-lookup-changed" with whether it is on.
+#[tauri::command]
+pub fn set_global_lookup_hotkey(
+    app: AppHandle,
+    hotkey: Option<String>,
+    state: State<'_, GlobalLookupState>,
+) -> Result<(), String> {
+    let mut current = state.hotkey.lock().unwrap_or_else(|e| e.into_inner());
+    register_hotkey(&app, current.as_deref(), hotkey.as_deref())?;
+    *current = hotkey;
+    let _ = app.emit("global-lookup-changed", current.is_some());
+    Ok(())
+}
+
+/// Hide the popup, remembering where it was
+pub(crate) fn hide_popup(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(POPUP_WINDOW) {
+        remember_popup_placement(app, &window);
+    }
+    hide_window(app)
+}
+
+// macOS: hide the panel without affecting focus (uses orderOut)
+#[cfg(target_os = "macos")]
+fn hide_window(app: &AppHandle) -> Result<(), String> {
+    // Use the panel's hide method which calls orderOut: and doesn't affect focus
+    if let Ok(panel) = app.get_webview_panel(POPUP_WINDOW) {
+        panel.hide();
+    } else if let Some(window) = app.get_webview_window(POPUP_WINDOW) {
+        // Fallback to regular window hide if panel not found
+        window.hide().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn hide_window(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(POPUP_WINDOW) {
+        window.hide().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn hide_lookup_panel(app: AppHandle) -> Result<(), String> {
+    hide_popup(&app)
+}
+
+/// Whether the popup stays open when it loses focus and reopens where it
+/// was left (see popup_placement)
+#[tauri::command]
+pub fn get_lookup_popup_pinned(app: AppHandle) -> bool {
+    popup_pinned(&app)
+}
+
+#[tauri::command]
+pub fn set_lookup_popup_pinned(app: AppHandle, pinned: bool) -> Result<(), String> {
+    let window = app.get_webview_window(POPUP_WINDOW);
+    with_popup_settings(&app, |settings| {
+        settings.pinned = pinned;
+        // Pinned in place: that place is where it reopens
+        if let Some(window) = &window {
+            popup_placement::remember(window, settings);
+        }
+        popup_placement::save_settings(&app, settings)
+    })
+}
@@ -0,0 +1,479 @@
+//! MDict .mdx: a UTF-16 XML header, then a key section (headwords with
+//! offsets into the records) and a record section (definitions), both
+//! stored as compressed blocks. Layout as documented by readmdict; LZO
+//! blocks and registration-key encryption are not supported.
+
+use super::ImportedDictionary;
+use crate::custom_packs::{read_within_limits, InstallLimits};
+use crate::markup::{html_to_text, parse_xml};
+use flate2::read::ZlibDecoder;
+use std::collections::HashMap;
+
+/// Placeholder title left by MdxBuilder when none was given
+const DEFAULT_TITLE: &str = "Title (No HTML code allowed)";
+
+const LINK_PREFIX: &str = "@@@LINK=";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Encoding {
+    Utf8,
+    Utf16,
+}
+
+impl Encoding {
+    /// Bytes per code unit, for sizes given in characters
+    fn unit(self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16 => 2,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Utf16 => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+    /// Numbers are 8 bytes from format 2.0 on, 4 bytes before
+    wide: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], wide: bool) -> Self {
+        Reader { data, at: 0, wide }
+    }
+
+    fn is_done(&self) -> bool {
+        self.at >= self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.at..self.at.saturating_add(len))
+            .ok_or("truncated .mdx file")?;
+        self.at += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<usize, String> {
+        Ok(self.take(1)?[0] as usize)
+    }
+
+    fn u16(&mut self) -> Result<usize, String> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]) as usize)
+    }
+
+    fn u32(&mut self) -> Result<usize, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        if !self.wide {
+            return self.u32();
+        }
+        let b = self.take(8)?;
+        let value = u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+        usize::try_from(value).map_err(|_| "size out of range".to_string())
+    }
+
+    /// Key-block text size: u16 from format 2.0 on, u8 before
+    fn text_size(&mut self) -> Result<usize, String> {
+        if self.wide {
+            self.u16()
+        } else {
+            self.u8()
+        }
+    }
+}
+
+/// Blocks start with a little-endian compression type and an Adler-32
+fn decompress_block(block: &[u8], limits: &InstallLimits) -> Result<Vec<u8>, String> {
+    if block.len() < 8 {
+        return Err("truncated block".into());
+    }
+    match u32::from_le_bytes([block[0], block[1], block[2], block[3]]) {
+        0 => Ok(block[8..].to_vec()),
+        1 => Err("LZO-compressed .mdx files are not supported".into()),
+        2 => read_within_limits(ZlibDecoder::new(&block[8..]), block.len() as u64, limits).map_err(|e| e.message),
+        other => Err(format!("unknown block compression {other}")),
+    }
+}
+
+/// RIPEMD-128, only used to derive the key-block-info key
+fn ripemd128(message: &[u8]) -> [u8; 16] {
+    const R_LEFT: [usize; 64] = [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, //
+        7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5, 2, 14, 11, 8, //
+        3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12, //
+        1, 9, 11, 10, 0, 8, 12, 4, 13, 3, 7, 15, 14, 5, 6, 2,
+    ];
+    const R_RIGHT: [usize; 64] = [
+        5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12, //
+        6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12, 4, 9, 1, 2, //
+        15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13, //
+        8, 6, 4, 1, 3, 11, 15, 0, 5, 12, 2, 13, 9, 7, 10, 14,
+    ];
+    const S_LEFT: [u32; 64] = [
+        11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8, //
+        7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15, 9, 11, 7, 13, 12, //
+        11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5, //
+        11, 12, 14, 15, 14, 15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12,
+    ];
+    const S_RIGHT: [u32; 64] = [
+        8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6, //
+        9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12, 7, 6, 15, 13, 11, //
+        9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5, //
+        15, 5, 8, 11, 14, 14, 6, 14, 6, 9, 12, 9, 12, 5, 15, 8,
+    ];
+    const K_LEFT: [u32; 4] = [0x0000_0000, 0x5a82_7999, 0x6ed9_eba1, 0x8f1b_bcdc];
+    const K_RIGHT: [u32; 4] = [0x50a2_8be6, 0x5c4d_d124, 0x6d70_3ef3, 0x0000_0000];
+
+    fn f(round: usize, x: u32, y: u32, z: u32) -> u32 {
+        match round {
+            0 => x ^ y ^ z,
+            1 => (x & y) | (!x & z),
+            2 => (x | !y) ^ z,
+            _ => (x & z) | (y & !z),
+        }
+    }
+
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((message.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut h: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    for chunk in padded.chunks_exact(64) {
+        let x: Vec<u32> = chunk
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+
+        let [mut a, mut b, mut c, mut d] = h;
+        let [mut a2, mut b2, mut c2, mut d2] = h;
+        for j in 0..64 {
+            let round = j / 16;
+            let t = a
+                .wrapping_add(f(round, b, c, d))
+                .wrapping_add(x[R_LEFT[j]])
+                .wrapping_add(K_LEFT[round])
+                .rotate_left(S_LEFT[j]);
+            (a, d, c, b) = (d, c, b, t);
+
+            let t = a2
+                .wrapping_add(f(3 - round, b2, c2, d2))
+                .wrapping_add(x[R_RIGHT[j]])
+                .wrapping_add(K_RIGHT[round])
+                .rotate_left(S_RIGHT[j]);
+            (a2, d2, c2, b2) = (d2, c2, b2, t);
+        }
+
+        let t = h[1].wrapping_add(c).wrapping_add(d2);
+        h[1] = h[2].wrapping_add(d).wrapping_add(a2);
+        h[2] = h[3].wrapping_add(a).wrapping_add(b2);
+        h[3] = h[0].wrapping_add(b).wrapping_add(c2);
+        h[0] = t;
+    }
+
+    let mut digest = [0u8; 16];
+    for (i, word) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+/// Undo the "Encrypted=2" scrambling of the key block info: the key is
+/// RIPEMD-128 of the block checksum followed by 0x3695.
+fn decrypt_key_block_info(block: &mut [u8]) {
+    if block.len() < 8 {
+        return;
+    }
+    let mut seed = block[4..8].to_vec();
+    seed.extend_from_slice(&0x3695u32.to_le_bytes());
+    let key = ripemd128(&seed);
+
+    let mut previous = 0x36u8;
+    for (i, byte) in block[8..].iter_mut().enumerate() {
+        let encrypted = *byte;
+        *byte = encrypted.rotate_left(4) ^ previous ^ (i as u8) ^ key[i % key.len()];
+        previous = encrypted;
+    }
+}
+
+/// (record offset, headword) pairs of a decompressed key block
+fn parse_key_block(block: &[u8], wide: bool, encoding: Encoding) -> Result<Vec<(usize, String)>, String> {
+    let unit = encoding.unit();
+    let mut reader = Reader::new(block, wide);
+    let mut keys = Vec::new();
+    while !reader.is_done() {
+        let offset = reader.number()?;
+        let rest = &block[reader.at..];
+        let len = rest
+            .chunks(unit)
+            .position(|c| c.iter().all(|b| *b == 0))
+            .map_or(rest.len(), |units| units * unit);
+        keys.push((offset, encoding.decode(&rest[..len])));
+        reader.take((len + unit).min(rest.len()))?;
+    }
+    Ok(keys)
+}
+
+pub(super) fn parse(data: &[u8], limits: &InstallLimits) -> Result<ImportedDictionary, String> {
+    let mut reader = Reader::new(data, false);
+
+    // Header: big-endian size, UTF-16LE XML attributes, little-endian Adler-32
+    let header_size = reader.u32()?;
+    let header = Encoding::Utf16.decode(reader.take(header_size)?);
+    reader.take(4)?;
+    let header = parse_xml(header.trim_end_matches('\0'))?;
+    let header = header.find("Dictionary").ok_or("not an MDict .mdx file")?;
+    let attribute = |name: &str| header.attribute(name).unwrap_or_default().trim().to_string();
+
+    let version: f32 = attribute("GeneratedByEngineVersion").parse().unwrap_or(2.0);
+    reader.wide = version >= 2.0;
+    let wide = reader.wide;
+
+    let encrypted: u32 = match attribute("Encrypted").as_str() {
+        "" | "No" => 0,
+        "Yes" => 1,
+        other => other.parse().unwrap_or(0),
+    };
+    if encrypted & 1 != 0 {
+        return Err("this .mdx needs a registration key".into());
+    }
+
+    let encoding = match attribute("Encoding").to_lowercase().as_str() {
+        "" | "utf-8" | "utf8" => Encoding::Utf8,
+        "utf-16" | "utf-16le" | "utf16" => Encoding::Utf16,
+        other => return Err(format!("{other} encoding is not supported")),
+    };
+    let is_html = !attribute("Format").eq_ignore_ascii_case("text");
+
+    // Key section
+    let key_block_count = reader.number()?;
+    reader.number()?; // entries
+    if wide {
+        reader.number()?; // decompressed key block info size
+    }
+    let key_block_info_size = reader.number()?;
+    let key_blocks_size = reader.number()?;
+    if wide {
+        reader.take(4)?; // Adler-32 of the numbers above
+    }
+
+    let key_block_info = reader.take(key_block_info_size)?;
+    let key_block_info = if wide {
+        let mut block = key_block_info.to_vec();
+        if encrypted & 2 != 0 {
+            decrypt_key_block_info(&mut block);
+        }
+        decompress_block(&block, limits)?
+    } else {
+        key_block_info.to_vec()
+    };
+
+    // Compressed size of each key block; the first and last headwords are
+    // skipped, terminated by a NUL code unit from format 2.0 on. Block counts
+    // come from the file, so nothing is allocated ahead from them.
+    let unit = encoding.unit();
+    let terminator = if wide { unit } else { 0 };
+    let mut info = Reader::new(&key_block_info, wide);
+    let mut key_block_sizes = Vec::new();
+    for _ in 0..key_block_count {
+        info.number()?; // entries in the block
+        let first = info.text_size()?;
+        info.take(first * unit + terminator)?;
+        let last = info.text_size()?;
+        info.take(last * unit + terminator)?;
+        key_block_sizes.push(info.number()?);
+        info.number()?; // decompressed size
+    }
+
+    let mut key_blocks = Reader::new(reader.take(key_blocks_size)?, wide);
+    let mut keys = Vec::new();
+    for size in key_block_sizes {
+        let block = decompress_block(key_blocks.take(size)?, limits)?;
+        keys.extend(parse_key_block(&block, wide, encoding)?);
+    }
+
+    // Record section: all blocks decompressed back to back, key offsets
+    // index into the whole
+    let record_block_count = reader.number()?;
+    reader.number()?; // entries
+    reader.number()?; // record block info size
+    reader.number()?; // record blocks size
+    let mut record_block_sizes = Vec::new();
+    for _ in 0..record_block_count {
+        record_block_sizes.push(reader.number()?);
+        reader.number()?; // decompressed size
+    }
+    let mut records = Vec::new();
+    for size in record_block_sizes {
+        records.extend(decompress_block(reader.take(size)?, limits)?);
+    }
+
+    let mut raw: Vec<(String, String)> = Vec::with_capacity(keys.len());
+    for (i, (offset, headword)) in keys.iter().enumerate() {
+        let start = (*offset).min(records.len());
+        let end = keys
+            .get(i + 1)
+            .map_or(records.len(), |(next, _)| *next)
+            .clamp(start, records.len());
+        let text = encoding.decode(&records[start..end]);
+        raw.push((headword.clone(), text.trim_end_matches('\0').trim().to_string()));
+    }
+
+    // "@@@LINK=other" entries redirect to another headword's definition
+    let by_headword: HashMap<&str, &str> = raw
+        .iter()
+        .filter(|(_, text)| !text.starts_with(LINK_PREFIX))
+        .map(|(headword, text)| (headword.as_str(), text.as_str()))
+        .collect();
+    let entries = raw
+        .iter()
+        .filter_map(|(headword, text)| {
+            let text = match text.strip_prefix(LINK_PREFIX) {
+                Some(target) => by_headword.get(target.trim())?,
+                None => text.as_str(),
+            };
+            let definition = if is_html { html_to_text(text) } else { text.to_string() };
+            Some((headword.clone(), definition))
+        })
+        .collect();
+
+    let name = Some(attribute("Title")).filter(|title| !title.is_empty() && title.as_str() != DEFAULT_TITLE);
+
+    Ok(ImportedDictionary { name, entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(n: usize) -> Vec<u8> {
+        (n as u64).to_be_bytes().to_vec()
+    }
+
+    /// An uncompressed block: type 0 and an (unchecked) Adler-32
+    fn block(data: &[u8]) -> Vec<u8> {
+        let mut block = vec![0, 0, 0, 0, 0, 0, 0, 0];
+        block.extend_from_slice(data);
+        block
+    }
+
+    fn header(attributes: &str) -> Vec<u8> {
+        let xml = format!("<Dictionary {attributes}/>\0");
+        let utf16: Vec<u8> = xml.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+        let mut data = (utf16.len() as u32).to_be_bytes().to_vec();
+        data.extend(utf16);
+        data.extend([0; 4]);
+        data
+    }
+
+    /// A format 2.0, UTF-8 .mdx with one key block and one record block
+    fn mdx(attributes: &str, entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut keys = Vec::new();
+        let mut records = Vec::new();
+        for (headword, definition) in entries {
+            keys.extend(number(records.len()));
+            keys.extend(headword.as_bytes());
+            keys.push(0);
+            records.extend(definition.as_bytes());
+            records.push(0);
+        }
+        let key_block = block(&keys);
+        let record_block = block(&records);
+
+        let (first, last) = (entries[0].0, entries[entries.len() - 1].0);
+        let mut info = number(entries.len());
+        for headword in [first, last] {
+            info.extend((headword.len() as u16).to_be_bytes());
+            info.extend(headword.as_bytes());
+            info.push(0);
+        }
+        info.extend(number(key_block.len()));
+        info.extend(number(keys.len()));
+        let info = block(&info);
+
+        let mut data = header(attributes);
+        data.extend(number(1));
+        data.extend(number(entries.len()));
+        data.extend(number(info.len()));
+        data.extend(number(info.len()));
+        data.extend(number(key_block.len()));
+        data.extend([0; 4]);
+        data.extend(info);
+        data.extend(key_block);
+
+        data.extend(number(1));
+        data.extend(number(entries.len()));
+        data.extend(number(16));
+        data.extend(number(record_block.len()));
+        data.extend(number(record_block.len()));
+        data.extend(number(records.len()));
+        data.extend(record_block);
+        data
+    }
+
+    const TEXT: &str = r#"GeneratedByEngineVersion="2.0" Encrypted="0" Encoding="UTF-8" Format="Text" Title="Test""#;
+
+    #[test]
+    fn reads_headwords_and_definitions() {
+        let data = mdx(TEXT, &[("ཀ་", "the letter ka"), ("ཁ་", "the letter kha")]);
+        let dictionary = parse(&data, &InstallLimits::default()).unwrap();
+        assert_eq!(dictionary.name.as_deref(), Some("Test"));
+        assert_eq!(
+            dictionary.entries,
+            vec![
+                ("ཀ་".to_string(), "the letter ka".to_string()),
+                ("ཁ་".to_string(), "the letter kha".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_links_and_flattens_html() {
+        let attributes = TEXT.replace(r#"Format="Text""#, r#"Format="Html""#);
+        let data = mdx(&attributes, &[("ཀ་", "<b>ka</b><br>letter"), ("ཀཿ", "@@@LINK=ཀ་")]);
+        let dictionary = parse(&data, &InstallLimits::default()).unwrap();
+        assert_eq!(dictionary.entries[1], ("ཀཿ".to_string(), "ka\nletter".to_string()));
+    }
+
+    #[test]
+    fn rejects_encrypted_and_unknown_files() {
+        let encrypted = TEXT.replace(r#"Encrypted="0""#, r#"Encrypted="1""#);
+        assert!(parse(&mdx(&encrypted, &[("ཀ་", "ka")]), &InstallLimits::default()).is_err());
+
+        let mut data = mdx(TEXT, &[("ཀ་", "ka")]);
+        data.truncate(data.len() - 10);
+        assert!(parse(&data, &InstallLimits::default()).is_err());
+
+        assert!(parse(&header("Title=\"x\""), &InstallLimits::default()).is_err());
+        assert!(parse(b"not an mdx", &InstallLimits::default()).is_err());
+    }
+
+    #[test]
+    fn rejects_block_counts_the_file_cannot_hold() {
+        let mut data = header(TEXT);
+        data.extend(number(usize::MAX / 2));
+        data.extend([0; 40]);
+        assert!(parse(&data, &InstallLimits::default()).is_err());
+    }
+}
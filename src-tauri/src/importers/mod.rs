@@ -0,0 +1,189 @@
+//! Third-party dictionary formats as custom packs.
+//!
+//! Each importer reads headwords and plain-text definitions out of its
+//! format; entries whose headword is Tibetan become a one-dictionary
+//! .tibdict, installed through the regular installer (same error codes,
+//! same `force` conflict handling). Supported: StarDict (.ifo next to its
+//! .idx/.dict[.dz], or all of them in a .zip), XDXF, MDict .mdx and TEI-lite
+//! XML.
+
+mod mdict;
+mod stardict;
+mod tei;
+mod xdxf;
+
+#[cfg(feature = "app")]
+use crate::custom_packs::{install_from_bytes, InstalledCustomPack};
+use crate::custom_packs::{InstallError, InstallLimits};
+use crate::tibdict_builder::{build_tibdict_bytes, slug_id, TibdictEntry, TibdictMetadata};
+use crate::tibetan::tibetan_runs;
+use serde::Deserialize;
+use std::collections::HashSet;
+#[cfg(feature = "app")]
+use std::fs::File;
+#[cfg(feature = "app")]
+use std::io::Read;
+use std::path::Path;
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+
+/// Headwords and definitions read by an importer, in file order
+pub(crate) struct ImportedDictionary {
+    /// Title found in the file, if any
+    pub name: Option<String>,
+    pub entries: Vec<(String, String)>,
+}
+
+/// Optional pack metadata; defaults come from the dictionary's title or
+/// the file name.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DictionaryImportOptions {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Pick the importer from the extension, sniffing XML to tell XDXF from TEI.
+/// `path` is only needed for a bare .ifo, whose sibling files are read too.
+fn read_dictionary(
+    path: Option<&Path>,
+    file_name: &str,
+    bytes: &[u8],
+    limits: &InstallLimits,
+) -> Result<(ImportedDictionary, &'static str), String> {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match extension.as_str() {
+        "ifo" => {
+            let path = path.ok_or("a StarDict .ifo needs its .idx and .dict files: import them as a .zip")?;
+            Ok((stardict::parse(stardict::files_from_ifo(path, limits)?)?, "StarDict"))
+        }
+        "zip" if stardict::zip_contains_stardict(bytes) => {
+            Ok((stardict::parse(stardict::files_from_zip(bytes, limits)?)?, "StarDict"))
+        }
+        "mdx" => Ok((mdict::parse(bytes, limits)?, "MDict")),
+        "xdxf" => Ok((xdxf::parse(&String::from_utf8_lossy(bytes))?, "XDXF")),
+        "xml" | "tei" => {
+            let source = String::from_utf8_lossy(bytes);
+            if source.contains("<xdxf") {
+                Ok((xdxf::parse(&source)?, "XDXF"))
+            } else {
+                Ok((tei::parse(&source)?, "TEI"))
+            }
+        }
+        _ => Err(format!("unsupported dictionary file: {file_name}")),
+    }
+}
+
+/// Convert a dictionary file to .tibdict bytes, ready for the installer.
+/// The file is held to max_sqlite_bytes, and its compressed parts to
+/// `limits` as they are decompressed.
+fn convert_dictionary(
+    path: Option<&Path>,
+    file_name: &str,
+    bytes: &[u8],
+    options: DictionaryImportOptions,
+    limits: &InstallLimits,
+) -> Result<Vec<u8>, InstallError> {
+    if bytes.len() as u64 > limits.max_sqlite_bytes {
+        return Err(InstallError::new("limits", "dictionary file is larger than allowed"));
+    }
+    let (dictionary, format) =
+        read_dictionary(path, file_name, bytes, limits).map_err(|e| InstallError::new("format", &e))?;
+
+    let mut seen = HashSet::new();
+    let entries: Vec<TibdictEntry> = dictionary
+        .entries
+        .into_iter()
+        .filter(|(term, definition)| !definition.is_empty() && !tibetan_runs(term).is_empty())
+        .filter(|entry| seen.insert(entry.clone()))
+        .map(|(term, definition)| TibdictEntry {
+            term,
+            definition,
+            dictionary: None,
+            term_phonetics_strict: None,
+            term_phonetics_loose: None,
+            definition_phonetics_words_strict: None,
+            definition_phonetics_words_loose: None,
+        })
+        .collect();
+    if entries.is_empty() {
+        return Err(InstallError::new("format", &format!("no Tibetan headwords in this {format} dictionary")));
+    }
+
+    let file_stem = Path::new(file_name).file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let name = options
+        .name
+        .or(dictionary.name)
+        .unwrap_or_else(|| file_stem.to_string());
+    let id = options.id.unwrap_or_else(|| slug_id(&[&name, file_stem]));
+    let metadata = TibdictMetadata {
+        id,
+        name,
+        description: options.description.unwrap_or_else(|| format!("Imported from {format}")),
+        author: None,
+        version: None,
+        icon: None,
+        update_url: None,
+    };
+
+    build_tibdict_bytes(metadata, &entries)
+}
+
+/// Import a StarDict, XDXF, MDict or TEI dictionary as a custom pack.
+/// Errors use the same codes as install_custom_pack.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn import_dictionary(
+    app: AppHandle,
+    file_path: String,
+    force: Option<bool>,
+    options: Option<DictionaryImportOptions>,
+    limits: Option<InstallLimits>,
+) -> Result<InstalledCustomPack, InstallError> {
+    let limits = limits.unwrap_or_default();
+    let task_limits = limits.clone();
+    let bytes = tauri::async_runtime::spawn_blocking(move || {
+        let path = Path::new(&file_path);
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        // One byte over the cap is enough for convert_dictionary to refuse it
+        let mut bytes = Vec::new();
+        File::open(path)
+            .and_then(|file| file.take(task_limits.max_sqlite_bytes.saturating_add(1)).read_to_end(&mut bytes))
+            .map_err(|e| InstallError::new("path", &format!("read {file_path}: {e}")))?;
+        convert_dictionary(Some(path), file_name, &bytes, options.unwrap_or_default(), &task_limits)
+    })
+    .await
+    .map_err(|e| InstallError::new("path", &format!("import task: {e}")))??;
+    install_from_bytes(app, bytes, force, limits).await
+}
+
+/// Same as import_dictionary but takes the file's name and raw bytes, for
+/// the drag-drop path. StarDict dictionaries must be zipped.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn import_dictionary_from_bytes(
+    app: AppHandle,
+    data: Vec<u8>,
+    file_name: String,
+    force: Option<bool>,
+    options: Option<DictionaryImportOptions>,
+    limits: Option<InstallLimits>,
+) -> Result<InstalledCustomPack, InstallError> {
+    let limits = limits.unwrap_or_default();
+    let task_limits = limits.clone();
+    let bytes = tauri::async_runtime::spawn_blocking(move || {
+        convert_dictionary(None, &file_name, &data, options.unwrap_or_default(), &task_limits)
+    })
+    .await
+    .map_err(|e| InstallError::new("path", &format!("import task: {e}")))??;
+    install_from_bytes(app, bytes, force, limits).await
+}
@@ -0,0 +1,302 @@
+//! StarDict: `.ifo` (metadata), `.idx` (sorted headwords with offsets into
+//! the data file), `.dict` or dictzip'ed `.dict.dz` (definitions) and an
+//! optional `.syn` (extra headwords pointing to `.idx` entries).
+
+use super::ImportedDictionary;
+use crate::custom_packs::{read_within_limits, InstallLimits};
+use crate::markup::html_to_text;
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use zip::ZipArchive;
+
+pub(super) struct StarDictFiles {
+    ifo: String,
+    idx: Vec<u8>,
+    dict: Vec<u8>,
+    syn: Option<Vec<u8>>,
+}
+
+fn gunzip(data: &[u8], limits: &InstallLimits) -> Result<Vec<u8>, String> {
+    read_within_limits(GzDecoder::new(data), data.len() as u64, limits).map_err(|e| e.message)
+}
+
+/// Files by suffix (".ifo", ".idx.gz"...), decompressed into StarDictFiles
+fn assemble(mut files: HashMap<&'static str, Vec<u8>>, limits: &InstallLimits) -> Result<StarDictFiles, String> {
+    let ifo = files.remove(".ifo").ok_or("missing .ifo file")?;
+    let idx = match (files.remove(".idx"), files.remove(".idx.gz")) {
+        (Some(idx), _) => idx,
+        (None, Some(gz)) => gunzip(&gz, limits)?,
+        (None, None) => return Err("missing .idx file".into()),
+    };
+    let dict = match (files.remove(".dict"), files.remove(".dict.dz")) {
+        (Some(dict), _) => dict,
+        (None, Some(dz)) => gunzip(&dz, limits)?,
+        (None, None) => return Err("missing .dict file".into()),
+    };
+    Ok(StarDictFiles {
+        ifo: String::from_utf8_lossy(&ifo).into_owned(),
+        idx,
+        dict,
+        syn: files.remove(".syn"),
+    })
+}
+
+const SUFFIXES: [&str; 6] = [".ifo", ".idx", ".idx.gz", ".dict", ".dict.dz", ".syn"];
+
+/// Read the files sitting next to an .ifo
+pub(super) fn files_from_ifo(ifo_path: &Path, limits: &InstallLimits) -> Result<StarDictFiles, String> {
+    let stem = ifo_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("bad .ifo file name")?;
+    let dir = ifo_path.parent().ok_or("bad .ifo path")?;
+
+    let mut files = HashMap::new();
+    for suffix in SUFFIXES {
+        let path = dir.join(format!("{stem}{suffix}"));
+        if path.exists() {
+            files.insert(suffix, fs::read(&path).map_err(|e| format!("read {}: {e}", path.display()))?);
+        }
+    }
+    assemble(files, limits)
+}
+
+/// Read a ZIP holding the files of one StarDict dictionary
+pub(super) fn files_from_zip(bytes: &[u8], limits: &InstallLimits) -> Result<StarDictFiles, String> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("read zip: {e}"))?;
+    let names: Vec<String> = archive.file_names().map(String::from).collect();
+
+    let mut files = HashMap::new();
+    for suffix in SUFFIXES {
+        let Some(name) = names.iter().find(|n| n.ends_with(suffix)) else {
+            continue;
+        };
+        let entry = archive.by_name(name).map_err(|e| format!("zip entry: {e}"))?;
+        let compressed = entry.compressed_size();
+        files.insert(suffix, read_within_limits(entry, compressed, limits).map_err(|e| e.message)?);
+    }
+    assemble(files, limits)
+}
+
+pub(super) fn zip_contains_stardict(bytes: &[u8]) -> bool {
+    match ZipArchive::new(Cursor::new(bytes)) {
+        Ok(archive) => archive.file_names().any(|name| name.ends_with(".ifo")),
+        Err(_) => false,
+    }
+}
+
+fn parse_ifo(ifo: &str) -> HashMap<String, String> {
+    ifo.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// (headword, offset, size) triples of the .idx
+fn parse_idx(idx: &[u8], offset_bits: u32) -> Result<Vec<(String, usize, usize)>, String> {
+    let offset_len = if offset_bits == 64 { 8 } else { 4 };
+    let mut entries = Vec::new();
+    let mut at = 0;
+    while at < idx.len() {
+        let nul = idx[at..].iter().position(|b| *b == 0).ok_or("truncated .idx")?;
+        let word = String::from_utf8_lossy(&idx[at..at + nul]).into_owned();
+        at += nul + 1;
+        let offset = if offset_len == 8 {
+            read_u64(idx, at).map(|o| o as usize)
+        } else {
+            read_u32(idx, at).map(|o| o as usize)
+        }
+        .ok_or("truncated .idx")?;
+        let size = read_u32(idx, at + offset_len).ok_or("truncated .idx")? as usize;
+        at += offset_len + 4;
+        entries.push((word, offset, size));
+    }
+    Ok(entries)
+}
+
+/// Text of one typed field; markup types are flattened
+fn field_text(field_type: char, data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    match field_type {
+        'm' | 'l' | 't' | 'y' | 'w' => Some(text.trim().to_string()),
+        'h' | 'g' | 'x' | 'k' => Some(html_to_text(&text)),
+        // Resource lists, WordNet data and binary fields (uppercase types)
+        _ => None,
+    }
+}
+
+/// Split a data record into its text fields. With `sametypesequence` the
+/// types are given once in the .ifo; otherwise each field starts with its
+/// type. Lowercase types are NUL-terminated text (except the last field of
+/// a sametypesequence record), uppercase ones are size-prefixed binary data.
+fn record_text(record: &[u8], same_type_sequence: Option<&str>) -> String {
+    let mut fields = Vec::new();
+    let mut at = 0;
+    let types: Vec<char> = same_type_sequence.map(|s| s.chars().collect()).unwrap_or_default();
+    let mut type_index = 0;
+
+    while at < record.len() {
+        let field_type = if types.is_empty() {
+            let t = record[at] as char;
+            at += 1;
+            t
+        } else {
+            match types.get(type_index) {
+                Some(t) => *t,
+                None => break,
+            }
+        };
+        let last_of_sequence = !types.is_empty() && type_index == types.len() - 1;
+        type_index += 1;
+
+        let (data, next) = if last_of_sequence {
+            (&record[at..], record.len())
+        } else if field_type.is_ascii_lowercase() {
+            let len = record[at..].iter().position(|b| *b == 0).unwrap_or(record.len() - at);
+            (&record[at..at + len], at + len + 1)
+        } else {
+            let size = read_u32(record, at).unwrap_or(0) as usize;
+            let end = (at + 4 + size).min(record.len());
+            (&record[(at + 4).min(end)..end], end)
+        };
+        if let Some(text) = field_text(field_type, data) {
+            if !text.is_empty() {
+                fields.push(text);
+            }
+        }
+        at = next;
+    }
+    fields.join("\n")
+}
+
+pub(super) fn parse(files: StarDictFiles) -> Result<ImportedDictionary, String> {
+    let ifo = parse_ifo(&files.ifo);
+    if !files.ifo.starts_with("StarDict's dict ifo file") {
+        return Err("not a StarDict .ifo file".into());
+    }
+    let offset_bits = ifo.get("idxoffsetbits").and_then(|b| b.parse().ok()).unwrap_or(32);
+    let same_type_sequence = ifo.get("sametypesequence").map(String::as_str);
+
+    let index = parse_idx(&files.idx, offset_bits)?;
+    let mut entries = Vec::with_capacity(index.len());
+    for (word, offset, size) in &index {
+        let record = offset
+            .checked_add(*size)
+            .and_then(|end| files.dict.get(*offset..end))
+            .ok_or_else(|| format!("definition of {word} is out of bounds"))?;
+        entries.push((word.clone(), record_text(record, same_type_sequence)));
+    }
+
+    // Synonyms: headword, then the .idx position of the entry they stand for
+    if let Some(syn) = files.syn {
+        let mut at = 0;
+        while let Some(nul) = syn[at..].iter().position(|b| *b == 0) {
+            let word = String::from_utf8_lossy(&syn[at..at + nul]).into_owned();
+            at += nul + 1;
+            let Some(target) = read_u32(&syn, at) else {
+                break;
+            };
+            at += 4;
+            if let Some((_, definition)) = entries.get(target as usize) {
+                let definition = definition.clone();
+                entries.push((word, definition));
+            }
+        }
+    }
+
+    Ok(ImportedDictionary {
+        name: ifo.get("bookname").cloned(),
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const IFO: &str = "StarDict's dict ifo file\nversion=2.4.2\nbookname=Test\nsametypesequence=m\n";
+
+    fn idx_entry(word: &str, offset: u32, size: u32) -> Vec<u8> {
+        let mut entry = word.as_bytes().to_vec();
+        entry.push(0);
+        entry.extend(offset.to_be_bytes());
+        entry.extend(size.to_be_bytes());
+        entry
+    }
+
+    fn files(ifo: &str, idx: Vec<u8>, dict: &[u8], syn: Option<Vec<u8>>) -> StarDictFiles {
+        StarDictFiles { ifo: ifo.to_string(), idx, dict: dict.to_vec(), syn }
+    }
+
+    #[test]
+    fn reads_entries_and_synonyms() {
+        let mut idx = idx_entry("ཀ་", 0, 2);
+        idx.extend(idx_entry("ཁ་", 2, 3));
+        let mut syn = "ཀཿ".as_bytes().to_vec();
+        syn.push(0);
+        syn.extend(0u32.to_be_bytes());
+
+        let dictionary = parse(files(IFO, idx, b"kakha", Some(syn))).unwrap();
+        assert_eq!(dictionary.name.as_deref(), Some("Test"));
+        assert_eq!(
+            dictionary.entries,
+            vec![
+                ("ཀ་".to_string(), "ka".to_string()),
+                ("ཁ་".to_string(), "kha".to_string()),
+                ("ཀཿ".to_string(), "ka".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_typed_fields() {
+        let ifo = IFO.replace("sametypesequence=m\n", "");
+        let record = b"m<plain>\0hka<br>kha\0";
+        let dictionary = parse(files(&ifo, idx_entry("ཀ་", 0, record.len() as u32), record, None)).unwrap();
+        assert_eq!(dictionary.entries[0].1, "<plain>\nka\nkha");
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(parse(files("not an ifo", idx_entry("ཀ་", 0, 2), b"ka", None)).is_err());
+
+        let mut truncated = idx_entry("ཀ་", 0, 2);
+        truncated.truncate(truncated.len() - 3);
+        assert!(parse(files(IFO, truncated, b"ka", None)).is_err());
+
+        assert!(parse(files(IFO, idx_entry("ཀ་", 1, 2), b"ka", None)).is_err());
+
+        // A 64-bit offset whose end overflows
+        let ifo = format!("{IFO}idxoffsetbits=64\n");
+        let mut idx = "ཀ་".as_bytes().to_vec();
+        idx.push(0);
+        idx.extend(u64::MAX.to_be_bytes());
+        idx.extend(2u32.to_be_bytes());
+        assert!(parse(files(&ifo, idx, b"ka", None)).is_err());
+    }
+
+    #[test]
+    fn caps_decompressed_size() {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::best());
+        gz.write_all(&vec![0; 4 * 1024 * 1024]).unwrap();
+        let gz = gz.finish().unwrap();
+
+        assert!(gunzip(&gz, &InstallLimits::default()).is_err());
+        let lenient = InstallLimits { max_compression_ratio: u64::MAX, ..InstallLimits::default() };
+        assert_eq!(gunzip(&gz, &lenient).unwrap().len(), 4 * 1024 * 1024);
+    }
+}
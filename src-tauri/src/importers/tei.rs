@@ -0,0 +1,78 @@
+//! TEI-lite dictionaries (FreeDict style): `<entry>` elements with the
+//! headword in `<form><orth>` and the meanings in `<sense>`, either as
+//! `<def>` text or as `<cit type="trans"><quote>` translations.
+
+use super::ImportedDictionary;
+use crate::markup::{parse_xml, XmlElement};
+
+fn sense_text(sense: &XmlElement) -> String {
+    let translations: Vec<String> = sense
+        .find_all("cit")
+        .into_iter()
+        .filter(|cit| matches!(cit.attribute("type"), Some("trans" | "translation")))
+        .filter_map(|cit| cit.find("quote"))
+        .map(|quote| quote.text())
+        .filter(|text| !text.is_empty())
+        .collect();
+
+    let definitions: Vec<String> = sense
+        .find_all("def")
+        .into_iter()
+        .map(|def| def.text())
+        .filter(|text| !text.is_empty())
+        .collect();
+
+    match (translations.is_empty(), definitions.is_empty()) {
+        (true, true) => sense.text_excluding(&["note"]),
+        (false, true) => translations.join("; "),
+        (true, false) => definitions.join("\n"),
+        (false, false) => format!("{}\n{}", translations.join("; "), definitions.join("\n")),
+    }
+}
+
+pub(super) fn parse(source: &str) -> Result<ImportedDictionary, String> {
+    let document = parse_xml(source)?;
+
+    let name = document
+        .find("titleStmt")
+        .and_then(|title_statement| title_statement.find("title"))
+        .map(|title| title.text())
+        .filter(|name| !name.is_empty());
+
+    let entries_elements = document.find_all("entry");
+    if entries_elements.is_empty() {
+        return Err("no <entry> elements found".into());
+    }
+
+    let mut entries = Vec::new();
+    for entry in entries_elements {
+        let orths: Vec<String> = entry
+            .find_all("orth")
+            .into_iter()
+            .map(|orth| orth.text())
+            .filter(|orth| !orth.is_empty())
+            .collect();
+
+        let senses = entry.find_all("sense");
+        let definition = if senses.is_empty() {
+            entry.text_excluding(&["form"])
+        } else {
+            let texts: Vec<String> = senses.into_iter().map(sense_text).filter(|t| !t.is_empty()).collect();
+            match texts.len() {
+                1 => texts.into_iter().collect(),
+                _ => texts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, text)| format!("{}. {}", i + 1, text))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            }
+        };
+
+        for orth in orths {
+            entries.push((orth, definition.clone()));
+        }
+    }
+
+    Ok(ImportedDictionary { name, entries })
+}
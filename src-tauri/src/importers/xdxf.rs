@@ -0,0 +1,77 @@
+//! XDXF: `<xdxf>` documents made of `<ar>` articles, each with one or more
+//! `<k>` headwords followed by the definition.
+
+use super::ImportedDictionary;
+use crate::markup::parse_xml;
+
+pub(super) fn parse(source: &str) -> Result<ImportedDictionary, String> {
+    let document = parse_xml(source)?;
+    let root = document.find("xdxf").ok_or("not an XDXF document")?;
+
+    // <full_name> in the original format, <meta_info><full_title> in XDXF 1.0
+    let name = root
+        .find("full_name")
+        .or_else(|| root.find("full_title"))
+        .map(|element| element.text())
+        .filter(|name| !name.is_empty());
+
+    let mut entries = Vec::new();
+    for article in root.find_all("ar") {
+        let definition = article.text_excluding(&["k"]);
+        for key in article.find_all("k") {
+            // <opt> marks optional parts of the headword: keep the full form
+            let headword = key.text();
+            if !headword.is_empty() {
+                entries.push((headword, definition.clone()));
+            }
+        }
+    }
+
+    Ok(ImportedDictionary { name, entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_articles() {
+        let source = r#"<?xml version="1.0"?>
+            <xdxf lang_from="BOD" lang_to="ENG" format="visual">
+              <full_name>Test &amp; Co</full_name>
+              <ar><k>ཀ་</k><k>ཀཿ</k>the letter <i>ka</i></ar>
+              <ar><k>ཁ་</k>kha<br/>second line</ar>
+            </xdxf>"#;
+        let dictionary = parse(source).unwrap();
+        assert_eq!(dictionary.name.as_deref(), Some("Test & Co"));
+        assert_eq!(
+            dictionary.entries,
+            vec![
+                ("ཀ་".to_string(), "the letter ka".to_string()),
+                ("ཀཿ".to_string(), "the letter ka".to_string()),
+                ("ཁ་".to_string(), "kha\nsecond line".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tolerates_unbalanced_tags() {
+        let source = "<xdxf><ar><k>ཀ་</k>ka <b>bold</i></ar><ar><k>ཁ་</k>kha";
+        let dictionary = parse(source).unwrap();
+        assert_eq!(dictionary.entries.len(), 2);
+        assert_eq!(dictionary.entries[0].1, "ka bold");
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        assert!(parse("<tei><entry/></tei>").is_err());
+        assert!(parse("<xdxf><ar><k>ཀ་</k><!-- unterminated</ar></xdxf>").is_err());
+    }
+
+    #[test]
+    fn survives_deep_nesting() {
+        let source = format!("<xdxf><ar><k>ཀ་</k>{}ka</ar></xdxf>", "<b>".repeat(100_000));
+        let dictionary = parse(&source).unwrap();
+        assert_eq!(dictionary.entries, vec![("ཀ་".to_string(), "ka".to_string())]);
+    }
+}
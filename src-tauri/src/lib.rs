@@ -1,81 +1,53 @@
+// Without the app feature only cli.rs uses this crate, and the helpers
+// behind the Tauri commands go unused
+#![cfg_attr(not(feature = "app"), allow(dead_code))]
+
+mod apkg_import;
+#[cfg(feature = "app")]
+mod app;
+#[cfg(all(feature = "app", desktop))]
+mod app_menu;
+#[cfg(feature = "app")]
+mod browse;
+pub mod cli;
+mod collation;
 mod custom_packs;
+mod data_host;
+#[cfg(feature = "app")]
 mod database;
+#[cfg(feature = "app")]
+mod deep_links;
+mod dictionary_export;
+mod dictionary_settings;
+#[cfg(feature = "app")]
+mod global_lookup;
+mod importers;
+#[cfg(feature = "app")]
+mod lookup_panel;
+mod lookup_server;
+mod markup;
+mod pack_indexes;
+mod pack_migrations;
+mod pack_phonetics;
+mod pack_signing;
+mod pack_updates;
+mod pack_validation;
 mod packs;
+#[cfg(feature = "app")]
+mod popup_placement;
+mod query_sandbox;
+mod repository;
+mod reverse_index;
+mod sanskrit_index;
+#[cfg(feature = "app")]
 mod scans;
+mod term_links;
+mod tibdict_builder;
+mod tibetan;
+#[cfg(all(feature = "app", desktop))]
+mod tray;
+mod user_pack;
+mod verb_index;
 
-use custom_packs::{install_custom_pack, install_custom_pack_from_bytes, list_custom_packs, remove_custom_pack};
-use database::{
-    execute_query, get_all_terms, get_dictionaries, get_entries_for_term, init_database,
-    search_entries,
-};
-use packs::{
-    download_pack, ensure_pack_available, fetch_pack_manifest, get_installed_packs,
-    get_pack_database_size, get_pack_path, pack_execute_query, pack_get_all_terms,
-    pack_get_dictionaries, pack_get_entries_for_term, pack_search_entries, read_pack_database,
-    read_pack_database_chunk, remove_pack, supports_modular_packs, update_pack,
-};
-use scans::{check_scan_downloaded, delete_scan, download_scan_images, get_scan_image_data};
-
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    let mut builder = tauri::Builder::default()
-        .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_clipboard_manager::init());
-
-    // Add desktop-only plugins
-    #[cfg(desktop)]
-    {
-        builder = builder
-            .plugin(tauri_plugin_global_shortcut::Builder::new().build())
-            .plugin(tauri_plugin_updater::Builder::new().build())
-            .plugin(tauri_plugin_process::init());
-    }
-
-    // Add macOS permissions plugin (only on macOS)
-    #[cfg(target_os = "macos")]
-    {
-        builder = builder.plugin(tauri_plugin_macos_permissions::init());
-    }
-
-    builder
-        .invoke_handler(tauri::generate_handler![
-            // Database commands
-            init_database,
-            get_all_terms,
-            get_dictionaries,
-            get_entries_for_term,
-            search_entries,
-            execute_query,
-            // Scan commands
-            check_scan_downloaded,
-            get_scan_image_data,
-            download_scan_images,
-            delete_scan,
-            // Pack commands
-            fetch_pack_manifest,
-            get_installed_packs,
-            download_pack,
-            update_pack,
-            remove_pack,
-            get_pack_path,
-            read_pack_database,
-            read_pack_database_chunk,
-            get_pack_database_size,
-            ensure_pack_available,
-            supports_modular_packs,
-            // Native SQLite pack queries (for mobile performance)
-            pack_get_all_terms,
-            pack_get_entries_for_term,
-            pack_search_entries,
-            pack_get_dictionaries,
-            pack_execute_query,
-            // Custom pack commands
-            install_custom_pack,
-            install_custom_pack_from_bytes,
-            list_custom_packs,
-            remove_custom_pack,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
+#[cfg(feature = "app")]
+pub use app::{builder, handle_run_event, run};
@@ -0,0 +1,82 @@
+//! The global lookup popup as an NSPanel on macOS, so that it shows over
+//! fullscreen apps without activating ours. Other platforms keep a plain
+//! window and the command does nothing.
+
+#[cfg(target_os = "macos")]
+use crate::global_lookup;
+#[cfg(target_os = "macos")]
+use tauri::Manager;
+
+// macOS-only: NSPanel support for fullscreen overlay
+#[cfg(target_os = "macos")]
+use tauri_nspanel::{
+    tauri_panel, CollectionBehavior, PanelLevel, StyleMask, WebviewWindowExt,
+};
+
+// Define the panel type for the popup window (macOS only)
+#[cfg(target_os = "macos")]
+tauri_panel! {
+    panel!(LookupPanel {
+        config: {
+            can_become_key_window: true,
+            is_floating_panel: true
+        }
+    })
+
+    panel_event!(LookupPanelEventHandler {
+        window_did_resign_key(notification: &NSNotification) -> ()
+    })
+}
+
+// macOS-only: Configure popup window as NSPanel for fullscreen overlay
+#[cfg(target_os = "macos")]
+#[tauri::command]
+pub fn configure_window_for_fullscreen(app: tauri::AppHandle) -> Result<(), String> {
+    if let Some(popup_window) = app.get_webview_window("global-lookup-popup") {
+        // Convert the window to an NSPanel
+        let panel = popup_window
+            .to_panel::<LookupPanel>()
+            .map_err(|e| format!("Failed to convert window to panel: {:?}", e))?;
+
+        // Set floating level so it appears above other windows
+        panel.set_level(PanelLevel::Floating.value());
+
+        // Set non-activating style so it doesn't steal focus from other apps
+        // Also add resizable so users can resize the panel
+        panel.set_style_mask(StyleMask::empty().nonactivating_panel().resizable().into());
+
+        // Configure collection behavior for fullscreen overlay:
+        // - full_screen_auxiliary: appear on same space as fullscreen windows
+        // - can_join_all_spaces: appear on all desktop spaces
+        panel.set_collection_behavior(
+            CollectionBehavior::new()
+                .full_screen_auxiliary()
+                .can_join_all_spaces()
+                .into(),
+        );
+
+        // Set up event handler to auto-hide panel when it loses key window status
+        // This handles click-outside-to-close behavior
+        let handler = LookupPanelEventHandler::new();
+        let app_handle = app.clone();
+        handler.window_did_resign_key(move |_notification| {
+            // Hide the panel when it loses focus, unless the user pinned it
+            if !global_lookup::popup_pinned(&app_handle) {
+                println!("[GlobalLookupPopup] Panel resigned key window - hiding");
+                let _ = global_lookup::hide_popup(&app_handle);
+            }
+        });
+        panel.set_event_handler(Some(handler.as_ref()));
+
+        Ok(())
+    } else {
+        Err("Popup window not found".to_string())
+    }
+}
+
+// Stub for non-macOS platforms
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+pub fn configure_window_for_fullscreen(_app: tauri::AppHandle) -> Result<(), String> {
+    Ok(())
+}
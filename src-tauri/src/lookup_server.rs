@@ -0,0 +1,503 @@
+//! Opt-in HTTP/JSON server so that other tools on the same machine (CAT
+//! tools, notebooks, browser extensions) can look words up in the installed
+//! packs.
+//!
+//! It only listens on 127.0.0.1 and every request needs the token from
+//! `<app_data>/lookup-server.json` as `Authorization: Bearer <token>`. The
+//! app and the command-line tool share that file, so a tool configured once
+//! works against either. Browsers are only let in from the origins listed
+//! there, and the Host header must name the loopback address, so web pages
+//! can't reach the server through DNS rebinding.
+//!
+//! Endpoints (GET, JSON responses, errors as `{"error": "..."}`):
+//!   /v1/entries?term=<term>                 Entries for an exact term
+//!   /v1/search?q=<query>&type=<type>        Full-text search
+//!   /v1/segment?text=<text>                 Split Tibetan text into words
+//!   /v1/dictionaries                        Installed dictionaries
+
+use crate::data_host::DataHost;
+#[cfg(feature = "app")]
+use crate::packs::ensure_pack_available;
+use crate::repository::{PackSet, Repository};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::PathBuf;
+#[cfg(feature = "app")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "app")]
+use std::thread::JoinHandle;
+#[cfg(feature = "app")]
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const DEFAULT_PORT: u16 = 47321;
+
+/// Requests answered at once, so a slow lookup doesn't hold up the others
+const WORKERS: usize = 4;
+
+/// Longest text /v1/segment accepts, in bytes
+const MAX_SEGMENT_TEXT: usize = 20_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LookupServerSettings {
+    /// Start with the app
+    pub enabled: bool,
+    pub port: u16,
+    pub token: String,
+    /// Origins browsers may call from, e.g. "chrome-extension://<id>";
+    /// "*" allows any origin
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for LookupServerSettings {
+    fn default() -> Self {
+        LookupServerSettings {
+            enabled: false,
+            port: DEFAULT_PORT,
+            token: String::new(),
+            allowed_origins: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LookupServerStatus {
+    #[serde(flatten)]
+    pub settings: LookupServerSettings,
+    pub running: bool,
+}
+
+fn settings_file(host: &dyn DataHost) -> Result<PathBuf, String> {
+    Ok(host.app_data_dir()?.join("lookup-server.json"))
+}
+
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "Failed to generate lookup server token".to_string())?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// The saved settings, with a token generated (and saved) on first use. A
+/// settings file that can't be read is an error rather than replaced, which
+/// would change the token tools are configured with.
+pub(crate) fn load_settings(host: &dyn DataHost) -> Result<LookupServerSettings, String> {
+    let path = settings_file(host)?;
+    let mut settings: LookupServerSettings = match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Bad lookup server settings in {}: {}", path.display(), e))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => LookupServerSettings::default(),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    if settings.token.is_empty() {
+        settings.token = generate_token()?;
+        save_settings(host, &settings)?;
+    }
+    Ok(settings)
+}
+
+fn save_settings(host: &dyn DataHost, settings: &LookupServerSettings) -> Result<(), String> {
+    let path = settings_file(host)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    }
+    let json = serde_json::to_vec_pretty(settings)
+        .map_err(|e| format!("Failed to serialize lookup server settings: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write lookup server settings: {}", e))
+}
+
+/// Listen on 127.0.0.1:`port`
+pub(crate) fn bind(port: u16) -> Result<Server, String> {
+    Server::http(("127.0.0.1", port)).map_err(|e| format!("Failed to start lookup server on port {}: {}", port, e))
+}
+
+/// Answer requests on WORKERS threads until `server` is unblocked
+pub(crate) fn serve(server: &Server, host: &dyn DataHost, settings: &LookupServerSettings) {
+    std::thread::scope(|scope| {
+        for _ in 0..WORKERS {
+            scope.spawn(|| {
+                while let Ok(request) = server.recv() {
+                    let response = handle(&request, host, settings);
+                    if let Err(e) = request.respond(response) {
+                        eprintln!("[lookup_server] Failed to send response: {}", e);
+                    }
+                }
+                // unblock() only wakes one worker, pass it on
+                server.unblock();
+            });
+        }
+    });
+}
+
+struct HttpError(u16, String);
+
+impl From<String> for HttpError {
+    fn from(message: String) -> Self {
+        HttpError(500, message)
+    }
+}
+
+fn header_value<'r>(request: &'r Request, name: &'static str) -> Option<&'r str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+fn header(name: &str, value: &str) -> Option<Header> {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).ok()
+}
+
+/// Compare without returning early, so response times don't leak how much
+/// of a guessed token was right
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Decode a query string component ("+" and %XX escapes)
+pub(crate) fn decode_component(component: &str) -> Option<String> {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = component.get(i + 1..i + 3)?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 2;
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn query_param(query: &str, name: &str) -> Result<Option<String>, HttpError> {
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if key == name {
+            return decode_component(value)
+                .map(Some)
+                .ok_or_else(|| HttpError(400, format!("Invalid encoding in parameter '{}'", name)));
+        }
+    }
+    Ok(None)
+}
+
+fn required_param(query: &str, name: &str) -> Result<String, HttpError> {
+    query_param(query, name)?
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| HttpError(400, format!("Missing parameter '{}'", name)))
+}
+
+fn json<T: Serialize>(value: T) -> Result<serde_json::Value, HttpError> {
+    serde_json::to_value(value).map_err(|e| HttpError(500, format!("Failed to serialize response: {}", e)))
+}
+
+fn route(path: &str, query: &str, host: &dyn DataHost) -> Result<serde_json::Value, HttpError> {
+    match path {
+        "/v1/entries" => {
+            let term = required_param(query, "term")?;
+            json(PackSet::load(host)?.entries_for_term(&term)?)
+        }
+        "/v1/search" => {
+            let q = required_param(query, "q")?;
+            let search_type = query_param(query, "type")?.unwrap_or_else(|| "regular".to_string());
+            if !["regular", "phonetics_strict", "phonetics_loose"].contains(&search_type.as_str()) {
+                return Err(HttpError(400, format!("Unknown search type: {}", search_type)));
+            }
+            json(PackSet::load(host)?.search_entries(&q, &search_type)?)
+        }
+        "/v1/segment" => {
+            let text = required_param(query, "text")?;
+            if text.len() > MAX_SEGMENT_TEXT {
+                return Err(HttpError(413, format!("Text longer than {} bytes", MAX_SEGMENT_TEXT)));
+            }
+            json(PackSet::load(host)?.segment(&text)?)
+        }
+        "/v1/dictionaries" => json(PackSet::load(host)?.dictionaries()?),
+        _ => Err(HttpError(404, format!("Unknown endpoint: {}", path))),
+    }
+}
+
+fn handle(request: &Request, host: &dyn DataHost, settings: &LookupServerSettings) -> Response<Cursor<Vec<u8>>> {
+    let origin = header_value(request, "Origin");
+    let origin_allowed = origin.map_or(true, |origin| {
+        settings
+            .allowed_origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed == origin)
+    });
+
+    let result = check_request(request, settings, origin_allowed).and_then(|()| {
+        if *request.method() == Method::Options {
+            return Ok(None);
+        }
+        let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+        route(path, query, host).map(Some)
+    });
+
+    let (status, body) = match result {
+        Ok(Some(value)) => (200, value.to_string()),
+        Ok(None) => (204, String::new()),
+        Err(HttpError(status, message)) => (status, serde_json::json!({ "error": message }).to_string()),
+    };
+
+    let mut response = Response::from_string(body).with_status_code(status);
+    let mut headers = vec![header("Content-Type", "application/json; charset=utf-8")];
+    if let (Some(origin), true) = (origin, origin_allowed) {
+        headers.extend([
+            header("Access-Control-Allow-Origin", origin),
+            header("Access-Control-Allow-Methods", "GET, OPTIONS"),
+            header("Access-Control-Allow-Headers", "Authorization"),
+            header("Access-Control-Max-Age", "600"),
+            header("Vary", "Origin"),
+        ]);
+    }
+    for header in headers.into_iter().flatten() {
+        response = response.with_header(header);
+    }
+    response
+}
+
+/// Host, origin, method and token checks; preflight requests carry no token
+fn check_request(request: &Request, settings: &LookupServerSettings, origin_allowed: bool) -> Result<(), HttpError> {
+    let expected_hosts = [
+        format!("127.0.0.1:{}", settings.port),
+        format!("localhost:{}", settings.port),
+    ];
+    if !header_value(request, "Host").is_some_and(|host| expected_hosts.iter().any(|expected| expected == host)) {
+        return Err(HttpError(403, "Requests must be addressed to 127.0.0.1".to_string()));
+    }
+    if !origin_allowed {
+        return Err(HttpError(403, "Origin not allowed".to_string()));
+    }
+    match request.method() {
+        Method::Options => return Ok(()),
+        Method::Get => {}
+        _ => return Err(HttpError(405, "Only GET requests are supported".to_string())),
+    }
+    let token = header_value(request, "Authorization").and_then(|value| value.strip_prefix("Bearer "));
+    if !token.is_some_and(|token| token_matches(token.trim(), &settings.token)) {
+        return Err(HttpError(401, "Missing or wrong token".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "app")]
+struct RunningServer {
+    server: Arc<Server>,
+    thread: JoinHandle<()>,
+}
+
+#[cfg(feature = "app")]
+/// The server started by the app, if any
+#[derive(Default)]
+pub struct LookupServerState {
+    running: Mutex<Option<RunningServer>>,
+}
+
+#[cfg(feature = "app")]
+impl LookupServerState {
+    fn stop(&self) {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(RunningServer { server, thread }) = running {
+            server.unblock();
+            let _ = thread.join();
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+    }
+}
+
+#[cfg(feature = "app")]
+/// Stop the running server, then start it again if `settings` enable it
+async fn apply_settings(app: &AppHandle, settings: &LookupServerSettings) -> Result<(), String> {
+    app.state::<LookupServerState>().stop();
+    if !settings.enabled {
+        return Ok(());
+    }
+
+    ensure_pack_available(app.clone(), "core".to_string()).await?;
+    let server = Arc::new(bind(settings.port)?);
+    let thread = {
+        let (server, app, settings) = (server.clone(), app.clone(), settings.clone());
+        std::thread::spawn(move || serve(&server, &app, &settings))
+    };
+    let state = app.state::<LookupServerState>();
+    *state.running.lock().unwrap_or_else(|e| e.into_inner()) = Some(RunningServer { server, thread });
+    println!("[lookup_server] Listening on http://127.0.0.1:{}", settings.port);
+    Ok(())
+}
+
+#[cfg(feature = "app")]
+fn status(app: &AppHandle, settings: LookupServerSettings) -> LookupServerStatus {
+    LookupServerStatus {
+        settings,
+        running: app.state::<LookupServerState>().is_running(),
+    }
+}
+
+#[cfg(feature = "app")]
+/// Start the server on launch if the user turned it on
+pub(crate) fn start_lookup_server_in_background(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = match load_settings(&app) {
+            Ok(settings) if settings.enabled => apply_settings(&app, &settings).await,
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("[lookup_server] {}", e);
+        }
+    });
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub fn get_lookup_server(app: AppHandle) -> Result<LookupServerStatus, String> {
+    Ok(status(&app, load_settings(&app)?))
+}
+
+/// Save the server settings and start, restart or stop it to match
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn set_lookup_server(
+    app: AppHandle,
+    enabled: bool,
+    port: u16,
+    allowed_origins: Vec<String>,
+) -> Result<LookupServerStatus, String> {
+    if port < 1024 {
+        return Err("Port must be between 1024 and 65535".to_string());
+    }
+    let mut settings = load_settings(&app)?;
+    settings.enabled = enabled;
+    settings.port = port;
+    settings.allowed_origins = allowed_origins
+        .iter()
+        .map(|origin| origin.trim().trim_end_matches('/').to_string())
+        .filter(|origin| !origin.is_empty())
+        .collect();
+    save_settings(&app, &settings)?;
+    apply_settings(&app, &settings).await?;
+    Ok(status(&app, settings))
+}
+
+/// Replace the token; tools using the old one stop working
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn regenerate_lookup_server_token(app: AppHandle) -> Result<LookupServerStatus, String> {
+    let mut settings = load_settings(&app)?;
+    settings.token = generate_token()?;
+    save_settings(&app, &settings)?;
+    apply_settings(&app, &settings).await?;
+    Ok(status(&app, settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_host::TestDataDir;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tiny_http::TestRequest;
+
+    static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+    fn settings() -> LookupServerSettings {
+        LookupServerSettings {
+            token: "secret".to_string(),
+            allowed_origins: vec!["chrome-extension://known".to_string()],
+            ..LookupServerSettings::default()
+        }
+    }
+
+    /// Status and Access-Control-Allow-Origin of a request to an unknown
+    /// endpoint: 404 once every check passed
+    fn respond(headers: &[(&str, &str)], method: Method) -> (u16, Option<String>) {
+        let dir = TestDataDir::new(&format!("lookup-server-{}", REQUESTS.fetch_add(1, Ordering::Relaxed)));
+        let mut request = TestRequest::new().with_method(method).with_path("/v1/nothing");
+        for (name, value) in headers {
+            request = request.with_header(header(name, value).unwrap());
+        }
+        let response = handle(&request.into(), &dir, &settings());
+        let allow_origin = response
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Access-Control-Allow-Origin"))
+            .map(|header| header.value.to_string());
+        (response.status_code().0, allow_origin)
+    }
+
+    const HOST: (&str, &str) = ("Host", "127.0.0.1:47321");
+    const TOKEN: (&str, &str) = ("Authorization", "Bearer secret");
+
+    #[test]
+    fn needs_the_token() {
+        assert_eq!(respond(&[HOST, TOKEN], Method::Get).0, 404);
+        assert_eq!(respond(&[HOST], Method::Get).0, 401);
+        assert_eq!(respond(&[HOST, ("Authorization", "Bearer secreT")], Method::Get).0, 401);
+        assert_eq!(respond(&[HOST, ("Authorization", "secret")], Method::Get).0, 401);
+        assert_eq!(respond(&[HOST, TOKEN], Method::Post).0, 405);
+    }
+
+    #[test]
+    fn needs_a_loopback_host() {
+        assert_eq!(respond(&[("Host", "localhost:47321"), TOKEN], Method::Get).0, 404);
+        assert_eq!(respond(&[TOKEN], Method::Get).0, 403);
+        assert_eq!(respond(&[("Host", "attacker.example:47321"), TOKEN], Method::Get).0, 403);
+        assert_eq!(respond(&[("Host", "127.0.0.1:80"), TOKEN], Method::Get).0, 403);
+    }
+
+    #[test]
+    fn lets_browsers_in_from_allowed_origins_only() {
+        let known = ("Origin", "chrome-extension://known");
+        assert_eq!(
+            respond(&[HOST, TOKEN, known], Method::Get),
+            (404, Some("chrome-extension://known".to_string()))
+        );
+        assert_eq!(respond(&[HOST, TOKEN, ("Origin", "https://example.com")], Method::Get), (403, None));
+        // Preflight requests carry no token
+        assert_eq!(respond(&[HOST, known], Method::Options).0, 204);
+        assert_eq!(respond(&[HOST, ("Origin", "https://example.com")], Method::Options).0, 403);
+    }
+
+    #[test]
+    fn keeps_the_token_and_refuses_a_broken_settings_file() {
+        let dir = TestDataDir::new("lookup-server-settings");
+        let token = load_settings(&dir).unwrap().token;
+        assert_eq!(token.len(), 64);
+        assert_eq!(load_settings(&dir).unwrap().token, token);
+
+        let path = settings_file(&dir).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+        assert!(load_settings(&dir).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{ not json");
+    }
+
+    #[test]
+    fn every_worker_stops_when_unblocked() {
+        let dir = TestDataDir::new("lookup-server-stop");
+        let server = bind(0).unwrap();
+        std::thread::scope(|scope| {
+            scope.spawn(|| serve(&server, &dir, &settings()));
+            server.unblock();
+        });
+    }
+}
@@ -0,0 +1,285 @@
+//! Plain text out of the HTML and XML found in imported dictionaries.
+//!
+//! Definitions in our packs are plain text, so markup is flattened with line
+//! breaks where blocks end. The XML parser is deliberately small and lenient:
+//! it builds a tree good enough to walk XDXF and TEI articles, and tolerates
+//! the unbalanced tags common in hand-made glossaries.
+
+/// Deepest element nesting kept by parse_xml. Start tags below it become
+/// empty siblings, which bounds the recursion of find_all and collect_text
+/// (and of dropping the tree) on hostile or badly unbalanced files.
+const MAX_DEPTH: usize = 256;
+
+/// Elements whose end starts a new line in extracted text
+const BLOCK_ELEMENTS: &[&str] = &[
+    "br", "p", "div", "li", "tr", "lb", "def", "sense", "cit", "ar", "entry", "dd", "dt",
+];
+
+pub(crate) fn decode_entity(entity: &str) -> Option<String> {
+    let decoded = match entity {
+        "nbsp" => ' ',
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let value = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(value)?
+        }
+    };
+    Some(decoded.to_string())
+}
+
+/// Length of the entity starting at `text` ("&amp;..." gives the decoded
+/// character and 5), if it is one.
+fn entity_at(text: &str) -> Option<(String, usize)> {
+    let end = text.char_indices().take(12).find(|(_, c)| *c == ';').map(|(i, _)| i)?;
+    decode_entity(&text[1..end]).map(|decoded| (decoded, end + 1))
+}
+
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '&' {
+            if let Some((entity, len)) = entity_at(rest) {
+                decoded.push_str(&entity);
+                rest = &rest[len..];
+                continue;
+            }
+        }
+        decoded.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    decoded
+}
+
+/// Trim every line and drop the empty ones
+fn tidy_lines(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Keep the text of an HTML fragment, with line breaks for <br> and blocks.
+/// Anki "[sound:...]" references are dropped too.
+pub(crate) fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>') {
+                let tag = rest[1..end].trim().to_lowercase();
+                let name = tag.trim_start_matches('/').split([' ', '/']).next().unwrap_or("");
+                if name == "br" || (tag.starts_with('/') && BLOCK_ELEMENTS.contains(&name)) {
+                    text.push('\n');
+                }
+                rest = &rest[end + 1..];
+                continue;
+            }
+        } else if c == '&' {
+            if let Some((entity, len)) = entity_at(rest) {
+                text.push_str(&entity);
+                rest = &rest[len..];
+                continue;
+            }
+        } else if rest.starts_with("[sound:") {
+            if let Some(end) = rest.find(']') {
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    tidy_lines(&text)
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct XmlElement {
+    /// Local name, without namespace prefix
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlNode>,
+}
+
+impl XmlElement {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Descendants named `name`, without looking inside the ones found
+    pub fn find_all(&self, name: &str) -> Vec<&XmlElement> {
+        let mut found = Vec::new();
+        for child in &self.children {
+            if let XmlNode::Element(element) = child {
+                if element.name == name {
+                    found.push(element);
+                } else {
+                    found.extend(element.find_all(name));
+                }
+            }
+        }
+        found
+    }
+
+    pub fn find(&self, name: &str) -> Option<&XmlElement> {
+        self.find_all(name).into_iter().next()
+    }
+
+    fn collect_text(&self, skip: &[&str], out: &mut String) {
+        for child in &self.children {
+            match child {
+                XmlNode::Text(text) => out.push_str(text),
+                XmlNode::Element(element) if skip.contains(&element.name.as_str()) => {}
+                XmlNode::Element(element) => {
+                    element.collect_text(skip, out);
+                    if BLOCK_ELEMENTS.contains(&element.name.as_str()) {
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+    }
+
+    /// Text content, skipping the `skip` elements. Layout whitespace is
+    /// collapsed; block elements end lines.
+    pub fn text_excluding(&self, skip: &[&str]) -> String {
+        let mut raw = String::new();
+        self.collect_text(skip, &mut raw);
+        let lines: Vec<String> = raw
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        tidy_lines(&lines.join("\n"))
+    }
+
+    pub fn text(&self) -> String {
+        self.text_excluding(&[])
+    }
+}
+
+fn local_name(name: &str) -> String {
+    name.rsplit(':').next().unwrap_or(name).to_string()
+}
+
+fn parse_attributes(source: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = source.trim();
+    while let Some(eq) = rest.find('=') {
+        let key = local_name(rest[..eq].trim());
+        let after = rest[eq + 1..].trim_start();
+        let Some(quote) = after.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            break;
+        };
+        let Some(len) = after[1..].find(quote) else {
+            break;
+        };
+        attributes.push((key, decode_entities(&after[1..1 + len])));
+        rest = after[len + 2..].trim_start();
+    }
+    attributes
+}
+
+/// Parse an XML document into a tree under a nameless root element
+pub(crate) fn parse_xml(source: &str) -> Result<XmlElement, String> {
+    let mut stack: Vec<XmlElement> = vec![XmlElement::default()];
+    let mut rest = source.trim_start_matches('\u{feff}');
+
+    let append = |stack: &mut Vec<XmlElement>, node: XmlNode| {
+        if let Some(parent) = stack.last_mut() {
+            parent.children.push(node);
+        }
+    };
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            append(&mut stack, XmlNode::Text(decode_entities(rest)));
+            break;
+        };
+        if lt > 0 {
+            append(&mut stack, XmlNode::Text(decode_entities(&rest[..lt])));
+        }
+        rest = &rest[lt..];
+
+        if let Some(after) = rest.strip_prefix("<!--") {
+            let end = after.find("-->").ok_or("unterminated comment")?;
+            rest = &after[end + 3..];
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after.find("]]>").ok_or("unterminated CDATA section")?;
+            append(&mut stack, XmlNode::Text(after[..end].to_string()));
+            rest = &after[end + 3..];
+        } else if rest.starts_with("<?") {
+            let end = rest.find("?>").ok_or("unterminated processing instruction")?;
+            rest = &rest[end + 2..];
+        } else if rest.starts_with("<!") {
+            // DOCTYPE, possibly with an internal subset in brackets
+            let bracket = rest.find('[');
+            let close = rest.find('>').ok_or("unterminated declaration")?;
+            let end = match bracket {
+                Some(b) if b < close => rest.find("]>").map(|e| e + 1).ok_or("unterminated DOCTYPE")?,
+                _ => close,
+            };
+            rest = &rest[end + 1..];
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').ok_or("unterminated end tag")?;
+            let name = local_name(after[..end].trim());
+            rest = &after[end + 1..];
+            // Close up to the matching element; ignore stray end tags
+            if let Some(depth) = stack.iter().rposition(|e| e.name == name) {
+                while stack.len() > depth.max(1) {
+                    let element = stack.pop().unwrap_or_default();
+                    append(&mut stack, XmlNode::Element(element));
+                }
+            }
+        } else {
+            let end = rest.find('>').ok_or("unterminated start tag")?;
+            let inner = &rest[1..end];
+            rest = &rest[end + 1..];
+            let (inner, self_closing) = match inner.strip_suffix('/') {
+                Some(inner) => (inner, true),
+                None => (inner, false),
+            };
+            let (name, attributes) = match inner.find(char::is_whitespace) {
+                Some(space) => (&inner[..space], parse_attributes(&inner[space..])),
+                None => (inner, Vec::new()),
+            };
+            let element = XmlElement {
+                name: local_name(name),
+                attributes,
+                children: Vec::new(),
+            };
+            // The stack holds the nameless root too
+            if self_closing || stack.len() > MAX_DEPTH {
+                append(&mut stack, XmlNode::Element(element));
+            } else {
+                stack.push(element);
+            }
+        }
+    }
+
+    // Close whatever was left open
+    while stack.len() > 1 {
+        let element = stack.pop().unwrap_or_default();
+        append(&mut stack, XmlNode::Element(element));
+    }
+    stack.pop().ok_or_else(|| "empty document".to_string())
+}
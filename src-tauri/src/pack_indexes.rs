@@ -0,0 +1,185 @@
+//! Auxiliary indexes derived from installed packs.
+//!
+//! Some lookups (reverse English search, Sanskrit search, related terms,
+//! verb stems...) need data extracted from the entries of every pack. Rather
+//! than touching the pack databases, which are replaced wholesale on update,
+//! each index lives in its own SQLite file under `<app_data>/indexes/` and
+//! remembers which pack files it was built from. Syncing re-indexes packs
+//! whose file changed and drops packs that were removed.
+
+use crate::data_host::DataHost;
+use crate::packs::get_all_pack_db_paths;
+use crate::reverse_index::ReverseIndex;
+use crate::sanskrit_index::SanskritIndex;
+use crate::term_links::TermLinksIndex;
+use crate::verb_index::VerbIndex;
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection, OpenFlags};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+
+/// Serializes syncs so an install-time refresh and a lookup never index the
+/// same pack twice at once.
+static SYNC_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+pub(crate) trait PackIndexer {
+    /// File name (without extension) of the index database
+    const NAME: &'static str;
+    /// Bump whenever extraction changes so every pack gets re-indexed
+    const VERSION: u32;
+
+    fn create_tables(index: &Connection) -> rusqlite::Result<()>;
+    fn clear_pack(index: &Connection, pack_id: &str) -> rusqlite::Result<()>;
+    /// Extract this index's rows from one pack. Returns the number of rows written.
+    fn index_pack(index: &Connection, pack_id: &str, pack: &Connection) -> rusqlite::Result<usize>;
+}
+
+/// Identifies a pack file's content well enough to detect replacement
+pub(crate) fn pack_signature(path: &Path) -> String {
+    match fs::metadata(path) {
+        Ok(meta) => {
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            format!("{}-{}", meta.len(), modified)
+        }
+        Err(_) => "missing".to_string(),
+    }
+}
+
+fn get_indexes_dir(host: &dyn DataHost) -> Result<PathBuf, String> {
+    let indexes_dir = host.app_data_dir()?.join("indexes");
+    fs::create_dir_all(&indexes_dir)
+        .map_err(|e| format!("Failed to create indexes dir: {}", e))?;
+    Ok(indexes_dir)
+}
+
+fn open_index<I: PackIndexer>(host: &dyn DataHost) -> Result<Connection, String> {
+    let path = get_indexes_dir(host)?.join(format!("{}.sqlite", I::NAME));
+    let conn = Connection::open(&path)
+        .map_err(|e| format!("Failed to open {} index at {:?}: {}", I::NAME, path, e))?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS indexed_packs (
+            pack_id    TEXT PRIMARY KEY,
+            signature  TEXT NOT NULL,
+            version    INTEGER NOT NULL
+        );",
+    )
+    .map_err(|e| format!("Failed to create {} index bookkeeping: {}", I::NAME, e))?;
+    I::create_tables(&conn)
+        .map_err(|e| format!("Failed to create {} index tables: {}", I::NAME, e))?;
+
+    Ok(conn)
+}
+
+fn reindex_pack<I: PackIndexer>(
+    index: &mut Connection,
+    pack_id: &str,
+    db_path: &Path,
+    signature: &str,
+) -> Result<usize, String> {
+    let pack = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open pack {} at {:?}: {}", pack_id, db_path, e))?;
+
+    let tx = index
+        .transaction()
+        .map_err(|e| format!("Failed to start {} index transaction: {}", I::NAME, e))?;
+    I::clear_pack(&tx, pack_id)
+        .map_err(|e| format!("Failed to clear pack {} from {} index: {}", pack_id, I::NAME, e))?;
+    let count = I::index_pack(&tx, pack_id, &pack)
+        .map_err(|e| format!("Failed to index pack {} into {}: {}", pack_id, I::NAME, e))?;
+    tx.execute(
+        "INSERT OR REPLACE INTO indexed_packs (pack_id, signature, version) VALUES (?, ?, ?)",
+        params![pack_id, signature, I::VERSION],
+    )
+    .map_err(|e| format!("Failed to record pack {} in {} index: {}", pack_id, I::NAME, e))?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit {} index: {}", I::NAME, e))?;
+
+    Ok(count)
+}
+
+/// Bring an index up to date with the installed packs and return a
+/// connection to it, ready to be queried.
+pub(crate) fn sync_index<I: PackIndexer>(host: &dyn DataHost) -> Result<Connection, String> {
+    let _guard = SYNC_LOCK
+        .lock()
+        .map_err(|e| format!("Failed to lock index sync: {}", e))?;
+
+    let mut index = open_index::<I>(host)?;
+    let pack_paths = get_all_pack_db_paths(host)?;
+
+    let indexed: Vec<(String, String, u32)> = {
+        let mut stmt = index
+            .prepare("SELECT pack_id, signature, version FROM indexed_packs")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| format!("Failed to read {} index state: {}", I::NAME, e))?
+            .filter_map(|r| r.ok())
+            .collect();
+        rows
+    };
+
+    // Drop packs that are no longer installed
+    let installed: HashSet<&str> = pack_paths.iter().map(|(id, _)| id.as_str()).collect();
+    for (pack_id, _, _) in &indexed {
+        if !installed.contains(pack_id.as_str()) {
+            I::clear_pack(&index, pack_id)
+                .and_then(|_| {
+                    index.execute("DELETE FROM indexed_packs WHERE pack_id = ?", params![pack_id])
+                })
+                .map_err(|e| format!("Failed to drop pack {} from {} index: {}", pack_id, I::NAME, e))?;
+        }
+    }
+
+    for (pack_id, db_path) in &pack_paths {
+        let signature = pack_signature(db_path);
+        let up_to_date = indexed
+            .iter()
+            .any(|(id, sig, version)| id == pack_id && *sig == signature && *version == I::VERSION);
+        if up_to_date {
+            continue;
+        }
+
+        match reindex_pack::<I>(&mut index, pack_id, db_path, &signature) {
+            Ok(count) => println!("[pack_indexes] {}: indexed {} rows from pack {}", I::NAME, count, pack_id),
+            Err(e) => eprintln!("Warning: {}", e),
+        }
+    }
+
+    Ok(index)
+}
+
+/// Re-sync every auxiliary index, e.g. right after a pack was installed,
+/// updated or removed.
+pub(crate) fn sync_all_indexes(host: &dyn DataHost) {
+    if let Err(e) = sync_index::<ReverseIndex>(host) {
+        eprintln!("Warning: Failed to sync {} index: {}", ReverseIndex::NAME, e);
+    }
+    if let Err(e) = sync_index::<SanskritIndex>(host) {
+        eprintln!("Warning: Failed to sync {} index: {}", SanskritIndex::NAME, e);
+    }
+    if let Err(e) = sync_index::<TermLinksIndex>(host) {
+        eprintln!("Warning: Failed to sync {} index: {}", TermLinksIndex::NAME, e);
+    }
+    if let Err(e) = sync_index::<VerbIndex>(host) {
+        eprintln!("Warning: Failed to sync {} index: {}", VerbIndex::NAME, e);
+    }
+}
+
+/// Same as sync_all_indexes but off the calling thread, so installs return
+/// as soon as the pack itself is in place.
+#[cfg(feature = "app")]
+pub(crate) fn refresh_indexes_in_background(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || sync_all_indexes(&app));
+}
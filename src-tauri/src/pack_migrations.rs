@@ -0,0 +1,444 @@
+//! Step-by-step upgrades of pack databases built for an older schema.
+//!
+//! Each migration takes a pack from one schema version to the next, inside
+//! a transaction, and is written so that running it on a pack that already
+//! has the change is harmless. Custom packs record their version in their
+//! manifest; when it's missing the version is detected from the database
+//! itself.
+//!
+//! Only custom packs are migrated, when installed and on startup: their
+//! source may be gone. Stale official packs are still deleted and copied
+//! or downloaded again (see packs.rs), which brings their phonetics, while
+//! the phonetic columns a migration adds are empty until the frontend fills
+//! them in (see pack_phonetics.rs).
+//!
+//! A dry run applies the steps and rolls them back, so the report says
+//! whether the upgrade would succeed without touching the file.
+
+use crate::custom_packs::{get_custom_pack_paths, SUPPORTED_SCHEMA_VERSION};
+use crate::data_host::DataHost;
+#[cfg(feature = "app")]
+use crate::pack_indexes::refresh_indexes_in_background;
+use crate::pack_signing::retire_signature;
+use crate::tibdict_builder::PACK_SCHEMA;
+use rusqlite::{Connection, Transaction};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+
+struct Migration {
+    /// Schema version the pack has once this step ran
+    to: u32,
+    description: &'static str,
+    apply: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        to: 2,
+        description: "add the phonetic columns to entries",
+        apply: add_phonetic_columns,
+    },
+    Migration {
+        to: 3,
+        description: "rebuild entries_fts with the unicode61 tokenizer and create the term index",
+        apply: rebuild_search_index,
+    },
+];
+
+const PHONETIC_COLUMNS: &[&str] = &[
+    "termPhoneticsStrict",
+    "termPhoneticsLoose",
+    "definitionPhoneticsWordsStrict",
+    "definitionPhoneticsWordsLoose",
+];
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    pub pack_id: String,
+    pub from_version: u32,
+    pub to_version: u32,
+    /// Description of each step, in order
+    pub steps: Vec<String>,
+    pub dry_run: bool,
+    /// True once the steps were committed (never for a dry run)
+    pub applied: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info(\"{table}\")"))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    for name in columns {
+        if name?.eq_ignore_ascii_case(column) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn object_sql(conn: &Connection, name: &str) -> rusqlite::Result<Option<String>> {
+    let mut stmt = conn.prepare("SELECT sql FROM sqlite_master WHERE name = ?")?;
+    let mut rows = stmt.query([name])?;
+    match rows.next()? {
+        Some(row) => row.get(0),
+        None => Ok(None),
+    }
+}
+
+fn add_phonetic_columns(tx: &Transaction) -> rusqlite::Result<()> {
+    for column in PHONETIC_COLUMNS {
+        if !has_column(tx, "entries", column)? {
+            tx.execute_batch(&format!("ALTER TABLE entries ADD COLUMN {column} text not null default ''"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Recreate entries_fts, its triggers and the term index exactly as
+/// PACK_SCHEMA declares them, then reindex every entry
+fn rebuild_search_index(tx: &Transaction) -> rusqlite::Result<()> {
+    let reference = Connection::open_in_memory()?;
+    reference.execute_batch(PACK_SCHEMA)?;
+    let mut stmt = reference.prepare(
+        "SELECT type, name, sql FROM sqlite_master
+         WHERE name = 'entries_fts' OR type IN ('trigger', 'index')
+         ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'trigger' THEN 1 ELSE 2 END",
+    )?;
+    let objects = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // Older builders may have named their FTS triggers differently
+    let old_triggers = tx
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'trigger' AND tbl_name = 'entries'")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for name in old_triggers {
+        tx.execute_batch(&format!("DROP TRIGGER IF EXISTS \"{name}\""))?;
+    }
+    for (kind, name, _) in objects.iter().rev() {
+        let kind = if kind == "table" { "TABLE" } else { kind.as_str() };
+        tx.execute_batch(&format!("DROP {kind} IF EXISTS \"{name}\""))?;
+    }
+    for (_, _, sql) in &objects {
+        if let Some(sql) = sql {
+            tx.execute_batch(sql)?;
+        }
+    }
+    tx.execute_batch("INSERT INTO entries_fts(entries_fts) VALUES('rebuild')")
+}
+
+/// Best guess of a pack's schema version from its tables
+pub(crate) fn detect_schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    for column in PHONETIC_COLUMNS {
+        if !has_column(conn, "entries", column)? {
+            return Ok(1);
+        }
+    }
+    let fts = object_sql(conn, "entries_fts")?.unwrap_or_default().to_lowercase();
+    let has_term_index = object_sql(conn, "idx_entries_term")?.is_some();
+    if !has_term_index || !fts.contains("unicode61") {
+        return Ok(2);
+    }
+    Ok(SUPPORTED_SCHEMA_VERSION)
+}
+
+/// Upgrade the database at `path` from `from_version` (detected when None)
+/// to SUPPORTED_SCHEMA_VERSION
+pub(crate) fn migrate_database(
+    pack_id: &str,
+    path: &Path,
+    from_version: Option<u32>,
+    dry_run: bool,
+) -> Result<MigrationReport, String> {
+    let mut conn = Connection::open(path).map_err(|e| format!("Failed to open pack {}: {}", pack_id, e))?;
+    let from_version = match from_version {
+        Some(version) => version,
+        None => detect_schema_version(&conn).map_err(|e| format!("Failed to read pack {} schema: {}", pack_id, e))?,
+    };
+    if from_version > SUPPORTED_SCHEMA_VERSION {
+        return Err(format!(
+            "Pack {} uses schema v{}, this app supports up to v{}",
+            pack_id, from_version, SUPPORTED_SCHEMA_VERSION
+        ));
+    }
+
+    let steps: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.to > from_version).collect();
+    let mut report = MigrationReport {
+        pack_id: pack_id.to_string(),
+        from_version,
+        to_version: SUPPORTED_SCHEMA_VERSION,
+        steps: steps.iter().map(|m| format!("v{}: {}", m.to, m.description)).collect(),
+        dry_run,
+        applied: false,
+        error: None,
+    };
+    if steps.is_empty() {
+        return Ok(report);
+    }
+
+    let tx = conn.transaction().map_err(|e| format!("Failed to migrate pack {}: {}", pack_id, e))?;
+    for step in &steps {
+        (step.apply)(&tx).map_err(|e| format!("Failed to migrate pack {} to v{}: {}", pack_id, step.to, e))?;
+    }
+    if dry_run {
+        tx.rollback().map_err(|e| format!("Failed to roll back pack {}: {}", pack_id, e))?;
+    } else {
+        tx.commit().map_err(|e| format!("Failed to migrate pack {}: {}", pack_id, e))?;
+        report.applied = true;
+    }
+    Ok(report)
+}
+
+/// Migrate an installed custom pack and bump its manifest's schemaVersion.
+/// The manifest is edited as JSON so fields this app doesn't know survive,
+/// and a signature, which no longer covers the files, is retired (see
+/// pack_signing.rs).
+pub(crate) fn migrate_custom_pack(pack_dir: &Path, pack_id: &str, dry_run: bool) -> Result<MigrationReport, String> {
+    let manifest_path = pack_dir.join("manifest.json");
+    let mut manifest: serde_json::Value = fs::read(&manifest_path)
+        .map_err(|e| format!("Failed to read manifest of {}: {}", pack_id, e))
+        .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| format!("Bad manifest in {}: {}", pack_id, e)))?;
+    let schema_version = manifest
+        .get("schemaVersion")
+        .and_then(serde_json::Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or_else(|| format!("Bad manifest in {}: no schemaVersion", pack_id))?;
+
+    let report = migrate_database(pack_id, &pack_dir.join("data.sqlite"), Some(schema_version), dry_run)?;
+    if report.applied {
+        manifest["schemaVersion"] = report.to_version.into();
+        let contents = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| format!("Failed to serialize manifest of {}: {}", pack_id, e))?;
+        fs::write(&manifest_path, contents).map_err(|e| format!("Failed to write manifest of {}: {}", pack_id, e))?;
+        retire_signature(pack_dir, report.from_version)
+            .map_err(|e| format!("Failed to migrate pack {}: {}", pack_id, e))?;
+    }
+    Ok(report)
+}
+
+/// Migrate every installed custom pack. Official packs are replaced rather
+/// than migrated, and the user pack is created at the current schema.
+fn migrate_installed_packs(host: &dyn DataHost, dry_run: bool) -> Vec<MigrationReport> {
+    let mut reports = Vec::new();
+    let mut record = |pack_id: &str, result: Result<MigrationReport, String>| {
+        let report = result.unwrap_or_else(|error| MigrationReport {
+            pack_id: pack_id.to_string(),
+            from_version: 0,
+            to_version: SUPPORTED_SCHEMA_VERSION,
+            steps: Vec::new(),
+            dry_run,
+            applied: false,
+            error: Some(error),
+        });
+        reports.push(report);
+    };
+
+    for (pack_id, sqlite_path) in get_custom_pack_paths(host) {
+        if let Some(pack_dir) = sqlite_path.parent() {
+            record(&pack_id, migrate_custom_pack(pack_dir, &pack_id, dry_run));
+        }
+    }
+
+    reports.sort_by(|a, b| a.pack_id.cmp(&b.pack_id));
+    reports
+}
+
+/// Reindex the migrated packs, and tell the frontend which ones need their
+/// phonetics ('custom-packs-migrated' event, with their ids)
+#[cfg(feature = "app")]
+fn finish_migrations(app: &AppHandle, reports: &[MigrationReport]) {
+    let migrated: Vec<&str> = reports.iter().filter(|r| r.applied).map(|r| r.pack_id.as_str()).collect();
+    if !migrated.is_empty() {
+        refresh_indexes_in_background(app);
+        DataHost::emit(app, "custom-packs-migrated", serde_json::json!(migrated));
+    }
+}
+
+/// Upgrade installed custom packs on startup
+#[cfg(feature = "app")]
+pub(crate) fn migrate_packs_in_background(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let reports = migrate_installed_packs(&app, false);
+        for report in &reports {
+            if let Some(error) = &report.error {
+                eprintln!("[pack_migrations] {}", error);
+            } else if report.applied {
+                println!(
+                    "[pack_migrations] {} migrated from v{} to v{}",
+                    report.pack_id, report.from_version, report.to_version
+                );
+            }
+        }
+        finish_migrations(&app, &reports);
+    });
+}
+
+/// Migrate all installed custom packs to the current schema. With `dry_run`
+/// the steps are applied and rolled back, and nothing is written.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn migrate_packs(app: AppHandle, dry_run: Option<bool>) -> Result<Vec<MigrationReport>, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        let reports = migrate_installed_packs(&app, dry_run);
+        finish_migrations(&app, &reports);
+        reports
+    })
+    .await
+    .map_err(|e| format!("Migration task failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::custom_packs::TibdictManifest;
+    use crate::data_host::TestDataDir;
+    use crate::pack_signing::{installed_provenance, TrustSettings, SIGNATURE_FILE};
+    use crate::pack_validation::validate_pack;
+
+    /// Schema of the first packs: no phonetics, and an FTS index on the
+    /// default tokenizer kept up to date by differently named triggers
+    const SCHEMA_V1: &str = "
+        CREATE TABLE dictionaries (
+          id integer primary key, name text not null, position integer NOT NULL, enabled boolean default true
+        );
+        CREATE TABLE entries (
+          id integer primary key, term text not null, definition text not null, dictionaryId integer
+        );
+        CREATE VIRTUAL TABLE entries_fts USING fts5(term, definition, content = 'entries', content_rowid = 'id');
+        CREATE TRIGGER entries_ai AFTER INSERT ON entries BEGIN
+          INSERT INTO entries_fts(rowid, term, definition) VALUES (new.id, new.term, new.definition);
+        END;
+    ";
+
+    /// v2 added the phonetic columns to entries and entries_fts
+    const SCHEMA_V2: &str = "
+        CREATE TABLE dictionaries (
+          id integer primary key, name text not null, position integer NOT NULL, enabled boolean default true
+        );
+        CREATE TABLE entries (
+          id integer primary key, term text not null,
+          termPhoneticsStrict text not null, termPhoneticsLoose text not null,
+          definition text not null,
+          definitionPhoneticsWordsStrict text not null, definitionPhoneticsWordsLoose text not null,
+          dictionaryId integer
+        );
+        CREATE VIRTUAL TABLE entries_fts USING fts5(
+          term, termPhoneticsStrict, termPhoneticsLoose,
+          definition, definitionPhoneticsWordsStrict, definitionPhoneticsWordsLoose,
+          content = 'entries', content_rowid = 'id'
+        );
+    ";
+
+    const SIGNATURE: &str = r#"{
+        "algorithm": "ed25519",
+        "publicKey": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=",
+        "signer": "Publisher",
+        "manifestSha256": "00",
+        "sqliteSha256": "00",
+        "signature": "AA=="
+    }"#;
+
+    /// An installed custom pack of two entries at `version`, signed, with a
+    /// manifest field this app doesn't know
+    fn old_pack(name: &str, version: u32) -> TestDataDir {
+        let dir = TestDataDir::new(&format!("pack-migrations-{}", name));
+        let conn = Connection::open(dir.root.join("data.sqlite")).unwrap();
+        conn.execute_batch(if version == 1 { SCHEMA_V1 } else { SCHEMA_V2 }).unwrap();
+        conn.execute("INSERT INTO dictionaries (id, name, position) VALUES (1, 'First', 1)", [])
+            .unwrap();
+        for term in ["ཀ་", "ཁ་"] {
+            if version == 1 {
+                conn.execute(
+                    "INSERT INTO entries (term, definition, dictionaryId) VALUES (?, 'letter', 1)",
+                    [term],
+                )
+                .unwrap();
+            } else {
+                conn.execute(
+                    "INSERT INTO entries VALUES (NULL, ?, '', '', 'letter', '', '', 1)",
+                    [term],
+                )
+                .unwrap();
+            }
+        }
+        if version == 2 {
+            conn.execute_batch("INSERT INTO entries_fts(entries_fts) VALUES('rebuild')").unwrap();
+        }
+        let manifest = serde_json::json!({
+            "format": "tibdict",
+            "formatVersion": 1,
+            "schemaVersion": version,
+            "id": name,
+            "name": "Old pack",
+            "description": "",
+            "dictionaries": [{ "name": "First", "entriesCount": 2 }],
+            "publisherNote": "kept",
+        });
+        fs::write(dir.root.join("manifest.json"), serde_json::to_vec_pretty(&manifest).unwrap()).unwrap();
+        fs::write(dir.root.join(SIGNATURE_FILE), SIGNATURE).unwrap();
+        dir
+    }
+
+    fn assert_migrated(dir: &TestDataDir, from_version: u32) {
+        let report = migrate_custom_pack(&dir.root, "custom-old", false).unwrap();
+        assert!(report.applied);
+        assert_eq!((report.from_version, report.to_version), (from_version, SUPPORTED_SCHEMA_VERSION));
+
+        // The database matches PACK_SCHEMA and its FTS index covers the entries
+        let manifest_bytes = fs::read(dir.root.join("manifest.json")).unwrap();
+        let manifest: TibdictManifest = serde_json::from_slice(&manifest_bytes).unwrap();
+        let conn = Connection::open(dir.root.join("data.sqlite")).unwrap();
+        assert_eq!(detect_schema_version(&conn).unwrap(), SUPPORTED_SCHEMA_VERSION);
+        if let Err(e) = validate_pack(&conn, &manifest) {
+            panic!("migrated pack doesn't validate: {:?}", e.schema_report.map(|r| r.issues));
+        }
+        let found: i64 = conn
+            .query_row("SELECT COUNT(*) FROM entries_fts WHERE entries_fts MATCH 'letter'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(found, 2);
+
+        // Only schemaVersion changed in the manifest
+        let manifest: serde_json::Value = serde_json::from_slice(&manifest_bytes).unwrap();
+        assert_eq!(manifest["schemaVersion"], SUPPORTED_SCHEMA_VERSION);
+        assert_eq!(manifest["publisherNote"], "kept");
+
+        // The signature no longer covers the files
+        assert!(!dir.root.join(SIGNATURE_FILE).exists());
+        let provenance = installed_provenance(&dir.root, &TrustSettings::default());
+        assert_eq!(provenance.status, "migrated");
+        assert_eq!(provenance.migrated_from, Some(from_version));
+        assert_eq!(provenance.signer.as_deref(), Some("Publisher"));
+    }
+
+    #[test]
+    fn v1_pack_ends_up_at_the_current_schema() {
+        assert_migrated(&old_pack("v1", 1), 1);
+    }
+
+    #[test]
+    fn v2_pack_ends_up_at_the_current_schema() {
+        assert_migrated(&old_pack("v2", 2), 2);
+    }
+
+    #[test]
+    fn dry_run_leaves_the_files_unchanged() {
+        let dir = old_pack("dry-run", 1);
+        let files = ["data.sqlite", "manifest.json", SIGNATURE_FILE];
+        let read_all = || files.map(|name| fs::read(dir.root.join(name)).unwrap());
+        let before = read_all();
+
+        let report = migrate_custom_pack(&dir.root, "custom-dry-run", true).unwrap();
+        assert!(report.dry_run && !report.applied && report.error.is_none());
+        assert_eq!(report.steps.len(), 2);
+        assert!(read_all() == before);
+        assert_eq!(installed_provenance(&dir.root, &TrustSettings::default()).status, "untrusted");
+    }
+}
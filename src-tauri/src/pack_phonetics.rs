@@ -0,0 +1,197 @@
+//! Phonetics for custom pack entries that were installed without them.
+//!
+//! Phonetics are computed by the frontend (src/utils.js), so packs converted
+//! or imported in Rust (glossaries without phonetics columns, Anki decks,
+//! StarDict, MDict...) and packs migrated from schema v1 come with empty
+//! phonetic columns, and can't be found by phonetic search. The frontend
+//! asks for those entries page by page, computes their phonetics and saves
+//! them back; the FTS triggers keep the search index up to date.
+
+use crate::custom_packs::get_custom_pack_paths;
+use crate::data_host::DataHost;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+
+const DEFAULT_PAGE_SIZE: usize = 500;
+
+/// Text of an entry whose phonetics are missing
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryText {
+    pub id: i64,
+    pub term: String,
+    pub definition: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPhonetics {
+    pub id: i64,
+    pub term_phonetics_strict: String,
+    pub term_phonetics_loose: String,
+    pub definition_phonetics_words_strict: String,
+    pub definition_phonetics_words_loose: String,
+}
+
+/// Only custom packs are filled in: official packs ship with their phonetics
+fn custom_pack_path(host: &dyn DataHost, pack_id: &str) -> Result<PathBuf, String> {
+    get_custom_pack_paths(host)
+        .into_iter()
+        .find(|(id, _)| id == pack_id)
+        .map(|(_, path)| path)
+        .ok_or_else(|| format!("Custom pack not installed: {}", pack_id))
+}
+
+/// Entries after `after_id` with none of the four phonetic columns set,
+/// by id. Paging by id lets the caller go through the pack once, even when
+/// an entry has no Tibetan to compute phonetics from.
+pub(crate) fn entries_without_phonetics(
+    conn: &Connection,
+    after_id: i64,
+    limit: usize,
+) -> rusqlite::Result<Vec<EntryText>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, term, definition FROM entries
+         WHERE id > ? AND termPhoneticsStrict = '' AND termPhoneticsLoose = ''
+           AND definitionPhoneticsWordsStrict = '' AND definitionPhoneticsWordsLoose = ''
+         ORDER BY id LIMIT ?",
+    )?;
+    let entries = stmt
+        .query_map(params![after_id, limit as i64], |row| {
+            Ok(EntryText {
+                id: row.get(0)?,
+                term: row.get(1)?,
+                definition: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+/// Save computed phonetics, in one transaction. Returns the number of
+/// entries updated.
+pub(crate) fn save_phonetics(conn: &mut Connection, phonetics: &[EntryPhonetics]) -> rusqlite::Result<usize> {
+    let tx = conn.transaction()?;
+    let mut updated = 0;
+    {
+        let mut update = tx.prepare(
+            "UPDATE entries SET termPhoneticsStrict = ?, termPhoneticsLoose = ?,
+                                definitionPhoneticsWordsStrict = ?, definitionPhoneticsWordsLoose = ?
+             WHERE id = ?",
+        )?;
+        for entry in phonetics {
+            updated += update.execute(params![
+                entry.term_phonetics_strict,
+                entry.term_phonetics_loose,
+                entry.definition_phonetics_words_strict,
+                entry.definition_phonetics_words_loose,
+                entry.id
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(updated)
+}
+
+/// Entries of a custom pack still missing their phonetics, `limit` at a
+/// time (500 by default) starting after the entry id `after_id`
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn list_entries_missing_phonetics(
+    app: AppHandle,
+    pack_id: String,
+    after_id: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<EntryText>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = Connection::open(custom_pack_path(&app, &pack_id)?)
+            .map_err(|e| format!("Failed to open pack {}: {}", pack_id, e))?;
+        entries_without_phonetics(&conn, after_id.unwrap_or(0), limit.unwrap_or(DEFAULT_PAGE_SIZE))
+            .map_err(|e| format!("Failed to read pack {}: {}", pack_id, e))
+    })
+    .await
+    .map_err(|e| format!("Phonetics task failed: {}", e))?
+}
+
+/// Store phonetics computed by the frontend for entries of a custom pack
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn save_entry_phonetics(
+    app: AppHandle,
+    pack_id: String,
+    phonetics: Vec<EntryPhonetics>,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut conn = Connection::open(custom_pack_path(&app, &pack_id)?)
+            .map_err(|e| format!("Failed to open pack {}: {}", pack_id, e))?;
+        save_phonetics(&mut conn, &phonetics).map_err(|e| format!("Failed to save phonetics of {}: {}", pack_id, e))
+    })
+    .await
+    .map_err(|e| format!("Phonetics task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tibdict_builder::{build_pack_sqlite, TibdictEntry};
+
+    fn entry(term: &str, phonetics: Option<&str>) -> TibdictEntry {
+        TibdictEntry {
+            term: term.to_string(),
+            definition: "definition".to_string(),
+            dictionary: None,
+            term_phonetics_strict: phonetics.map(String::from),
+            term_phonetics_loose: phonetics.map(String::from),
+            definition_phonetics_words_strict: None,
+            definition_phonetics_words_loose: None,
+        }
+    }
+
+    #[test]
+    fn fills_in_missing_phonetics() {
+        let dir = std::env::temp_dir().join(format!("pack-phonetics-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.sqlite");
+        let _ = std::fs::remove_file(&path);
+        build_pack_sqlite(
+            &path,
+            &[entry("ཀ་", None), entry("ཁ་", Some("kha")), entry("ག་", None), entry("abc", None)],
+            "Test",
+        )
+        .unwrap();
+        let mut conn = Connection::open(&path).unwrap();
+
+        let missing = entries_without_phonetics(&conn, 0, 2).unwrap();
+        assert_eq!(missing.iter().map(|e| e.term.as_str()).collect::<Vec<_>>(), ["ཀ་", "ག་"]);
+        let rest = entries_without_phonetics(&conn, missing[1].id, 2).unwrap();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].term, "abc་");
+
+        let updated = save_phonetics(
+            &mut conn,
+            &[EntryPhonetics {
+                id: missing[0].id,
+                term_phonetics_strict: "ka".to_string(),
+                term_phonetics_loose: "ka".to_string(),
+                definition_phonetics_words_strict: String::new(),
+                definition_phonetics_words_loose: String::new(),
+            }],
+        )
+        .unwrap();
+        assert_eq!(updated, 1);
+        assert_eq!(entries_without_phonetics(&conn, 0, 10).unwrap().len(), 2);
+
+        let found: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM entries_fts WHERE entries_fts MATCH 'termPhoneticsStrict : ka'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(found, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,709 @@
+//! Optional Ed25519 signatures on .tibdict packs.
+//!
+//! A signed pack carries `signature.json` next to manifest.json and
+//! data.sqlite. The signature covers the SHA-256 of both files (manifest as
+//! stored, byte for byte) and the signer's name, so neither can be swapped
+//! after signing. Whether a valid signature is trusted depends on the keys
+//! the user added to trusted-keys.json; in strict mode only packs signed by
+//! one of them can be installed. While trusted-keys.json exists but can't be
+//! read, every install is refused.
+//!
+//! Statuses reported with installed packs:
+//!   - "unsigned"  : no signature.json
+//!   - "verified"  : valid signature by a trusted key
+//!   - "untrusted" : valid signature by an unknown key
+//!   - "tampered"  : signature or hashes don't match (never installed)
+//!   - "migrated"  : was signed when installed, then migrated to the current
+//!     schema (see pack_migrations.rs), so the signature no longer covers the
+//!     files. signature.json is kept as signature.migrated.json, with the
+//!     schema version it was made for.
+
+use crate::custom_packs::InstallError;
+use crate::data_host::DataHost;
+use crate::tibdict_builder::now_iso8601;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use once_cell::sync::Lazy;
+use ring::digest::{Context, SHA256};
+#[cfg(feature = "app")]
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub(crate) const SIGNATURE_FILE: &str = "signature.json";
+
+/// Replaces signature.json once a signed pack was migrated
+pub(crate) const MIGRATED_SIGNATURE_FILE: &str = "signature.migrated.json";
+
+/// First line of every signed message, so signatures can't be replayed
+/// from another context using the same key
+const SIGNATURE_CONTEXT: &str = "tibdict-signature-v1";
+
+const ALGORITHM: &str = "ed25519";
+
+/// Serializes read-modify-write of trusted-keys.json
+static TRUST_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Contents of signature.json
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PackSignature {
+    pub algorithm: String,
+    /// Base64 of the 32-byte Ed25519 public key
+    pub public_key: String,
+    #[serde(default)]
+    pub signer: Option<String>,
+    pub manifest_sha256: String,
+    pub sqlite_sha256: String,
+    /// Base64 of the 64-byte signature
+    pub signature: String,
+    #[serde(default)]
+    pub signed_at: Option<String>,
+}
+
+/// Contents of signature.migrated.json
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct MigratedSignature {
+    /// Schema version of the signed files
+    from_schema_version: u32,
+    signature: PackSignature,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// "unsigned", "verified", "untrusted", "tampered" or "migrated"
+    pub status: String,
+    pub signed: bool,
+    #[serde(default)]
+    pub public_key: Option<String>,
+    /// Name given by the signer, as found in the pack
+    #[serde(default)]
+    pub signer: Option<String>,
+    /// Name the key was given in the trusted keys store
+    #[serde(default)]
+    pub trusted_as: Option<String>,
+    /// Schema version the signature was made for, once migrated
+    #[serde(default)]
+    pub migrated_from: Option<u32>,
+}
+
+impl Provenance {
+    fn unsigned() -> Self {
+        Provenance {
+            status: "unsigned".to_string(),
+            signed: false,
+            public_key: None,
+            signer: None,
+            trusted_as: None,
+            migrated_from: None,
+        }
+    }
+
+    /// Provenance of a signed pack once migrated from schema `from_version`:
+    /// the signer is still shown, but the files are no longer verified
+    pub(crate) fn migrated(self, from_version: u32) -> Self {
+        if !self.signed {
+            return self;
+        }
+        Provenance {
+            status: "migrated".to_string(),
+            migrated_from: Some(from_version),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedKey {
+    pub name: String,
+    pub public_key: String,
+    #[serde(default)]
+    pub added_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustSettings {
+    /// Refuse packs not signed by a trusted key
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default)]
+    pub keys: Vec<TrustedKey>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningKey {
+    pub public_key: String,
+    /// Base64 PKCS#8 document; keep it private
+    pub private_key: String,
+}
+
+/// Incremental SHA-256, so data.sqlite can be hashed while it is streamed
+/// to disk by the installer
+pub(crate) struct ContentHasher(Context);
+
+impl ContentHasher {
+    pub(crate) fn new() -> Self {
+        ContentHasher(Context::new(&SHA256))
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Lowercase hex digest
+    pub(crate) fn finish(self) -> String {
+        self.0
+            .finish()
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = ContentHasher::new();
+    hasher.update(data);
+    hasher.finish()
+}
+
+fn signed_message(manifest_sha256: &str, sqlite_sha256: &str, signer: Option<&str>) -> Vec<u8> {
+    format!(
+        "{}\n{}\n{}\n{}\n",
+        SIGNATURE_CONTEXT,
+        manifest_sha256,
+        sqlite_sha256,
+        signer.unwrap_or("")
+    )
+    .into_bytes()
+}
+
+fn trust_file(host: &dyn DataHost) -> Result<PathBuf, String> {
+    Ok(host.app_data_dir()?.join("trusted-keys.json"))
+}
+
+/// The trust store, or the default (no keys, not strict) before anything
+/// was saved. A store that can't be read or parsed is an error rather than
+/// the default, which would quietly turn strict mode off.
+pub(crate) fn load_trust_settings(host: &dyn DataHost) -> Result<TrustSettings, String> {
+    let path = trust_file(host)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(TrustSettings::default()),
+        Err(e) => return Err(format!("Failed to read trusted keys ({}): {}", path.display(), e)),
+    };
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse trusted keys ({}): {}", path.display(), e))
+}
+
+fn save_trust_settings(host: &dyn DataHost, settings: &TrustSettings) -> Result<(), String> {
+    let path = trust_file(host)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    }
+    let json = serde_json::to_vec_pretty(settings)
+        .map_err(|e| format!("Failed to serialize trusted keys: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write trusted keys: {}", e))
+}
+
+/// Provenance of a signature whose hashes were already checked: only the
+/// trust store decides between verified and untrusted
+fn trust_status(signature: &PackSignature, settings: &TrustSettings) -> Provenance {
+    let trusted = settings
+        .keys
+        .iter()
+        .find(|key| key.public_key == signature.public_key);
+    Provenance {
+        status: if trusted.is_some() {
+            "verified"
+        } else {
+            "untrusted"
+        }
+        .to_string(),
+        signed: true,
+        public_key: Some(signature.public_key.clone()),
+        signer: signature.signer.clone(),
+        trusted_as: trusted.map(|key| key.name.clone()),
+        migrated_from: None,
+    }
+}
+
+/// Check a pack's signature against its files. Err carries the reason it
+/// is tampered.
+fn verify_signature(
+    manifest_bytes: &[u8],
+    sqlite_sha256: &str,
+    signature: &PackSignature,
+) -> Result<(), String> {
+    if signature.algorithm != ALGORITHM {
+        return Err(format!(
+            "unsupported signature algorithm: {}",
+            signature.algorithm
+        ));
+    }
+    if sha256_hex(manifest_bytes) != signature.manifest_sha256 {
+        return Err("manifest.json does not match its signature".to_string());
+    }
+    if sqlite_sha256 != signature.sqlite_sha256 {
+        return Err("data.sqlite does not match its signature".to_string());
+    }
+    let public_key = STANDARD
+        .decode(&signature.public_key)
+        .map_err(|_| "bad public key encoding".to_string())?;
+    let signature_bytes = STANDARD
+        .decode(&signature.signature)
+        .map_err(|_| "bad signature encoding".to_string())?;
+    let message = signed_message(
+        &signature.manifest_sha256,
+        &signature.sqlite_sha256,
+        signature.signer.as_deref(),
+    );
+    UnparsedPublicKey::new(&ED25519, &public_key)
+        .verify(&message, &signature_bytes)
+        .map_err(|_| "signature is not valid".to_string())
+}
+
+/// Provenance of an incoming pack, refusing tampered packs, and packs not
+/// signed by a trusted key in strict mode. `sqlite_sha256` is the hex
+/// digest of its data.sqlite (see ContentHasher).
+pub(crate) fn check_provenance(
+    host: &dyn DataHost,
+    manifest_bytes: &[u8],
+    sqlite_sha256: &str,
+    signature_bytes: Option<&[u8]>,
+) -> Result<Provenance, InstallError> {
+    // Without a readable trust store, strict mode can't be ruled out
+    let settings = load_trust_settings(host).map_err(|e| InstallError::new("untrusted", &e))?;
+
+    let provenance = match signature_bytes {
+        None => Provenance::unsigned(),
+        Some(bytes) => {
+            let tampered = |message: &str| {
+                let mut err = InstallError::new("tampered", message);
+                err.provenance = Some(Box::new(Provenance {
+                    status: "tampered".to_string(),
+                    ..Provenance::unsigned()
+                }));
+                err
+            };
+            let signature: PackSignature = serde_json::from_slice(bytes)
+                .map_err(|e| tampered(&format!("bad signature.json: {e}")))?;
+            verify_signature(manifest_bytes, sqlite_sha256, &signature).map_err(|e| {
+                let mut err = tampered(&e);
+                if let Some(provenance) = err.provenance.as_mut() {
+                    provenance.signed = true;
+                    provenance.public_key = Some(signature.public_key.clone());
+                    provenance.signer = signature.signer.clone();
+                }
+                err
+            })?;
+            trust_status(&signature, &settings)
+        }
+    };
+
+    if settings.strict && provenance.status != "verified" {
+        let mut err = InstallError::new(
+            "untrusted",
+            match provenance.status.as_str() {
+                "unsigned" => "strict mode: only packs signed by a trusted key can be installed",
+                _ => "strict mode: this pack is signed by a key that is not trusted",
+            },
+        );
+        err.provenance = Some(Box::new(provenance));
+        return Err(err);
+    }
+    Ok(provenance)
+}
+
+/// Provenance of an installed pack directory. Hashes were verified at
+/// install time; trust is re-evaluated against the current store.
+pub(crate) fn installed_provenance(pack_dir: &Path, settings: &TrustSettings) -> Provenance {
+    let read = |name: &str| fs::read(pack_dir.join(name)).ok();
+    if let Some(bytes) = read(SIGNATURE_FILE) {
+        return serde_json::from_slice::<PackSignature>(&bytes)
+            .map(|signature| trust_status(&signature, settings))
+            .unwrap_or_else(|_| Provenance::unsigned());
+    }
+    read(MIGRATED_SIGNATURE_FILE)
+        .and_then(|bytes| serde_json::from_slice::<MigratedSignature>(&bytes).ok())
+        .map(|migrated| trust_status(&migrated.signature, settings).migrated(migrated.from_schema_version))
+        .unwrap_or_else(Provenance::unsigned)
+}
+
+/// Move signature.json of a pack whose files were just migrated from
+/// `from_version` to signature.migrated.json. A pack migrated before keeps
+/// the version its signature was made for.
+pub(crate) fn retire_signature(pack_dir: &Path, from_version: u32) -> Result<(), String> {
+    let signature_path = pack_dir.join(SIGNATURE_FILE);
+    let bytes = match fs::read(&signature_path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Failed to read signature: {}", e)),
+    };
+    // A signature that can't be parsed never counted, see installed_provenance
+    if let Ok(signature) = serde_json::from_slice::<PackSignature>(&bytes) {
+        let migrated = MigratedSignature {
+            from_schema_version: from_version,
+            signature,
+        };
+        let json = serde_json::to_vec_pretty(&migrated).map_err(|e| e.to_string())?;
+        fs::write(pack_dir.join(MIGRATED_SIGNATURE_FILE), json)
+            .map_err(|e| format!("Failed to write migrated signature: {}", e))?;
+    }
+    fs::remove_file(&signature_path).map_err(|e| format!("Failed to remove signature: {}", e))
+}
+
+fn decode_public_key(public_key: &str) -> Result<String, String> {
+    let bytes = STANDARD
+        .decode(public_key.trim())
+        .map_err(|_| "public key must be base64".to_string())?;
+    if bytes.len() != 32 {
+        return Err("an Ed25519 public key is 32 bytes".to_string());
+    }
+    // Normalized encoding, so keys compare as strings
+    Ok(STANDARD.encode(bytes))
+}
+
+fn write_signed_tibdict(
+    output: &Path,
+    manifest_bytes: &[u8],
+    sqlite_bytes: &[u8],
+    signature: &PackSignature,
+) -> io::Result<()> {
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    let signature_json = serde_json::to_vec_pretty(signature)?;
+
+    let mut zip = ZipWriter::new(File::create(output)?);
+    zip.start_file("manifest.json", options)?;
+    zip.write_all(manifest_bytes)?;
+    zip.start_file("data.sqlite", options)?;
+    zip.write_all(sqlite_bytes)?;
+    zip.start_file(SIGNATURE_FILE, options)?;
+    zip.write_all(&signature_json)?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// Sign the .tibdict at `path` in place, replacing any previous signature
+fn sign_tibdict(
+    path: &Path,
+    key_pair: &Ed25519KeyPair,
+    signer: Option<String>,
+) -> Result<PackSignature, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read pack: {}", e))?;
+    let mut archive =
+        ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("Failed to read zip: {}", e))?;
+    let mut read_entry = |name: &str| -> Result<Vec<u8>, String> {
+        let mut entry = archive
+            .by_name(name)
+            .map_err(|_| format!("missing {}", name))?;
+        let mut buf = Vec::new();
+        entry
+            .read_to_end(&mut buf)
+            .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        Ok(buf)
+    };
+    let manifest_bytes = read_entry("manifest.json")?;
+    let sqlite_bytes = read_entry("data.sqlite")?;
+
+    let signer = signer
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    let manifest_sha256 = sha256_hex(&manifest_bytes);
+    let sqlite_sha256 = sha256_hex(&sqlite_bytes);
+    let message = signed_message(&manifest_sha256, &sqlite_sha256, signer.as_deref());
+    let signature = PackSignature {
+        algorithm: ALGORITHM.to_string(),
+        public_key: STANDARD.encode(key_pair.public_key().as_ref()),
+        signer,
+        manifest_sha256,
+        sqlite_sha256,
+        signature: STANDARD.encode(key_pair.sign(&message).as_ref()),
+        signed_at: Some(now_iso8601()),
+    };
+
+    // Rewrite next to the original, then swap
+    let temp_path = PathBuf::from(format!("{}.signing.tmp", path.display()));
+    let written = write_signed_tibdict(&temp_path, &manifest_bytes, &sqlite_bytes, &signature);
+    if let Err(e) = written.and_then(|_| fs::rename(&temp_path, path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to write signed pack: {}", e));
+    }
+    Ok(signature)
+}
+
+/// Create a new Ed25519 key pair for signing packs
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn generate_signing_key() -> Result<SigningKey, String> {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+        .map_err(|_| "Failed to generate key".to_string())?;
+    let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+        .map_err(|_| "Failed to generate key".to_string())?;
+    Ok(SigningKey {
+        public_key: STANDARD.encode(key_pair.public_key().as_ref()),
+        private_key: STANDARD.encode(pkcs8.as_ref()),
+    })
+}
+
+/// Sign a .tibdict file with a key from generate_signing_key. `signer` is
+/// the name recipients will see next to the signature.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn sign_custom_pack(
+    file_path: String,
+    private_key: String,
+    signer: Option<String>,
+) -> Result<PackSignature, String> {
+    let pkcs8 = STANDARD
+        .decode(private_key.trim())
+        .map_err(|_| "private key must be base64".to_string())?;
+    let key_pair =
+        Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|_| "invalid private key".to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        sign_tibdict(Path::new(&file_path), &key_pair, signer)
+    })
+    .await
+    .map_err(|e| format!("Signing task failed: {}", e))?
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn get_trust_settings(app: AppHandle) -> Result<TrustSettings, String> {
+    load_trust_settings(&app)
+}
+
+/// Trust packs signed by `public_key` (base64), shown as `name`
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn add_trusted_key(
+    app: AppHandle,
+    name: String,
+    public_key: String,
+) -> Result<TrustSettings, String> {
+    let public_key = decode_public_key(&public_key)?;
+    let _guard = TRUST_LOCK
+        .lock()
+        .map_err(|e| format!("Failed to lock trusted keys: {}", e))?;
+
+    let mut settings = load_trust_settings(&app)?;
+    settings.keys.retain(|key| key.public_key != public_key);
+    settings.keys.push(TrustedKey {
+        name: name.trim().to_string(),
+        public_key,
+        added_at: Some(now_iso8601()),
+    });
+    save_trust_settings(&app, &settings)?;
+    Ok(settings)
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn remove_trusted_key(
+    app: AppHandle,
+    public_key: String,
+) -> Result<TrustSettings, String> {
+    let public_key = decode_public_key(&public_key)?;
+    let _guard = TRUST_LOCK
+        .lock()
+        .map_err(|e| format!("Failed to lock trusted keys: {}", e))?;
+
+    let mut settings = load_trust_settings(&app)?;
+    settings.keys.retain(|key| key.public_key != public_key);
+    save_trust_settings(&app, &settings)?;
+    Ok(settings)
+}
+
+/// In strict mode, unsigned and untrusted packs are refused at install
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn set_strict_signatures(app: AppHandle, strict: bool) -> Result<TrustSettings, String> {
+    let _guard = TRUST_LOCK
+        .lock()
+        .map_err(|e| format!("Failed to lock trusted keys: {}", e))?;
+
+    let mut settings = load_trust_settings(&app)?;
+    settings.strict = strict;
+    save_trust_settings(&app, &settings)?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_host::TestDataDir;
+    use ring::rand::SystemRandom;
+
+    const MANIFEST: &[u8] = br#"{"id":"test","name":"Test","version":"1.0.0"}"#;
+    const SQLITE: &[u8] = b"SQLite format 3\0 pretend";
+
+    fn key_pair() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    fn public_key(key_pair: &Ed25519KeyPair) -> String {
+        STANDARD.encode(key_pair.public_key().as_ref())
+    }
+
+    /// signature.json of MANIFEST and SQLITE signed by `key_pair`, read back
+    /// from a signed .tibdict
+    fn signed(dir: &TestDataDir, key_pair: &Ed25519KeyPair) -> Vec<u8> {
+        let path = dir.root.join("pack.tibdict");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("manifest.json", SimpleFileOptions::default()).unwrap();
+        zip.write_all(MANIFEST).unwrap();
+        zip.start_file("data.sqlite", SimpleFileOptions::default()).unwrap();
+        zip.write_all(SQLITE).unwrap();
+        zip.finish().unwrap();
+
+        sign_tibdict(&path, key_pair, Some(" Publisher ".to_string())).unwrap();
+        let mut archive = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut read = |name: &str| {
+            let mut buf = Vec::new();
+            archive.by_name(name).unwrap().read_to_end(&mut buf).unwrap();
+            buf
+        };
+        assert_eq!(read("manifest.json"), MANIFEST);
+        assert_eq!(read("data.sqlite"), SQLITE);
+        read(SIGNATURE_FILE)
+    }
+
+    fn trust(dir: &TestDataDir, key_pair: &Ed25519KeyPair, strict: bool) {
+        let settings = TrustSettings {
+            strict,
+            keys: vec![TrustedKey {
+                name: "Known publisher".to_string(),
+                public_key: public_key(key_pair),
+                added_at: None,
+            }],
+        };
+        save_trust_settings(dir, &settings).unwrap();
+    }
+
+    fn code(result: Result<Provenance, InstallError>) -> String {
+        result.map(|provenance| provenance.status).unwrap_or_else(|e| e.code)
+    }
+
+    #[test]
+    fn verifies_signatures_against_the_trust_store() {
+        let dir = TestDataDir::new("signing-round-trip");
+        let key_pair = key_pair();
+        let signature = signed(&dir, &key_pair);
+        let sqlite_sha256 = sha256_hex(SQLITE);
+
+        let provenance = check_provenance(&dir, MANIFEST, &sqlite_sha256, Some(&signature)).unwrap();
+        assert_eq!(provenance.status, "untrusted");
+        assert!(provenance.signed);
+        assert_eq!(provenance.signer.as_deref(), Some("Publisher"));
+        assert_eq!(provenance.public_key, Some(public_key(&key_pair)));
+
+        trust(&dir, &key_pair, false);
+        let provenance = check_provenance(&dir, MANIFEST, &sqlite_sha256, Some(&signature)).unwrap();
+        assert_eq!(provenance.status, "verified");
+        assert_eq!(provenance.trusted_as.as_deref(), Some("Known publisher"));
+
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &sqlite_sha256, None)), "unsigned");
+    }
+
+    #[test]
+    fn refuses_tampered_packs() {
+        let dir = TestDataDir::new("signing-tampered");
+        let key_pair = key_pair();
+        let signature = signed(&dir, &key_pair);
+        trust(&dir, &key_pair, false);
+        let sqlite_sha256 = sha256_hex(SQLITE);
+
+        let manifest = br#"{"id":"test","name":"Renamed","version":"1.0.0"}"#;
+        let err = check_provenance(&dir, manifest, &sqlite_sha256, Some(&signature)).unwrap_err();
+        assert_eq!(err.code, "tampered");
+        assert_eq!(err.provenance.map(|p| p.status).as_deref(), Some("tampered"));
+
+        let other_sqlite = sha256_hex(b"SQLite format 3\0 changed");
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &other_sqlite, Some(&signature))), "tampered");
+
+        // Hashes rewritten to match, but the signature no longer covers them
+        let mut forged: PackSignature = serde_json::from_slice(&signature).unwrap();
+        forged.sqlite_sha256 = other_sqlite.clone();
+        let forged = serde_json::to_vec(&forged).unwrap();
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &other_sqlite, Some(&forged))), "tampered");
+
+        // A trusted key claimed, the signature made with another one
+        let mut impostor: PackSignature = serde_json::from_slice(&signed(&dir, &self::key_pair())).unwrap();
+        impostor.public_key = public_key(&key_pair);
+        let impostor = serde_json::to_vec(&impostor).unwrap();
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &sqlite_sha256, Some(&impostor))), "tampered");
+
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &sqlite_sha256, Some(b"{}"))), "tampered");
+    }
+
+    #[test]
+    fn strict_mode_only_installs_trusted_packs() {
+        let dir = TestDataDir::new("signing-strict");
+        let trusted = key_pair();
+        let unknown = key_pair();
+        trust(&dir, &trusted, true);
+        let sqlite_sha256 = sha256_hex(SQLITE);
+
+        let signature = signed(&dir, &trusted);
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &sqlite_sha256, Some(&signature))), "verified");
+
+        let signature = signed(&dir, &unknown);
+        let err = check_provenance(&dir, MANIFEST, &sqlite_sha256, Some(&signature)).unwrap_err();
+        assert_eq!(err.code, "untrusted");
+        assert_eq!(err.provenance.map(|p| p.status).as_deref(), Some("untrusted"));
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &sqlite_sha256, None)), "untrusted");
+    }
+
+    #[test]
+    fn refuses_every_install_while_the_trust_store_is_unreadable() {
+        let dir = TestDataDir::new("signing-unreadable");
+        let key_pair = key_pair();
+        let signature = signed(&dir, &key_pair);
+        let sqlite_sha256 = sha256_hex(SQLITE);
+        let trust_path = trust_file(&dir).unwrap();
+
+        fs::write(&trust_path, b"{ \"strict\": true, ").unwrap();
+        assert!(load_trust_settings(&dir).is_err());
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &sqlite_sha256, None)), "untrusted");
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &sqlite_sha256, Some(&signature))), "untrusted");
+
+        fs::remove_file(&trust_path).unwrap();
+        fs::create_dir(&trust_path).unwrap();
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &sqlite_sha256, None)), "untrusted");
+
+        fs::remove_dir(&trust_path).unwrap();
+        assert_eq!(code(check_provenance(&dir, MANIFEST, &sqlite_sha256, None)), "unsigned");
+    }
+
+    #[test]
+    fn installed_packs_are_trusted_by_the_current_store() {
+        let dir = TestDataDir::new("signing-installed");
+        let key_pair = key_pair();
+        let pack_dir = dir.root.join("installed");
+        fs::create_dir_all(&pack_dir).unwrap();
+        assert_eq!(installed_provenance(&pack_dir, &TrustSettings::default()).status, "unsigned");
+
+        fs::write(pack_dir.join(SIGNATURE_FILE), signed(&dir, &key_pair)).unwrap();
+        assert_eq!(installed_provenance(&pack_dir, &TrustSettings::default()).status, "untrusted");
+        trust(&dir, &key_pair, false);
+        let settings = load_trust_settings(&dir).unwrap();
+        assert_eq!(installed_provenance(&pack_dir, &settings).status, "verified");
+        assert_eq!(decode_public_key(&public_key(&key_pair)), Ok(public_key(&key_pair)));
+        assert!(decode_public_key("c2hvcnQ=").is_err());
+    }
+}
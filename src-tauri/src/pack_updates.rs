@@ -0,0 +1,639 @@
+//! Update channels for custom packs.
+//!
+//! A pack may carry an `updateUrl` in its manifest, pointing to a small JSON
+//! feed over http(s). The user may set a feed of their own for a pack
+//! (`<app_data>/pack-feeds.json`), which takes precedence and may also be a
+//! local file (a path or a file:// URL); a manifest can't point at local
+//! files:
+//!
+//! ```json
+//! { "id": "my-pack", "version": "1.2.0", "url": "my-pack-1.2.0.tibdict",
+//!   "notes": "Fixed typos", "sha256": "…" }
+//! ```
+//!
+//! `url` may be relative to the feed; `id`, `notes` and `sha256` are
+//! optional, except that anything served over plain http needs `sha256`. A
+//! remote feed can only point at http(s) URLs. Requests give up on a server
+//! that stops answering (see CONNECT_TIMEOUT and READ_TIMEOUT), so one hung
+//! feed doesn't stall the check of the others. Updating downloads the new
+//! .tibdict, backs up the installed pack to `.backup-<id>` and installs
+//! through install_custom_pack with force, so the new file gets the same
+//! validation, signature checks and migrations as any other install.
+
+#[cfg(feature = "app")]
+use crate::custom_packs::{
+    custom_packs_dir, install_custom_pack, InstalledCustomPack, CUSTOM_ID_PREFIX,
+};
+use crate::custom_packs::{InstallError, TibdictManifest};
+use crate::data_host::DataHost;
+#[cfg(feature = "app")]
+use crate::pack_indexes::refresh_indexes_in_background;
+#[cfg(feature = "app")]
+use crate::pack_signing::{installed_provenance, load_trust_settings};
+use crate::pack_signing::ContentHasher;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+use zip::ZipArchive;
+
+/// Longest wait for a feed or pack server to accept the connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Longest wait for a whole feed, or for the next part of a pack download
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct UpdateFeed {
+    #[serde(default)]
+    id: Option<String>,
+    version: String,
+    url: String,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomPackUpdate {
+    pub pack_id: String,
+    pub name: String,
+    pub installed_version: Option<String>,
+    pub latest_version: Option<String>,
+    pub update_available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Set when the feed couldn't be read; the other packs are still checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Compare two semantic versions ("1.2.0", "v1.10", "2.0.0-beta.1").
+/// Missing components count as 0, a pre-release sorts before its release,
+/// build metadata is ignored. None when either isn't a version.
+pub(crate) fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    fn parse(version: &str) -> Option<(Vec<u64>, Option<&str>)> {
+        let version = version.trim();
+        let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+        let version = version.split('+').next().unwrap_or_default();
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version, None),
+        };
+        let numbers = core.split('.').map(|n| n.parse().ok()).collect::<Option<Vec<u64>>>()?;
+        Some((numbers, pre))
+    }
+
+    let (a_numbers, a_pre) = parse(a)?;
+    let (b_numbers, b_pre) = parse(b)?;
+    for i in 0..a_numbers.len().max(b_numbers.len()) {
+        let x = a_numbers.get(i).copied().unwrap_or(0);
+        let y = b_numbers.get(i).copied().unwrap_or(0);
+        if x != y {
+            return Some(x.cmp(&y));
+        }
+    }
+    Some(match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(x), Some(y)) => compare_prerelease(x, y),
+    })
+}
+
+/// Dot-separated identifiers: numeric ones compare as numbers and sort
+/// before alphanumeric ones
+fn compare_prerelease(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    (Err(_), Err(_)) => x.cmp(y),
+                };
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+        }
+    }
+}
+
+fn is_remote(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+fn local_path(location: &str) -> PathBuf {
+    PathBuf::from(location.strip_prefix("file://").unwrap_or(location))
+}
+
+fn user_feeds_file(host: &dyn DataHost) -> Result<PathBuf, String> {
+    Ok(host.app_data_dir()?.join("pack-feeds.json"))
+}
+
+/// Feeds the user set, by pack id
+pub(crate) fn load_user_feeds(host: &dyn DataHost) -> Result<BTreeMap<String, String>, String> {
+    let path = user_feeds_file(host)?;
+    match fs::read(&path) {
+        Ok(contents) => serde_json::from_slice(&contents).map_err(|e| format!("Bad pack feeds in {}: {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Set or clear (None or blank) the feed the user chose for `pack_id`
+pub(crate) fn save_user_feed(host: &dyn DataHost, pack_id: &str, location: Option<&str>) -> Result<(), String> {
+    let mut feeds = load_user_feeds(host)?;
+    match location.map(str::trim).filter(|location| !location.is_empty()) {
+        Some(location) => feeds.insert(pack_id.to_string(), location.to_string()),
+        None => feeds.remove(pack_id),
+    };
+    let path = user_feeds_file(host)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    }
+    let json = serde_json::to_vec_pretty(&feeds).map_err(|e| format!("Failed to serialize pack feeds: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write pack feeds: {}", e))
+}
+
+/// The feed to check for `pack_id`: the user's, else the manifest's
+/// updateUrl, which has to be http(s). None when the pack has neither.
+fn feed_location(
+    user_feeds: &BTreeMap<String, String>,
+    pack_id: &str,
+    manifest: &TibdictManifest,
+) -> Result<Option<String>, String> {
+    if let Some(location) = user_feeds.get(pack_id) {
+        return Ok(Some(location.clone()));
+    }
+    match manifest.update_url.as_deref() {
+        Some(url) if !is_remote(url) => Err(format!(
+            "updateUrl {} is not an http(s) URL; a local feed can only be set by you",
+            url
+        )),
+        url => Ok(url.map(str::to_string)),
+    }
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Resolve the pack URL of a feed, which may be relative to the feed itself.
+/// A remote feed may only point at http(s) URLs: local paths and file://
+/// are only followed from a local feed.
+fn resolve_location(feed_location: &str, url: &str) -> Result<String, String> {
+    if is_remote(feed_location) {
+        let base = reqwest::Url::parse(feed_location).map_err(|e| format!("Bad feed URL: {}", e))?;
+        let joined = base.join(url).map_err(|e| format!("Bad pack URL: {}", e))?;
+        if !matches!(joined.scheme(), "http" | "https") {
+            return Err(format!("A remote feed can't point at {}", url));
+        }
+        return Ok(joined.to_string());
+    }
+    if is_remote(url) || url.starts_with("file://") || Path::new(url).is_absolute() {
+        return Ok(url.to_string());
+    }
+    let feed_path = local_path(feed_location);
+    let dir = feed_path.parent().unwrap_or_else(|| Path::new(""));
+    Ok(dir.join(url).to_string_lossy().to_string())
+}
+
+/// What comes over plain http can be swapped on the way: the feed has to
+/// give the pack's sha256 for it
+fn require_sha256(location: &str, feed: &UpdateFeed) -> Result<(), String> {
+    if location.starts_with("http://") && feed.sha256.is_none() {
+        return Err(format!("{} is served over plain http and the feed gives no sha256", location));
+    }
+    Ok(())
+}
+
+async fn fetch_feed(client: &reqwest::Client, location: &str) -> Result<UpdateFeed, String> {
+    if !is_remote(location) {
+        let contents = fs::read(local_path(location)).map_err(|e| format!("Failed to read feed: {}", e))?;
+        return serde_json::from_slice(&contents).map_err(|e| format!("Failed to parse feed: {}", e));
+    }
+
+    let response = client
+        .get(location)
+        .timeout(READ_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch feed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch feed: HTTP {}", response.status()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse feed: {}", e))
+}
+
+/// Writes to the file and to the hash at once, for io::copy
+struct HashingWriter<'a> {
+    file: &'a mut File,
+    hasher: &'a mut ContentHasher,
+}
+
+impl Write for HashingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.file.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Copy or download the new .tibdict to `dest`, returning its SHA-256. A
+/// download only fails when the server goes quiet for READ_TIMEOUT, however
+/// long the whole pack takes.
+async fn fetch_pack(client: &reqwest::Client, location: &str, dest: &Path) -> Result<String, String> {
+    let mut file = File::create(dest).map_err(|e| format!("Failed to create file: {}", e))?;
+    let mut hasher = ContentHasher::new();
+
+    if !is_remote(location) {
+        let mut source = File::open(local_path(location)).map_err(|e| format!("Failed to read pack: {}", e))?;
+        let mut writer = HashingWriter { file: &mut file, hasher: &mut hasher };
+        io::copy(&mut source, &mut writer).map_err(|e| format!("Failed to copy pack: {}", e))?;
+        return Ok(hasher.finish());
+    }
+
+    let stalled = || format!("Download failed: no answer from the server for {}s", READ_TIMEOUT.as_secs());
+    let response = tokio::time::timeout(READ_TIMEOUT, client.get(location).send())
+        .await
+        .map_err(|_| stalled())?
+        .map_err(|e| format!("Download failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Download failed: HTTP {}", response.status()));
+    }
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = tokio::time::timeout(READ_TIMEOUT, stream.next()).await.map_err(|_| stalled())? {
+        let chunk = chunk.map_err(|e| format!("Download error: {}", e))?;
+        hasher.update(&chunk);
+        file.write_all(&chunk).map_err(|e| format!("Write error: {}", e))?;
+    }
+    Ok(hasher.finish())
+}
+
+fn read_installed_manifest(pack_dir: &Path) -> Result<TibdictManifest, String> {
+    let contents = fs::read(pack_dir.join("manifest.json")).map_err(|e| format!("Failed to read manifest: {}", e))?;
+    serde_json::from_slice(&contents).map_err(|e| format!("Failed to parse manifest: {}", e))
+}
+
+/// Check the feed against the installed manifest
+async fn check_pack(
+    client: &reqwest::Client,
+    manifest: &TibdictManifest,
+    update_url: &str,
+) -> Result<(UpdateFeed, bool), String> {
+    let feed = fetch_feed(client, update_url).await?;
+    require_sha256(update_url, &feed)?;
+    if let Some(id) = feed.id.as_deref().filter(|id| *id != manifest.id) {
+        return Err(format!("Feed is for pack {}, not {}", id, manifest.id));
+    }
+    let installed = manifest.version.as_deref().unwrap_or("0");
+    let newer = compare_versions(&feed.version, installed)
+        .ok_or_else(|| format!("Cannot compare versions {} and {}", feed.version, installed))?
+        == Ordering::Greater;
+    Ok((feed, newer))
+}
+
+/// Check every custom pack that has a feed for a newer version
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn check_custom_pack_updates(app: AppHandle) -> Result<Vec<CustomPackUpdate>, String> {
+    let packs_dir = custom_packs_dir(&app).map_err(|e| e.message)?;
+    let user_feeds = load_user_feeds(&app)?;
+    let client = http_client()?;
+    let entries = match fs::read_dir(&packs_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut updates = Vec::new();
+    for entry in entries.flatten() {
+        let pack_id = entry.file_name().to_string_lossy().to_string();
+        if !pack_id.starts_with(CUSTOM_ID_PREFIX) {
+            continue;
+        }
+        let Ok(manifest) = read_installed_manifest(&entry.path()) else {
+            continue;
+        };
+        let Some(location) = feed_location(&user_feeds, &pack_id, &manifest).transpose() else {
+            continue;
+        };
+
+        let mut update = CustomPackUpdate {
+            pack_id,
+            name: manifest.name.clone(),
+            installed_version: manifest.version.clone(),
+            latest_version: None,
+            update_available: false,
+            notes: None,
+            error: None,
+        };
+        let checked = match location {
+            Ok(location) => check_pack(&client, &manifest, &location).await,
+            Err(e) => Err(e),
+        };
+        match checked {
+            Ok((feed, newer)) => {
+                update.latest_version = Some(feed.version);
+                update.update_available = newer;
+                update.notes = feed.notes;
+            }
+            Err(e) => update.error = Some(e),
+        }
+        updates.push(update);
+    }
+
+    updates.sort_by(|a, b| a.pack_id.cmp(&b.pack_id));
+    Ok(updates)
+}
+
+/// Copy the installed pack's files to `.backup-<id>`, replacing any older backup
+fn backup_pack(pack_dir: &Path, backup_dir: &Path) -> Result<(), String> {
+    if backup_dir.exists() {
+        fs::remove_dir_all(backup_dir).map_err(|e| format!("Failed to remove old backup: {}", e))?;
+    }
+    fs::create_dir_all(backup_dir).map_err(|e| format!("Failed to create backup: {}", e))?;
+    for entry in fs::read_dir(pack_dir).map_err(|e| format!("Failed to read pack: {}", e))?.flatten() {
+        if entry.path().is_file() {
+            fs::copy(entry.path(), backup_dir.join(entry.file_name()))
+                .map_err(|e| format!("Failed to back up {:?}: {}", entry.file_name(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Install the newer version announced by the pack's feed. The previous
+/// version is kept in `.backup-<id>` (see restore_custom_pack_backup).
+/// Errors use the install_custom_pack codes; "path" also covers feeds and
+/// downloads that fail, "format" a feed with no newer version.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn update_custom_pack(app: AppHandle, pack_id: String) -> Result<InstalledCustomPack, InstallError> {
+    let packs_dir = custom_packs_dir(&app)?;
+    let pack_dir = packs_dir.join(&pack_id);
+    if !pack_id.starts_with(CUSTOM_ID_PREFIX) || !pack_dir.is_dir() {
+        return Err(InstallError::new("path", &format!("Custom pack {} not found", pack_id)));
+    }
+    let manifest = read_installed_manifest(&pack_dir).map_err(|e| InstallError::new("corrupt", &e))?;
+    let user_feeds = load_user_feeds(&app).map_err(|e| InstallError::new("path", &e))?;
+    let update_url = feed_location(&user_feeds, &pack_id, &manifest)
+        .map_err(|e| InstallError::new("path", &e))?
+        .ok_or_else(|| InstallError::new("format", "pack has no update feed"))?;
+
+    let client = http_client().map_err(|e| InstallError::new("path", &e))?;
+    let (feed, newer) = check_pack(&client, &manifest, &update_url)
+        .await
+        .map_err(|e| InstallError::new("path", &e))?;
+    if !newer {
+        return Err(InstallError::new("format", "no newer version available"));
+    }
+
+    let location = resolve_location(&update_url, &feed.url)
+        .and_then(|location| require_sha256(&location, &feed).map(|()| location))
+        .map_err(|e| InstallError::new("path", &e))?;
+    let download_path = packs_dir.join(format!(".download-{}.tibdict", pack_id));
+    let result = install_update(&app, &client, &pack_dir, &manifest, &feed, &location, &download_path).await;
+    let _ = fs::remove_file(&download_path);
+    result
+}
+
+#[cfg(feature = "app")]
+async fn install_update(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    pack_dir: &Path,
+    manifest: &TibdictManifest,
+    feed: &UpdateFeed,
+    location: &str,
+    download_path: &Path,
+) -> Result<InstalledCustomPack, InstallError> {
+    let sha256 = fetch_pack(client, location, download_path)
+        .await
+        .map_err(|e| InstallError::new("path", &e))?;
+    if feed.sha256.as_deref().map_or(false, |expected| !expected.eq_ignore_ascii_case(&sha256)) {
+        return Err(InstallError::new("tampered", "downloaded pack doesn't match the feed's sha256"));
+    }
+
+    // A feed must not swap the pack for another one: the install is forced
+    let incoming = downloaded_manifest(download_path)?;
+    if incoming.id != manifest.id {
+        return Err(InstallError::new(
+            "format",
+            &format!("update is for pack {}, not {}", incoming.id, manifest.id),
+        ));
+    }
+
+    let pack_id = format!("{CUSTOM_ID_PREFIX}{}", manifest.id);
+    let backup_dir = pack_dir.with_file_name(format!(".backup-{}", pack_id));
+    backup_pack(pack_dir, &backup_dir).map_err(|e| InstallError::new("path", &e))?;
+    install_custom_pack(
+        app.clone(),
+        download_path.to_string_lossy().to_string(),
+        Some(true),
+        None,
+        None,
+    )
+    .await
+}
+
+fn downloaded_manifest(path: &Path) -> Result<TibdictManifest, InstallError> {
+    let file = File::open(path).map_err(|e| InstallError::new("path", &format!("read download: {e}")))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| InstallError::new("corrupt", &format!("read zip: {e}")))?;
+    let entry = archive
+        .by_name("manifest.json")
+        .map_err(|_| InstallError::new("format", "missing manifest.json"))?;
+    serde_json::from_reader(entry.take(1024 * 1024))
+        .map_err(|e| InstallError::new("format", &format!("bad manifest json: {e}")))
+}
+
+/// Check `pack_id` for updates at `url`, a feed over http(s) or a local
+/// file, instead of its manifest's updateUrl; None or blank goes back to
+/// the manifest's
+#[cfg(feature = "app")]
+#[tauri::command]
+pub fn set_custom_pack_feed(app: AppHandle, pack_id: String, url: Option<String>) -> Result<(), String> {
+    if !pack_id.starts_with(CUSTOM_ID_PREFIX) {
+        return Err(format!("Custom pack {} not found", pack_id));
+    }
+    save_user_feed(&app, &pack_id, url.as_deref())
+}
+
+/// Put back the version saved by the last update_custom_pack
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn restore_custom_pack_backup(app: AppHandle, pack_id: String) -> Result<InstalledCustomPack, String> {
+    let packs_dir = custom_packs_dir(&app).map_err(|e| e.message)?;
+    let backup_dir = packs_dir.join(format!(".backup-{}", pack_id));
+    if !pack_id.starts_with(CUSTOM_ID_PREFIX) || !backup_dir.is_dir() {
+        return Err(format!("No backup for {}", pack_id));
+    }
+    let manifest = read_installed_manifest(&backup_dir)?;
+
+    let pack_dir = packs_dir.join(&pack_id);
+    if pack_dir.exists() {
+        fs::remove_dir_all(&pack_dir).map_err(|e| format!("Failed to remove {}: {}", pack_id, e))?;
+    }
+    fs::rename(&backup_dir, &pack_dir).map_err(|e| format!("Failed to restore {}: {}", pack_id, e))?;
+    refresh_indexes_in_background(&app);
+
+    let provenance = installed_provenance(&pack_dir, &load_trust_settings(&app).unwrap_or_default());
+    Ok(InstalledCustomPack { id: pack_id, manifest, provenance })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_release_versions() {
+        assert_eq!(compare_versions("1.2.0", "1.10.0"), Some(Ordering::Less));
+        assert_eq!(compare_versions("v2", "1.9.9"), Some(Ordering::Greater));
+        assert_eq!(compare_versions("1.2", "1.2.0"), Some(Ordering::Equal));
+        assert_eq!(compare_versions(" V1.0.1 ", "1.0.0"), Some(Ordering::Greater));
+        assert_eq!(compare_versions("1.0.0+build.5", "1.0.0+build.7"), Some(Ordering::Equal));
+    }
+
+    #[test]
+    fn pre_releases_sort_before_their_release() {
+        assert_eq!(compare_versions("2.0.0-beta.1", "2.0.0"), Some(Ordering::Less));
+        assert_eq!(compare_versions("2.0.0", "2.0.0-rc.1"), Some(Ordering::Greater));
+        assert_eq!(compare_versions("2.0.0-rc.1", "1.9.0"), Some(Ordering::Greater));
+    }
+
+    #[test]
+    fn compares_pre_release_identifiers() {
+        assert_eq!(compare_prerelease("alpha", "beta"), Ordering::Less);
+        assert_eq!(compare_prerelease("beta.2", "beta.10"), Ordering::Less);
+        assert_eq!(compare_prerelease("1", "alpha"), Ordering::Less);
+        assert_eq!(compare_prerelease("alpha", "alpha.1"), Ordering::Less);
+        assert_eq!(compare_prerelease("rc.1", "rc.1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn resolves_pack_locations() {
+        assert_eq!(
+            resolve_location("https://example.org/packs/feed.json", "pack.tibdict").unwrap(),
+            "https://example.org/packs/pack.tibdict"
+        );
+        assert_eq!(
+            resolve_location("https://example.org/feed.json", "https://cdn.example.org/pack.tibdict").unwrap(),
+            "https://cdn.example.org/pack.tibdict"
+        );
+        assert_eq!(
+            resolve_location("/srv/packs/feed.json", "pack.tibdict").unwrap(),
+            Path::new("/srv/packs").join("pack.tibdict").to_string_lossy()
+        );
+        assert_eq!(resolve_location("/srv/feed.json", "file:///srv/pack.tibdict").unwrap(), "file:///srv/pack.tibdict");
+    }
+
+    #[test]
+    fn remote_feeds_cannot_point_at_local_files() {
+        for url in ["file:///etc/passwd", "file:/etc/passwd", "C:\\packs\\pack.tibdict"] {
+            assert!(resolve_location("https://example.org/feed.json", url).is_err(), "{url}");
+        }
+        // An absolute path is a path on the feed's server
+        assert_eq!(
+            resolve_location("https://example.org/a/feed.json", "/etc/passwd").unwrap(),
+            "https://example.org/etc/passwd"
+        );
+    }
+
+    #[test]
+    fn plain_http_needs_a_sha256() {
+        let mut feed = UpdateFeed {
+            id: None,
+            version: "2.0.0".to_string(),
+            url: "pack.tibdict".to_string(),
+            notes: None,
+            sha256: None,
+        };
+        assert!(require_sha256("http://example.org/feed.json", &feed).is_err());
+        assert!(require_sha256("https://example.org/feed.json", &feed).is_ok());
+        assert!(require_sha256("/srv/feed.json", &feed).is_ok());
+        feed.sha256 = Some("00".repeat(32));
+        assert!(require_sha256("http://example.org/feed.json", &feed).is_ok());
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_version() {
+        assert_eq!(compare_versions("latest", "1.0.0"), None);
+        assert_eq!(compare_versions("1.0.0", "1.x"), None);
+        assert_eq!(compare_versions("", "1.0.0"), None);
+    }
+
+    fn manifest(update_url: Option<&str>) -> TibdictManifest {
+        TibdictManifest {
+            format: "tibdict".to_string(),
+            format_version: 1,
+            schema_version: 3,
+            id: "pack".to_string(),
+            name: "Pack".to_string(),
+            description: String::new(),
+            author: None,
+            version: Some("1.0.0".to_string()),
+            created_at: None,
+            icon: None,
+            update_url: update_url.map(str::to_string),
+            dictionaries: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn manifests_cannot_name_local_feeds() {
+        let none = BTreeMap::new();
+        for url in ["file:///srv/feed.json", "/srv/feed.json", "feed.json", "C:\\feeds\\feed.json"] {
+            assert!(feed_location(&none, "custom-pack", &manifest(Some(url))).is_err(), "{url}");
+        }
+        assert_eq!(
+            feed_location(&none, "custom-pack", &manifest(Some("https://example.org/feed.json"))).unwrap(),
+            Some("https://example.org/feed.json".to_string())
+        );
+        assert_eq!(feed_location(&none, "custom-pack", &manifest(None)).unwrap(), None);
+    }
+
+    #[test]
+    fn user_feeds_come_first_and_may_be_local() {
+        let dir = crate::data_host::TestDataDir::new("pack-updates-feeds");
+        save_user_feed(&dir, "custom-pack", Some(" /srv/feed.json ")).unwrap();
+        save_user_feed(&dir, "custom-other", Some("https://example.org/other.json")).unwrap();
+        let feeds = load_user_feeds(&dir).unwrap();
+        assert_eq!(
+            feed_location(&feeds, "custom-pack", &manifest(Some("file:///etc/feed.json"))).unwrap(),
+            Some("/srv/feed.json".to_string())
+        );
+
+        save_user_feed(&dir, "custom-pack", Some("  ")).unwrap();
+        let feeds = load_user_feeds(&dir).unwrap();
+        assert_eq!(feeds.keys().collect::<Vec<_>>(), ["custom-other"]);
+        assert_eq!(
+            feed_location(&feeds, "custom-pack", &manifest(Some("https://example.org/feed.json"))).unwrap(),
+            Some("https://example.org/feed.json".to_string())
+        );
+    }
+}
@@ -0,0 +1,423 @@
+//! Deep validation of a custom pack's SQLite before it is installed.
+//!
+//! The pack is compared with a reference database built from PACK_SCHEMA:
+//! tables and column types, the entries_fts table and its row count, and
+//! the manifest's dictionaries and entriesCount. Packs come from untrusted
+//! files, so any view, and any trigger that isn't one of the schema's own
+//! FTS triggers, is refused.
+
+use crate::custom_packs::{InstallError, TibdictManifest};
+use crate::tibdict_builder::PACK_SCHEMA;
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// One problem found in a pack, with the InstallError code it maps to:
+/// "schema" for missing or mistyped columns, "corrupt" for an FTS index or
+/// rows that don't add up, "format" for executable objects and manifest
+/// mismatches.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaIssue {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDictionaryCount {
+    pub name: String,
+    pub entries_count: u32,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaReport {
+    pub entries_count: i64,
+    pub fts_rows: i64,
+    pub dictionaries: Vec<SchemaDictionaryCount>,
+    pub issues: Vec<SchemaIssue>,
+}
+
+impl SchemaReport {
+    fn issue(&mut self, code: &str, message: String) {
+        self.issues.push(SchemaIssue { code: code.into(), message });
+    }
+}
+
+struct SchemaObject {
+    kind: String,
+    name: String,
+    table: String,
+    sql: Option<String>,
+}
+
+fn schema_objects(conn: &Connection) -> rusqlite::Result<Vec<SchemaObject>> {
+    let mut stmt = conn.prepare("SELECT type, name, tbl_name, sql FROM sqlite_master")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(SchemaObject {
+            kind: row.get(0)?,
+            name: row.get(1)?,
+            table: row.get(2)?,
+            sql: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Column name -> declared type, lowercased
+fn table_columns(conn: &Connection, table: &str) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info(\"{table}\")"))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(1)?, row.get::<_, String>(2)?.to_lowercase()))
+    })?;
+    rows.collect()
+}
+
+/// Compare SQL ignoring case and whitespace layout
+fn normalize_sql(sql: &str) -> String {
+    sql.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+fn count(conn: &Connection, sql: &str) -> rusqlite::Result<i64> {
+    conn.query_row(sql, [], |r| r.get(0))
+}
+
+fn check_objects(conn: &Connection, reference: &Connection, report: &mut SchemaReport) -> rusqlite::Result<()> {
+    let expected = schema_objects(reference)?;
+    let found = schema_objects(conn)?;
+    let expected_object = |name: &str| expected.iter().find(|e| e.name == name);
+
+    for object in &found {
+        match object.kind.as_str() {
+            "view" => report.issue("format", format!("pack contains a view ({})", object.name)),
+            "trigger" => {
+                let same = expected_object(&object.name).is_some_and(|e| {
+                    e.kind == "trigger" && e.sql.as_deref().map(normalize_sql) == object.sql.as_deref().map(normalize_sql)
+                });
+                if !same {
+                    report.issue("format", format!("pack contains an unexpected trigger ({})", object.name));
+                }
+            }
+            // The reference includes the FTS5 shadow tables
+            "table" if expected_object(&object.name).is_none() && !object.name.starts_with("sqlite_") => {
+                report.issue("format", format!("pack contains an unexpected table ({})", object.name));
+            }
+            "index" if expected_object(&object.table).is_none() => {
+                report.issue("format", format!("pack contains an index on an unknown table ({})", object.name));
+            }
+            _ => {}
+        }
+    }
+
+    for object in expected.iter().filter(|e| e.kind == "table") {
+        let name = &object.name;
+        let Some(present) = found.iter().find(|f| &f.name == name) else {
+            let code = if name.starts_with("entries_fts") { "corrupt" } else { "schema" };
+            report.issue(code, format!("missing table {name}"));
+            continue;
+        };
+        // Shadow table layouts belong to SQLite, not to the pack format
+        if name.starts_with("entries_fts_") {
+            continue;
+        }
+        if name == "entries_fts" {
+            let sql = present.sql.as_deref().map(normalize_sql).unwrap_or_default();
+            if !sql.contains("using fts5") || !sql.replace(' ', "").contains("content='entries'") {
+                report.issue("corrupt", "entries_fts is not an fts5 index of entries".into());
+            }
+        }
+        let columns = table_columns(conn, name)?;
+        for (column, kind) in table_columns(reference, name)? {
+            match columns.iter().find(|(c, _)| c.eq_ignore_ascii_case(&column)) {
+                None => report.issue("schema", format!("{name} is missing column {column}")),
+                Some((_, found_kind)) if found_kind != &kind => report.issue(
+                    "schema",
+                    format!("{name}.{column} is {found_kind}, expected {kind}"),
+                ),
+                Some(_) => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_rows(conn: &Connection, manifest: &TibdictManifest, report: &mut SchemaReport) -> rusqlite::Result<()> {
+    report.entries_count = count(conn, "SELECT COUNT(*) FROM entries")?;
+    // entries_fts is external-content: COUNT(*) on it reads entries, the
+    // docsize shadow table has one row per indexed entry
+    match count(conn, "SELECT COUNT(*) FROM entries_fts_docsize") {
+        Ok(rows) => {
+            report.fts_rows = rows;
+            if rows != report.entries_count {
+                report.issue(
+                    "corrupt",
+                    format!("entries_fts indexes {rows} rows, entries has {}", report.entries_count),
+                );
+            }
+        }
+        Err(_) => report.issue("corrupt", "entries_fts has no docsize table".into()),
+    }
+
+    let orphans = count(
+        conn,
+        "SELECT COUNT(*) FROM entries WHERE dictionaryId IS NULL
+           OR dictionaryId NOT IN (SELECT id FROM dictionaries)",
+    )?;
+    if orphans > 0 {
+        report.issue("corrupt", format!("{orphans} entries belong to no dictionary"));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT dictionaries.name, COUNT(entries.id) FROM dictionaries
+         LEFT JOIN entries ON entries.dictionaryId = dictionaries.id
+         GROUP BY dictionaries.id ORDER BY dictionaries.position",
+    )?;
+    report.dictionaries = stmt
+        .query_map([], |row| {
+            Ok(SchemaDictionaryCount {
+                name: row.get(0)?,
+                entries_count: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for declared in &manifest.dictionaries {
+        match report.dictionaries.iter().find(|d| d.name == declared.name) {
+            None => report.issue("format", format!("manifest lists {} but the pack has no such dictionary", declared.name)),
+            Some(actual) => {
+                if let Some(expected) = declared.entries_count.filter(|c| *c != actual.entries_count) {
+                    report.issue(
+                        "format",
+                        format!("manifest says {} has {expected} entries, the pack has {}", declared.name, actual.entries_count),
+                    );
+                }
+            }
+        }
+    }
+    let missing: Vec<String> = report
+        .dictionaries
+        .iter()
+        .filter(|d| !manifest.dictionaries.iter().any(|m| m.name == d.name))
+        .map(|d| d.name.clone())
+        .collect();
+    for name in missing {
+        report.issue("format", format!("dictionary {name} is not listed in the manifest"));
+    }
+    Ok(())
+}
+
+/// Check a pack's SQLite against PACK_SCHEMA and its manifest. Returns the
+/// report when the pack is sound, otherwise an InstallError carrying it,
+/// coded after the first issue found.
+pub(crate) fn validate_pack(conn: &Connection, manifest: &TibdictManifest) -> Result<SchemaReport, InstallError> {
+    let reference = Connection::open_in_memory()
+        .and_then(|c| c.execute_batch(PACK_SCHEMA).map(|_| c))
+        .map_err(|e| InstallError::new("path", &format!("reference schema: {e}")))?;
+
+    let mut report = SchemaReport::default();
+    check_objects(conn, &reference, &mut report)
+        .map_err(|e| InstallError::new("corrupt", &format!("bad sqlite: {e}")))?;
+    // Row checks query the tables, only run them on a usable schema
+    if report.issues.is_empty() {
+        check_rows(conn, manifest, &mut report)
+            .map_err(|e| InstallError::new("corrupt", &format!("bad sqlite: {e}")))?;
+    }
+
+    match report.issues.first() {
+        None => Ok(report),
+        Some(first) => {
+            let message = report.issues.iter().map(|i| i.message.as_str()).collect::<Vec<_>>().join("; ");
+            let mut err = InstallError::new(&first.code, &message);
+            err.schema_report = Some(Box::new(report));
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::custom_packs::TibdictManifestDictionary;
+    use crate::data_host::TestDataDir;
+    use crate::tibdict_builder::{build_pack_sqlite, TibdictEntry};
+
+    /// A sound pack of three entries in two dictionaries, and its manifest
+    struct TestPack {
+        conn: Connection,
+        manifest: TibdictManifest,
+        _dir: TestDataDir,
+    }
+
+    impl TestPack {
+        fn new(name: &str) -> Self {
+            let dir = TestDataDir::new(&format!("pack-validation-{}", name));
+            let path = dir.root.join("data.sqlite");
+            let entry = |term: &str, dictionary: &str| TibdictEntry {
+                term: term.to_string(),
+                definition: "definition".to_string(),
+                dictionary: Some(dictionary.to_string()),
+                term_phonetics_strict: None,
+                term_phonetics_loose: None,
+                definition_phonetics_words_strict: None,
+                definition_phonetics_words_loose: None,
+            };
+            let dictionaries = build_pack_sqlite(
+                &path,
+                &[entry("ཀ་", "First"), entry("ཁ་", "First"), entry("ག་", "Second")],
+                "First",
+            )
+            .unwrap();
+            let manifest = TibdictManifest {
+                format: "tibdict".to_string(),
+                format_version: 1,
+                schema_version: 3,
+                id: "test".to_string(),
+                name: "Test".to_string(),
+                description: String::new(),
+                author: None,
+                version: None,
+                created_at: None,
+                icon: None,
+                update_url: None,
+                dictionaries,
+            };
+            TestPack {
+                conn: Connection::open(&path).unwrap(),
+                manifest,
+                _dir: dir,
+            }
+        }
+
+        fn issues(&self) -> Vec<(String, String)> {
+            match validate_pack(&self.conn, &self.manifest) {
+                Ok(_) => Vec::new(),
+                Err(e) => e
+                    .schema_report
+                    .unwrap()
+                    .issues
+                    .into_iter()
+                    .map(|issue| (issue.code, issue.message))
+                    .collect(),
+            }
+        }
+
+        fn trigger_sql(&self, name: &str) -> String {
+            self.conn
+                .query_row("SELECT sql FROM sqlite_master WHERE name = ?", [name], |row| row.get(0))
+                .unwrap()
+        }
+    }
+
+    fn codes(issues: &[(String, String)]) -> Vec<&str> {
+        issues.iter().map(|(code, _)| code.as_str()).collect()
+    }
+
+    #[test]
+    fn accepts_a_sound_pack() {
+        let pack = TestPack::new("sound");
+        let report = validate_pack(&pack.conn, &pack.manifest).unwrap();
+        assert_eq!((report.entries_count, report.fts_rows), (3, 3));
+        let counts: Vec<(&str, u32)> = report.dictionaries.iter().map(|d| (d.name.as_str(), d.entries_count)).collect();
+        assert_eq!(counts, [("First", 2), ("Second", 1)]);
+    }
+
+    #[test]
+    fn refuses_views_and_extra_objects() {
+        let pack = TestPack::new("view");
+        pack.conn
+            .execute_batch("CREATE VIEW everything AS SELECT * FROM entries;")
+            .unwrap();
+        assert_eq!(codes(&pack.issues()), ["format"]);
+
+        let pack = TestPack::new("table");
+        pack.conn.execute_batch("CREATE TABLE extra (value text);").unwrap();
+        assert_eq!(codes(&pack.issues()), ["format"]);
+    }
+
+    #[test]
+    fn refuses_modified_triggers() {
+        let pack = TestPack::new("trigger");
+        let original = pack.trigger_sql("entries_after_insert");
+        let modified = original.replacen("BEGIN", "BEGIN DELETE FROM dictionaries;", 1);
+        assert_ne!(modified, original);
+        pack.conn
+            .execute_batch(&format!("DROP TRIGGER entries_after_insert; {};", modified))
+            .unwrap();
+        let issues = pack.issues();
+        assert_eq!(codes(&issues), ["format"]);
+        assert!(issues[0].1.contains("entries_after_insert"));
+
+        let pack = TestPack::new("new-trigger");
+        pack.conn
+            .execute_batch("CREATE TRIGGER sneaky AFTER INSERT ON dictionaries BEGIN DELETE FROM entries; END;")
+            .unwrap();
+        assert_eq!(codes(&pack.issues()), ["format"]);
+
+        // The schema's own triggers, whatever their layout
+        let pack = TestPack::new("reformatted-trigger");
+        let original = pack.trigger_sql("entries_after_delete");
+        pack.conn
+            .execute_batch(&format!("DROP TRIGGER entries_after_delete; {};", original.replace('\n', "\n\n  ")))
+            .unwrap();
+        assert!(pack.issues().is_empty());
+    }
+
+    #[test]
+    fn refuses_missing_fts_rows() {
+        let pack = TestPack::new("fts");
+        let trigger = pack.trigger_sql("entries_after_insert");
+        pack.conn
+            .execute_batch(&format!(
+                "DROP TRIGGER entries_after_insert;
+                 INSERT INTO entries (term, termPhoneticsStrict, termPhoneticsLoose, definition,
+                                      definitionPhoneticsWordsStrict, definitionPhoneticsWordsLoose, dictionaryId)
+                 VALUES ('ང་', '', '', 'unindexed', '', '', 1);
+                 {};",
+                trigger
+            ))
+            .unwrap();
+        let issues = pack.issues();
+        assert!(codes(&issues).contains(&"corrupt"), "{:?}", issues);
+        assert!(issues.iter().any(|(_, message)| message.contains("entries_fts indexes 3 rows")));
+    }
+
+    #[test]
+    fn refuses_missing_columns() {
+        let pack = TestPack::new("column");
+        pack.conn
+            .execute_batch("DROP TABLE dictionaries; CREATE TABLE dictionaries (id integer primary key, name text);")
+            .unwrap();
+        let issues = pack.issues();
+        assert_eq!(codes(&issues)[0], "schema");
+        assert!(issues.iter().any(|(_, message)| message.contains("missing column position")));
+    }
+
+    #[test]
+    fn checks_the_manifest_against_the_rows() {
+        let mut pack = TestPack::new("counts");
+        pack.manifest.dictionaries[0].entries_count = Some(5);
+        let issues = pack.issues();
+        assert_eq!(codes(&issues), ["format"]);
+        assert!(issues[0].1.contains("First has 5 entries, the pack has 2"));
+
+        // Counts are optional
+        pack.manifest.dictionaries[0].entries_count = None;
+        assert!(pack.issues().is_empty());
+
+        pack.manifest.dictionaries[1].name = "Renamed".to_string();
+        let messages: Vec<String> = pack.issues().into_iter().map(|(_, message)| message).collect();
+        assert_eq!(
+            messages,
+            [
+                "manifest lists Renamed but the pack has no such dictionary",
+                "dictionary Second is not listed in the manifest"
+            ]
+        );
+
+        pack.manifest.dictionaries.push(TibdictManifestDictionary {
+            name: "Second".to_string(),
+            entries_count: Some(1),
+        });
+        assert_eq!(codes(&pack.issues()), ["format"]);
+    }
+}
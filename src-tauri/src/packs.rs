@@ -1,12 +1,19 @@
 use crate::custom_packs::get_custom_pack_paths;
-use rusqlite::{params, Connection};
+use crate::data_host::DataHost;
+#[cfg(feature = "app")]
+use crate::pack_indexes::refresh_indexes_in_background;
+use crate::user_pack::{get_user_pack_path, USER_PACK_ID};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs::{self, File};
+use std::fs;
+#[cfg(feature = "app")]
+use std::fs::File;
+#[cfg(feature = "app")]
 use std::io::Write;
-use std::path::PathBuf;
-#[cfg(desktop)]
+use std::path::{Path, PathBuf};
+#[cfg(all(desktop, feature = "app"))]
 use std::process::Command;
+#[cfg(feature = "app")]
 use tauri::{AppHandle, Emitter, Manager, Window};
 #[cfg(mobile)]
 use tauri_plugin_fs::FsExt;
@@ -62,6 +69,7 @@ struct PackCacheMeta {
     schema_version: u32,
 }
 
+#[cfg(feature = "app")]
 /// Read the bundled pack manifest to get the schema version
 fn get_bundled_schema_version(app: &AppHandle) -> Result<u32, String> {
     let manifest_path = app
@@ -79,7 +87,7 @@ fn get_bundled_schema_version(app: &AppHandle) -> Result<u32, String> {
 }
 
 /// Read cached pack's schema version from meta file
-fn get_cached_schema_version(packs_dir: &PathBuf, pack_id: &str) -> Option<u32> {
+fn get_cached_schema_version(packs_dir: &Path, pack_id: &str) -> Option<u32> {
     let meta_path = packs_dir.join(format!("{}.meta.json", pack_id));
     if let Ok(contents) = fs::read_to_string(&meta_path) {
         if let Ok(meta) = serde_json::from_str::<PackCacheMeta>(&contents) {
@@ -90,7 +98,7 @@ fn get_cached_schema_version(packs_dir: &PathBuf, pack_id: &str) -> Option<u32>
 }
 
 /// Save schema version to cache meta file
-fn save_cache_meta(packs_dir: &PathBuf, pack_id: &str, schema_version: u32) -> Result<(), String> {
+fn save_cache_meta(packs_dir: &Path, pack_id: &str, schema_version: u32) -> Result<(), String> {
     let meta_path = packs_dir.join(format!("{}.meta.json", pack_id));
     let meta = PackCacheMeta { schema_version };
     let contents = serde_json::to_string(&meta)
@@ -100,6 +108,7 @@ fn save_cache_meta(packs_dir: &PathBuf, pack_id: &str, schema_version: u32) -> R
     Ok(())
 }
 
+#[cfg(feature = "app")]
 /// Check if cached pack is stale (bundled version is newer)
 fn is_cache_stale(app: &AppHandle, packs_dir: &PathBuf, pack_id: &str) -> bool {
     let bundled_version = match get_bundled_schema_version(app) {
@@ -113,6 +122,7 @@ fn is_cache_stale(app: &AppHandle, packs_dir: &PathBuf, pack_id: &str) -> bool {
     }
 }
 
+#[cfg(feature = "app")]
 /// Delete stale cached pack files
 fn delete_cached_pack(packs_dir: &PathBuf, pack_id: &str) {
     let sqlite_path = packs_dir.join(format!("{}.sqlite", pack_id));
@@ -123,12 +133,8 @@ fn delete_cached_pack(packs_dir: &PathBuf, pack_id: &str) {
 }
 
 /// Get the packs directory in app data
-fn get_packs_dir(app: &AppHandle) -> Result<PathBuf, String> {
-    let app_data = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-    let packs_dir = app_data.join("packs");
+pub(crate) fn get_packs_dir(host: &dyn DataHost) -> Result<PathBuf, String> {
+    let packs_dir = host.app_data_dir()?.join("packs");
     fs::create_dir_all(&packs_dir).map_err(|e| format!("Failed to create packs dir: {}", e))?;
     Ok(packs_dir)
 }
@@ -139,6 +145,7 @@ fn get_releases_base_url() -> String {
 }
 
 /// Fetch the pack manifest from GitHub releases
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn fetch_pack_manifest() -> Result<PackManifest, String> {
     let url = format!("{}/pack-manifest.json", get_releases_base_url());
@@ -163,6 +170,7 @@ pub async fn fetch_pack_manifest() -> Result<PackManifest, String> {
 }
 
 /// Get list of installed packs
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn get_installed_packs(app: AppHandle) -> Result<Vec<String>, String> {
     let packs_dir = get_packs_dir(&app)?;
@@ -187,13 +195,44 @@ pub async fn get_installed_packs(app: AppHandle) -> Result<Vec<String>, String>
     Ok(installed)
 }
 
+#[cfg(feature = "app")]
+/// Tell progress listeners that the download or update of `pack_id` stopped
+/// short of "complete"
+fn emit_failed(window: &Window, event: &str, pack_id: String) {
+    let _ = window.emit(
+        event,
+        DownloadProgress {
+            pack_id,
+            downloaded: 0,
+            total: 0,
+            percentage: 0.0,
+            status: "failed".to_string(),
+        },
+    );
+}
+
 /// Download a pack with progress events
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn download_pack(
     app: AppHandle,
     window: Window,
     pack_id: String,
     schema_version: u32,
+) -> Result<(), String> {
+    let result = download_pack_files(app, window.clone(), pack_id.clone(), schema_version).await;
+    if result.is_err() {
+        emit_failed(&window, "pack-download-progress", pack_id);
+    }
+    result
+}
+
+#[cfg(feature = "app")]
+async fn download_pack_files(
+    app: AppHandle,
+    window: Window,
+    pack_id: String,
+    schema_version: u32,
 ) -> Result<(), String> {
     let packs_dir = get_packs_dir(&app)?;
     let compressed_path = packs_dir.join(format!("{}.7z", pack_id));
@@ -286,6 +325,9 @@ pub async fn download_pack(
     // Save cache meta with schema version
     let _ = save_cache_meta(&packs_dir, &pack_id, schema_version);
 
+    // Extract reverse-lookup glosses etc. from the new pack
+    refresh_indexes_in_background(&app);
+
     // Emit complete status
     let _ = window.emit(
         "pack-download-progress",
@@ -302,6 +344,7 @@ pub async fn download_pack(
 }
 
 /// Remove an installed pack
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn remove_pack(app: AppHandle, pack_id: String) -> Result<(), String> {
     let packs_dir = get_packs_dir(&app)?;
@@ -311,10 +354,13 @@ pub async fn remove_pack(app: AppHandle, pack_id: String) -> Result<(), String>
         fs::remove_file(&sqlite_path).map_err(|e| format!("Failed to remove pack: {}", e))?;
     }
 
+    refresh_indexes_in_background(&app);
+
     Ok(())
 }
 
 /// Get path to a pack's database file
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn get_pack_path(
     app: AppHandle,
@@ -342,6 +388,7 @@ pub async fn get_pack_path(
 /// Ensure a pack is available in app data and return its path
 /// For core pack: copies from bundled resources if not in app data
 /// For other packs: returns path if exists
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn ensure_pack_available(
     app: AppHandle,
@@ -393,6 +440,8 @@ pub async fn ensure_pack_available(
             let _ = save_cache_meta(&packs_dir, &pack_id, schema_version);
         }
 
+        refresh_indexes_in_background(&app);
+
         return Ok(sqlite_path.to_string_lossy().to_string());
     }
 
@@ -401,6 +450,7 @@ pub async fn ensure_pack_available(
 
 /// Read pack database file as bytes (for WASM)
 /// Note: On mobile, this is typically not called since mobile uses native SQLite
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn read_pack_database(
     app: AppHandle,
@@ -458,12 +508,14 @@ pub async fn read_pack_database(
 }
 
 /// Check if platform supports modular packs (always true for Tauri)
+#[cfg(feature = "app")]
 #[tauri::command]
 pub fn supports_modular_packs() -> bool {
     true
 }
 
 /// Get pack database file size (for chunked loading)
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn get_pack_database_size(
     app: AppHandle,
@@ -497,6 +549,7 @@ pub async fn get_pack_database_size(
 
 /// Read a chunk of pack database file (for WASM on memory-constrained devices)
 /// offset and length are in bytes
+#[cfg(feature = "app")]
 #[tauri::command]
 pub async fn read_pack_database_chunk(
     app: AppHandle,
@@ -533,7 +586,7 @@ pub async fn read_pack_database_chunk(
 }
 
 // ============================================
-// Native SQLite Pack Queries (for mobile performance)
+// Pack entries and dictionaries (queried in repository.rs)
 // ============================================
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -556,6 +609,9 @@ pub struct PackEntry {
     pub dictionary_position: Option<i64>,
     #[serde(rename = "_sourcePackId")]
     pub source_pack_id: Option<String>,
+    /// Personal note attached to this entry (see user_pack.rs)
+    #[serde(rename = "userNote", default, skip_serializing_if = "Option::is_none")]
+    pub user_note: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -563,13 +619,15 @@ pub struct PackDictionary {
     pub id: String,  // Compound ID: "pack_id:dictionary_id" (e.g., "core:1")
     pub name: String,
     pub position: i64,
+    /// False when the user turned off the dictionary or its pack (see dictionary_settings.rs)
+    pub enabled: bool,
     #[serde(rename = "_sourcePackId")]
     pub source_pack_id: Option<String>,
 }
 
 /// Get paths to all installed pack databases
-fn get_all_pack_db_paths(app: &AppHandle) -> Result<Vec<(String, PathBuf)>, String> {
-    let packs_dir = get_packs_dir(app)?;
+pub(crate) fn get_all_pack_db_paths(host: &dyn DataHost) -> Result<Vec<(String, PathBuf)>, String> {
+    let packs_dir = get_packs_dir(host)?;
     let mut pack_paths = Vec::new();
 
     // Check for core pack in app data first
@@ -578,10 +636,7 @@ fn get_all_pack_db_paths(app: &AppHandle) -> Result<Vec<(String, PathBuf)>, Stri
         pack_paths.push(("core".to_string(), core_path));
     } else {
         // Try bundled resource for core
-        if let Ok(resource_path) = app
-            .path()
-            .resolve("core.sqlite", tauri::path::BaseDirectory::Resource)
-        {
+        if let Some(resource_path) = host.resource("core.sqlite") {
             // On desktop, we can use the resource path directly
             #[cfg(desktop)]
             if resource_path.exists() {
@@ -607,351 +662,37 @@ fn get_all_pack_db_paths(app: &AppHandle) -> Result<Vec<(String, PathBuf)>, Stri
     }
 
     // Also include custom packs from packs/custom/<id>/data.sqlite
-    for (id, path) in get_custom_pack_paths(app) {
+    for (id, path) in get_custom_pack_paths(host) {
         pack_paths.push((id, path));
     }
 
-    Ok(pack_paths)
-}
-
-/// Get all unique terms from all installed packs (for Define page autocomplete)
-#[tauri::command]
-pub async fn pack_get_all_terms(app: AppHandle) -> Result<Vec<String>, String> {
-    println!("[pack_get_all_terms] Starting...");
-
-    // First ensure core pack is available in app data
-    let core_path = ensure_pack_available(app.clone(), "core".to_string()).await?;
-    println!("[pack_get_all_terms] Core pack ensured at: {}", core_path);
-
-    let pack_paths = get_all_pack_db_paths(&app)?;
-    println!("[pack_get_all_terms] Found {} pack paths", pack_paths.len());
-    for (pack_id, path) in &pack_paths {
-        println!("[pack_get_all_terms]   - {}: {:?}", pack_id, path);
+    // And the user's own entries, once there are any
+    if let Some(path) = get_user_pack_path(host) {
+        pack_paths.push((USER_PACK_ID.to_string(), path));
     }
 
-    let mut all_terms = std::collections::HashSet::new();
-
-    for (pack_id, db_path) in pack_paths {
-        println!("[pack_get_all_terms] Opening pack: {} at {:?}", pack_id, db_path);
-        match Connection::open(&db_path) {
-            Ok(conn) => {
-                let mut stmt = conn
-                    .prepare("SELECT DISTINCT term FROM entries")
-                    .map_err(|e| format!("Failed to prepare statement for pack {}: {}", pack_id, e))?;
-
-                let terms: Vec<String> = stmt
-                    .query_map([], |row| row.get(0))
-                    .map_err(|e| format!("Failed to query terms from pack {}: {}", pack_id, e))?
-                    .filter_map(|r| r.ok())
-                    .collect();
-
-                println!("[pack_get_all_terms] Pack {} returned {} terms", pack_id, terms.len());
-                all_terms.extend(terms);
-            }
-            Err(e) => {
-                eprintln!("[pack_get_all_terms] Warning: Failed to open pack {} at {:?}: {}", pack_id, db_path, e);
-                // Continue with other packs
-            }
-        }
-    }
-
-    let mut terms_vec: Vec<String> = all_terms.into_iter().collect();
-    terms_vec.sort();
-    println!("[pack_get_all_terms] Total unique terms: {}", terms_vec.len());
-    Ok(terms_vec)
-}
-
-/// Get entries for a specific term from all installed packs
-#[tauri::command]
-pub async fn pack_get_entries_for_term(app: AppHandle, term: String) -> Result<Vec<PackEntry>, String> {
-    // First ensure core pack is available in app data
-    ensure_pack_available(app.clone(), "core".to_string()).await?;
-
-    let pack_paths = get_all_pack_db_paths(&app)?;
-    let mut all_entries = Vec::new();
-
-    for (pack_id, db_path) in pack_paths {
-        match Connection::open(&db_path) {
-            Ok(conn) => {
-                let mut stmt = conn
-                    .prepare(
-                        "SELECT entries.id, entries.term, entries.termPhoneticsStrict, entries.termPhoneticsLoose,
-                                entries.definition, entries.definitionPhoneticsWordsStrict,
-                                entries.definitionPhoneticsWordsLoose, entries.dictionaryId,
-                                dictionaries.name AS dictionary, dictionaries.position AS dictionaryPosition
-                         FROM entries
-                         INNER JOIN dictionaries ON dictionaries.id = entries.dictionaryId
-                         WHERE entries.term = ?
-                         ORDER BY dictionaries.position",
-                    )
-                    .map_err(|e| format!("Failed to prepare statement for pack {}: {}", pack_id, e))?;
-
-                let entries: Vec<PackEntry> = stmt
-                    .query_map(params![term], |row| {
-                        let raw_dict_id: i64 = row.get(7)?;
-                        Ok(PackEntry {
-                            id: row.get(0)?,
-                            term: row.get(1)?,
-                            term_phonetics_strict: row.get(2)?,
-                            term_phonetics_loose: row.get(3)?,
-                            definition: row.get(4)?,
-                            definition_phonetics_words_strict: row.get(5)?,
-                            definition_phonetics_words_loose: row.get(6)?,
-                            dictionary_id: format!("{}:{}", pack_id, raw_dict_id),  // Compound ID
-                            dictionary: row.get(8)?,
-                            dictionary_position: row.get(9)?,
-                            source_pack_id: Some(pack_id.clone()),
-                        })
-                    })
-                    .map_err(|e| format!("Failed to query entries from pack {}: {}", pack_id, e))?
-                    .filter_map(|r| r.ok())
-                    .collect();
-
-                all_entries.extend(entries);
-            }
-            Err(e) => {
-                eprintln!("Warning: Failed to open pack {} at {:?}: {}", pack_id, db_path, e);
-            }
-        }
-    }
-
-    // Sort by dictionary position across all packs
-    all_entries.sort_by(|a, b| {
-        a.dictionary_position
-            .unwrap_or(i64::MAX)
-            .cmp(&b.dictionary_position.unwrap_or(i64::MAX))
-    });
-
-    Ok(all_entries)
-}
-
-/// Search entries across all installed packs using FTS
-#[tauri::command]
-pub async fn pack_search_entries(
-    app: AppHandle,
-    query: String,
-    search_type: String,
-) -> Result<Vec<PackEntry>, String> {
-    // First ensure core pack is available in app data
-    ensure_pack_available(app.clone(), "core".to_string()).await?;
-
-    let pack_paths = get_all_pack_db_paths(&app)?;
-    let mut all_entries = Vec::new();
-
-    // Escape for both the outer SQL string literal (single quotes → double
-    // singles) and the inner FTS5 phrase (double quotes → double doubles).
-    // Missing the apostrophe escape used to break any query containing a
-    // plain "'" (e.g. "d'après"), since the apostrophe closed the SQL string.
-    let escaped_query = query.replace('\'', "''").replace('"', "\"\"");
-
-    // Build the FTS5 MATCH clause based on search type
-    let fts_query = match search_type.as_str() {
-        "phonetics_strict" => format!(
-            "(termPhoneticsStrict : \"{0}\" OR definitionPhoneticsWordsStrict : \"{0}\")",
-            escaped_query
-        ),
-        "phonetics_loose" => format!(
-            "(termPhoneticsLoose : \"{0}\" OR definitionPhoneticsWordsLoose : \"{0}\")",
-            escaped_query
-        ),
-        _ => format!(
-            "(term : \"{0}\" OR definition : \"{0}\")",
-            escaped_query
-        ),
-    };
-
-    let sql = format!(
-        "SELECT entries.id, entries.term, entries.termPhoneticsStrict, entries.termPhoneticsLoose,
-                entries.definition, entries.definitionPhoneticsWordsStrict,
-                entries.definitionPhoneticsWordsLoose, entries.dictionaryId,
-                dictionaries.name AS dictionary, dictionaries.position AS dictionaryPosition
-         FROM entries
-         INNER JOIN dictionaries ON dictionaries.id = entries.dictionaryId
-         INNER JOIN entries_fts ON entries.id = entries_fts.rowid
-         WHERE entries_fts MATCH '{}'
-         LIMIT 2000",
-        fts_query
-    );
-
-    for (pack_id, db_path) in pack_paths {
-        match Connection::open(&db_path) {
-            Ok(conn) => {
-                let mut stmt = match conn.prepare(&sql) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        eprintln!("Warning: Failed to prepare FTS query for pack {}: {}", pack_id, e);
-                        continue;
-                    }
-                };
-
-                let entries: Vec<PackEntry> = match stmt.query_map([], |row| {
-                    let raw_dict_id: i64 = row.get(7)?;
-                    Ok(PackEntry {
-                        id: row.get(0)?,
-                        term: row.get(1)?,
-                        term_phonetics_strict: row.get(2)?,
-                        term_phonetics_loose: row.get(3)?,
-                        definition: row.get(4)?,
-                        definition_phonetics_words_strict: row.get(5)?,
-                        definition_phonetics_words_loose: row.get(6)?,
-                        dictionary_id: format!("{}:{}", pack_id, raw_dict_id),  // Compound ID
-                        dictionary: row.get(8)?,
-                        dictionary_position: row.get(9)?,
-                        source_pack_id: Some(pack_id.clone()),
-                    })
-                }) {
-                    Ok(rows) => rows.filter_map(|r| r.ok()).collect(),
-                    Err(_) => Vec::new(),
-                };
-
-                all_entries.extend(entries);
-
-                // Stop if we have enough results
-                if all_entries.len() >= 5000 {
-                    break;
-                }
-            }
-            Err(e) => {
-                eprintln!("Warning: Failed to open pack {} at {:?}: {}", pack_id, db_path, e);
-            }
-        }
-    }
-
-    // Truncate to limit
-    all_entries.truncate(5000);
-
-    Ok(all_entries)
+    Ok(pack_paths)
 }
 
-/// Execute arbitrary SQL query across all installed packs
-/// Results are merged from all packs with source pack ID added
+/// Update a pack (download new version, replacing existing)
+/// For core pack, downloads to app data dir (overrides bundled version)
+#[cfg(feature = "app")]
 #[tauri::command]
-pub async fn pack_execute_query(
+pub async fn update_pack(
     app: AppHandle,
-    sql: String,
-    params_json: String,
-) -> Result<Vec<serde_json::Value>, String> {
-    // First ensure core pack is available in app data
-    ensure_pack_available(app.clone(), "core".to_string()).await?;
-
-    let pack_paths = get_all_pack_db_paths(&app)?;
-    let mut all_results = Vec::new();
-
-    // Parse params
-    let params: Vec<String> = serde_json::from_str(&params_json).unwrap_or_default();
-
-    for (pack_id, db_path) in pack_paths {
-        match Connection::open(&db_path) {
-            Ok(conn) => {
-                let mut stmt = match conn.prepare(&sql) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        eprintln!("Warning: Failed to prepare query for pack {}: {}", pack_id, e);
-                        continue;
-                    }
-                };
-
-                let column_count = stmt.column_count();
-                let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
-
-                let rusqlite_params: Vec<&dyn rusqlite::ToSql> = params
-                    .iter()
-                    .map(|s| s as &dyn rusqlite::ToSql)
-                    .collect();
-
-                let rows: Vec<serde_json::Value> = match stmt.query_map(rusqlite_params.as_slice(), |row| {
-                    let mut obj = serde_json::Map::new();
-                    for i in 0..column_count {
-                        let value: rusqlite::Result<rusqlite::types::Value> = row.get(i);
-                        if let Ok(v) = value {
-                            let col_name = &column_names[i];
-                            let json_value = match v {
-                                rusqlite::types::Value::Null => serde_json::Value::Null,
-                                rusqlite::types::Value::Integer(int_val) => {
-                                    // Convert dictionaryId to compound ID
-                                    if col_name == "dictionaryId" {
-                                        serde_json::Value::String(format!("{}:{}", pack_id, int_val))
-                                    } else {
-                                        serde_json::json!(int_val)
-                                    }
-                                },
-                                rusqlite::types::Value::Real(f) => serde_json::json!(f),
-                                rusqlite::types::Value::Text(s) => serde_json::Value::String(s),
-                                rusqlite::types::Value::Blob(b) => serde_json::json!(b),
-                            };
-                            obj.insert(col_name.clone(), json_value);
-                        }
-                    }
-                    // Add source pack ID
-                    obj.insert("_sourcePackId".to_string(), serde_json::Value::String(pack_id.clone()));
-                    Ok(serde_json::Value::Object(obj))
-                }) {
-                    Ok(iter) => iter.filter_map(|r| r.ok()).collect(),
-                    Err(e) => {
-                        eprintln!("Warning: Query failed for pack {}: {}", pack_id, e);
-                        continue;
-                    }
-                };
-
-                all_results.extend(rows);
-            }
-            Err(e) => {
-                eprintln!("Warning: Failed to open pack {} at {:?}: {}", pack_id, db_path, e);
-            }
-        }
-    }
-
-    Ok(all_results)
-}
-
-/// Get dictionaries from all installed packs
-#[tauri::command]
-pub async fn pack_get_dictionaries(app: AppHandle) -> Result<Vec<PackDictionary>, String> {
-    // First ensure core pack is available in app data
-    ensure_pack_available(app.clone(), "core".to_string()).await?;
-
-    let pack_paths = get_all_pack_db_paths(&app)?;
-    let mut all_dictionaries = Vec::new();
-
-    for (pack_id, db_path) in pack_paths {
-        match Connection::open(&db_path) {
-            Ok(conn) => {
-                let mut stmt = conn
-                    .prepare("SELECT id, name, position FROM dictionaries ORDER BY position")
-                    .map_err(|e| format!("Failed to prepare statement for pack {}: {}", pack_id, e))?;
-
-                let dictionaries: Vec<PackDictionary> = stmt
-                    .query_map([], |row| {
-                        let raw_id: i64 = row.get(0)?;
-                        Ok(PackDictionary {
-                            id: format!("{}:{}", pack_id, raw_id),  // Compound ID
-                            name: row.get(1)?,
-                            position: row.get(2)?,
-                            source_pack_id: Some(pack_id.clone()),
-                        })
-                    })
-                    .map_err(|e| format!("Failed to query dictionaries from pack {}: {}", pack_id, e))?
-                    .filter_map(|r| r.ok())
-                    .collect();
-
-                all_dictionaries.extend(dictionaries);
-            }
-            Err(e) => {
-                eprintln!("Warning: Failed to open pack {} at {:?}: {}", pack_id, db_path, e);
-            }
-        }
+    window: Window,
+    pack_id: String,
+    schema_version: u32,
+) -> Result<(), String> {
+    let result = update_pack_files(app, window.clone(), pack_id.clone(), schema_version).await;
+    if result.is_err() {
+        emit_failed(&window, "pack-update-progress", pack_id);
     }
-
-    // Sort by position across all packs
-    all_dictionaries.sort_by(|a, b| a.position.cmp(&b.position));
-
-    Ok(all_dictionaries)
+    result
 }
 
-/// Update a pack (download new version, replacing existing)
-/// For core pack, downloads to app data dir (overrides bundled version)
-#[tauri::command]
-pub async fn update_pack(
+#[cfg(feature = "app")]
+async fn update_pack_files(
     app: AppHandle,
     window: Window,
     pack_id: String,
@@ -1053,6 +794,9 @@ pub async fn update_pack(
     // Save cache meta with schema version
     let _ = save_cache_meta(&packs_dir, &pack_id, schema_version);
 
+    // Extract reverse-lookup glosses etc. from the new pack
+    refresh_indexes_in_background(&app);
+
     // Emit complete status
     let _ = window.emit(
         "pack-update-progress",
@@ -1068,6 +812,7 @@ pub async fn update_pack(
     Ok(())
 }
 
+#[cfg(feature = "app")]
 /// Extract 7z archive using pure Rust implementation
 /// Works on all platforms including mobile (iOS/Android)
 fn extract_7z(archive_path: &PathBuf, output_dir: &PathBuf) -> Result<(), String> {
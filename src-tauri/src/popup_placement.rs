@@ -0,0 +1,197 @@
+//! Where the lookup popup opens, and what it remembers between lookups.
+//!
+//! The popup opens next to the mouse cursor, on the monitor the cursor is
+//! on, inside that monitor's work area (menu bar, dock and taskbar
+//! excluded). Its size is remembered per monitor, in logical pixels, so it
+//! keeps its apparent size on screens with different scale factors.
+//!
+//! A pinned popup stays open when it loses focus and reopens where the user
+//! left it on that monitor instead of at the cursor. The window is visible
+//! on every space / virtual desktop (tauri.conf.json, and the NSPanel
+//! collection behavior on macOS), so it is there wherever the cursor is.
+//!
+//! Everything is saved in `<app_data>/lookup-popup.json`.
+
+use crate::data_host::DataHost;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow};
+
+/// Size before the user resizes the popup on a monitor, in logical pixels
+/// (matches tauri.conf.json)
+const DEFAULT_WIDTH: f64 = 650.0;
+const DEFAULT_HEIGHT: f64 = 500.0;
+
+/// Gap between the cursor and the popup, in logical pixels
+const CURSOR_OFFSET: f64 = 16.0;
+
+/// Popup position and size on one monitor, in logical pixels from the
+/// top-left corner of its work area
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub(crate) struct Placement {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct PopupSettings {
+    pub pinned: bool,
+    /// Last placement on each monitor, by monitor_key
+    monitors: HashMap<String, Placement>,
+}
+
+/// A rectangle in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Bounds {
+    fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+}
+
+fn settings_file(host: &dyn DataHost) -> Result<PathBuf, String> {
+    Ok(host.app_data_dir()?.join("lookup-popup.json"))
+}
+
+pub(crate) fn load_settings(host: &dyn DataHost) -> PopupSettings {
+    settings_file(host)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn save_settings(host: &dyn DataHost, settings: &PopupSettings) -> Result<(), String> {
+    let path = settings_file(host)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    }
+    let json =
+        serde_json::to_vec_pretty(settings).map_err(|e| format!("Failed to serialize popup settings: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write popup settings: {}", e))
+}
+
+/// Identifies a monitor across launches: its name, or where it sits in the
+/// desktop when the platform doesn't name monitors
+fn monitor_key(monitor: &Monitor) -> String {
+    monitor.name().cloned().unwrap_or_else(|| {
+        let position = monitor.position();
+        let size = monitor.size();
+        format!("{}x{}@{},{}", size.width, size.height, position.x, position.y)
+    })
+}
+
+fn work_area(monitor: &Monitor) -> Bounds {
+    let area = monitor.work_area();
+    Bounds {
+        x: area.position.x,
+        y: area.position.y,
+        width: area.size.width as i32,
+        height: area.size.height as i32,
+    }
+}
+
+/// Moves `start` so that a `length` long span starting there stays within
+/// `min..max`, or starts at `min` when it can't fit
+fn clamp_span(start: i32, length: i32, min: i32, max: i32) -> i32 {
+    start.min(max - length).max(min)
+}
+
+/// Top-left corner for a `width` x `height` popup next to `cursor`: below
+/// and to the right of it, or on the other side where that runs off `area`
+fn beside_cursor(cursor: (i32, i32), width: i32, height: i32, offset: i32, area: Bounds) -> (i32, i32) {
+    let mut x = cursor.0 + offset;
+    if x + width > area.right() {
+        x = cursor.0 - offset - width;
+    }
+    let mut y = cursor.1 + offset;
+    if y + height > area.bottom() {
+        y = cursor.1 - offset - height;
+    }
+    (
+        clamp_span(x, width, area.x, area.right()),
+        clamp_span(y, height, area.y, area.bottom()),
+    )
+}
+
+/// Size and move the popup for a lookup, on the monitor under the cursor
+pub(crate) fn place(app: &AppHandle, window: &WebviewWindow, settings: &PopupSettings) {
+    let cursor = app.cursor_position().ok();
+    let monitor = cursor
+        .and_then(|cursor| app.monitor_from_point(cursor.x, cursor.y).ok().flatten())
+        .or_else(|| window.current_monitor().ok().flatten());
+    let Some(monitor) = monitor else {
+        return;
+    };
+    let scale = monitor.scale_factor();
+    let area = work_area(&monitor);
+    let saved = settings.monitors.get(&monitor_key(&monitor));
+
+    let (width, height) = saved.map_or((DEFAULT_WIDTH, DEFAULT_HEIGHT), |placement| {
+        (placement.width, placement.height)
+    });
+    let width = ((width * scale).round() as i32).min(area.width);
+    let height = ((height * scale).round() as i32).min(area.height);
+
+    let (x, y) = match (saved, cursor) {
+        (Some(placement), _) if settings.pinned => (
+            clamp_span(area.x + (placement.x * scale).round() as i32, width, area.x, area.right()),
+            clamp_span(area.y + (placement.y * scale).round() as i32, height, area.y, area.bottom()),
+        ),
+        (_, Some(cursor)) => beside_cursor(
+            (cursor.x.round() as i32, cursor.y.round() as i32),
+            width,
+            height,
+            (CURSOR_OFFSET * scale).round() as i32,
+            area,
+        ),
+        // No cursor position: centered, in the upper third
+        (_, None) => (
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 3,
+        ),
+    };
+
+    // Moved first: landing on a monitor with another scale factor may
+    // rescale the window, which the new size then overrides
+    let _ = window.set_position(PhysicalPosition::new(x, y));
+    let _ = window.set_size(PhysicalSize::new(width as u32, height as u32));
+}
+
+/// Record where the popup is on its monitor. False when there is nothing
+/// to record (hidden window, unknown monitor).
+pub(crate) fn remember(window: &WebviewWindow, settings: &mut PopupSettings) -> bool {
+    if !window.is_visible().unwrap_or(false) {
+        return false;
+    }
+    let (Ok(Some(monitor)), Ok(position), Ok(size)) =
+        (window.current_monitor(), window.outer_position(), window.inner_size())
+    else {
+        return false;
+    };
+    let scale = monitor.scale_factor();
+    let area = work_area(&monitor);
+    let placement = Placement {
+        x: (position.x - area.x) as f64 / scale,
+        y: (position.y - area.y) as f64 / scale,
+        width: size.width as f64 / scale,
+        height: size.height as f64 / scale,
+    };
+    settings.monitors.insert(monitor_key(&monitor), placement);
+    true
+}
@@ -0,0 +1,340 @@
+//! Sandbox for the raw SQL the webview sends through execute_query and
+//! pack_execute_query.
+//!
+//! Databases are opened read-only, and an authorizer only lets through
+//! SELECTs that read the dictionary tables: no writes, no ATTACH, no
+//! PRAGMA, no sqlite_master. A progress handler interrupts queries that run
+//! past the deadline, so a runaway query can't hold a connection, and rows
+//! beyond the cap are dropped. Parameters keep their JSON type: numbers,
+//! booleans and null are bound as such, not as strings.
+
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::types::Value;
+use rusqlite::{Connection, ErrorCode, OpenFlags};
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Tables a query may read. FTS5 reads its own shadow tables
+/// (entries_fts_data, entries_fts_idx...) while matching.
+const READABLE_TABLES: &[&str] = &["dictionaries", "entries", "entries_fts"];
+
+/// Functions refused even inside a SELECT
+const DENIED_FUNCTIONS: &[&str] = &["load_extension", "fts3_tokenizer"];
+
+/// How long one command may spend running queries, across all packs
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most rows one command returns, across all packs
+const MAX_ROWS: usize = 10_000;
+
+/// SQLite virtual machine steps between two deadline checks
+const PROGRESS_STEPS: i32 = 10_000;
+
+#[derive(Debug)]
+pub(crate) enum SandboxError {
+    /// The query isn't allowed or ran out of time: no other database will
+    /// accept it either
+    Refused(String),
+    /// The query failed on this database (e.g. a missing column)
+    Failed(String),
+}
+
+impl fmt::Display for SandboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SandboxError::Refused(message) | SandboxError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+pub(crate) struct SandboxRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Parse `params_json`, a JSON array of strings, numbers, booleans or nulls
+pub(crate) fn parse_params(params_json: &str) -> Result<Vec<Value>, String> {
+    if params_json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let values: Vec<serde_json::Value> =
+        serde_json::from_str(params_json).map_err(|e| format!("Invalid query parameters: {}", e))?;
+    values
+        .into_iter()
+        .map(|value| match value {
+            serde_json::Value::Null => Ok(Value::Null),
+            serde_json::Value::Bool(b) => Ok(Value::Integer(b as i64)),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Ok(Value::Integer(i)),
+                None => Ok(Value::Real(n.as_f64().unwrap_or_default())),
+            },
+            serde_json::Value::String(s) => Ok(Value::Text(s)),
+            other => Err(format!("Unsupported query parameter: {}", other)),
+        })
+        .collect()
+}
+
+fn authorize(context: AuthContext<'_>) -> Authorization {
+    match context.action {
+        AuthAction::Select | AuthAction::Recursive => Authorization::Allow,
+        AuthAction::Read { table_name, .. }
+            if READABLE_TABLES.contains(&table_name) || table_name.starts_with("entries_fts_") =>
+        {
+            Authorization::Allow
+        }
+        AuthAction::Function { function_name }
+            if !DENIED_FUNCTIONS.iter().any(|f| f.eq_ignore_ascii_case(function_name)) =>
+        {
+            Authorization::Allow
+        }
+        // FTS5 checks whether the file changed before every query
+        AuthAction::Pragma {
+            pragma_name: "data_version",
+            pragma_value: None,
+        } => Authorization::Allow,
+        _ => Authorization::Deny,
+    }
+}
+
+/// Runs queries for one command, sharing its deadline and row cap
+pub(crate) struct QuerySandbox {
+    deadline: Instant,
+    rows_left: usize,
+}
+
+impl QuerySandbox {
+    pub(crate) fn new() -> Self {
+        Self::with_limits(QUERY_TIMEOUT, MAX_ROWS)
+    }
+
+    fn with_limits(timeout: Duration, max_rows: usize) -> Self {
+        QuerySandbox {
+            deadline: Instant::now() + timeout,
+            rows_left: max_rows,
+        }
+    }
+
+    pub(crate) fn run(&mut self, db_path: &Path, sql: &str, params: &[Value]) -> Result<SandboxRows, SandboxError> {
+        let failed = |e: rusqlite::Error| SandboxError::Failed(format!("Failed to query: {}", e));
+        let conn = Connection::open_with_flags(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(failed)?;
+
+        let deadline = self.deadline;
+        conn.progress_handler(PROGRESS_STEPS, Some(move || Instant::now() > deadline));
+        // Denied functions fail with a plain SQLITE_ERROR, so the
+        // authorizer records refusals itself
+        let denied = Arc::new(AtomicBool::new(false));
+        let denied_flag = denied.clone();
+        conn.authorizer(Some(move |context: AuthContext<'_>| {
+            let authorization = authorize(context);
+            if matches!(authorization, Authorization::Deny) {
+                denied_flag.store(true, Ordering::Relaxed);
+            }
+            authorization
+        }));
+
+        let mut stmt = conn.prepare(sql).map_err(|e| {
+            if denied.load(Ordering::Relaxed) {
+                SandboxError::Refused(format!("Query not allowed: {}", e))
+            } else {
+                failed(e)
+            }
+        })?;
+        if !stmt.readonly() {
+            return Err(SandboxError::Refused("Only SELECT queries are allowed".to_string()));
+        }
+
+        let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+        let mut rows = Vec::new();
+        let mut cursor = stmt.query(rusqlite::params_from_iter(params.iter())).map_err(failed)?;
+        while rows.len() < self.rows_left {
+            let row = match cursor.next() {
+                Ok(Some(row)) => row,
+                Ok(None) => break,
+                Err(e) if e.sqlite_error_code() == Some(ErrorCode::OperationInterrupted) => {
+                    return Err(SandboxError::Refused("Query timed out".to_string()));
+                }
+                Err(e) => return Err(failed(e)),
+            };
+            let values = (0..columns.len())
+                .map(|i| row.get::<_, Value>(i))
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .map_err(failed)?;
+            rows.push(values);
+        }
+        self.rows_left -= rows.len();
+        Ok(SandboxRows { columns, rows })
+    }
+}
+
+/// A SQLite value as JSON, the way execute_query has always returned them
+pub(crate) fn value_to_json(value: Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Integer(i) => serde_json::json!(i),
+        Value::Real(f) => serde_json::json!(f),
+        Value::Text(s) => serde_json::Value::String(s),
+        Value::Blob(b) => serde_json::json!(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_host::TestDataDir;
+    use crate::tibdict_builder::{build_pack_sqlite, TibdictEntry};
+    use std::path::PathBuf;
+
+    struct TestPack {
+        path: PathBuf,
+        _dir: TestDataDir,
+    }
+
+    impl TestPack {
+        fn new(name: &str) -> Self {
+            let dir = TestDataDir::new(&format!("query-sandbox-{}", name));
+            let path = dir.root.join("data.sqlite");
+            let entries: Vec<TibdictEntry> = ["ཀ་", "ཁ་", "ག་", "ང་", "ཅ་"]
+                .iter()
+                .map(|term| TibdictEntry {
+                    term: term.to_string(),
+                    definition: format!("letter {}", term),
+                    dictionary: None,
+                    term_phonetics_strict: None,
+                    term_phonetics_loose: None,
+                    definition_phonetics_words_strict: None,
+                    definition_phonetics_words_loose: None,
+                })
+                .collect();
+            build_pack_sqlite(&path, &entries, "Test").unwrap();
+            Connection::open(&path)
+                .unwrap()
+                .execute_batch("CREATE TABLE secrets (value text); INSERT INTO secrets VALUES ('hidden');")
+                .unwrap();
+            TestPack { path, _dir: dir }
+        }
+
+        fn run(&self, sql: &str, params: &[Value]) -> Result<SandboxRows, SandboxError> {
+            QuerySandbox::new().run(&self.path, sql, params)
+        }
+    }
+
+    fn is_refused(result: Result<SandboxRows, SandboxError>) -> bool {
+        matches!(result, Err(SandboxError::Refused(_)))
+    }
+
+    #[test]
+    fn reads_the_dictionary_tables() {
+        let pack = TestPack::new("reads");
+        let result = pack
+            .run(
+                "SELECT entries.term, dictionaries.name FROM entries
+                 INNER JOIN dictionaries ON dictionaries.id = entries.dictionaryId
+                 INNER JOIN entries_fts ON entries.id = entries_fts.rowid
+                 WHERE entries_fts MATCH ? ORDER BY entries.id",
+                &[Value::Text("letter".to_string())],
+            )
+            .unwrap();
+        assert_eq!(result.columns, ["term", "name"]);
+        assert_eq!(result.rows.len(), 5);
+        assert_eq!(result.rows[0], [Value::Text("ཀ་".to_string()), Value::Text("Test".to_string())]);
+    }
+
+    #[test]
+    fn refuses_writes_and_schema_changes() {
+        let pack = TestPack::new("writes");
+        for sql in [
+            "DELETE FROM entries",
+            "UPDATE entries SET term = ''",
+            "INSERT INTO dictionaries (name, position) VALUES ('x', 1)",
+            "DROP TABLE entries",
+            "CREATE TABLE more (value text)",
+            "CREATE TEMP TABLE more (value text)",
+            "ATTACH DATABASE ':memory:' AS other",
+            "DETACH DATABASE main",
+            "VACUUM",
+        ] {
+            assert!(is_refused(pack.run(sql, &[])), "{} was allowed", sql);
+        }
+        let count = pack.run("SELECT count(*) FROM entries", &[]).unwrap();
+        assert_eq!(count.rows, [[Value::Integer(5)]]);
+    }
+
+    #[test]
+    fn refuses_pragmas_functions_and_other_tables() {
+        let pack = TestPack::new("denied");
+        for sql in [
+            "PRAGMA table_info(entries)",
+            "PRAGMA user_version",
+            "PRAGMA writable_schema = ON",
+            "SELECT * FROM pragma_table_info('entries')",
+            "SELECT load_extension('libevil')",
+            "SELECT fts3_tokenizer('simple')",
+            "SELECT name FROM sqlite_master",
+            "SELECT value FROM secrets",
+            "SELECT term FROM entries WHERE term IN (SELECT value FROM secrets)",
+        ] {
+            assert!(is_refused(pack.run(sql, &[])), "{} was allowed", sql);
+        }
+    }
+
+    #[test]
+    fn stops_at_the_deadline() {
+        let pack = TestPack::new("timeout");
+        let mut sandbox = QuerySandbox::with_limits(Duration::from_millis(50), MAX_ROWS);
+        let started = Instant::now();
+        let result = sandbox.run(
+            &pack.path,
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) SELECT max(i) FROM n",
+            &[],
+        );
+        assert!(matches!(result, Err(SandboxError::Refused(ref message)) if message == "Query timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn caps_rows_across_runs() {
+        let pack = TestPack::new("rows");
+        let mut sandbox = QuerySandbox::with_limits(QUERY_TIMEOUT, 3);
+        assert_eq!(sandbox.run(&pack.path, "SELECT term FROM entries", &[]).unwrap().rows.len(), 3);
+        assert!(sandbox.run(&pack.path, "SELECT term FROM entries", &[]).unwrap().rows.is_empty());
+    }
+
+    #[test]
+    fn binds_parameters_with_their_json_type() {
+        let pack = TestPack::new("params");
+        let params = parse_params(r#"[1, 2.5, true, null, "1"]"#).unwrap();
+        assert_eq!(
+            params,
+            [
+                Value::Integer(1),
+                Value::Real(2.5),
+                Value::Integer(1),
+                Value::Null,
+                Value::Text("1".to_string())
+            ]
+        );
+        let result = pack
+            .run("SELECT typeof(?), typeof(?), typeof(?), typeof(?), typeof(?)", &params)
+            .unwrap();
+        let types: Vec<Value> = ["integer", "real", "integer", "null", "text"]
+            .iter()
+            .map(|t| Value::Text(t.to_string()))
+            .collect();
+        assert_eq!(result.rows, [types]);
+
+        let by_id = pack.run("SELECT term FROM entries WHERE id = ?", &parse_params("[2]").unwrap()).unwrap();
+        assert_eq!(by_id.rows, [[Value::Text("ཁ་".to_string())]]);
+
+        assert!(parse_params("").unwrap().is_empty());
+        assert!(parse_params(r#"[{"a": 1}]"#).is_err());
+        assert!(parse_params(r#"[[1]]"#).is_err());
+        assert!(parse_params("not json").is_err());
+    }
+}
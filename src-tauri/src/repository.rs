@@ -0,0 +1,660 @@
+//! One read layer over the two ways dictionaries are stored.
+//!
+//! The legacy build reads a single TibetanTranslator.sqlite (see
+//! database.rs), the modular build a set of packs (see packs.rs). Both have
+//! the same tables, so the entry queries, the FTS escaping and the row
+//! mapping live here once, behind the Repository trait.
+//!
+//! One set of lookup commands serves both, and each call says which backend
+//! it reads (Backend). Only the pack set has the user pack, personal notes
+//! and dictionary settings; the pack_* commands always read it, so the
+//! popup and the mobile path never depend on what the main window opened.
+//! The single database answers in the legacy shape, with numeric
+//! dictionaryId values.
+
+use crate::data_host::DataHost;
+#[cfg(feature = "app")]
+use crate::database::{with_connection, DatabasePool};
+use crate::dictionary_settings::{load_applied_settings, AppliedSettings};
+#[cfg(feature = "app")]
+use crate::packs::ensure_pack_available;
+use crate::packs::{get_all_pack_db_paths, PackDictionary, PackEntry};
+#[cfg(feature = "app")]
+use crate::query_sandbox::parse_params;
+use crate::query_sandbox::{value_to_json, QuerySandbox, SandboxError, SandboxRows};
+use crate::tibetan::{segment, word_candidates, Segment};
+use crate::user_pack::attach_notes;
+use rusqlite::types::Value;
+use rusqlite::{Connection, Row, ToSql};
+#[cfg(feature = "app")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+#[cfg(feature = "app")]
+use tauri::{AppHandle, Manager};
+
+/// Pack id given to the dictionaries of the single legacy database
+pub(crate) const LEGACY_PACK_ID: &str = "legacy";
+
+/// Most entries a search returns
+const MAX_SEARCH_RESULTS: usize = 5000;
+
+/// Most entries a search takes from one pack, so that every pack gets a
+/// share of the results
+const PACK_SEARCH_LIMIT: usize = 2000;
+
+/// Candidate words looked up per query when segmenting
+const SEGMENT_LOOKUP_CHUNK: usize = 500;
+
+const ENTRY_SELECT: &str = "
+    SELECT entries.id, entries.term, entries.termPhoneticsStrict, entries.termPhoneticsLoose,
+           entries.definition, entries.definitionPhoneticsWordsStrict,
+           entries.definitionPhoneticsWordsLoose, entries.dictionaryId,
+           dictionaries.name AS dictionary, dictionaries.position AS dictionaryPosition
+    FROM entries
+    INNER JOIN dictionaries ON dictionaries.id = entries.dictionaryId";
+
+pub(crate) trait Repository {
+    /// Every distinct term, sorted (for the Define page autocomplete)
+    fn all_terms(&self) -> Result<Vec<String>, String>;
+
+    /// Every dictionary, disabled ones included, sorted by position
+    fn dictionaries(&self) -> Result<Vec<PackDictionary>, String>;
+
+    /// Entries whose term is exactly `term`, sorted by dictionary position
+    fn entries_for_term(&self, term: &str) -> Result<Vec<PackEntry>, String>;
+
+    /// Full-text search; `search_type` is "regular", "phonetics_strict" or
+    /// "phonetics_loose"
+    fn search_entries(&self, query: &str, search_type: &str) -> Result<Vec<PackEntry>, String>;
+
+    /// Run a read-only query from the webview (see query_sandbox.rs)
+    fn execute_query(&self, sql: &str, params: &[Value]) -> Result<Vec<serde_json::Value>, String>;
+}
+
+/// FTS5 MATCH expression for `query`, bound as a parameter.
+///
+/// The query is one quoted phrase, with its double quotes doubled, so FTS5
+/// operators (`*`, `NEAR`, `-`, column filters) typed by the user are
+/// searched as words rather than run.
+fn fts_match(query: &str, search_type: &str) -> String {
+    let escaped_query = query.replace('"', "\"\"");
+    match search_type {
+        "phonetics_strict" => format!(
+            "(termPhoneticsStrict : \"{0}\" OR definitionPhoneticsWordsStrict : \"{0}\")",
+            escaped_query
+        ),
+        "phonetics_loose" => format!(
+            "(termPhoneticsLoose : \"{0}\" OR definitionPhoneticsWordsLoose : \"{0}\")",
+            escaped_query
+        ),
+        _ => format!("(term : \"{0}\" OR definition : \"{0}\")", escaped_query),
+    }
+}
+
+/// Map a row of ENTRY_SELECT
+fn read_entry(row: &Row, pack_id: &str) -> rusqlite::Result<PackEntry> {
+    let raw_dict_id: i64 = row.get(7)?;
+    Ok(PackEntry {
+        id: row.get(0)?,
+        term: row.get(1)?,
+        term_phonetics_strict: row.get(2)?,
+        term_phonetics_loose: row.get(3)?,
+        definition: row.get(4)?,
+        definition_phonetics_words_strict: row.get(5)?,
+        definition_phonetics_words_loose: row.get(6)?,
+        dictionary_id: format!("{}:{}", pack_id, raw_dict_id), // Compound ID
+        dictionary: row.get(8)?,
+        dictionary_position: row.get(9)?,
+        source_pack_id: Some(pack_id.to_string()),
+        user_note: None,
+    })
+}
+
+/// ENTRY_SELECT followed by `clauses` (joins, WHERE, ORDER BY, LIMIT)
+fn query_entries(
+    conn: &Connection,
+    pack_id: &str,
+    clauses: &str,
+    params: &[&dyn ToSql],
+) -> rusqlite::Result<Vec<PackEntry>> {
+    let mut stmt = conn.prepare(&format!("{} {}", ENTRY_SELECT, clauses))?;
+    let entries = stmt
+        .query_map(params, |row| read_entry(row, pack_id))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(entries)
+}
+
+fn query_terms(conn: &Connection, condition: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("SELECT DISTINCT term FROM entries WHERE {} ORDER BY term", condition))?;
+    let terms = stmt.query_map([], |row| row.get(0))?.filter_map(|r| r.ok()).collect();
+    Ok(terms)
+}
+
+/// Dictionaries as stored in the database, before any user setting
+fn query_dictionaries(conn: &Connection, pack_id: &str) -> rusqlite::Result<Vec<PackDictionary>> {
+    let mut stmt = conn.prepare("SELECT id, name, position, enabled FROM dictionaries ORDER BY position")?;
+    let dictionaries = stmt
+        .query_map([], |row| {
+            let raw_id: i64 = row.get(0)?;
+            Ok(PackDictionary {
+                id: format!("{}:{}", pack_id, raw_id), // Compound ID
+                name: row.get(1)?,
+                position: row.get(2)?,
+                enabled: row.get::<_, Option<i64>>(3)? != Some(0),
+                source_pack_id: Some(pack_id.to_string()),
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(dictionaries)
+}
+
+/// Rows of a raw query as JSON objects, with compound dictionary ids and
+/// the source pack id added
+fn rows_to_json(result: SandboxRows, pack_id: &str) -> Vec<serde_json::Value> {
+    let SandboxRows { columns, rows } = result;
+    rows.into_iter()
+        .map(|values| {
+            let mut obj = serde_json::Map::new();
+            for (col_name, value) in columns.iter().zip(values) {
+                let json_value = match value {
+                    // Convert dictionaryId to compound ID
+                    Value::Integer(int_val) if col_name == "dictionaryId" => {
+                        serde_json::Value::String(format!("{}:{}", pack_id, int_val))
+                    }
+                    value => value_to_json(value),
+                };
+                obj.insert(col_name.clone(), json_value);
+            }
+            obj.insert("_sourcePackId".to_string(), serde_json::Value::String(pack_id.to_string()));
+            serde_json::Value::Object(obj)
+        })
+        .collect()
+}
+
+/// The legacy TibetanTranslator.sqlite, read through a pooled connection
+pub(crate) struct SingleDatabase<'a> {
+    conn: &'a Connection,
+    path: PathBuf,
+}
+
+impl Repository for SingleDatabase<'_> {
+    fn all_terms(&self) -> Result<Vec<String>, String> {
+        query_terms(self.conn, "1").map_err(|e| format!("Failed to query terms: {}", e))
+    }
+
+    fn dictionaries(&self) -> Result<Vec<PackDictionary>, String> {
+        query_dictionaries(self.conn, LEGACY_PACK_ID).map_err(|e| format!("Failed to query dictionaries: {}", e))
+    }
+
+    fn entries_for_term(&self, term: &str) -> Result<Vec<PackEntry>, String> {
+        query_entries(
+            self.conn,
+            LEGACY_PACK_ID,
+            "WHERE entries.term = ? ORDER BY dictionaries.position",
+            &[&term],
+        )
+        .map_err(|e| format!("Failed to query entries: {}", e))
+    }
+
+    fn search_entries(&self, query: &str, search_type: &str) -> Result<Vec<PackEntry>, String> {
+        let clauses = format!(
+            "INNER JOIN entries_fts ON entries.id = entries_fts.rowid
+             WHERE entries_fts MATCH ?
+             LIMIT {}",
+            MAX_SEARCH_RESULTS
+        );
+        query_entries(self.conn, LEGACY_PACK_ID, &clauses, &[&fts_match(query, search_type)])
+            .map_err(|e| format!("Failed to execute search: {}", e))
+    }
+
+    fn execute_query(&self, sql: &str, params: &[Value]) -> Result<Vec<serde_json::Value>, String> {
+        let result = QuerySandbox::new()
+            .run(&self.path, sql, params)
+            .map_err(|e| e.to_string())?;
+        Ok(rows_to_json(result, LEGACY_PACK_ID))
+    }
+}
+
+/// Every installed pack, with the user's dictionary settings applied
+pub(crate) struct PackSet<'a> {
+    host: &'a dyn DataHost,
+    /// Every installed pack, the ones the user turned off included
+    pub(crate) packs: Vec<(String, PathBuf)>,
+    pub(crate) settings: AppliedSettings,
+}
+
+impl<'a> PackSet<'a> {
+    pub(crate) fn load(host: &'a dyn DataHost) -> Result<Self, String> {
+        Ok(PackSet {
+            host,
+            packs: get_all_pack_db_paths(host)?,
+            settings: load_applied_settings(host),
+        })
+    }
+
+    /// Open each pack, leaving out the ones the user turned off unless
+    /// `include_disabled`. Packs that can't be opened are skipped.
+    fn open_packs(&self, include_disabled: bool) -> impl Iterator<Item = (&str, Connection)> + '_ {
+        self.packs
+            .iter()
+            .filter(move |(pack_id, _)| include_disabled || self.settings.pack_enabled(pack_id))
+            .filter_map(|(pack_id, db_path)| match Connection::open(db_path) {
+                Ok(conn) => Some((pack_id.as_str(), conn)),
+                Err(e) => {
+                    eprintln!("Warning: Failed to open pack {} at {:?}: {}", pack_id, db_path, e);
+                    None
+                }
+            })
+    }
+
+    /// Give entries the user's dictionary order and their notes
+    fn finish_entries(&self, entries: &mut [PackEntry]) {
+        for entry in entries.iter_mut() {
+            if let Some(position) = entry.dictionary_position {
+                entry.dictionary_position = Some(self.settings.position(&entry.dictionary_id, position));
+            }
+        }
+        attach_notes(self.host, entries);
+    }
+
+    /// Drop rows of disabled dictionaries from a raw query's results and
+    /// give the others the user's dictionary order
+    fn apply_row_settings(&self, mut row: serde_json::Value) -> Option<serde_json::Value> {
+        let dictionary_id = match row.get("dictionaryId").and_then(|id| id.as_str()) {
+            Some(id) => id.to_string(),
+            None => return Some(row),
+        };
+        if !self.settings.dictionary_enabled(&dictionary_id) {
+            return None;
+        }
+        if let Some(position) = row.get_mut("dictionaryPosition") {
+            if let Some(pack_position) = position.as_i64() {
+                *position = serde_json::json!(self.settings.position(&dictionary_id, pack_position));
+            }
+        }
+        Some(row)
+    }
+
+    /// Split the Tibetan in `text` into the longest words found in the
+    /// enabled dictionaries (see tibetan::segment)
+    pub(crate) fn segment(&self, text: &str) -> Result<Vec<Segment>, String> {
+        let candidates: Vec<String> = word_candidates(text).into_iter().collect();
+        let mut known = HashSet::new();
+        for (pack_id, conn) in self.open_packs(false) {
+            for chunk in candidates.chunks(SEGMENT_LOOKUP_CHUNK) {
+                let sql = format!(
+                    "SELECT DISTINCT term FROM entries WHERE term IN ({}) AND {}",
+                    vec!["?"; chunk.len()].join(", "),
+                    self.settings.entries_filter(pack_id)
+                );
+                let terms = conn.prepare(&sql).and_then(|mut stmt| {
+                    stmt.query_map(rusqlite::params_from_iter(chunk), |row| row.get::<_, String>(0))?
+                        .collect::<rusqlite::Result<Vec<String>>>()
+                });
+                match terms {
+                    Ok(terms) => known.extend(terms),
+                    Err(e) => eprintln!("Warning: Failed to look up words in pack {}: {}", pack_id, e),
+                }
+            }
+        }
+        Ok(segment(text, &known))
+    }
+}
+
+impl Repository for PackSet<'_> {
+    fn all_terms(&self) -> Result<Vec<String>, String> {
+        let mut all_terms = HashSet::new();
+        for (pack_id, conn) in self.open_packs(false) {
+            match query_terms(&conn, &self.settings.entries_filter(pack_id)) {
+                Ok(terms) => all_terms.extend(terms),
+                Err(e) => eprintln!("Warning: Failed to query terms from pack {}: {}", pack_id, e),
+            }
+        }
+
+        let mut terms: Vec<String> = all_terms.into_iter().collect();
+        terms.sort();
+        Ok(terms)
+    }
+
+    fn dictionaries(&self) -> Result<Vec<PackDictionary>, String> {
+        // Disabled dictionaries are listed too, so they can be turned back on
+        let mut all_dictionaries = Vec::new();
+        for (pack_id, conn) in self.open_packs(true) {
+            match query_dictionaries(&conn, pack_id) {
+                Ok(dictionaries) => all_dictionaries.extend(dictionaries.into_iter().map(|mut dictionary| {
+                    dictionary.position = self.settings.position(&dictionary.id, dictionary.position);
+                    dictionary.enabled = self.settings.dictionary_enabled(&dictionary.id);
+                    dictionary
+                })),
+                Err(e) => eprintln!("Warning: Failed to query dictionaries from pack {}: {}", pack_id, e),
+            }
+        }
+
+        // Sort by position across all packs
+        all_dictionaries.sort_by_key(|d| d.position);
+        Ok(all_dictionaries)
+    }
+
+    fn entries_for_term(&self, term: &str) -> Result<Vec<PackEntry>, String> {
+        let mut all_entries = Vec::new();
+        for (pack_id, conn) in self.open_packs(false) {
+            let clauses = format!(
+                "WHERE entries.term = ? AND {} ORDER BY dictionaries.position",
+                self.settings.entries_filter(pack_id)
+            );
+            match query_entries(&conn, pack_id, &clauses, &[&term]) {
+                Ok(entries) => all_entries.extend(entries),
+                Err(e) => eprintln!("Warning: Failed to query entries from pack {}: {}", pack_id, e),
+            }
+        }
+
+        // Sort by dictionary position across all packs
+        self.finish_entries(&mut all_entries);
+        all_entries.sort_by(|a, b| {
+            a.dictionary_position
+                .unwrap_or(i64::MAX)
+                .cmp(&b.dictionary_position.unwrap_or(i64::MAX))
+        });
+        Ok(all_entries)
+    }
+
+    fn search_entries(&self, query: &str, search_type: &str) -> Result<Vec<PackEntry>, String> {
+        let fts_query = fts_match(query, search_type);
+        let mut all_entries = Vec::new();
+        for (pack_id, conn) in self.open_packs(false) {
+            let clauses = format!(
+                "INNER JOIN entries_fts ON entries.id = entries_fts.rowid
+                 WHERE entries_fts MATCH ? AND {}
+                 LIMIT {}",
+                self.settings.entries_filter(pack_id),
+                PACK_SEARCH_LIMIT
+            );
+            match query_entries(&conn, pack_id, &clauses, &[&fts_query]) {
+                Ok(entries) => all_entries.extend(entries),
+                Err(e) => eprintln!("Warning: Failed to run FTS query for pack {}: {}", pack_id, e),
+            }
+
+            // Stop if we have enough results
+            if all_entries.len() >= MAX_SEARCH_RESULTS {
+                break;
+            }
+        }
+
+        all_entries.truncate(MAX_SEARCH_RESULTS);
+        self.finish_entries(&mut all_entries);
+        Ok(all_entries)
+    }
+
+    fn execute_query(&self, sql: &str, params: &[Value]) -> Result<Vec<serde_json::Value>, String> {
+        let mut sandbox = QuerySandbox::new();
+        let mut all_results = Vec::new();
+        for (pack_id, db_path) in &self.packs {
+            if !self.settings.pack_enabled(pack_id) {
+                continue;
+            }
+            let result = match sandbox.run(db_path, sql, params) {
+                Ok(result) => result,
+                Err(SandboxError::Failed(e)) => {
+                    eprintln!("Warning: Query failed for pack {}: {}", pack_id, e);
+                    continue;
+                }
+                Err(e) => return Err(e.to_string()),
+            };
+            all_results.extend(
+                rows_to_json(result, pack_id)
+                    .into_iter()
+                    .filter_map(|row| self.apply_row_settings(row)),
+            );
+        }
+        Ok(all_results)
+    }
+}
+
+/// Results of the single database in the shape the legacy commands always
+/// returned: numeric dictionary ids and no source pack
+fn with_legacy_ids(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Array(items) => items.into_iter().map(with_legacy_ids).collect(),
+        serde_json::Value::Object(mut object) => {
+            object.remove("_sourcePackId");
+            for key in ["id", "dictionaryId"] {
+                let raw_id = object
+                    .get(key)
+                    .and_then(|id| id.as_str())
+                    .and_then(|id| id.strip_prefix(LEGACY_PACK_ID)?.strip_prefix(':')?.parse::<i64>().ok());
+                if let Some(raw_id) = raw_id {
+                    object.insert(key.to_string(), raw_id.into());
+                }
+            }
+            serde_json::Value::Object(object)
+        }
+        value => value,
+    }
+}
+
+/// Which storage a lookup command reads
+#[cfg(feature = "app")]
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Backend {
+    /// The legacy TibetanTranslator.sqlite (see database.rs). The default,
+    /// as these commands only read it before packs existed.
+    #[default]
+    SingleDatabase,
+    /// The installed packs, with the user pack, notes and settings
+    Packs,
+}
+
+/// Run `read` on a blocking thread against the installed packs
+#[cfg(feature = "app")]
+async fn with_pack_set<T, F>(app: AppHandle, read: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&PackSet) -> Result<T, String> + Send + 'static,
+{
+    // First ensure core pack is available in app data
+    ensure_pack_available(app.clone(), "core".to_string()).await?;
+    tauri::async_runtime::spawn_blocking(move || read(&PackSet::load(&app)?))
+        .await
+        .map_err(|e| format!("Pack query task failed: {}", e))?
+}
+
+/// Run `read` on a blocking thread against `backend`
+#[cfg(feature = "app")]
+async fn with_repository<T, F>(app: AppHandle, backend: Option<Backend>, read: F) -> Result<serde_json::Value, String>
+where
+    T: Serialize + Send + 'static,
+    F: FnOnce(&dyn Repository) -> Result<T, String> + Send + 'static,
+{
+    let to_json = |result: T| serde_json::to_value(result).map_err(|e| format!("Failed to serialize results: {}", e));
+    match backend.unwrap_or_default() {
+        Backend::SingleDatabase => {
+            let pool_app = app.clone();
+            let result = with_connection(app, move |conn| {
+                let path = pool_app.state::<DatabasePool>().path(&pool_app)?;
+                read(&SingleDatabase { conn, path })
+            })
+            .await?;
+            to_json(result).map(with_legacy_ids)
+        }
+        Backend::Packs => to_json(with_pack_set(app, |packs| read(packs)).await?),
+    }
+}
+
+/// Get all unique terms (for Define page autocomplete)
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn get_all_terms(app: AppHandle, backend: Option<Backend>) -> Result<serde_json::Value, String> {
+    with_repository(app, backend, |repository| repository.all_terms()).await
+}
+
+/// Get every dictionary, the disabled ones included
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn get_dictionaries(app: AppHandle, backend: Option<Backend>) -> Result<serde_json::Value, String> {
+    with_repository(app, backend, |repository| repository.dictionaries()).await
+}
+
+/// Get entries for a specific term
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn get_entries_for_term(
+    app: AppHandle,
+    term: String,
+    backend: Option<Backend>,
+) -> Result<serde_json::Value, String> {
+    with_repository(app, backend, move |repository| repository.entries_for_term(&term)).await
+}
+
+/// Search entries using FTS
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn search_entries(
+    app: AppHandle,
+    query: String,
+    search_type: String,
+    backend: Option<Backend>,
+) -> Result<serde_json::Value, String> {
+    with_repository(app, backend, move |repository| repository.search_entries(&query, &search_type)).await
+}
+
+/// Execute a read-only SQL query
+/// With packs, results are merged from all packs with source pack ID added
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn execute_query(
+    app: AppHandle,
+    sql: String,
+    params_json: String,
+    backend: Option<Backend>,
+) -> Result<serde_json::Value, String> {
+    let params = parse_params(&params_json)?;
+    with_repository(app, backend, move |repository| repository.execute_query(&sql, &params)).await
+}
+
+// The pack commands: always the pack set, whatever else the app opened
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn pack_get_all_terms(app: AppHandle) -> Result<Vec<String>, String> {
+    with_pack_set(app, |packs| packs.all_terms()).await
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn pack_get_dictionaries(app: AppHandle) -> Result<Vec<PackDictionary>, String> {
+    with_pack_set(app, |packs| packs.dictionaries()).await
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn pack_get_entries_for_term(app: AppHandle, term: String) -> Result<Vec<PackEntry>, String> {
+    with_pack_set(app, move |packs| packs.entries_for_term(&term)).await
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn pack_search_entries(
+    app: AppHandle,
+    query: String,
+    search_type: String,
+) -> Result<Vec<PackEntry>, String> {
+    with_pack_set(app, move |packs| packs.search_entries(&query, &search_type)).await
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn pack_execute_query(
+    app: AppHandle,
+    sql: String,
+    params_json: String,
+) -> Result<Vec<serde_json::Value>, String> {
+    let params = parse_params(&params_json)?;
+    with_pack_set(app, move |packs| packs.execute_query(&sql, &params)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tibdict_builder::{build_pack_sqlite, TibdictEntry};
+    use serde_json::json;
+
+    fn entry(term: &str, definition: &str) -> TibdictEntry {
+        TibdictEntry {
+            term: term.to_string(),
+            definition: definition.to_string(),
+            dictionary: None,
+            term_phonetics_strict: None,
+            term_phonetics_loose: None,
+            definition_phonetics_words_strict: None,
+            definition_phonetics_words_loose: None,
+        }
+    }
+
+    #[test]
+    fn search_takes_fts_syntax_literally() {
+        let dir = std::env::temp_dir().join(format!("repository-search-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.sqlite");
+        let _ = std::fs::remove_file(&path);
+        build_pack_sqlite(
+            &path,
+            &[
+                entry("ཀ་", "d'après the \"root\" text"),
+                entry("ཁ་", "bodhi mind"),
+                entry("ག་", "near the lake"),
+                entry("ང་", "well known"),
+                entry("ཅ་", "known well"),
+            ],
+            "Test",
+        )
+        .unwrap();
+        let conn = Connection::open(&path).unwrap();
+        let database = SingleDatabase { conn: &conn, path: path.clone() };
+        let terms = |query: &str| -> Vec<String> {
+            let mut terms: Vec<String> = database
+                .search_entries(query, "regular")
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.term)
+                .collect();
+            terms.sort();
+            terms
+        };
+
+        assert_eq!(terms("d'après"), ["ཀ་"]);
+        assert_eq!(terms("\"root\""), ["ཀ་"]);
+        assert_eq!(terms("'; DROP TABLE entries; --"), Vec::<String>::new());
+        // A prefix query would find "bodhi"
+        assert_eq!(terms("bod*"), Vec::<String>::new());
+        assert_eq!(terms("NEAR"), ["ག་"]);
+        assert_eq!(terms("NEAR(lake mind)"), Vec::<String>::new());
+        // "-" is not a NOT: the phrase "well known" in that order
+        assert_eq!(terms("well-known"), ["ང་"]);
+        assert_eq!(terms("definition : mind"), Vec::<String>::new());
+
+        drop(conn);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn legacy_results_keep_numeric_ids() {
+        let entries = json!([{
+            "id": 12,
+            "term": "ཀ་",
+            "dictionaryId": "legacy:3",
+            "_sourcePackId": "legacy",
+        }]);
+        assert_eq!(with_legacy_ids(entries), json!([{ "id": 12, "term": "ཀ་", "dictionaryId": 3 }]));
+
+        let dictionaries = json!([{ "id": "legacy:3", "name": "Rangjung Yeshe", "_sourcePackId": "legacy" }]);
+        assert_eq!(with_legacy_ids(dictionaries), json!([{ "id": 3, "name": "Rangjung Yeshe" }]));
+
+        // Other packs' ids and plain values are left alone
+        assert_eq!(with_legacy_ids(json!([{ "id": "core:3" }])), json!([{ "id": "core:3" }]));
+        assert_eq!(with_legacy_ids(json!(["ཀ་", "ཁ་"])), json!(["ཀ་", "ཁ་"]));
+    }
+}
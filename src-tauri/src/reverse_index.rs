@@ -0,0 +1,469 @@
+//! Reverse English → Tibetan lookup.
+//!
+//! Definitions are long free text, so an FTS match on `definition` returns
+//! every entry that merely mentions a word. Instead we cut each definition
+//! into its short glosses ("to meet/ come together; to hit on" gives "meet",
+//! "come together", "hit on"), normalize them and index them per term. A
+//! lookup then ranks terms by how many dictionaries give that gloss and how
+//! early in the definition they give it.
+
+use crate::collation::compare_tibetan;
+#[cfg(feature = "app")]
+use crate::pack_indexes::sync_index;
+use crate::pack_indexes::PackIndexer;
+#[cfg(feature = "app")]
+use crate::packs::ensure_pack_available;
+use crate::tibetan::is_tibetan;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+
+/// Glosses longer than this are phrases or explanations, not translations
+const MAX_GLOSS_WORDS: usize = 4;
+
+/// Only the first glosses of a definition are worth indexing
+const MAX_GLOSSES_PER_ENTRY: usize = 12;
+
+const DEFAULT_LIMIT: usize = 200;
+
+/// Editorial abbreviations found in definitions, compared lowercase
+const ABBREVIATIONS: &[&str] = &[
+    "syn.", "syn", "lit.", "lit", "skt.", "skt", "tib.", "fig.", "hon.", "h.", "e.g.", "i.e.",
+    "esp.", "etc.", "etc", "cf.", "abbr.", "n.", "v.", "vt.", "vi.", "adj.", "adv.", "pl.",
+    "sg.", "sb.", "sth.", "s.o.", "s.t.", "obs.", "coll.", "arch.", "var.", "usu.",
+];
+
+const LEADING_WORDS: &[&str] = &["to", "a", "an", "the"];
+
+const IRREGULAR_FORMS: &[(&str, &str)] = &[
+    ("men", "man"),
+    ("women", "woman"),
+    ("children", "child"),
+    ("people", "person"),
+    ("feet", "foot"),
+    ("teeth", "tooth"),
+    ("mice", "mouse"),
+    ("geese", "goose"),
+    ("lives", "life"),
+    ("wives", "wife"),
+    ("knives", "knife"),
+    ("leaves", "leaf"),
+    ("selves", "self"),
+    ("phenomena", "phenomenon"),
+    ("criteria", "criterion"),
+    ("is", "be"),
+    ("are", "be"),
+    ("was", "be"),
+    ("were", "be"),
+    ("been", "be"),
+    ("has", "have"),
+    ("had", "have"),
+    ("did", "do"),
+    ("done", "do"),
+    ("does", "do"),
+    ("went", "go"),
+    ("gone", "go"),
+    ("goes", "go"),
+    ("saw", "see"),
+    ("seen", "see"),
+    ("took", "take"),
+    ("taken", "take"),
+    ("gave", "give"),
+    ("given", "give"),
+    ("made", "make"),
+    ("said", "say"),
+    ("thought", "think"),
+    ("brought", "bring"),
+    ("taught", "teach"),
+    ("fell", "fall"),
+    ("fallen", "fall"),
+    ("came", "come"),
+    ("knew", "know"),
+    ("known", "know"),
+];
+
+pub(crate) struct ReverseIndex;
+
+impl PackIndexer for ReverseIndex {
+    const NAME: &'static str = "reverse";
+    const VERSION: u32 = 1;
+
+    fn create_tables(index: &Connection) -> rusqlite::Result<()> {
+        index.execute_batch(
+            "CREATE TABLE IF NOT EXISTS glosses (
+                pack_id        TEXT NOT NULL,
+                dictionary_id  INTEGER NOT NULL,
+                term           TEXT NOT NULL,
+                gloss          TEXT NOT NULL,
+                original       TEXT NOT NULL,
+                position       INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_glosses_gloss ON glosses(gloss);
+            CREATE INDEX IF NOT EXISTS idx_glosses_pack ON glosses(pack_id);",
+        )
+    }
+
+    fn clear_pack(index: &Connection, pack_id: &str) -> rusqlite::Result<()> {
+        index.execute("DELETE FROM glosses WHERE pack_id = ?", params![pack_id])?;
+        Ok(())
+    }
+
+    fn index_pack(index: &Connection, pack_id: &str, pack: &Connection) -> rusqlite::Result<usize> {
+        let mut select = pack.prepare("SELECT term, definition, dictionaryId FROM entries")?;
+        let mut insert = index.prepare(
+            "INSERT INTO glosses (pack_id, dictionary_id, term, gloss, original, position)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )?;
+
+        let mut count = 0;
+        let mut rows = select.query([])?;
+        while let Some(row) = rows.next()? {
+            let term: String = row.get(0)?;
+            let definition: String = row.get(1)?;
+            let dictionary_id: i64 = row.get(2)?;
+
+            for (position, (original, gloss)) in extract_glosses(&definition).into_iter().enumerate() {
+                insert.execute(params![pack_id, dictionary_id, term, gloss, original, position as i64])?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// Remove {...}, [...], (...) and <...> spans: Tibetan cross-references,
+/// editorial notes, source sigla and tags like 84000's "<term>".
+fn strip_bracketed(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut depth = 0usize;
+    for c in text.chars() {
+        match c {
+            '{' | '[' | '(' | '<' => depth += 1,
+            '}' | ']' | ')' | '>' if depth > 0 => {
+                depth -= 1;
+                out.push(' ');
+            }
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn is_abbreviation(word: &str) -> bool {
+    let lower = word.to_lowercase();
+    if ABBREVIATIONS.contains(&lower.as_str()) {
+        return true;
+    }
+    // Source sigla such as "KYN", "LZ", "TC" in Verbinator or "MSA" in Hopkins
+    word.len() >= 2
+        && word.len() <= 5
+        && word.chars().all(|c| c.is_ascii_uppercase() || c == '.')
+}
+
+fn is_gloss_word(word: &str) -> bool {
+    word.chars()
+        .all(|c| c.is_alphabetic() || c == '-' || c == '\'' || c == '’')
+        && word.chars().any(|c| c.is_alphabetic())
+        && !word.chars().any(is_tibetan)
+}
+
+/// Cut a definition into its short glosses.
+/// Returns (gloss as written, normalized gloss) pairs, most prominent first.
+pub(crate) fn extract_glosses(definition: &str) -> Vec<(String, String)> {
+    let text = strip_bracketed(&definition.replace("\\n", "\n"));
+    let mut glosses: Vec<(String, String)> = Vec::new();
+
+    for segment in text.split([';', ',', '/', '\n', '|', '•', '=']) {
+        // Drop labels such as "Syn.:" or "Meaning:"
+        let segment = segment.rsplit(':').next().unwrap_or(segment);
+
+        for sentence in segment.split(". ") {
+            let words: Vec<&str> = sentence
+                .split_whitespace()
+                .map(|w| w.trim_matches(|c: char| matches!(c, '"' | '“' | '”' | '!' | '?' | '…')))
+                // Numbered list markers: "1.", "2)", "ii."
+                .filter(|w| !w.trim_end_matches(['.', ')']).chars().all(|c| c.is_ascii_digit()))
+                .filter(|w| !is_abbreviation(w))
+                .map(|w| w.trim_end_matches('.'))
+                .filter(|w| !w.is_empty())
+                .collect();
+
+            if words.is_empty() || words.len() > MAX_GLOSS_WORDS || !words.iter().all(|w| is_gloss_word(w)) {
+                continue;
+            }
+
+            let original = words.join(" ");
+            let normalized = normalize_gloss(&original);
+            if normalized.chars().count() < 2 || glosses.iter().any(|(_, g)| *g == normalized) {
+                continue;
+            }
+            glosses.push((original, normalized));
+            if glosses.len() >= MAX_GLOSSES_PER_ENTRY {
+                return glosses;
+            }
+        }
+    }
+
+    glosses
+}
+
+fn lemmatize(word: &str) -> String {
+    if let Some((_, lemma)) = IRREGULAR_FORMS.iter().find(|(form, _)| *form == word) {
+        return lemma.to_string();
+    }
+    if word.len() <= 3 || !word.is_ascii() {
+        return word.to_string();
+    }
+    if word.len() > 4 && word.ends_with("ies") {
+        return format!("{}y", &word[..word.len() - 3]);
+    }
+    if ["sses", "shes", "ches", "xes", "zes"].iter().any(|s| word.ends_with(s)) {
+        return word[..word.len() - 2].to_string();
+    }
+    if word.ends_with('s') && !["ss", "us", "is"].iter().any(|s| word.ends_with(s)) {
+        return word[..word.len() - 1].to_string();
+    }
+    word.to_string()
+}
+
+/// Lowercase, strip punctuation and possessives, drop the infinitive "to"
+/// and leading articles, and lemmatize each word. Used both when indexing
+/// glosses and when normalizing the user's query, so both sides match.
+pub(crate) fn normalize_gloss(text: &str) -> String {
+    let lower = text.to_lowercase().replace('’', "'");
+    let mut words: Vec<String> = lower
+        .split_whitespace()
+        .map(|w| w.trim_end_matches("'s"))
+        .map(|w| {
+            w.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '-')
+                .collect::<String>()
+        })
+        .map(|w| w.trim_matches('-').to_string())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    while words.len() > 1 && LEADING_WORDS.contains(&words[0].as_str()) {
+        words.remove(0);
+    }
+
+    words.iter().map(|w| lemmatize(w)).collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReverseLookupResult {
+    pub term: String,
+    pub score: f64,
+    /// Compound IDs ("pack_id:dictionary_id") of the dictionaries giving this gloss
+    pub dictionary_ids: Vec<String>,
+    /// The gloss as written in each dictionary
+    pub glosses: Vec<String>,
+    /// Earliest position of the gloss in any definition (0 = first gloss)
+    pub best_position: i64,
+}
+
+#[derive(Default)]
+struct TermMatches {
+    positions: HashMap<String, i64>,
+    glosses: Vec<String>,
+}
+
+fn lookup(index: &Connection, gloss: &str, limit: usize) -> Result<Vec<ReverseLookupResult>, String> {
+    let mut stmt = index
+        .prepare("SELECT term, pack_id, dictionary_id, original, position FROM glosses WHERE gloss = ?")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let mut by_term: HashMap<String, TermMatches> = HashMap::new();
+    let rows = stmt
+        .query_map(params![gloss], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to query reverse index: {}", e))?;
+
+    for (term, pack_id, dictionary_id, original, position) in rows.filter_map(|r| r.ok()) {
+        let matches = by_term.entry(term).or_default();
+        let best = matches
+            .positions
+            .entry(format!("{}:{}", pack_id, dictionary_id))
+            .or_insert(position);
+        *best = (*best).min(position);
+        if !matches.glosses.contains(&original) {
+            matches.glosses.push(original);
+        }
+    }
+
+    let mut results: Vec<ReverseLookupResult> = by_term
+        .into_iter()
+        .map(|(term, matches)| {
+            // Each dictionary counts for 1, plus up to 1 more the earlier it gives the gloss
+            let score: f64 = matches
+                .positions
+                .values()
+                .map(|p| 1.0 + 1.0 / (1.0 + *p as f64))
+                .sum();
+            let best_position = matches.positions.values().copied().min().unwrap_or(0);
+            let mut dictionary_ids: Vec<String> = matches.positions.into_keys().collect();
+            dictionary_ids.sort();
+            ReverseLookupResult {
+                term,
+                score,
+                dictionary_ids,
+                glosses: matches.glosses,
+                best_position,
+            }
+        })
+        .collect();
+
+    results.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| compare_tibetan(&a.term, &b.term))
+    });
+    results.truncate(limit);
+    Ok(results)
+}
+
+/// Find Tibetan terms whose definitions give `english` as a gloss, best first
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn reverse_lookup(
+    app: AppHandle,
+    english: String,
+    limit: Option<usize>,
+) -> Result<Vec<ReverseLookupResult>, String> {
+    // First ensure core pack is available in app data
+    ensure_pack_available(app.clone(), "core".to_string()).await?;
+
+    let gloss = normalize_gloss(&english);
+    if gloss.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Indexing newly installed packs can take a while: keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let index = sync_index::<ReverseIndex>(&app)?;
+        lookup(&index, &gloss, limit.unwrap_or(DEFAULT_LIMIT))
+    })
+    .await
+    .map_err(|e| format!("Reverse lookup task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(definition: &str) -> Vec<String> {
+        extract_glosses(definition).into_iter().map(|(_, gloss)| gloss).collect()
+    }
+
+    #[test]
+    fn extracts_short_glosses() {
+        let cases: &[(&str, &[&str])] = &[
+            ("to go; to walk, to move", &["go", "walk", "move"]),
+            ("1. mind 2. heart", &["mind", "heart"]),
+            ("Syn.: {ཐུགས་} mind [KYN]", &["mind"]),
+            ("h. body; skt. kāya", &["body", "kāya"]),
+            ("mind, minds, the mind", &["mind"]),
+            ("the state of being free from all suffering; liberation", &["liberation"]),
+            ("ཀ་ letter; “emptiness”!", &["emptiness"]),
+            ("(lit.) great seal\\nmahāmudrā", &["great seal", "mahāmudrā"]),
+            ("a; 3.; KYN", &[]),
+        ];
+        for (definition, expected) in cases {
+            assert_eq!(normalized(definition), *expected, "{definition}");
+        }
+    }
+
+    #[test]
+    fn keeps_glosses_as_written() {
+        assert_eq!(
+            extract_glosses("To Go; the Buddha’s teachings"),
+            [
+                ("To Go".to_string(), "go".to_string()),
+                ("the Buddha’s teachings".to_string(), "buddha teaching".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn indexes_the_first_glosses_only() {
+        let definition: Vec<String> = (0..20).map(|i| format!("gloss{}", "x".repeat(i))).collect();
+        assert_eq!(extract_glosses(&definition.join(", ")).len(), MAX_GLOSSES_PER_ENTRY);
+    }
+
+    #[test]
+    fn lemmatizes_words() {
+        let cases = [
+            ("children", "child"),
+            ("went", "go"),
+            ("studies", "study"),
+            ("churches", "church"),
+            ("boxes", "box"),
+            ("teachings", "teaching"),
+            ("lies", "lie"),
+            ("glass", "glass"),
+            ("status", "status"),
+            ("basis", "basis"),
+            ("gas", "gas"),
+            ("kāyas", "kāyas"),
+        ];
+        for (word, lemma) in cases {
+            assert_eq!(lemmatize(word), lemma, "{word}");
+        }
+    }
+
+    #[test]
+    fn normalizes_glosses_and_queries_alike() {
+        let cases = [
+            ("To Go", "go"),
+            ("the Buddha's teachings", "buddha teaching"),
+            ("an", "an"),
+            ("to the", "the"),
+            ("self-aware minds!", "self-aware mind"),
+            ("-- wisdom --", "wisdom"),
+            ("", ""),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(normalize_gloss(text), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn ranks_terms_given_early_by_more_dictionaries() {
+        let index = Connection::open_in_memory().unwrap();
+        ReverseIndex::create_tables(&index).unwrap();
+        let rows = [
+            ("core", 1, "སེམས་", "mind", 0),
+            ("core", 2, "སེམས་", "Mind", 3),
+            ("core", 1, "བློ་", "mind", 0),
+            ("core", 1, "ཡིད་", "mind", 5),
+            ("core", 1, "ལུས་", "body", 0),
+        ];
+        for (pack_id, dictionary_id, term, original, position) in rows {
+            index
+                .execute(
+                    "INSERT INTO glosses VALUES (?, ?, ?, 'mind', ?, ?)",
+                    params![pack_id, dictionary_id, term, original, position],
+                )
+                .unwrap();
+        }
+        index.execute("UPDATE glosses SET gloss = 'body' WHERE original = 'body'", []).unwrap();
+
+        let results = lookup(&index, "mind", 10).unwrap();
+        let terms: Vec<&str> = results.iter().map(|r| r.term.as_str()).collect();
+        assert_eq!(terms, ["སེམས་", "བློ་", "ཡིད་"]);
+        assert_eq!(results[0].dictionary_ids, ["core:1", "core:2"]);
+        assert_eq!(results[0].glosses, ["mind", "Mind"]);
+        assert_eq!(lookup(&index, "mind", 1).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,419 @@
+//! Sanskrit → Tibetan lookup.
+//!
+//! Sanskrit equivalents come from two places: the Sanskrit dictionaries
+//! (Hopkins-Skt, Mahavyutpatti-Skt, 84000Skt, LokeshChandraSkt...), whose
+//! definitions are nothing but Sanskrit, and the IAST words scattered through
+//! English definitions ("nirvāṇa", "(Skt. tamas)"). Both are indexed per term
+//! with a diacritic-free form, so "nirvana" finds entries giving "nirvāṇa".
+
+use crate::collation::compare_tibetan;
+#[cfg(feature = "app")]
+use crate::pack_indexes::sync_index;
+use crate::pack_indexes::PackIndexer;
+#[cfg(feature = "app")]
+use crate::packs::ensure_pack_available;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+
+/// Longer segments of a Sanskrit dictionary are explanations, not equivalents
+const MAX_SANSKRIT_WORDS: usize = 5;
+
+const DEFAULT_LIMIT: usize = 200;
+
+/// Letters only found in IAST transliteration
+const IAST_LETTERS: &str = "āīūṛṝḷḹṅñṭḍṇśṣṃṁḥĀĪŪṚṜḶḸṄÑṬḌṆŚṢṂṀḤ";
+
+/// Words introducing a Sanskrit word in English definitions, compared lowercase
+const SANSKRIT_MARKERS: &[&str] = &["skt.", "skt:", "skt.:", "sk.", "sanskrit:"];
+
+/// Harvard-Kyoto letters used by older glossaries (e.g. Hopkins-Skt1992)
+const HARVARD_KYOTO: &[(char, &str)] = &[
+    ('A', "ā"),
+    ('I', "ī"),
+    ('U', "ū"),
+    ('R', "ṛ"),
+    ('L', "ḷ"),
+    ('G', "ṅ"),
+    ('J', "ñ"),
+    ('T', "ṭ"),
+    ('D', "ḍ"),
+    ('N', "ṇ"),
+    ('z', "ś"),
+    ('S', "ṣ"),
+    ('M', "ṃ"),
+    ('H', "ḥ"),
+];
+
+fn is_sanskrit_dictionary(dictionary_name: &str) -> bool {
+    dictionary_name.contains("Skt") || dictionary_name.contains("Sanskrit")
+}
+
+fn has_iast_letters(text: &str) -> bool {
+    text.chars().any(|c| IAST_LETTERS.contains(c))
+}
+
+/// Whether a word is clearly Harvard-Kyoto: a capital inside the word
+/// ("vyAyAma") or a 'z', which IAST never uses. A capital letter alone is
+/// taken as plain capitalisation ("Tara").
+fn is_harvard_kyoto(word: &str) -> bool {
+    word.chars().enumerate().any(|(i, c)| {
+        c == 'z' || (i > 0 && c.is_ascii_uppercase() && HARVARD_KYOTO.iter().any(|(hk, _)| *hk == c))
+    })
+}
+
+/// Convert the Harvard-Kyoto words of `text` to IAST, leaving the others as they are
+fn harvard_kyoto_to_iast(text: &str) -> String {
+    text.split(' ')
+        .map(|word| {
+            if !is_harvard_kyoto(word) {
+                return word.to_string();
+            }
+            word.chars()
+                .map(|c| match HARVARD_KYOTO.iter().find(|(hk, _)| *hk == c) {
+                    Some((_, iast)) => iast.to_string(),
+                    None => c.to_string(),
+                })
+                .collect()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Fold a Sanskrit word to lowercase ASCII, so searches ignore diacritics
+pub(crate) fn normalize_sanskrit(text: &str) -> String {
+    let folded: String = text
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            'ā' => Some('a'),
+            'ī' => Some('i'),
+            'ū' => Some('u'),
+            'ṛ' | 'ṝ' => Some('r'),
+            'ḷ' | 'ḹ' => Some('l'),
+            'ṅ' | 'ñ' | 'ṇ' => Some('n'),
+            'ṭ' => Some('t'),
+            'ḍ' => Some('d'),
+            'ś' | 'ṣ' => Some('s'),
+            'ṃ' | 'ṁ' => Some('m'),
+            'ḥ' => Some('h'),
+            // Combining marks left by decomposed input
+            '\u{0300}'..='\u{036F}' => None,
+            c if c.is_alphanumeric() => Some(c),
+            c if c.is_whitespace() || c == '-' => Some(' '),
+            _ => None,
+        })
+        .collect();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Remove "[C]" sigla, "<term>" tags and "(√yat)" or "(devamanuṣyāṇām)" asides
+fn strip_annotations(text: &str) -> String {
+    let mut result = String::new();
+    let mut closing: Vec<char> = Vec::new();
+    for c in text.chars() {
+        match c {
+            '[' => closing.push(']'),
+            '<' => closing.push('>'),
+            '(' => closing.push(')'),
+            _ if closing.last() == Some(&c) => {
+                closing.pop();
+            }
+            _ if closing.is_empty() => result.push(c),
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Cut a Sanskrit dictionary definition into its equivalents:
+/// "[C]yatna; [MSA](√yat): yateta" gives "yatna" and "yateta",
+/// "1) kapardaka 2) kākaṇi, kākiṇī" gives "kapardaka", "kākaṇi" and "kākiṇī".
+fn split_sanskrit_definition(definition: &str) -> Vec<String> {
+    let text = strip_annotations(&definition.replace("\\n", "\n"));
+    let mut segments = Vec::new();
+
+    for segment in text.split([';', ',', '\n', '/', '|']) {
+        // Numbered senses ("1) x 2) y") and "yatna. vyAyAma" lists
+        for part in segment.split(". ").flat_map(|s| s.split(')')) {
+            let part = part
+                .trim()
+                .trim_start_matches(|c: char| c.is_ascii_digit() || c == ':' || c.is_whitespace())
+                .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ':' || c.is_whitespace());
+            if part.is_empty() || part.split_whitespace().count() > MAX_SANSKRIT_WORDS {
+                continue;
+            }
+            if part.contains('?') {
+                continue;
+            }
+            // Without any diacritic the word is either plain or Harvard-Kyoto
+            let part = if has_iast_letters(part) {
+                part.to_string()
+            } else {
+                harvard_kyoto_to_iast(part)
+            };
+            segments.push(part.to_lowercase());
+        }
+    }
+    segments
+}
+
+fn trim_word(word: &str) -> &str {
+    word.trim_matches(|c: char| !c.is_alphanumeric() && !IAST_LETTERS.contains(c))
+}
+
+/// Pick the Sanskrit words out of an English definition: words written
+/// with IAST letters, and the word following a "Skt." marker.
+fn extract_inline_sanskrit(definition: &str) -> Vec<String> {
+    let text = definition.replace("\\n", "\n");
+    let mut words = Vec::new();
+
+    // Braces hold Tibetan: skip them
+    let mut depth = 0usize;
+    let mut plain = String::new();
+    for c in text.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => plain.push(c),
+            _ => {}
+        }
+    }
+
+    let plain_words: Vec<&str> = plain.split_whitespace().collect();
+    for (i, word) in plain_words.iter().enumerate() {
+        let marker = word.trim_start_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+        if SANSKRIT_MARKERS.contains(&marker.as_str()) {
+            let next = plain_words.get(i + 1).map(|w| trim_word(w)).unwrap_or("");
+            if next.chars().count() > 1 && next.chars().all(|c| c.is_alphabetic()) {
+                words.push(next.to_lowercase());
+            }
+        }
+    }
+
+    for word in plain_words.iter().map(|w| trim_word(w)) {
+        if has_iast_letters(word) && word.chars().all(|c| c.is_alphabetic() || c == '-') {
+            words.push(word.to_lowercase());
+        }
+    }
+
+    words
+}
+
+pub(crate) fn extract_sanskrit(dictionary_name: &str, definition: &str) -> Vec<String> {
+    let candidates = if is_sanskrit_dictionary(dictionary_name) {
+        split_sanskrit_definition(definition)
+    } else {
+        extract_inline_sanskrit(definition)
+    };
+
+    let mut seen = BTreeSet::new();
+    candidates
+        .into_iter()
+        .filter(|word| seen.insert(normalize_sanskrit(word)))
+        .collect()
+}
+
+pub(crate) struct SanskritIndex;
+
+impl PackIndexer for SanskritIndex {
+    const NAME: &'static str = "sanskrit";
+    const VERSION: u32 = 2;
+
+    fn create_tables(index: &Connection) -> rusqlite::Result<()> {
+        index.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sanskrit_terms (
+                pack_id        TEXT NOT NULL,
+                dictionary_id  INTEGER NOT NULL,
+                term           TEXT NOT NULL,
+                sanskrit       TEXT NOT NULL,
+                normalized     TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_sanskrit_terms_normalized ON sanskrit_terms(normalized);
+            CREATE INDEX IF NOT EXISTS idx_sanskrit_terms_pack ON sanskrit_terms(pack_id);",
+        )
+    }
+
+    fn clear_pack(index: &Connection, pack_id: &str) -> rusqlite::Result<()> {
+        index.execute("DELETE FROM sanskrit_terms WHERE pack_id = ?", params![pack_id])?;
+        Ok(())
+    }
+
+    fn index_pack(index: &Connection, pack_id: &str, pack: &Connection) -> rusqlite::Result<usize> {
+        let mut select = pack.prepare(
+            "SELECT entries.term, entries.definition, entries.dictionaryId, dictionaries.name
+             FROM entries
+             INNER JOIN dictionaries ON dictionaries.id = entries.dictionaryId",
+        )?;
+        let mut insert = index.prepare(
+            "INSERT INTO sanskrit_terms (pack_id, dictionary_id, term, sanskrit, normalized)
+             VALUES (?, ?, ?, ?, ?)",
+        )?;
+
+        let mut count = 0;
+        let mut rows = select.query([])?;
+        while let Some(row) = rows.next()? {
+            let term: String = row.get(0)?;
+            let definition: String = row.get(1)?;
+            let dictionary_id: i64 = row.get(2)?;
+            let dictionary_name: String = row.get(3)?;
+
+            for sanskrit in extract_sanskrit(&dictionary_name, &definition) {
+                let normalized = normalize_sanskrit(&sanskrit);
+                if normalized.is_empty() {
+                    continue;
+                }
+                insert.execute(params![pack_id, dictionary_id, term, sanskrit, normalized])?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SanskritSearchResult {
+    pub term: String,
+    /// Sanskrit forms matching the query, as written in the dictionaries
+    pub sanskrit: Vec<String>,
+    /// Compound IDs ("pack_id:dictionary_id") of the dictionaries giving them
+    pub dictionary_ids: Vec<String>,
+    /// Whether the whole Sanskrit word matched, rather than just its beginning
+    pub exact: bool,
+}
+
+#[derive(Default)]
+struct TermMatches {
+    sanskrit: Vec<String>,
+    dictionary_ids: BTreeSet<String>,
+    exact: bool,
+}
+
+fn search(index: &Connection, normalized: &str, limit: usize) -> Result<Vec<SanskritSearchResult>, String> {
+    // A range rather than LIKE, so that idx_sanskrit_terms_normalized is used:
+    // every string starting with `normalized` sorts between the two bounds
+    let upper = format!("{}\u{10FFFF}", normalized);
+    let mut stmt = index
+        .prepare(
+            "SELECT term, sanskrit, normalized, pack_id, dictionary_id
+             FROM sanskrit_terms
+             WHERE normalized >= ? AND normalized < ?",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let rows = stmt
+        .query_map(params![normalized, upper], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to query Sanskrit index: {}", e))?;
+
+    let mut by_term: HashMap<String, TermMatches> = HashMap::new();
+    for (term, sanskrit, row_normalized, pack_id, dictionary_id) in rows.filter_map(|r| r.ok()) {
+        let matches = by_term.entry(term).or_default();
+        matches.exact |= row_normalized == normalized;
+        matches.dictionary_ids.insert(format!("{}:{}", pack_id, dictionary_id));
+        if !matches.sanskrit.contains(&sanskrit) {
+            matches.sanskrit.push(sanskrit);
+        }
+    }
+
+    let mut results: Vec<SanskritSearchResult> = by_term
+        .into_iter()
+        .map(|(term, matches)| SanskritSearchResult {
+            term,
+            sanskrit: matches.sanskrit,
+            dictionary_ids: matches.dictionary_ids.into_iter().collect(),
+            exact: matches.exact,
+        })
+        .collect();
+
+    // Exact matches first, then the terms most dictionaries agree on
+    results.sort_by(|a, b| {
+        b.exact
+            .cmp(&a.exact)
+            .then_with(|| b.dictionary_ids.len().cmp(&a.dictionary_ids.len()))
+            .then_with(|| compare_tibetan(&a.term, &b.term))
+    });
+    results.truncate(limit);
+    Ok(results)
+}
+
+/// Find the Tibetan terms whose Sanskrit equivalent matches `sanskrit`.
+/// Diacritics are optional ("prajnaparamita" finds "prajñāpāramitā"), and
+/// Sanskrit words starting with the query are returned after exact matches.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn search_by_sanskrit(
+    app: AppHandle,
+    sanskrit: String,
+    limit: Option<usize>,
+) -> Result<Vec<SanskritSearchResult>, String> {
+    // First ensure core pack is available in app data
+    ensure_pack_available(app.clone(), "core".to_string()).await?;
+
+    let normalized = normalize_sanskrit(&sanskrit);
+    if normalized.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Indexing newly installed packs can take a while: keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let index = sync_index::<SanskritIndex>(&app)?;
+        search(&index, &normalized, limit.unwrap_or(DEFAULT_LIMIT))
+    })
+    .await
+    .map_err(|e| format!("Sanskrit search task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_harvard_kyoto_words_only() {
+        assert_eq!(split_sanskrit_definition("vyAyAma"), vec!["vyāyāma"]);
+        assert_eq!(split_sanskrit_definition("zUnyatA"), vec!["śūnyatā"]);
+        assert_eq!(split_sanskrit_definition("Tara"), vec!["tara"]);
+        assert_eq!(split_sanskrit_definition("Tara, prajJA"), vec!["tara", "prajñā"]);
+    }
+
+    #[test]
+    fn searches_by_prefix_with_the_index() {
+        let index = Connection::open_in_memory().unwrap();
+        SanskritIndex::create_tables(&index).unwrap();
+        for (term, sanskrit) in [("ཤེས་རབ་", "prajñā"), ("ཤེས་རབ་ཀྱི་ཕ་རོལ་ཏུ་ཕྱིན་པ་", "prajñāpāramitā"), ("ཐབས་", "upāya")] {
+            index
+                .execute(
+                    "INSERT INTO sanskrit_terms (pack_id, dictionary_id, term, sanskrit, normalized)
+                     VALUES ('core', 1, ?, ?, ?)",
+                    params![term, sanskrit, normalize_sanskrit(sanskrit)],
+                )
+                .unwrap();
+        }
+
+        let plan: String = index
+            .query_row(
+                "EXPLAIN QUERY PLAN SELECT term FROM sanskrit_terms WHERE normalized >= ? AND normalized < ?",
+                params!["prajna", "prajna\u{10FFFF}"],
+                |row| row.get(3),
+            )
+            .unwrap();
+        assert!(plan.contains("idx_sanskrit_terms_normalized"), "{}", plan);
+
+        let results = search(&index, "prajna", 10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].exact);
+        assert_eq!(results[0].term, "ཤེས་རབ་");
+        assert!(!results[1].exact);
+        assert!(search(&index, "upayaa", 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,427 @@
+//! Cross-dictionary graph of related terms.
+//!
+//! Entries only point to each other through their definition text: synonym
+//! lists (Hopkins, 84000), "see X" references, numbered divisions and example
+//! phrases. This index extracts those references into typed edges so the
+//! Define page can offer navigation between related headwords.
+
+use crate::collation::compare_tibetan;
+#[cfg(feature = "app")]
+use crate::pack_indexes::sync_index;
+use crate::pack_indexes::PackIndexer;
+#[cfg(feature = "app")]
+use crate::packs::{ensure_pack_available, get_all_pack_db_paths};
+use crate::tibetan::{normalize_term, tibetan_runs};
+#[cfg(feature = "app")]
+use rusqlite::OpenFlags;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkType {
+    Synonym,
+    SeeAlso,
+    DivisionOf,
+    Example,
+}
+
+impl LinkType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LinkType::Synonym => "synonym",
+            LinkType::SeeAlso => "see-also",
+            LinkType::DivisionOf => "division-of",
+            LinkType::Example => "example",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "synonym" => Some(LinkType::Synonym),
+            "see-also" => Some(LinkType::SeeAlso),
+            "division-of" => Some(LinkType::DivisionOf),
+            "example" => Some(LinkType::Example),
+            _ => None,
+        }
+    }
+}
+
+/// Words introducing a reference in English definitions, longest first so
+/// that "see also" wins over "see". Compared against lowercase text.
+const SYNONYM_MARKERS: &[&str] = &[
+    "synonyms:", "synonym:", "synonym of", "synonyms", "synonym", "syn.:", "syn:", "syn.", "syn",
+];
+const SEE_ALSO_MARKERS: &[&str] = &[
+    "see also:", "see also", "see:", "see", "same as", "variant of", "var. of", "abbr. of",
+    "short for", "cf.", "cf", "=", "→",
+];
+
+/// Text allowed between two references of the same list ("see X, Y or Z")
+const LIST_SEPARATORS: &[&str] = &["", ",", ";", "/", "and", "or"];
+
+/// Dictionaries whose whole definition is a list of related terms
+fn dictionary_link_type(dictionary_name: &str) -> Option<LinkType> {
+    if dictionary_name.contains("Synonyms") {
+        Some(LinkType::Synonym)
+    } else if dictionary_name.contains("Divisions") {
+        Some(LinkType::DivisionOf)
+    } else if dictionary_name.contains("Examples") {
+        Some(LinkType::Example)
+    } else {
+        None
+    }
+}
+
+fn ends_with_marker(prefix: &str, markers: &[&str]) -> bool {
+    markers.iter().any(|marker| {
+        prefix.strip_suffix(marker).is_some_and(|before| {
+            // Don't match "see" at the end of "foresee"
+            !marker.starts_with(|c: char| c.is_alphabetic())
+                || !before.ends_with(|c: char| c.is_alphabetic())
+        })
+    })
+}
+
+fn is_numbered_item(prefix: &str) -> bool {
+    let trimmed = prefix.trim_matches(|c: char| c.is_whitespace() || c == '།' || c == '་');
+    trimmed.starts_with('(')
+        && trimmed.ends_with(')')
+        && trimmed[1..trimmed.len() - 1].chars().all(|c| c.is_ascii_digit())
+        && trimmed.len() > 2
+}
+
+/// Extract the related terms referenced by a definition
+fn extract_links(dictionary_name: &str, definition: &str) -> Vec<(LinkType, String)> {
+    let runs = tibetan_runs(definition);
+    let mut links = Vec::new();
+
+    if let Some(link_type) = dictionary_link_type(dictionary_name) {
+        let mut items: Vec<(bool, &str)> = Vec::new();
+        let mut previous_end = 0;
+        for (start, end) in runs {
+            items.push((is_numbered_item(&definition[previous_end..start]), &definition[start..end]));
+            previous_end = end;
+        }
+        // Divisions are introduced by a label (དབྱེ་བ།) then numbered: keep only the numbered items
+        let numbered_only = items.iter().any(|(numbered, _)| *numbered);
+        for (numbered, text) in items {
+            if numbered || !numbered_only {
+                links.push((link_type, normalize_term(text)));
+            }
+        }
+        return links;
+    }
+
+    let mut current: Option<LinkType> = None;
+    let mut previous_end = 0;
+    for (start, end) in runs {
+        let prefix = definition[previous_end..start]
+            .trim_matches(|c: char| c.is_whitespace() || c == '།' || c == '་')
+            .to_lowercase();
+        previous_end = end;
+
+        if ends_with_marker(&prefix, SYNONYM_MARKERS) {
+            current = Some(LinkType::Synonym);
+        } else if ends_with_marker(&prefix, SEE_ALSO_MARKERS) {
+            current = Some(LinkType::SeeAlso);
+        } else if !LIST_SEPARATORS.contains(&prefix.as_str()) {
+            current = None;
+        }
+
+        if let Some(link_type) = current {
+            links.push((link_type, normalize_term(&definition[start..end])));
+        }
+    }
+    links
+}
+
+pub(crate) struct TermLinksIndex;
+
+impl PackIndexer for TermLinksIndex {
+    const NAME: &'static str = "term_links";
+    const VERSION: u32 = 1;
+
+    fn create_tables(index: &Connection) -> rusqlite::Result<()> {
+        index.execute_batch(
+            "CREATE TABLE IF NOT EXISTS term_links (
+                pack_id        TEXT NOT NULL,
+                dictionary_id  INTEGER NOT NULL,
+                source_term    TEXT NOT NULL,
+                target_term    TEXT NOT NULL,
+                link_type      TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_term_links_source ON term_links(source_term);
+            CREATE INDEX IF NOT EXISTS idx_term_links_target ON term_links(target_term);
+            CREATE INDEX IF NOT EXISTS idx_term_links_pack ON term_links(pack_id);",
+        )
+    }
+
+    fn clear_pack(index: &Connection, pack_id: &str) -> rusqlite::Result<()> {
+        index.execute("DELETE FROM term_links WHERE pack_id = ?", params![pack_id])?;
+        Ok(())
+    }
+
+    fn index_pack(index: &Connection, pack_id: &str, pack: &Connection) -> rusqlite::Result<usize> {
+        let mut select = pack.prepare(
+            "SELECT entries.term, entries.definition, entries.dictionaryId, dictionaries.name
+             FROM entries
+             INNER JOIN dictionaries ON dictionaries.id = entries.dictionaryId",
+        )?;
+        let mut insert = index.prepare(
+            "INSERT INTO term_links (pack_id, dictionary_id, source_term, target_term, link_type)
+             VALUES (?, ?, ?, ?, ?)",
+        )?;
+
+        let mut count = 0;
+        let mut rows = select.query([])?;
+        while let Some(row) = rows.next()? {
+            let term: String = row.get(0)?;
+            let definition: String = row.get(1)?;
+            let dictionary_id: i64 = row.get(2)?;
+            let dictionary_name: String = row.get(3)?;
+
+            let term = normalize_term(&term);
+            for (link_type, other) in extract_links(&dictionary_name, &definition) {
+                if other.is_empty() || other == term {
+                    continue;
+                }
+                // "X division-of Y": the listed item is a division of the headword
+                let (source, target) = match link_type {
+                    LinkType::DivisionOf => (&other, &term),
+                    _ => (&term, &other),
+                };
+                insert.execute(params![pack_id, dictionary_id, source, target, link_type.as_str()])?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedTerm {
+    pub term: String,
+    pub link_type: LinkType,
+    /// "outgoing" when the looked-up term points to this one (e.g. its
+    /// divisions are "incoming" division-of edges). Synonyms are symmetric
+    /// and always reported as "outgoing".
+    pub direction: String,
+    /// Compound IDs ("pack_id:dictionary_id") of the dictionaries asserting the link
+    pub dictionary_ids: Vec<String>,
+    /// Whether the related term is itself a headword in an installed pack
+    pub has_entries: bool,
+}
+
+fn query_related(index: &Connection, term: &str) -> Result<Vec<RelatedTerm>, String> {
+    let mut stmt = index
+        .prepare(
+            "SELECT source_term, target_term, link_type, pack_id, dictionary_id
+             FROM term_links
+             WHERE source_term = ?1 OR target_term = ?1",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let rows = stmt
+        .query_map(params![term], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to query term links: {}", e))?;
+
+    let mut grouped: HashMap<(String, LinkType, &'static str), BTreeSet<String>> = HashMap::new();
+    for (source, target, link_type, pack_id, dictionary_id) in rows.filter_map(|r| r.ok()) {
+        let link_type = match LinkType::parse(&link_type) {
+            Some(t) => t,
+            None => continue,
+        };
+        let (other, direction) = if source == term {
+            (target, "outgoing")
+        } else if link_type == LinkType::Synonym {
+            (source, "outgoing")
+        } else {
+            (source, "incoming")
+        };
+        grouped
+            .entry((other, link_type, direction))
+            .or_default()
+            .insert(format!("{}:{}", pack_id, dictionary_id));
+    }
+
+    let mut related: Vec<RelatedTerm> = grouped
+        .into_iter()
+        .map(|((term, link_type, direction), dictionary_ids)| RelatedTerm {
+            term,
+            link_type,
+            direction: direction.to_string(),
+            dictionary_ids: dictionary_ids.into_iter().collect(),
+            has_entries: false,
+        })
+        .collect();
+
+    related.sort_by(|a, b| {
+        a.link_type
+            .cmp(&b.link_type)
+            .then_with(|| a.direction.cmp(&b.direction))
+            .then_with(|| compare_tibetan(&a.term, &b.term))
+    });
+    Ok(related)
+}
+
+/// Get the terms linked to `term` across all installed dictionaries:
+/// synonyms, "see also" references, divisions and example phrases.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn get_related_terms(app: AppHandle, term: String) -> Result<Vec<RelatedTerm>, String> {
+    // First ensure core pack is available in app data
+    ensure_pack_available(app.clone(), "core".to_string()).await?;
+
+    let term = normalize_term(&term);
+    if term.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Indexing newly installed packs can take a while: keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let index = sync_index::<TermLinksIndex>(&app)?;
+        let mut related = query_related(&index, &term)?;
+
+        // Flag the related terms that can actually be opened
+        for (pack_id, db_path) in get_all_pack_db_paths(&app)? {
+            let conn = match Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+                Ok(conn) => conn,
+                Err(e) => {
+                    eprintln!("Warning: Failed to open pack {} at {:?}: {}", pack_id, db_path, e);
+                    continue;
+                }
+            };
+            let mut stmt = match conn.prepare("SELECT 1 FROM entries WHERE term = ? LIMIT 1") {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            for related_term in related.iter_mut().filter(|r| !r.has_entries) {
+                related_term.has_entries = stmt.exists(params![related_term.term]).unwrap_or(false);
+            }
+        }
+
+        Ok(related)
+    })
+    .await
+    .map_err(|e| format!("Related terms task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_host::TestDataDir;
+    use crate::tibdict_builder::{build_pack_sqlite, TibdictEntry};
+
+    fn links(dictionary_name: &str, definition: &str) -> Vec<(LinkType, String)> {
+        extract_links(dictionary_name, definition)
+    }
+
+    fn typed(link_type: LinkType, terms: &[&str]) -> Vec<(LinkType, String)> {
+        terms.iter().map(|term| (link_type, term.to_string())).collect()
+    }
+
+    #[test]
+    fn finds_references_after_markers() {
+        let cases: &[(&str, LinkType, &[&str])] = &[
+            ("mind; syn. སེམས་ and བློ།", LinkType::Synonym, &["སེམས་", "བློ་"]),
+            ("Synonyms: ཀ་, ཁ་ / ག", LinkType::Synonym, &["ཀ་", "ཁ་", "ག་"]),
+            ("see also བྱང་ཆུབ།", LinkType::SeeAlso, &["བྱང་ཆུབ་"]),
+            ("= ཆོས་", LinkType::SeeAlso, &["ཆོས་"]),
+            ("short for སངས་རྒྱས་ or བཅོམ་ལྡན་འདས།", LinkType::SeeAlso, &["སངས་རྒྱས་", "བཅོམ་ལྡན་འདས་"]),
+        ];
+        for (definition, link_type, terms) in cases {
+            assert_eq!(links("Hopkins", definition), typed(*link_type, terms), "{definition}");
+        }
+    }
+
+    #[test]
+    fn ignores_unmarked_tibetan() {
+        for definition in ["ཀ་ཁ་", "foresee ཀ།", "the teaching; ཆོས་"] {
+            assert_eq!(links("Hopkins", definition), [], "{definition}");
+        }
+        // Other text ends the list a marker started
+        assert_eq!(links("Hopkins", "syn. ཀ་ a kind of tree ཁ་"), typed(LinkType::Synonym, &["ཀ་"]));
+    }
+
+    #[test]
+    fn list_dictionaries_link_every_term() {
+        assert_eq!(links("Tshig mdzod Synonyms", "ཀ་ ཁ། ག"), typed(LinkType::Synonym, &["ཀ་", "ཁ་", "ག་"]));
+        assert_eq!(links("Examples", "ཀ་ཁ་ ག་"), typed(LinkType::Example, &["ཀ་ཁ་", "ག་"]));
+        // Divisions keep their numbered items, not the label before them
+        assert_eq!(
+            links("Divisions", "དབྱེ་བ། (1) ཀ་ (2) ཁ་"),
+            typed(LinkType::DivisionOf, &["ཀ་", "ཁ་"])
+        );
+        assert_eq!(links("Divisions", "ཀ་ ཁ་"), typed(LinkType::DivisionOf, &["ཀ་", "ཁ་"]));
+    }
+
+    #[test]
+    fn recognizes_numbered_items() {
+        for (prefix, numbered) in [("(1) ", true), ("། (12)", true), ("()", false), ("(a)", false), ("1.", false)] {
+            assert_eq!(is_numbered_item(prefix), numbered, "{prefix}");
+        }
+    }
+
+    #[test]
+    fn relates_terms_in_both_directions() {
+        let dir = TestDataDir::new("term-links");
+        let path = dir.root.join("data.sqlite");
+        let entry = |term: &str, definition: &str, dictionary: &str| TibdictEntry {
+            term: term.to_string(),
+            definition: definition.to_string(),
+            dictionary: Some(dictionary.to_string()),
+            term_phonetics_strict: None,
+            term_phonetics_loose: None,
+            definition_phonetics_words_strict: None,
+            definition_phonetics_words_loose: None,
+        };
+        build_pack_sqlite(
+            &path,
+            &[
+                entry("ཆོས", "syn. དམ་ཆོས་ and ཆོས་", "Hopkins"),
+                entry("ཕུང་པོ་", "(1) གཟུགས་ (2) ཚོར་བ་", "Divisions"),
+            ],
+            "Hopkins",
+        )
+        .unwrap();
+        let index = Connection::open_in_memory().unwrap();
+        TermLinksIndex::create_tables(&index).unwrap();
+        // The self-reference is skipped
+        let count = TermLinksIndex::index_pack(&index, "core", &Connection::open(&path).unwrap()).unwrap();
+        assert_eq!(count, 3);
+
+        let related = |term: &str| -> Vec<(String, LinkType, String)> {
+            query_related(&index, term)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.term, r.link_type, r.direction))
+                .collect()
+        };
+        let edge = |term: &str, link_type, direction: &str| (term.to_string(), link_type, direction.to_string());
+        // In Tibetan order: གཟུགས sorts by its root letter ཟ, after ཚ
+        assert_eq!(
+            related("ཕུང་པོ་"),
+            [
+                edge("ཚོར་བ་", LinkType::DivisionOf, "incoming"),
+                edge("གཟུགས་", LinkType::DivisionOf, "incoming"),
+            ]
+        );
+        assert_eq!(related("གཟུགས་"), [edge("ཕུང་པོ་", LinkType::DivisionOf, "outgoing")]);
+        // Synonyms are symmetric
+        assert_eq!(related("དམ་ཆོས་"), [edge("ཆོས་", LinkType::Synonym, "outgoing")]);
+        assert_eq!(query_related(&index, "ཆོས་").unwrap()[0].dictionary_ids, ["core:1"]);
+    }
+}
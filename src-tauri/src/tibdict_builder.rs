@@ -0,0 +1,526 @@
+//! Authoring of .tibdict packs from tabular data.
+//!
+//! Mirrors build/lib/build-tibdict-sqlite.js and build/lib/tibdict-writer.js
+//! so glossaries can be made inside the app instead of with the build
+//! scripts. Phonetics are computed by the frontend (src/utils.js) and passed
+//! along with each row: CustomPackImporter.createPack reads the glossary
+//! with parse_glossary and fills in the missing ones before building. Packs
+//! built without them get them once installed (see pack_phonetics.rs).
+
+use crate::custom_packs::{is_valid_id, InstallError, TibdictManifest, TibdictManifestDictionary};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// .tibdict envelope version written by this app
+const FORMAT_VERSION: u32 = 1;
+
+/// Schema version of the data.sqlite written by this app
+/// MUST match SUPPORTED_SCHEMA_VERSION in src/config/pack-definitions.js
+const SCHEMA_VERSION: u32 = 3;
+
+/// Pack tables, identical to build/lib/pack-schema.js
+pub(crate) const PACK_SCHEMA: &str = "
+    CREATE TABLE dictionaries (
+      id        integer primary key,
+      name      text not null,
+      position  integer NOT NULL,
+      enabled   boolean default true
+    );
+
+    CREATE TABLE entries (
+      id                              integer primary key,
+      term                            text not null,
+      termPhoneticsStrict             text not null,
+      termPhoneticsLoose              text not null,
+      definition                      text not null,
+      definitionPhoneticsWordsStrict  text not null,
+      definitionPhoneticsWordsLoose   text not null,
+      dictionaryId                    integer
+    );
+
+    CREATE VIRTUAL TABLE entries_fts USING fts5(
+      term,
+      termPhoneticsStrict,
+      termPhoneticsLoose,
+      definition,
+      definitionPhoneticsWordsStrict,
+      definitionPhoneticsWordsLoose,
+      content = 'entries',
+      content_rowid = 'id',
+      tokenize = 'unicode61'
+    );
+
+    CREATE TRIGGER entries_after_insert AFTER INSERT ON entries BEGIN
+      INSERT INTO entries_fts(
+        rowid, term, termPhoneticsStrict, termPhoneticsLoose,
+        definition, definitionPhoneticsWordsStrict, definitionPhoneticsWordsLoose
+      ) VALUES (
+        new.id, new.term, new.termPhoneticsStrict, new.termPhoneticsLoose,
+        new.definition, new.definitionPhoneticsWordsStrict, new.definitionPhoneticsWordsLoose
+      );
+    END;
+    CREATE TRIGGER entries_after_delete AFTER DELETE ON entries BEGIN
+      INSERT INTO entries_fts(
+        entries_fts, rowid, term, termPhoneticsStrict, termPhoneticsLoose,
+        definition, definitionPhoneticsWordsStrict, definitionPhoneticsWordsLoose
+      ) VALUES (
+        'delete', old.id, old.term, old.termPhoneticsStrict, old.termPhoneticsLoose,
+        old.definition, old.definitionPhoneticsWordsStrict, old.definitionPhoneticsWordsLoose
+      );
+    END;
+    CREATE TRIGGER entries_after_update AFTER UPDATE ON entries BEGIN
+      INSERT INTO entries_fts(
+        entries_fts, rowid, term, termPhoneticsStrict, termPhoneticsLoose,
+        definition, definitionPhoneticsWordsStrict, definitionPhoneticsWordsLoose
+      ) VALUES (
+        'delete', old.id, old.term, old.termPhoneticsStrict, old.termPhoneticsLoose,
+        old.definition, old.definitionPhoneticsWordsStrict, old.definitionPhoneticsWordsLoose
+      );
+      INSERT INTO entries_fts(
+        rowid, term, termPhoneticsStrict, termPhoneticsLoose,
+        definition, definitionPhoneticsWordsStrict, definitionPhoneticsWordsLoose
+      ) VALUES (
+        new.id, new.term, new.termPhoneticsStrict, new.termPhoneticsLoose,
+        new.definition, new.definitionPhoneticsWordsStrict, new.definitionPhoneticsWordsLoose
+      );
+    END;
+
+    CREATE INDEX idx_entries_term ON entries(term);
+";
+
+/// One row of a glossary. `dictionary` defaults to the pack name.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TibdictEntry {
+    pub term: String,
+    pub definition: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub term_phonetics_strict: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub term_phonetics_loose: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub definition_phonetics_words_strict: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub definition_phonetics_words_loose: Option<String>,
+}
+
+/// Pack metadata chosen by the author; the rest of the manifest is derived.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TibdictMetadata {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Feed announcing newer versions, see pack_updates.rs
+    #[serde(default)]
+    pub update_url: Option<String>,
+}
+
+pub(crate) fn ensure_trailing_tsheg(term: &str) -> String {
+    let term = term.trim();
+    if term.ends_with(['་', '།', '༑', '༔']) {
+        term.to_string()
+    } else {
+        format!("{}་", term)
+    }
+}
+
+/// Split CSV text into records (RFC 4180: quoted fields may contain
+/// separators, newlines and doubled quotes).
+fn parse_csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+fn parse_tsv_records(text: &str) -> Vec<Vec<String>> {
+    text.lines()
+        .map(|line| line.trim_end_matches('\r').split('\t').map(String::from).collect())
+        .collect()
+}
+
+/// Map records to entries, using the header row when there is one
+/// (term, definition, dictionary and the four phonetics columns), and
+/// term / definition / dictionary by position otherwise.
+fn records_to_entries(mut records: Vec<Vec<String>>) -> Result<Vec<TibdictEntry>, String> {
+    records.retain(|record| record.iter().any(|field| !field.trim().is_empty()));
+
+    let header: Option<HashMap<String, usize>> = records.first().and_then(|first| {
+        let columns: HashMap<String, usize> = first
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.trim().to_lowercase(), i))
+            .collect();
+        (columns.contains_key("term") && columns.contains_key("definition")).then_some(columns)
+    });
+    let column = |name: &str, position: Option<usize>| match &header {
+        Some(columns) => columns.get(&name.to_lowercase()).copied(),
+        None => position,
+    };
+    let term_col = column("term", Some(0));
+    let definition_col = column("definition", Some(1));
+    let dictionary_col = column("dictionary", Some(2));
+    let phonetics_cols = [
+        column("termPhoneticsStrict", None),
+        column("termPhoneticsLoose", None),
+        column("definitionPhoneticsWordsStrict", None),
+        column("definitionPhoneticsWordsLoose", None),
+    ];
+
+    let skip = if header.is_some() { 1 } else { 0 };
+    let mut entries = Vec::new();
+    for (i, record) in records.iter().enumerate().skip(skip) {
+        let get = |col: Option<usize>| {
+            col.and_then(|c| record.get(c))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let (Some(term), Some(definition)) = (get(term_col), get(definition_col)) else {
+            return Err(format!("row {}: term and definition are required", i + 1));
+        };
+        entries.push(TibdictEntry {
+            term,
+            definition,
+            dictionary: get(dictionary_col),
+            term_phonetics_strict: get(phonetics_cols[0]),
+            term_phonetics_loose: get(phonetics_cols[1]),
+            definition_phonetics_words_strict: get(phonetics_cols[2]),
+            definition_phonetics_words_loose: get(phonetics_cols[3]),
+        });
+    }
+    Ok(entries)
+}
+
+/// Parse glossary rows from CSV, TSV, JSON (an array of entry objects) or
+/// JSON Lines (one entry object per line)
+pub(crate) fn parse_entries(content: &str, format: &str) -> Result<Vec<TibdictEntry>, String> {
+    let content = content.trim_start_matches('\u{feff}');
+    match format.to_lowercase().as_str() {
+        "csv" => records_to_entries(parse_csv_records(content)),
+        "tsv" | "tab" | "txt" => records_to_entries(parse_tsv_records(content)),
+        "json" => serde_json::from_str(content).map_err(|e| format!("bad json: {e}")),
+        "jsonl" | "ndjson" => content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("line {}: bad json: {e}", i + 1)))
+            .collect(),
+        other => Err(format!("unsupported format: {other}")),
+    }
+}
+
+/// Write a schema-v3 pack database at `path` and return its dictionaries
+/// with their entry counts. Entries without a dictionary go to `default_dictionary`.
+pub(crate) fn build_pack_sqlite(
+    path: &Path,
+    entries: &[TibdictEntry],
+    default_dictionary: &str,
+) -> rusqlite::Result<Vec<TibdictManifestDictionary>> {
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(PACK_SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    let mut dictionaries: Vec<TibdictManifestDictionary> = Vec::new();
+    {
+        let mut insert_dictionary =
+            tx.prepare("INSERT INTO dictionaries (id, name, position, enabled) VALUES (?, ?, ?, 1)")?;
+        let mut insert_entry = tx.prepare(
+            "INSERT INTO entries (term, termPhoneticsStrict, termPhoneticsLoose, definition,
+                                  definitionPhoneticsWordsStrict, definitionPhoneticsWordsLoose, dictionaryId)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )?;
+
+        for entry in entries {
+            let name = entry
+                .dictionary
+                .as_deref()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .unwrap_or(default_dictionary);
+            let index = match dictionaries.iter().position(|d| d.name == name) {
+                Some(index) => index,
+                None => {
+                    let id = dictionaries.len() + 1;
+                    insert_dictionary.execute(params![id as i64, name, id as i64])?;
+                    dictionaries.push(TibdictManifestDictionary {
+                        name: name.to_string(),
+                        entries_count: Some(0),
+                    });
+                    dictionaries.len() - 1
+                }
+            };
+
+            insert_entry.execute(params![
+                ensure_trailing_tsheg(&entry.term),
+                entry.term_phonetics_strict.as_deref().unwrap_or(""),
+                entry.term_phonetics_loose.as_deref().unwrap_or(""),
+                entry.definition,
+                entry.definition_phonetics_words_strict.as_deref().unwrap_or(""),
+                entry.definition_phonetics_words_loose.as_deref().unwrap_or(""),
+                (index + 1) as i64,
+            ])?;
+            if let Some(count) = dictionaries[index].entries_count.as_mut() {
+                *count += 1;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(dictionaries)
+}
+
+/// Package a manifest and a data.sqlite file into a .tibdict ZIP
+pub(crate) fn write_tibdict(output: &Path, manifest: &TibdictManifest, sqlite_path: &Path) -> io::Result<()> {
+    let manifest_json = serde_json::to_vec_pretty(manifest).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+
+    let mut zip = ZipWriter::new(File::create(output)?);
+    zip.start_file("manifest.json", options)?;
+    zip.write_all(&manifest_json)?;
+    zip.start_file("data.sqlite", options)?;
+    io::copy(&mut File::open(sqlite_path)?, &mut zip)?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// "My Tibetan Deck (2)" gives "my-tibetan-deck-2", usable as a pack id
+pub(crate) fn slugify(name: &str) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    slug.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-")
+}
+
+/// Pack id from the first of `names` that gives a usable slug. Tibetan
+/// titles slugify to nothing, so callers pass the file name as well.
+pub(crate) fn slug_id(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| slugify(name))
+        .find(|id| !id.is_empty())
+        .unwrap_or_else(|| "imported-dictionary".to_string())
+}
+
+/// Current UTC time as an ISO 8601 string, like `new Date().toISOString()`
+pub(crate) fn now_iso8601() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.000Z",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+/// Build a .tibdict from entries and metadata, written to `output`
+pub(crate) fn build_tibdict(
+    output: &Path,
+    metadata: TibdictMetadata,
+    entries: &[TibdictEntry],
+) -> Result<TibdictManifest, InstallError> {
+    if !is_valid_id(&metadata.id) {
+        return Err(InstallError::new("format", "invalid id"));
+    }
+    if metadata.name.trim().is_empty() {
+        return Err(InstallError::new("format", "name is required"));
+    }
+    if entries.is_empty() {
+        return Err(InstallError::new("format", "no entries"));
+    }
+
+    // Build next to the output so the final copy never crosses filesystems
+    let sqlite_path = PathBuf::from(format!("{}.data.sqlite.tmp", output.display()));
+    if sqlite_path.exists() {
+        let _ = fs::remove_file(&sqlite_path);
+    }
+
+    let dictionaries = match build_pack_sqlite(&sqlite_path, entries, metadata.name.trim()) {
+        Ok(dictionaries) => dictionaries,
+        Err(e) => {
+            let _ = fs::remove_file(&sqlite_path);
+            return Err(InstallError::new("corrupt", &format!("build sqlite: {e}")));
+        }
+    };
+
+    let manifest = TibdictManifest {
+        format: "tibdict".to_string(),
+        format_version: FORMAT_VERSION,
+        schema_version: SCHEMA_VERSION,
+        id: metadata.id,
+        name: metadata.name.trim().to_string(),
+        description: metadata.description,
+        author: metadata.author,
+        version: metadata.version,
+        created_at: Some(now_iso8601()),
+        icon: metadata.icon,
+        update_url: metadata.update_url,
+        dictionaries,
+    };
+
+    let written = write_tibdict(output, &manifest, &sqlite_path);
+    let _ = fs::remove_file(&sqlite_path);
+    written.map_err(|e| InstallError::new("path", &format!("write tibdict: {e}")))?;
+
+    Ok(manifest)
+}
+
+/// Build a .tibdict in the temp dir and return its bytes, for importers
+/// that hand the result straight to the installer.
+pub(crate) fn build_tibdict_bytes(
+    metadata: TibdictMetadata,
+    entries: &[TibdictEntry],
+) -> Result<Vec<u8>, InstallError> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let output = std::env::temp_dir().join(format!("tibdict-import-{}-{}.tibdict", std::process::id(), nanos));
+
+    let built = build_tibdict(&output, metadata, entries);
+    let bytes = built.and_then(|_| {
+        fs::read(&output).map_err(|e| InstallError::new("path", &format!("read built pack: {e}")))
+    });
+    let _ = fs::remove_file(&output);
+    bytes
+}
+
+/// Read glossary rows from a file or from its text, the format defaulting
+/// to the file extension
+#[cfg(feature = "app")]
+fn read_glossary(
+    source_path: Option<String>,
+    content: Option<String>,
+    format: Option<String>,
+) -> Result<Vec<TibdictEntry>, InstallError> {
+    let format = format
+        .or_else(|| {
+            source_path.as_deref().and_then(|p| {
+                Path::new(p).extension().and_then(|e| e.to_str()).map(String::from)
+            })
+        })
+        .ok_or_else(|| InstallError::new("format", "unknown input format"))?;
+
+    let content = match (content, &source_path) {
+        (Some(content), _) => content,
+        (None, Some(path)) => fs::read_to_string(path)
+            .map_err(|e| InstallError::new("path", &format!("read {path}: {e}")))?,
+        (None, None) => return Err(InstallError::new("format", "no input given")),
+    };
+
+    parse_entries(&content, &format).map_err(|e| InstallError::new("format", &e))
+}
+
+/// Parse a glossary into entries, so the frontend can add their phonetics
+/// before calling create_custom_pack. Takes the same input as create_custom_pack.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn parse_glossary(
+    source_path: Option<String>,
+    content: Option<String>,
+    format: Option<String>,
+) -> Result<Vec<TibdictEntry>, InstallError> {
+    tauri::async_runtime::spawn_blocking(move || read_glossary(source_path, content, format))
+        .await
+        .map_err(|e| InstallError::new("path", &format!("parse glossary task: {e}")))?
+}
+
+/// Create a .tibdict from a glossary so it can be shared or installed.
+/// The glossary is either a file (`source_path`) or its text (`content`);
+/// `format` is "csv", "tsv", "json" or "jsonl", defaulting to the file extension.
+/// Errors use the same codes as install_custom_pack ("format", "corrupt", "path").
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn create_custom_pack(
+    source_path: Option<String>,
+    content: Option<String>,
+    format: Option<String>,
+    metadata: TibdictMetadata,
+    output_path: String,
+) -> Result<TibdictManifest, InstallError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let entries = read_glossary(source_path, content, format)?;
+        build_tibdict(Path::new(&output_path), metadata, &entries)
+    })
+    .await
+    .map_err(|e| InstallError::new("path", &format!("create pack task: {e}")))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slug_id_falls_back_on_the_file_name() {
+        assert_eq!(slug_id(&["My Tibetan Deck (2)", "deck"]), "my-tibetan-deck-2");
+        assert_eq!(slug_id(&["བོད་ཡིག་", "tibetan-words"]), "tibetan-words");
+        assert_eq!(slug_id(&["བོད་ཡིག་", "བོད་ཡིག་"]), "imported-dictionary");
+        assert!(is_valid_id(&slug_id(&["བོད་ཡིག་", "བོད་ཡིག་"])));
+    }
+}
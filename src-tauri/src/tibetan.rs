@@ -0,0 +1,111 @@
+//! Small helpers for picking Tibetan out of mixed-script definitions.
+
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Shads and other marks that end a Tibetan phrase
+const PHRASE_DELIMITERS: [char; 8] = ['།', '༎', '༏', '༐', '༑', '༔', '༼', '༽'];
+
+/// Most syllables in a word `segment` looks up
+const MAX_WORD_SYLLABLES: usize = 8;
+
+pub(crate) fn is_tibetan(c: char) -> bool {
+    ('\u{0F00}'..='\u{0FFF}').contains(&c)
+}
+
+/// Byte ranges of the Tibetan phrases in `text`, in order. A phrase is a run
+/// of Tibetan characters, ended by a shad or by any non-Tibetan character.
+pub(crate) fn tibetan_runs(text: &str) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (i, c) in text.char_indices() {
+        let in_phrase = is_tibetan(c) && !PHRASE_DELIMITERS.contains(&c);
+        match (in_phrase, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                runs.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        runs.push((s, text.len()));
+    }
+
+    runs.retain(|(s, e)| text[*s..*e].chars().any(|c| ('\u{0F40}'..='\u{0FBC}').contains(&c)));
+    runs
+}
+
+/// Normalize a Tibetan word the way headwords are stored in packs: no
+/// surrounding whitespace or punctuation, and a single trailing tsheg.
+pub(crate) fn normalize_term(term: &str) -> String {
+    let trimmed = term.trim_matches(|c: char| c.is_whitespace() || c == '་' || PHRASE_DELIMITERS.contains(&c));
+    if trimmed.is_empty() {
+        return String::new();
+    }
+    format!("{}་", trimmed)
+}
+
+/// Syllables of each Tibetan phrase in `text`, without their tshegs
+fn phrase_syllables(text: &str) -> Vec<Vec<&str>> {
+    tibetan_runs(text)
+        .into_iter()
+        .map(|(start, end)| {
+            text[start..end]
+                .split(['་', '༌'])
+                .filter(|syllable| !syllable.is_empty())
+                .collect()
+        })
+        .collect()
+}
+
+/// `syllables` as a headword, with a trailing tsheg
+fn join_syllables(syllables: &[&str]) -> String {
+    format!("{}་", syllables.join("་"))
+}
+
+/// Every word `segment` may look up in `text`: each run of up to
+/// MAX_WORD_SYLLABLES syllables within a phrase
+pub(crate) fn word_candidates(text: &str) -> HashSet<String> {
+    let mut candidates = HashSet::new();
+    for syllables in phrase_syllables(text) {
+        for start in 0..syllables.len() {
+            for end in start + 1..=(start + MAX_WORD_SYLLABLES).min(syllables.len()) {
+                candidates.insert(join_syllables(&syllables[start..end]));
+            }
+        }
+    }
+    candidates
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Segment {
+    pub text: String,
+    /// False for a syllable that starts no known word
+    pub known: bool,
+}
+
+/// Split the Tibetan phrases of `text` into words: at each syllable, take
+/// the longest run of syllables found in `words` (normalized headwords).
+pub(crate) fn segment(text: &str, words: &HashSet<String>) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for syllables in phrase_syllables(text) {
+        let mut i = 0;
+        while i < syllables.len() {
+            let longest = (1..=MAX_WORD_SYLLABLES.min(syllables.len() - i))
+                .rev()
+                .map(|n| (n, join_syllables(&syllables[i..i + n])))
+                .find(|(_, word)| words.contains(word));
+            let (n, text, known) = match longest {
+                Some((n, word)) => (n, word, true),
+                None => (1, join_syllables(&syllables[i..i + 1]), false),
+            };
+            segments.push(Segment { text, known });
+            i += n;
+        }
+    }
+    segments
+}
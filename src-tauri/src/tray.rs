@@ -0,0 +1,304 @@
+//! The tray icon (a menu bar extra on macOS): quick search, recent lookups,
+//! the global lookup switch, pack download progress and Quit.
+//!
+//! With "Start in Background" (saved in `<app_data>/tray.json`, with the
+//! recent lookups) the app starts with its main window hidden; lookups then
+//! open in the popup until the user opens the main window from the tray.
+//! Closing the main window only hides it (see app.rs), so the app keeps
+//! running in the tray until Quit. Without a tray (e.g. no system tray on
+//! the Linux desktop) the main window is always shown and closes normally.
+//!
+//! A tray menu can't hold a text field, so Quick Search opens the popup,
+//! whose search field takes the typing.
+//!
+//! The menu follows the rest of the app through events:
+//! "term-looked-up" (services/recent-lookups.js), "global-lookup-changed"
+//! (global_lookup.rs) and the pack download and update progress (packs.rs).
+
+use crate::data_host::DataHost;
+use crate::deep_links::{open_link, DeepLink};
+use crate::global_lookup::open_popup;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::TrayIconBuilder;
+use tauri::{App, AppHandle, Emitter, Listener, Manager, Wry};
+
+const MAIN_WINDOW: &str = "main";
+
+const TRAY_ID: &str = "tray";
+
+/// How many terms "Recent Lookups" lists
+const MAX_RECENT_LOOKUPS: usize = 10;
+
+/// Menu id prefix of the recent lookups; the term follows
+const RECENT_PREFIX: &str = "tray-recent:";
+
+/// Where the download progress item goes while packs download: after
+/// Quick Search, Open, a separator, Recent Lookups and a separator
+const DOWNLOADS_POSITION: usize = 5;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct TraySettings {
+    start_in_background: bool,
+    /// Most recent first
+    recent_lookups: Vec<String>,
+}
+
+#[derive(Default)]
+struct Downloads {
+    /// Status line of each pack downloading or updating, by pack id
+    packs: BTreeMap<String, String>,
+    /// Whether the progress item is in the menu
+    shown: bool,
+}
+
+struct Tray {
+    menu: Menu<Wry>,
+    recent: Submenu<Wry>,
+    global_lookup: CheckMenuItem<Wry>,
+    downloads_item: MenuItem<Wry>,
+    settings: Mutex<TraySettings>,
+    downloads: Mutex<Downloads>,
+    /// Whether the global lookup hotkey is registered
+    global_lookup_on: Mutex<bool>,
+}
+
+fn settings_file(host: &dyn DataHost) -> Result<PathBuf, String> {
+    Ok(host.app_data_dir()?.join("tray.json"))
+}
+
+fn load_settings(host: &dyn DataHost) -> TraySettings {
+    settings_file(host)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(host: &dyn DataHost, settings: &TraySettings) -> Result<(), String> {
+    let path = settings_file(host)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    }
+    let json =
+        serde_json::to_vec_pretty(settings).map_err(|e| format!("Failed to serialize tray settings: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write tray settings: {}", e))
+}
+
+/// Whether the tray icon was added, so that the app can be reached once
+/// its main window is hidden
+pub fn has_tray(app: &AppHandle) -> bool {
+    app.tray_by_id(TRAY_ID).is_some()
+}
+
+/// Show the main window, and the Dock icon that goes with it on macOS
+fn show_main_window(app: &AppHandle) {
+    #[cfg(target_os = "macos")]
+    let _ = app.set_activation_policy(tauri::ActivationPolicy::Regular);
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn refresh_recent_lookups(app: &AppHandle, tray: &Tray) -> tauri::Result<()> {
+    for item in tray.recent.items()? {
+        tray.recent.remove(&item)?;
+    }
+    let settings = tray.settings.lock().unwrap_or_else(|e| e.into_inner());
+    if settings.recent_lookups.is_empty() {
+        let none = MenuItem::with_id(app, "tray-recent-none", "No Recent Lookups", false, None::<&str>)?;
+        return tray.recent.append(&none);
+    }
+    for term in &settings.recent_lookups {
+        let item = MenuItem::with_id(app, format!("{}{}", RECENT_PREFIX, term), term, true, None::<&str>)?;
+        tray.recent.append(&item)?;
+    }
+    Ok(())
+}
+
+fn record_lookup(app: &AppHandle, term: String) {
+    let tray = app.state::<Tray>();
+    {
+        let mut settings = tray.settings.lock().unwrap_or_else(|e| e.into_inner());
+        if settings.recent_lookups.first() == Some(&term) {
+            return;
+        }
+        settings.recent_lookups.retain(|recent| *recent != term);
+        settings.recent_lookups.insert(0, term);
+        settings.recent_lookups.truncate(MAX_RECENT_LOOKUPS);
+        if let Err(e) = save_settings(app, &settings) {
+            eprintln!("[tray] {}", e);
+        }
+    }
+    if let Err(e) = refresh_recent_lookups(app, &tray) {
+        eprintln!("[tray] Failed to update recent lookups: {}", e);
+    }
+}
+
+/// Follow a "pack-download-progress" or "pack-update-progress" event
+fn update_downloads(app: &AppHandle, payload: &str) {
+    let Ok(progress) = serde_json::from_str::<serde_json::Value>(payload) else {
+        return;
+    };
+    let (Some(pack_id), Some(status)) = (progress["packId"].as_str(), progress["status"].as_str()) else {
+        return;
+    };
+    let percentage = progress["percentage"].as_f64().unwrap_or(0.0);
+
+    let tray = app.state::<Tray>();
+    let mut downloads = tray.downloads.lock().unwrap_or_else(|e| e.into_inner());
+    match status {
+        "complete" | "failed" => {
+            downloads.packs.remove(pack_id);
+        }
+        "extracting" => {
+            downloads.packs.insert(pack_id.to_string(), format!("Installing {}…", pack_id));
+        }
+        _ => {
+            downloads
+                .packs
+                .insert(pack_id.to_string(), format!("Downloading {}: {:.0}%", pack_id, percentage));
+        }
+    }
+
+    let result = if downloads.packs.is_empty() {
+        downloads.shown = false;
+        tray.menu.remove(&tray.downloads_item)
+    } else {
+        let text = downloads.packs.values().cloned().collect::<Vec<_>>().join(", ");
+        tray.downloads_item.set_text(text).and_then(|()| {
+            if downloads.shown {
+                return Ok(());
+            }
+            downloads.shown = true;
+            tray.menu.insert(&tray.downloads_item, DOWNLOADS_POSITION)
+        })
+    };
+    if let Err(e) = result {
+        eprintln!("[tray] Failed to update download progress: {}", e);
+    }
+}
+
+fn handle_tray_menu_event(app: &AppHandle, event: MenuEvent) {
+    let tray = app.state::<Tray>();
+    match event.id().0.as_str() {
+        "tray-quick-search" => {
+            if let Err(e) = open_popup(app, None) {
+                eprintln!("[tray] Failed to show the popup: {}", e);
+            }
+        }
+        "tray-open" => show_main_window(app),
+        "tray-global-lookup" => {
+            // The item checked itself; it shows the hotkey's actual state
+            // until the frontend has switched it (or failed to)
+            let on = *tray.global_lookup_on.lock().unwrap_or_else(|e| e.into_inner());
+            let _ = tray.global_lookup.set_checked(on);
+            let _ = app.emit_to(MAIN_WINDOW, "tray-toggle-global-lookup", !on);
+        }
+        "tray-start-in-background" => {
+            let mut settings = tray.settings.lock().unwrap_or_else(|e| e.into_inner());
+            settings.start_in_background = !settings.start_in_background;
+            if let Err(e) = save_settings(app, &settings) {
+                eprintln!("[tray] {}", e);
+            }
+        }
+        "tray-quit" => app.exit(0),
+        id => {
+            if let Some(term) = id.strip_prefix(RECENT_PREFIX) {
+                open_link(app, DeepLink::Define { term: term.to_string() });
+            }
+        }
+    }
+}
+
+/// Add the tray icon, then hide the main window if the app starts in the
+/// background. Without a tray the window stays as configured: visible.
+pub fn setup_tray(app: &App) -> tauri::Result<()> {
+    let handle = app.handle();
+    let settings = load_settings(handle);
+
+    let quick_search = MenuItem::with_id(app, "tray-quick-search", "Quick Search…", true, None::<&str>)?;
+    let open = MenuItem::with_id(app, "tray-open", "Open Tibetan Translator", true, None::<&str>)?;
+    let recent = Submenu::with_id(app, "tray-recent", "Recent Lookups", true)?;
+    let downloads_item = MenuItem::with_id(app, "tray-downloads", "", false, None::<&str>)?;
+    let global_lookup = CheckMenuItem::with_id(app, "tray-global-lookup", "Global Lookup", true, false, None::<&str>)?;
+    let start_in_background = CheckMenuItem::with_id(
+        app,
+        "tray-start-in-background",
+        "Start in Background",
+        true,
+        settings.start_in_background,
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app, "tray-quit", "Quit Tibetan Translator", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &quick_search,
+            &open,
+            &PredefinedMenuItem::separator(app)?,
+            &recent,
+            &PredefinedMenuItem::separator(app)?,
+            &global_lookup,
+            &start_in_background,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let mut tray_icon = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Tibetan Translator")
+        .menu(&menu)
+        .on_menu_event(handle_tray_menu_event);
+    if let Some(icon) = app.default_window_icon() {
+        tray_icon = tray_icon.icon(icon.clone());
+    }
+    tray_icon.build(app)?;
+
+    let start_in_background = settings.start_in_background;
+    app.manage(Tray {
+        menu,
+        recent,
+        global_lookup,
+        downloads_item,
+        settings: Mutex::new(settings),
+        downloads: Mutex::new(Downloads::default()),
+        global_lookup_on: Mutex::new(false),
+    });
+    refresh_recent_lookups(handle, &app.state::<Tray>())?;
+
+    let app_handle = handle.clone();
+    handle.listen_any("term-looked-up", move |event| {
+        if let Ok(term) = serde_json::from_str::<String>(event.payload()) {
+            record_lookup(&app_handle, term);
+        }
+    });
+    let app_handle = handle.clone();
+    handle.listen_any("global-lookup-changed", move |event| {
+        let on = event.payload() == "true";
+        let tray = app_handle.state::<Tray>();
+        *tray.global_lookup_on.lock().unwrap_or_else(|e| e.into_inner()) = on;
+        let _ = tray.global_lookup.set_checked(on);
+    });
+    for progress_event in ["pack-download-progress", "pack-update-progress"] {
+        let app_handle = handle.clone();
+        handle.listen_any(progress_event, move |event| update_downloads(&app_handle, event.payload()));
+    }
+
+    if start_in_background {
+        if let Some(window) = handle.get_webview_window(MAIN_WINDOW) {
+            let _ = window.hide();
+        }
+        // A menu bar app until the main window opens
+        #[cfg(target_os = "macos")]
+        let _ = handle.set_activation_policy(tauri::ActivationPolicy::Accessory);
+    }
+    Ok(())
+}
@@ -0,0 +1,603 @@
+//! The user's own, writable pack.
+//!
+//! Every other pack is read-only reference data. This one lives in
+//! packs/user/data.sqlite, next to packs/custom, with the same schema, so
+//! repository.rs merges it into lookups and searches like any other pack
+//! (pack id "user"). The FTS table is kept up to date by the schema's triggers.
+//!
+//! It also holds personal notes on entries of any pack. Notes are set on a
+//! compound entry id ("pack_id:entry_id") but kept by pack, dictionary name
+//! and term: entry ids change when a pack is updated, so an id alone could
+//! put the note on another word.
+
+use crate::data_host::DataHost;
+use crate::packs::{get_all_pack_db_paths, get_packs_dir, PackEntry};
+use crate::tibdict_builder::{ensure_trailing_tsheg, now_iso8601, TibdictEntry, PACK_SCHEMA};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+
+pub(crate) const USER_PACK_ID: &str = "user";
+
+/// Dictionary for entries added without one
+const DEFAULT_DICTIONARY: &str = "My Dictionary";
+
+/// Position of user dictionaries: before every pack's
+const USER_DICTIONARY_POSITION: i64 = 0;
+
+/// `entryId` is the entry the note was last set on, which may be stale
+/// after its pack was updated
+const NOTES_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS notes (
+      packId     text not null,
+      dictionary text not null,
+      term       text not null,
+      entryId    integer not null,
+      note       text not null,
+      createdAt  text not null,
+      updatedAt  text not null,
+      PRIMARY KEY (packId, dictionary, term)
+    );
+";
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryNote {
+    /// Compound id: "pack_id:entry_id" (e.g., "core:1234") of the entry the
+    /// note was last set on
+    pub entry_id: String,
+    pub dictionary: String,
+    pub term: String,
+    pub note: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn user_pack_file(host: &dyn DataHost) -> Result<PathBuf, String> {
+    Ok(get_packs_dir(host)?.join(USER_PACK_ID).join("data.sqlite"))
+}
+
+/// The user pack's database, if anything was ever written to it
+pub(crate) fn get_user_pack_path(host: &dyn DataHost) -> Option<PathBuf> {
+    user_pack_file(host).ok().filter(|path| path.exists())
+}
+
+/// Open the user pack for writing, creating it on first use
+fn open_user_pack(host: &dyn DataHost) -> Result<Connection, String> {
+    let path = user_pack_file(host)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create user pack dir: {}", e))?;
+    }
+    let conn = Connection::open(&path).map_err(|e| format!("Failed to open user pack: {}", e))?;
+
+    let has_entries: bool = conn
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'entries'")
+        .and_then(|mut stmt| stmt.exists([]))
+        .map_err(|e| format!("Failed to read user pack: {}", e))?;
+    if !has_entries {
+        conn.execute_batch(PACK_SCHEMA)
+            .map_err(|e| format!("Failed to create user pack: {}", e))?;
+    }
+    conn.execute_batch(NOTES_SCHEMA)
+        .map_err(|e| format!("Failed to create notes table: {}", e))?;
+    Ok(conn)
+}
+
+fn parse_compound_id(compound_id: &str) -> Result<(String, i64), String> {
+    compound_id
+        .split_once(':')
+        .and_then(|(pack_id, id)| Some((pack_id.to_string(), id.parse().ok()?)))
+        .ok_or_else(|| format!("Invalid entry id: {}", compound_id))
+}
+
+/// Dictionary name and term of an entry of any pack, which key its note
+fn note_key(host: &dyn DataHost, pack_id: &str, id: i64) -> Result<(String, String), String> {
+    let db_path = get_all_pack_db_paths(host)?
+        .into_iter()
+        .find(|(installed, _)| installed == pack_id)
+        .map(|(_, path)| path)
+        .ok_or_else(|| format!("Pack not installed: {}", pack_id))?;
+    Connection::open(&db_path)
+        .and_then(|pack| {
+            pack.query_row(
+                "SELECT dictionaries.name, entries.term FROM entries
+                 INNER JOIN dictionaries ON dictionaries.id = entries.dictionaryId
+                 WHERE entries.id = ?",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+        })
+        .map_err(|_| format!("Entry {}:{} not found", pack_id, id))
+}
+
+/// Drop the notes of user entries that no longer exist
+fn delete_orphan_user_notes(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM notes WHERE packId = ? AND NOT EXISTS (
+           SELECT 1 FROM entries
+           INNER JOIN dictionaries ON dictionaries.id = entries.dictionaryId
+           WHERE dictionaries.name = notes.dictionary AND entries.term = notes.term
+         )",
+        params![USER_PACK_ID],
+    )
+}
+
+fn dictionary_id_for(conn: &Connection, name: &str) -> rusqlite::Result<i64> {
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM dictionaries WHERE name = ?",
+            params![name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match existing {
+        Some(id) => Ok(id),
+        None => {
+            conn.execute(
+                "INSERT INTO dictionaries (name, position, enabled) VALUES (?, ?, 1)",
+                params![name, USER_DICTIONARY_POSITION],
+            )?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+fn read_entry(conn: &Connection, id: i64) -> Result<PackEntry, String> {
+    conn.query_row(
+        "SELECT entries.id, entries.term, entries.termPhoneticsStrict, entries.termPhoneticsLoose,
+                entries.definition, entries.definitionPhoneticsWordsStrict,
+                entries.definitionPhoneticsWordsLoose, entries.dictionaryId,
+                dictionaries.name AS dictionary, dictionaries.position AS dictionaryPosition
+         FROM entries
+         INNER JOIN dictionaries ON dictionaries.id = entries.dictionaryId
+         WHERE entries.id = ?",
+        params![id],
+        |row| {
+            let raw_dict_id: i64 = row.get(7)?;
+            Ok(PackEntry {
+                id: row.get(0)?,
+                term: row.get(1)?,
+                term_phonetics_strict: row.get(2)?,
+                term_phonetics_loose: row.get(3)?,
+                definition: row.get(4)?,
+                definition_phonetics_words_strict: row.get(5)?,
+                definition_phonetics_words_loose: row.get(6)?,
+                dictionary_id: format!("{}:{}", USER_PACK_ID, raw_dict_id),
+                dictionary: row.get(8)?,
+                dictionary_position: row.get(9)?,
+                source_pack_id: Some(USER_PACK_ID.to_string()),
+                user_note: None,
+            })
+        },
+    )
+    .map_err(|e| format!("User entry {} not found: {}", id, e))
+}
+
+fn validate(entry: &TibdictEntry) -> Result<(), String> {
+    if entry.term.trim().is_empty() || entry.definition.trim().is_empty() {
+        return Err("term and definition are required".to_string());
+    }
+    Ok(())
+}
+
+/// Fill in `user_note` on entries from any pack
+pub(crate) fn attach_notes(host: &dyn DataHost, entries: &mut [PackEntry]) {
+    let Some(path) = get_user_pack_path(host) else {
+        return;
+    };
+    let Ok(conn) = Connection::open(&path) else {
+        return;
+    };
+    let Ok(mut stmt) = conn.prepare("SELECT packId, dictionary, term, note FROM notes") else {
+        return;
+    };
+    let notes: HashMap<(String, String, String), String> =
+        match stmt.query_map([], |row| Ok(((row.get(0)?, row.get(1)?, row.get(2)?), row.get(3)?))) {
+            Ok(rows) => rows.filter_map(|r| r.ok()).collect(),
+            Err(_) => return,
+        };
+
+    for entry in entries {
+        let key = (
+            entry.source_pack_id.clone().unwrap_or_default(),
+            entry.dictionary.clone().unwrap_or_default(),
+            entry.term.clone(),
+        );
+        entry.user_note = notes.get(&key).cloned();
+    }
+}
+
+fn add_entry(host: &dyn DataHost, entry: &TibdictEntry) -> Result<PackEntry, String> {
+    validate(entry)?;
+    let conn = open_user_pack(host)?;
+
+    let dictionary = entry
+        .dictionary
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty());
+    let dictionary_id = dictionary_id_for(&conn, dictionary.unwrap_or(DEFAULT_DICTIONARY))
+        .map_err(|e| format!("Failed to create dictionary: {}", e))?;
+    conn.execute(
+        "INSERT INTO entries (term, termPhoneticsStrict, termPhoneticsLoose, definition,
+                              definitionPhoneticsWordsStrict, definitionPhoneticsWordsLoose, dictionaryId)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![
+            ensure_trailing_tsheg(&entry.term),
+            entry.term_phonetics_strict.as_deref().unwrap_or(""),
+            entry.term_phonetics_loose.as_deref().unwrap_or(""),
+            entry.definition.trim(),
+            entry.definition_phonetics_words_strict.as_deref().unwrap_or(""),
+            entry.definition_phonetics_words_loose.as_deref().unwrap_or(""),
+            dictionary_id,
+        ],
+    )
+    .map_err(|e| format!("Failed to add entry: {}", e))?;
+
+    let added = read_entry(&conn, conn.last_insert_rowid())?;
+    host.packs_changed();
+    Ok(added)
+}
+
+fn update_entry(host: &dyn DataHost, id: i64, entry: &TibdictEntry) -> Result<PackEntry, String> {
+    validate(entry)?;
+    let conn = open_user_pack(host)?;
+    let previous = read_entry(&conn, id)?;
+
+    let dictionary = entry
+        .dictionary
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .unwrap_or(DEFAULT_DICTIONARY);
+    let dictionary_id = dictionary_id_for(&conn, dictionary)
+        .map_err(|e| format!("Failed to create dictionary: {}", e))?;
+    let updated = conn
+        .execute(
+            "UPDATE entries SET term = ?, termPhoneticsStrict = ?, termPhoneticsLoose = ?, definition = ?,
+                                definitionPhoneticsWordsStrict = ?, definitionPhoneticsWordsLoose = ?,
+                                dictionaryId = ?
+             WHERE id = ?",
+            params![
+                ensure_trailing_tsheg(&entry.term),
+                entry.term_phonetics_strict.as_deref().unwrap_or(""),
+                entry.term_phonetics_loose.as_deref().unwrap_or(""),
+                entry.definition.trim(),
+                entry.definition_phonetics_words_strict.as_deref().unwrap_or(""),
+                entry.definition_phonetics_words_loose.as_deref().unwrap_or(""),
+                dictionary_id,
+                id,
+            ],
+        )
+        .map_err(|e| format!("Failed to update entry: {}", e))?;
+    if updated == 0 {
+        return Err(format!("User entry {} not found", id));
+    }
+    // The note follows the entry to its new term and dictionary
+    conn.execute(
+        "UPDATE OR REPLACE notes SET dictionary = ?, term = ?, entryId = ?
+         WHERE packId = ? AND dictionary = ? AND term = ?",
+        params![
+            dictionary,
+            ensure_trailing_tsheg(&entry.term),
+            id,
+            USER_PACK_ID,
+            previous.dictionary,
+            previous.term
+        ],
+    )
+    .and_then(|_| delete_orphan_user_notes(&conn))
+    .map_err(|e| format!("Failed to update note: {}", e))?;
+    conn.execute(
+        "DELETE FROM dictionaries WHERE id NOT IN (SELECT DISTINCT dictionaryId FROM entries)",
+        [],
+    )
+    .map_err(|e| format!("Failed to clean up dictionaries: {}", e))?;
+
+    let mut updated = read_entry(&conn, id)?;
+    attach_notes(host, std::slice::from_mut(&mut updated));
+    host.packs_changed();
+    Ok(updated)
+}
+
+fn delete_entry(host: &dyn DataHost, id: i64) -> Result<(), String> {
+    let conn = open_user_pack(host)?;
+    let deleted = conn
+        .execute("DELETE FROM entries WHERE id = ?", params![id])
+        .map_err(|e| format!("Failed to delete entry: {}", e))?;
+    if deleted == 0 {
+        return Err(format!("User entry {} not found", id));
+    }
+    delete_orphan_user_notes(&conn)
+        .and_then(|_| {
+            conn.execute(
+                "DELETE FROM dictionaries WHERE id NOT IN (SELECT DISTINCT dictionaryId FROM entries)",
+                [],
+            )
+        })
+        .map_err(|e| format!("Failed to clean up after delete: {}", e))?;
+
+    host.packs_changed();
+    Ok(())
+}
+
+fn set_note(host: &dyn DataHost, entry_id: &str, note: &str) -> Result<Option<EntryNote>, String> {
+    let (pack_id, id) = parse_compound_id(entry_id)?;
+    let (dictionary, term) = note_key(host, &pack_id, id)?;
+    let conn = open_user_pack(host)?;
+
+    let note = note.trim();
+    if note.is_empty() {
+        conn.execute(
+            "DELETE FROM notes WHERE packId = ? AND dictionary = ? AND term = ?",
+            params![pack_id, dictionary, term],
+        )
+        .map_err(|e| format!("Failed to delete note: {}", e))?;
+        return Ok(None);
+    }
+
+    let now = now_iso8601();
+    conn.execute(
+        "INSERT INTO notes (packId, dictionary, term, entryId, note, createdAt, updatedAt)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+         ON CONFLICT (packId, dictionary, term) DO UPDATE SET entryId = ?4, note = ?5, updatedAt = ?6",
+        params![pack_id, dictionary, term, id, note, now],
+    )
+    .map_err(|e| format!("Failed to save note: {}", e))?;
+
+    read_note(host, entry_id)
+}
+
+fn read_note(host: &dyn DataHost, entry_id: &str) -> Result<Option<EntryNote>, String> {
+    let (pack_id, id) = parse_compound_id(entry_id)?;
+    let Some(path) = get_user_pack_path(host) else {
+        return Ok(None);
+    };
+    let (dictionary, term) = note_key(host, &pack_id, id)?;
+    let conn = Connection::open(&path).map_err(|e| format!("Failed to open user pack: {}", e))?;
+    conn.query_row(
+        "SELECT note, createdAt, updatedAt FROM notes WHERE packId = ? AND dictionary = ? AND term = ?",
+        params![pack_id, dictionary, term],
+        |row| {
+            Ok(EntryNote {
+                entry_id: entry_id.to_string(),
+                dictionary: dictionary.clone(),
+                term: term.clone(),
+                note: row.get(0)?,
+                created_at: row.get(1)?,
+                updated_at: row.get(2)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read note: {}", e))
+}
+
+/// Add an entry to the user pack. Phonetics are computed by the frontend,
+/// like for create_custom_pack; `dictionary` defaults to "My Dictionary".
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn add_user_entry(app: AppHandle, entry: TibdictEntry) -> Result<PackEntry, String> {
+    add_entry(&app, &entry)
+}
+
+/// Replace a user entry's term, definition, phonetics and dictionary
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn update_user_entry(
+    app: AppHandle,
+    id: i64,
+    entry: TibdictEntry,
+) -> Result<PackEntry, String> {
+    update_entry(&app, id, &entry)
+}
+
+/// Delete a user entry, with its note unless another entry has the same
+/// term in the same dictionary
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn delete_user_entry(app: AppHandle, id: i64) -> Result<(), String> {
+    delete_entry(&app, id)
+}
+
+/// Attach a personal note to an entry of any pack ("pack_id:entry_id").
+/// The note shows on every entry with the same term in that pack's
+/// dictionary. An empty note removes it.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn set_entry_note(
+    app: AppHandle,
+    entry_id: String,
+    note: String,
+) -> Result<Option<EntryNote>, String> {
+    set_note(&app, &entry_id, &note)
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn get_entry_note(app: AppHandle, entry_id: String) -> Result<Option<EntryNote>, String> {
+    read_note(&app, &entry_id)
+}
+
+/// All notes, most recently edited first
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn list_entry_notes(app: AppHandle) -> Result<Vec<EntryNote>, String> {
+    if get_user_pack_path(&app).is_none() {
+        return Ok(Vec::new());
+    }
+    let conn = open_user_pack(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT packId, entryId, dictionary, term, note, createdAt, updatedAt
+             FROM notes ORDER BY updatedAt DESC",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let notes = stmt
+        .query_map([], |row| {
+            let pack_id: String = row.get(0)?;
+            let entry_id: i64 = row.get(1)?;
+            Ok(EntryNote {
+                entry_id: format!("{}:{}", pack_id, entry_id),
+                dictionary: row.get(2)?,
+                term: row.get(3)?,
+                note: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query notes: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_host::TestDataDir;
+    use crate::repository::{PackSet, Repository};
+    use crate::tibdict_builder::build_pack_sqlite;
+
+    fn entry(term: &str, definition: &str, dictionary: Option<&str>) -> TibdictEntry {
+        TibdictEntry {
+            term: term.to_string(),
+            definition: definition.to_string(),
+            dictionary: dictionary.map(String::from),
+            term_phonetics_strict: None,
+            term_phonetics_loose: None,
+            definition_phonetics_words_strict: None,
+            definition_phonetics_words_loose: None,
+        }
+    }
+
+    fn build_core(dir: &TestDataDir, entries: &[TibdictEntry]) {
+        let path = get_packs_dir(dir).unwrap().join("core.sqlite");
+        let _ = fs::remove_file(&path);
+        build_pack_sqlite(&path, entries, "Rangjung Yeshe").unwrap();
+    }
+
+    /// (pack, dictionary, definition, note) of the entries for `term`
+    fn lookup(dir: &TestDataDir, term: &str) -> Vec<(String, String, String, Option<String>)> {
+        PackSet::load(dir)
+            .unwrap()
+            .entries_for_term(term)
+            .unwrap()
+            .into_iter()
+            .map(|entry| {
+                (
+                    entry.source_pack_id.unwrap_or_default(),
+                    entry.dictionary.unwrap_or_default(),
+                    entry.definition,
+                    entry.user_note,
+                )
+            })
+            .collect()
+    }
+
+    fn lookup_entry_id(dir: &TestDataDir, term: &str, definition: &str) -> String {
+        let entry = PackSet::load(dir)
+            .unwrap()
+            .entries_for_term(term)
+            .unwrap()
+            .into_iter()
+            .find(|entry| entry.definition == definition)
+            .unwrap();
+        format!("{}:{}", entry.source_pack_id.unwrap(), entry.id)
+    }
+
+    #[test]
+    fn user_entries_are_merged_into_lookups() {
+        let dir = TestDataDir::new("user-entries");
+        build_core(&dir, &[entry("ཀ་", "the first letter", None)]);
+
+        let added = add_entry(&dir, &entry("ཀ", "my definition", None)).unwrap();
+        assert_eq!(added.term, "ཀ་");
+        assert_eq!(added.dictionary.as_deref(), Some(DEFAULT_DICTIONARY));
+        // User dictionaries come first
+        assert_eq!(
+            lookup(&dir, "ཀ་"),
+            [
+                ("user".to_string(), DEFAULT_DICTIONARY.to_string(), "my definition".to_string(), None),
+                ("core".to_string(), "Rangjung Yeshe".to_string(), "the first letter".to_string(), None)
+            ]
+        );
+        let found = PackSet::load(&dir).unwrap().search_entries("my definition", "regular").unwrap();
+        assert_eq!(found.len(), 1);
+
+        let updated = update_entry(&dir, added.id, &entry("ཁ་", "changed", Some("Mine"))).unwrap();
+        assert_eq!(updated.dictionary.as_deref(), Some("Mine"));
+        assert_eq!(lookup(&dir, "ཀ་").len(), 1);
+        assert_eq!(lookup(&dir, "ཁ་")[0].2, "changed");
+        assert!(PackSet::load(&dir).unwrap().search_entries("my definition", "regular").unwrap().is_empty());
+        assert!(update_entry(&dir, added.id, &entry("ཁ་", " ", None)).is_err());
+
+        delete_entry(&dir, added.id).unwrap();
+        assert!(lookup(&dir, "ཁ་").is_empty());
+        assert!(PackSet::load(&dir).unwrap().search_entries("changed", "regular").unwrap().is_empty());
+        assert!(delete_entry(&dir, added.id).is_err());
+    }
+
+    #[test]
+    fn notes_are_kept_by_pack_dictionary_and_term() {
+        let dir = TestDataDir::new("user-notes");
+        build_core(
+            &dir,
+            &[
+                entry("ཀ་", "first sense", None),
+                entry("ཀ་", "second sense", None),
+                entry("ཀ་", "other dictionary", Some("Hopkins")),
+            ],
+        );
+
+        let entry_id = lookup_entry_id(&dir, "ཀ་", "first sense");
+        let note = set_note(&dir, &entry_id, " remember this ").unwrap().unwrap();
+        assert_eq!((note.dictionary.as_str(), note.term.as_str()), ("Rangjung Yeshe", "ཀ་"));
+        assert_eq!(note.note, "remember this");
+        let notes: Vec<Option<String>> = lookup(&dir, "ཀ་").into_iter().map(|entry| entry.3).collect();
+        assert_eq!(notes, [Some("remember this".to_string()), Some("remember this".to_string()), None]);
+
+        // The pack is updated and its entry ids shift: the note stays on the word
+        build_core(
+            &dir,
+            &[
+                entry("ཁ་", "new entry", None),
+                entry("ཀ་", "first sense", None),
+                entry("ཀ་", "other dictionary", Some("Hopkins")),
+            ],
+        );
+        assert_eq!(lookup(&dir, "ཁ་")[0].3, None);
+        let moved_id = lookup_entry_id(&dir, "ཀ་", "first sense");
+        assert_ne!(moved_id, entry_id);
+        assert_eq!(read_note(&dir, &moved_id).unwrap().unwrap().note, "remember this");
+
+        set_note(&dir, &moved_id, "").unwrap();
+        assert!(read_note(&dir, &moved_id).unwrap().is_none());
+        assert!(lookup(&dir, "ཀ་").iter().all(|entry| entry.3.is_none()));
+        assert!(set_note(&dir, "core:999", "missing").is_err());
+        assert!(set_note(&dir, "core", "no id").is_err());
+    }
+
+    #[test]
+    fn notes_on_user_entries_follow_edits_and_deletes() {
+        let dir = TestDataDir::new("user-entry-notes");
+        build_core(&dir, &[entry("ཀ་", "the first letter", None)]);
+        let added = add_entry(&dir, &entry("ཀ་", "mine", None)).unwrap();
+        let user_id = format!("{}:{}", USER_PACK_ID, added.id);
+        set_note(&dir, &user_id, "my note").unwrap();
+
+        update_entry(&dir, added.id, &entry("ཁ་", "mine", Some("Mine"))).unwrap();
+        let note = read_note(&dir, &user_id).unwrap().unwrap();
+        assert_eq!((note.dictionary.as_str(), note.term.as_str()), ("Mine", "ཁ་"));
+        assert_eq!(lookup(&dir, "ཁ་")[0].3.as_deref(), Some("my note"));
+
+        delete_entry(&dir, added.id).unwrap();
+        let conn = open_user_pack(&dir).unwrap();
+        let count: i64 = conn.query_row("SELECT count(*) FROM notes", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,434 @@
+//! Verb paradigms, so any stem of a verb leads to its lemma.
+//!
+//! Headwords are mostly present stems: looking up སོང་ (past of འགྲོ་) or
+//! ཕྱིན་ finds nothing unless that form happens to have its own entry. The
+//! verb dictionaries give the full paradigm though:
+//! - Verbinator: "Present: {'gro} CD.\nPast: {song} CD, DS. {phyin} TC.\n...",
+//!   plus redirect entries such as "{kud} ND gives as the present of {bku}".
+//! - Hopkins-TibetanTenses: "zhug/ 'jug/ zhugs/ zhugs/", i.e. future, present,
+//!   past and imperative.
+
+use crate::collation::compare_tibetan;
+#[cfg(feature = "app")]
+use crate::pack_indexes::sync_index;
+use crate::pack_indexes::PackIndexer;
+#[cfg(feature = "app")]
+use crate::packs::ensure_pack_available;
+use crate::tibetan::{normalize_term, tibetan_runs};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::BTreeSet;
+#[cfg(feature = "app")]
+use tauri::AppHandle;
+
+const TENSES: [&str; 4] = ["present", "past", "future", "imperative"];
+
+/// Stem order of the Hopkins tense lists
+const HOPKINS_TENSE_ORDER: [&str; 4] = ["future", "present", "past", "imperative"];
+
+#[derive(Default)]
+struct ParsedVerb {
+    /// (lemma, tense, form)
+    forms: Vec<(String, &'static str, String)>,
+    transitivity: Option<&'static str>,
+    volition: Option<&'static str>,
+}
+
+fn tibetan_forms(text: &str) -> Vec<String> {
+    tibetan_runs(text)
+        .into_iter()
+        .map(|(start, end)| normalize_term(&text[start..end]))
+        .filter(|form| !form.is_empty())
+        .collect()
+}
+
+/// "X gives as the past of Y" / "X, Y give as a present of Z"
+fn parse_verbinator_redirect(term: &str, definition: &str) -> Option<(String, &'static str, String)> {
+    for article in ["as the ", "as a "] {
+        let Some(found) = definition.find(article) else {
+            continue;
+        };
+        let rest = &definition[found + article.len()..];
+        let Some(tense) = TENSES.iter().find(|tense| rest.starts_with(**tense)) else {
+            continue;
+        };
+        let Some(of) = rest.find(" of ") else {
+            continue;
+        };
+        let Some(lemma) = tibetan_forms(&rest[of..]).into_iter().next() else {
+            continue;
+        };
+        return Some((lemma, *tense, term.to_string()));
+    }
+    None
+}
+
+fn parse_verbinator(term: &str, definition: &str) -> ParsedVerb {
+    let mut verb = ParsedVerb::default();
+    let definition = definition.replace("\\n", "\n");
+
+    for line in definition.lines() {
+        let Some((label, value)) = line.split_once(':') else {
+            continue;
+        };
+        let label = label.trim().to_lowercase();
+        if let Some(tense) = TENSES.iter().find(|tense| **tense == label) {
+            let forms = tibetan_forms(value).into_iter();
+            verb.forms.extend(forms.map(|form| (term.to_string(), *tense, form)));
+            continue;
+        }
+        match label.as_str() {
+            "transitive" => verb.transitivity = verb.transitivity.or(Some("transitive")),
+            "intransitive" => verb.transitivity = verb.transitivity.or(Some("intransitive")),
+            "voluntary" => verb.volition = verb.volition.or(Some("voluntary")),
+            "involuntary" => verb.volition = verb.volition.or(Some("involuntary")),
+            _ => {}
+        }
+    }
+
+    if verb.forms.is_empty() {
+        verb.forms.extend(parse_verbinator_redirect(term, &definition));
+    }
+    verb
+}
+
+fn parse_hopkins_tenses(term: &str, definition: &str) -> ParsedVerb {
+    let stems = tibetan_forms(definition);
+    let mut verb = ParsedVerb::default();
+
+    // Phrases built on the verb ("zhugs pa'i gnas") repeat the same list: only
+    // index it from the entries of the stems themselves
+    if stems.len() > HOPKINS_TENSE_ORDER.len() || !stems.iter().any(|stem| stem == term) {
+        return verb;
+    }
+    let lemma = match stems.get(1) {
+        Some(present) => present.clone(),
+        None => return verb,
+    };
+    for (tense, stem) in HOPKINS_TENSE_ORDER.iter().zip(stems) {
+        verb.forms.push((lemma.clone(), *tense, stem));
+    }
+    verb
+}
+
+fn parse_verb(dictionary_name: &str, term: &str, definition: &str) -> ParsedVerb {
+    if dictionary_name.contains("Verbinator") {
+        parse_verbinator(term, definition)
+    } else if dictionary_name.contains("Tenses") {
+        parse_hopkins_tenses(term, definition)
+    } else {
+        ParsedVerb::default()
+    }
+}
+
+pub(crate) struct VerbIndex;
+
+impl PackIndexer for VerbIndex {
+    const NAME: &'static str = "verbs";
+    const VERSION: u32 = 1;
+
+    fn create_tables(index: &Connection) -> rusqlite::Result<()> {
+        index.execute_batch(
+            "CREATE TABLE IF NOT EXISTS verb_forms (
+                pack_id        TEXT NOT NULL,
+                dictionary_id  INTEGER NOT NULL,
+                lemma          TEXT NOT NULL,
+                tense          TEXT NOT NULL,
+                form           TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS verb_lemmas (
+                pack_id        TEXT NOT NULL,
+                dictionary_id  INTEGER NOT NULL,
+                lemma          TEXT NOT NULL,
+                transitivity   TEXT,
+                volition       TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_verb_forms_form ON verb_forms(form);
+            CREATE INDEX IF NOT EXISTS idx_verb_forms_lemma ON verb_forms(lemma);
+            CREATE INDEX IF NOT EXISTS idx_verb_forms_pack ON verb_forms(pack_id);
+            CREATE INDEX IF NOT EXISTS idx_verb_lemmas_lemma ON verb_lemmas(lemma);
+            CREATE INDEX IF NOT EXISTS idx_verb_lemmas_pack ON verb_lemmas(pack_id);",
+        )
+    }
+
+    fn clear_pack(index: &Connection, pack_id: &str) -> rusqlite::Result<()> {
+        index.execute("DELETE FROM verb_forms WHERE pack_id = ?", params![pack_id])?;
+        index.execute("DELETE FROM verb_lemmas WHERE pack_id = ?", params![pack_id])?;
+        Ok(())
+    }
+
+    fn index_pack(index: &Connection, pack_id: &str, pack: &Connection) -> rusqlite::Result<usize> {
+        let mut select = pack.prepare(
+            "SELECT entries.term, entries.definition, entries.dictionaryId, dictionaries.name
+             FROM entries
+             INNER JOIN dictionaries ON dictionaries.id = entries.dictionaryId
+             WHERE dictionaries.name LIKE '%Verbinator%' OR dictionaries.name LIKE '%Tenses%'",
+        )?;
+        let mut insert_form = index.prepare(
+            "INSERT INTO verb_forms (pack_id, dictionary_id, lemma, tense, form) VALUES (?, ?, ?, ?, ?)",
+        )?;
+        let mut insert_lemma = index.prepare(
+            "INSERT INTO verb_lemmas (pack_id, dictionary_id, lemma, transitivity, volition)
+             VALUES (?, ?, ?, ?, ?)",
+        )?;
+
+        let mut count = 0;
+        let mut rows = select.query([])?;
+        while let Some(row) = rows.next()? {
+            let term: String = row.get(0)?;
+            let definition: String = row.get(1)?;
+            let dictionary_id: i64 = row.get(2)?;
+            let dictionary_name: String = row.get(3)?;
+
+            let verb = parse_verb(&dictionary_name, &normalize_term(&term), &definition);
+            let lemmas: BTreeSet<&String> = verb.forms.iter().map(|(lemma, _, _)| lemma).collect();
+            for lemma in lemmas {
+                insert_lemma.execute(params![pack_id, dictionary_id, lemma, verb.transitivity, verb.volition])?;
+            }
+            for (lemma, tense, form) in &verb.forms {
+                insert_form.execute(params![pack_id, dictionary_id, lemma, tense, form])?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VerbParadigm {
+    pub lemma: String,
+    /// Tenses of the looked-up form in this paradigm (empty when only the lemma matched)
+    pub matched_tenses: Vec<String>,
+    pub present: Vec<String>,
+    pub past: Vec<String>,
+    pub future: Vec<String>,
+    pub imperative: Vec<String>,
+    /// "transitive" or "intransitive", when a dictionary says so
+    pub transitivity: Option<String>,
+    /// "voluntary" or "involuntary", when a dictionary says so
+    pub volition: Option<String>,
+    /// Compound IDs ("pack_id:dictionary_id") of the dictionaries giving this paradigm
+    pub dictionary_ids: Vec<String>,
+}
+
+fn push_unique(forms: &mut Vec<String>, form: String) {
+    if !forms.contains(&form) {
+        forms.push(form);
+    }
+}
+
+fn load_paradigm(index: &Connection, lemma: &str, form: &str) -> Result<VerbParadigm, String> {
+    let mut paradigm = VerbParadigm {
+        lemma: lemma.to_string(),
+        ..Default::default()
+    };
+    let mut dictionary_ids = BTreeSet::new();
+
+    let mut stmt = index
+        .prepare("SELECT tense, form, pack_id, dictionary_id FROM verb_forms WHERE lemma = ? ORDER BY rowid")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![lemma], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to query verb forms: {}", e))?;
+
+    for (tense, stem, pack_id, dictionary_id) in rows.filter_map(|r| r.ok()) {
+        dictionary_ids.insert(format!("{}:{}", pack_id, dictionary_id));
+        if stem == form && !paradigm.matched_tenses.contains(&tense) {
+            paradigm.matched_tenses.push(tense.clone());
+        }
+        match tense.as_str() {
+            "present" => push_unique(&mut paradigm.present, stem),
+            "past" => push_unique(&mut paradigm.past, stem),
+            "future" => push_unique(&mut paradigm.future, stem),
+            "imperative" => push_unique(&mut paradigm.imperative, stem),
+            _ => {}
+        }
+    }
+
+    let mut stmt = index
+        .prepare(
+            "SELECT transitivity, volition FROM verb_lemmas
+             WHERE lemma = ? AND (transitivity IS NOT NULL OR volition IS NOT NULL)
+             ORDER BY rowid",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![lemma], |row| {
+            Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<String>>(1)?))
+        })
+        .map_err(|e| format!("Failed to query verb lemmas: {}", e))?;
+    for (transitivity, volition) in rows.filter_map(|r| r.ok()) {
+        paradigm.transitivity = paradigm.transitivity.or(transitivity);
+        paradigm.volition = paradigm.volition.or(volition);
+    }
+
+    paradigm.dictionary_ids = dictionary_ids.into_iter().collect();
+    Ok(paradigm)
+}
+
+fn lookup(index: &Connection, form: &str) -> Result<Vec<VerbParadigm>, String> {
+    let mut stmt = index
+        .prepare("SELECT DISTINCT lemma FROM verb_forms WHERE form = ?1 OR lemma = ?1")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let lemmas: Vec<String> = stmt
+        .query_map(params![form], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to query verb forms: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut paradigms = lemmas
+        .iter()
+        .map(|lemma| load_paradigm(index, lemma, form))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // The verb whose lemma is the looked-up form first, then the ones with most sources
+    paradigms.sort_by(|a, b| {
+        (b.lemma == form)
+            .cmp(&(a.lemma == form))
+            .then_with(|| b.dictionary_ids.len().cmp(&a.dictionary_ids.len()))
+            .then_with(|| compare_tibetan(&a.lemma, &b.lemma))
+    });
+    Ok(paradigms)
+}
+
+/// Resolve a verb stem (present, past, future or imperative) to its lemma
+/// and return the full conjugation table of every matching verb.
+#[cfg(feature = "app")]
+#[tauri::command]
+pub async fn lookup_verb(app: AppHandle, term: String) -> Result<Vec<VerbParadigm>, String> {
+    // First ensure core pack is available in app data
+    ensure_pack_available(app.clone(), "core".to_string()).await?;
+
+    let form = normalize_term(&term);
+    if form.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Indexing newly installed packs can take a while: keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let index = sync_index::<VerbIndex>(&app)?;
+        lookup(&index, &form)
+    })
+    .await
+    .map_err(|e| format!("Verb lookup task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_host::TestDataDir;
+    use crate::tibdict_builder::{build_pack_sqlite, TibdictEntry};
+
+    const GRO: &str = "Present: {འགྲོ་} CD.\\nPast: {སོང་} CD, DS. {ཕྱིན་} TC.\\nFuture: {འགྲོ་} CD.\\n\
+                       Imperative: {སོང་} CD.\\nIntransitive: CD\\nInvoluntary: DS";
+
+    /// (lemma, tense, form)
+    type Stem<'a> = (&'a str, &'a str, &'a str);
+
+    fn forms(verb: &ParsedVerb) -> Vec<Stem<'_>> {
+        verb.forms
+            .iter()
+            .map(|(lemma, tense, form)| (lemma.as_str(), *tense, form.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_verbinator_paradigms() {
+        let verb = parse_verb("Verbinator", "འགྲོ་", GRO);
+        assert_eq!(
+            forms(&verb),
+            [
+                ("འགྲོ་", "present", "འགྲོ་"),
+                ("འགྲོ་", "past", "སོང་"),
+                ("འགྲོ་", "past", "ཕྱིན་"),
+                ("འགྲོ་", "future", "འགྲོ་"),
+                ("འགྲོ་", "imperative", "སོང་"),
+            ]
+        );
+        assert_eq!((verb.transitivity, verb.volition), (Some("intransitive"), Some("involuntary")));
+    }
+
+    #[test]
+    fn parses_verbinator_redirects() {
+        let cases: &[(&str, &[Stem])] = &[
+            ("{ཀུད་} ND gives as the present of {བཀུ་}", &[("བཀུ་", "present", "ཀུད་")]),
+            ("{ཀུད་}, {བཀུད་} give as a past of {བཀུ་}.", &[("བཀུ་", "past", "ཀུད་")]),
+            ("{ཀུད་} gives as the meaning of {བཀུ་}", &[]),
+            ("{ཀུད་} gives as the past", &[]),
+        ];
+        for (definition, expected) in cases {
+            assert_eq!(forms(&parse_verb("Verbinator", "ཀུད་", definition)), *expected, "{definition}");
+        }
+    }
+
+    #[test]
+    fn parses_hopkins_tense_lists() {
+        let tenses = "གཞུག/ འཇུག/ བཅུག/ ཆུག/";
+        assert_eq!(
+            forms(&parse_verb("Hopkins-TibetanTenses", "བཅུག་", tenses)),
+            [
+                ("འཇུག་", "future", "གཞུག་"),
+                ("འཇུག་", "present", "འཇུག་"),
+                ("འཇུག་", "past", "བཅུག་"),
+                ("འཇུག་", "imperative", "ཆུག་"),
+            ]
+        );
+        // Only the stems' own entries count, not phrases repeating their list
+        assert!(parse_verb("Hopkins-TibetanTenses", "ཞུགས་པའི་གནས་", tenses).forms.is_empty());
+        assert!(parse_verb("Hopkins-TibetanTenses", "ཀ་", "ཀ/ ཁ/ ག/ ང/ ཅ/").forms.is_empty());
+        assert!(parse_verb("Hopkins-TibetanTenses", "ཀ་", "ཀ/").forms.is_empty());
+        assert!(parse_verb("Hopkins", "བཅུག་", tenses).forms.is_empty());
+    }
+
+    #[test]
+    fn any_stem_leads_to_its_lemma() {
+        let dir = TestDataDir::new("verb-index");
+        let path = dir.root.join("data.sqlite");
+        let entry = |term: &str, definition: &str, dictionary: &str| TibdictEntry {
+            term: term.to_string(),
+            definition: definition.to_string(),
+            dictionary: Some(dictionary.to_string()),
+            term_phonetics_strict: None,
+            term_phonetics_loose: None,
+            definition_phonetics_words_strict: None,
+            definition_phonetics_words_loose: None,
+        };
+        build_pack_sqlite(
+            &path,
+            &[
+                entry("འགྲོ", GRO, "Verbinator"),
+                entry("ཕྱིན་", "Present: {ཕྱིན་}\\nTransitive: X", "Verbinator"),
+                // Not a verb dictionary: not indexed
+                entry("སོང་", "Present: {ཀ་}", "Hopkins"),
+            ],
+            "Verbinator",
+        )
+        .unwrap();
+        let index = Connection::open_in_memory().unwrap();
+        VerbIndex::create_tables(&index).unwrap();
+        VerbIndex::index_pack(&index, "core", &Connection::open(&path).unwrap()).unwrap();
+
+        let paradigms = lookup(&index, "སོང་").unwrap();
+        assert_eq!(paradigms.len(), 1);
+        let gro = &paradigms[0];
+        assert_eq!(gro.lemma, "འགྲོ་");
+        assert_eq!(gro.matched_tenses, ["past", "imperative"]);
+        assert_eq!(gro.past, ["སོང་", "ཕྱིན་"]);
+        assert_eq!(gro.future, ["འགྲོ་"]);
+        assert_eq!(gro.transitivity.as_deref(), Some("intransitive"));
+        assert_eq!(gro.dictionary_ids, ["core:1"]);
+
+        // The verb whose lemma was looked up comes first
+        let lemmas: Vec<String> = lookup(&index, "ཕྱིན་").unwrap().into_iter().map(|p| p.lemma).collect();
+        assert_eq!(lemmas, ["ཕྱིན་", "འགྲོ་"]);
+        assert!(lookup(&index, "ཀ་").unwrap().is_empty());
+    }
+}
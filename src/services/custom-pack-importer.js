@@ -2,24 +2,46 @@
  * CustomPackImporter — orchestrates .tibdict installation from the frontend.
  *
  * Returns a normalized result object:
- *   { status: 'installed', pack }
+ *   { status: 'installed', pack }  -- pack.provenance.status: unsigned | verified | untrusted | migrated
+ *     -- migrated: signed for an older schema, upgraded on install (provenance.migratedFrom)
  *   { status: 'conflict', message }  -- caller should ask user to confirm, then retry with force=true
- *   { status: 'error', errorKind, message }
+ *   { status: 'needsFields', message } -- .apkg: caller should pick fields (see inspectApkg), then retry with options.apkg
+ *   { status: 'error', errorKind, message, provenance, schemaReport }
+ *     -- schemaReport lists every SQLite/manifest problem ({ code, message }) when validation failed
  *
  * errorKind is one of:
  *   - 'incompatible'   : schema or format version mismatch
  *   - 'corrupt'        : ZIP or SQLite unreadable
  *   - 'notADictionary' : not a .tibdict / missing files / bad id
+ *   - 'tampered'       : signed, but the signature doesn't match the content
+ *   - 'untrusted'      : strict mode is on and no trusted key signed the pack
+ *   - 'tooLarge'       : over the install limits (size, file count, compression ratio)
  *   - 'unknown'        : anything else
+ *
+ * options.limits may override { maxSqliteBytes, maxMetadataBytes, maxEntries,
+ * maxCompressionRatio }. Progress is reported through the
+ * 'custom-pack-install-progress' event ({ packId, extracted, total, percentage, status }).
  */
 
 import { invoke } from '@tauri-apps/api/core';
+import { withPhonetics } from './pack-phonetics';
+
+/** Command arguments without the options left unset, which the backend
+ *  takes as None */
+function withoutUnset(args) {
+  return Object.fromEntries(
+    Object.entries(args).filter(([, value]) => value !== null && value !== undefined)
+  );
+}
 
 function classifyError(err) {
   const code = err?.code || '';
   if (code === 'schema') return 'incompatible';
   if (code === 'corrupt') return 'corrupt';
   if (code === 'format') return 'notADictionary';
+  if (code === 'tampered') return 'tampered';
+  if (code === 'untrusted') return 'untrusted';
+  if (code === 'limits') return 'tooLarge';
   return 'unknown';
 }
 
@@ -29,6 +51,7 @@ function classifyResult(promise) {
     .catch((err) => {
       const code = err?.code || '';
       const message = err?.message || String(err);
+      if (code === 'fields') return { status: 'needsFields', message };
       if (code === 'conflict') {
         return {
           status: 'conflict',
@@ -37,20 +60,98 @@ function classifyResult(promise) {
           existingManifest: err?.existingManifest || null,
         };
       }
-      return { status: 'error', errorKind: classifyError(err), message };
+      return {
+        status: 'error',
+        errorKind: classifyError(err),
+        message,
+        provenance: err?.provenance || null,
+        schemaReport: err?.schemaReport || null,
+      };
     });
 }
 
 export const CustomPackImporter = {
   async install(filePath, options = {}) {
     const force = !!options.force;
-    return classifyResult(invoke('install_custom_pack', { filePath, force }));
+    return classifyResult(
+      invoke('install_custom_pack', withoutUnset({ filePath, force, apkg: options.apkg, limits: options.limits }))
+    );
   },
   /** Install from in-memory bytes (used by HTML5 drag-drop, where only the file
    *  content is available — the webview's File API doesn't expose an OS path). */
   async installFromBytes(data, options = {}) {
     const force = !!options.force;
-    return classifyResult(invoke('install_custom_pack_from_bytes', { data, force }));
+    return classifyResult(
+      invoke(
+        'install_custom_pack_from_bytes',
+        withoutUnset({ data, force, apkg: options.apkg, limits: options.limits })
+      )
+    );
+  },
+  /** List an Anki deck's note types and fields, so the user can choose which
+   *  field is the term and which the definition ({ termField, definitionField }). */
+  async inspectApkg(filePath, options = {}) {
+    return invoke('inspect_apkg', withoutUnset({ filePath, limits: options.limits }));
+  },
+  async inspectApkgFromBytes(data, options = {}) {
+    return invoke('inspect_apkg_from_bytes', withoutUnset({ data, limits: options.limits }));
+  },
+  /** Import a StarDict (.ifo or zipped), XDXF, MDict (.mdx) or TEI dictionary
+   *  as a custom pack. options.metadata may set { id, name, description }. */
+  async importDictionary(filePath, options = {}) {
+    const force = !!options.force;
+    return classifyResult(
+      invoke(
+        'import_dictionary',
+        withoutUnset({ filePath, force, options: options.metadata, limits: options.limits })
+      )
+    );
+  },
+  async importDictionaryFromBytes(data, fileName, options = {}) {
+    const force = !!options.force;
+    return classifyResult(
+      invoke(
+        'import_dictionary_from_bytes',
+        withoutUnset({ data, fileName, force, options: options.metadata, limits: options.limits })
+      )
+    );
+  },
+  /** Build a .tibdict at outputPath from a CSV, TSV, JSON or JSON Lines
+   *  glossary (source.filePath, or source.content with source.format), for
+   *  sharing or installing. Rows without phonetics get them computed here.
+   *  metadata is { id, name, description, author, version }; resolves to the
+   *  pack's manifest, or rejects with an { code, message } install error. */
+  async createPack(source, metadata, outputPath) {
+    const entries = await invoke(
+      'parse_glossary',
+      withoutUnset({ sourcePath: source.filePath, content: source.content, format: source.format })
+    );
+    return invoke('create_custom_pack', {
+      content: JSON.stringify(withPhonetics(entries)),
+      format: 'json',
+      metadata,
+      outputPath,
+    });
+  },
+  /** Packs with an update feed (their manifest's updateUrl, or one set with
+   *  setFeed), with the latest version it announces: [{ packId, name,
+   *  installedVersion, latestVersion, updateAvailable, notes, error }]. */
+  async checkUpdates() {
+    return invoke('check_custom_pack_updates');
+  },
+  /** Install the newer version of a pack; the previous one is kept and can
+   *  be put back with restoreBackup(packId). */
+  async update(packId) {
+    return classifyResult(invoke('update_custom_pack', { packId }));
+  },
+  async restoreBackup(packId) {
+    return invoke('restore_custom_pack_backup', { packId });
+  },
+  /** Check packId for updates at url (http(s), a local path or file://)
+   *  instead of its manifest's updateUrl; null goes back to the manifest's.
+   *  Only feeds set here may be local files. */
+  async setFeed(packId, url) {
+    return invoke('set_custom_pack_feed', { packId, url });
   },
 };
 
@@ -0,0 +1,35 @@
+/**
+ * DeepLinks — tibetan-translator:// links opened from other apps
+ * (src-tauri/src/deep_links.rs). Rust keeps the latest link for the window
+ * it picked and sends that window a "deep-link" event; the window then
+ * takes the link. Taking once more on startup catches the link that
+ * launched the app before this page was listening.
+ */
+
+import { isTauri } from '../config/platform';
+
+export const DeepLinks = {
+  /**
+   * Call `onLink` with each link meant for this window. Resolves to an
+   * unlisten function (or null outside Tauri).
+   */
+  async listen(onLink) {
+    if (!isTauri()) return null;
+    const { invoke } = await import('@tauri-apps/api/core');
+    const { listen } = await import('@tauri-apps/api/event');
+
+    const take = async () => {
+      try {
+        const link = await invoke('take_deep_link');
+        if (link) onLink(link);
+      } catch (e) {
+        console.error('[DeepLinks] Failed to take link:', e);
+      }
+    };
+    const unlisten = await listen('deep-link', take);
+    await take();
+    return unlisten;
+  },
+};
+
+export default DeepLinks;
@@ -0,0 +1,36 @@
+/**
+ * DictionarySettings — mirrors the dictionary order and enabled flags chosen
+ * on the settings page into the Rust settings store
+ * (src-tauri/src/dictionary_settings.rs), which every pack_* command applies.
+ * That way the lookup popup and the native mobile path follow the same
+ * choices as the main window. Only pack dictionaries (compound "pack:id"
+ * ids) are stored there.
+ */
+
+import { isTauri } from '../config/platform';
+
+export const DictionarySettings = {
+  async save(dictionaries) {
+    if (!isTauri()) return;
+    const packDictionaries = dictionaries.filter(
+      (dictionary) => typeof dictionary.id === 'string' && dictionary.id.includes(':')
+    );
+    if (!packDictionaries.length) return;
+
+    const { invoke } = await import('@tauri-apps/api/core');
+    await invoke('save_dictionary_settings', {
+      dictionaries: packDictionaries.map((dictionary) => ({
+        dictionaryId: dictionary.id,
+        enabled: dictionary.enabled !== false,
+        position: dictionary.position,
+      })),
+    });
+  },
+  async setPackEnabled(packId, enabled) {
+    if (!isTauri()) return;
+    const { invoke } = await import('@tauri-apps/api/core');
+    await invoke('set_pack_enabled', { packId, enabled });
+  },
+};
+
+export default DictionarySettings;
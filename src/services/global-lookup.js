@@ -1,7 +1,9 @@
 /**
  * Global Lookup Service
  *
- * Handles system-wide hotkey for looking up Tibetan text from clipboard.
+ * Handles the system-wide hotkey for looking up Tibetan text selected in
+ * any app. The Rust side (global_lookup.rs) owns the hotkey: it copies the
+ * selection, restores the clipboard and shows the popup with the lookup.
  * Only available on desktop Tauri apps (macOS, Windows, Linux).
  */
 
@@ -11,14 +13,9 @@ import Storage from './storage';
 // Default hotkey: Cmd+Shift+C on Mac, Ctrl+Shift+C on Windows/Linux
 const DEFAULT_HOTKEY = 'CommandOrControl+Shift+C';
 
-// Popup window configuration
-const POPUP_WIDTH = 650;
-const POPUP_HEIGHT = 500;
-
 let isInitialized = false;
 let currentShortcut = null;
-let onLookupCallback = null;
-let popupWindow = null;
+let unlistenTrayToggle = null;
 
 /**
  * Check if global lookup is supported on this platform
@@ -151,80 +148,6 @@ export function keyEventToHotkeyString(event) {
   return parts.join('+');
 }
 
-/**
- * Show the popup window with clipboard text
- */
-async function showPopupWindow(text) {
-  try {
-    const { WebviewWindow } = await import('@tauri-apps/api/webviewWindow');
-    const { currentMonitor } = await import('@tauri-apps/api/window');
-
-    // Get the pre-defined popup window by its label
-    let existingWindow = await WebviewWindow.getByLabel('global-lookup-popup');
-    console.log('[GlobalLookup] Existing popup window:', existingWindow);
-
-    // Get current monitor to center the popup
-    const monitor = await currentMonitor();
-    let x = 100;
-    let y = 100;
-
-    if (monitor) {
-      const scaleFactor = monitor.scaleFactor || 1;
-      const screenWidth = monitor.size.width / scaleFactor;
-      const screenHeight = monitor.size.height / scaleFactor;
-      x = Math.round((screenWidth - POPUP_WIDTH) / 2);
-      y = Math.round((screenHeight - POPUP_HEIGHT) / 3); // Position in upper third
-    }
-
-    if (existingWindow) {
-      console.log('[GlobalLookup] Using existing popup window');
-      // Window exists, position it and use the panel-aware show command
-      await existingWindow.setPosition({ type: 'Physical', x: Math.round(x * (monitor?.scaleFactor || 1)), y: Math.round(y * (monitor?.scaleFactor || 1)) });
-      // Use our custom show command that properly handles NSPanel key window status
-      const { invoke } = await import('@tauri-apps/api/core');
-      await invoke('show_lookup_panel');
-      popupWindow = existingWindow;
-    } else {
-      console.log('[GlobalLookup] Creating new popup window');
-      // Create a new popup window
-      popupWindow = new WebviewWindow('global-lookup-popup', {
-        url: 'popup.html',
-        title: 'Tibetan Lookup',
-        width: POPUP_WIDTH,
-        height: POPUP_HEIGHT,
-        x: x,
-        y: y,
-        resizable: false,
-        minimizable: false,
-        maximizable: false,
-        alwaysOnTop: true,
-        focus: true,
-        decorations: false,
-        transparent: false,
-        skipTaskbar: true,
-        center: false,
-        shadow: true,
-      });
-
-      popupWindow.once('tauri://error', (e) => {
-        console.error('Error creating popup window:', e);
-        popupWindow = null;
-      });
-
-      popupWindow.once('tauri://destroyed', () => {
-        popupWindow = null;
-      });
-    }
-
-  } catch (err) {
-    console.error('Failed to show popup window:', err);
-    // Fallback to callback if available
-    if (onLookupCallback) {
-      onLookupCallback(text);
-    }
-  }
-}
-
 /**
  * Format a hotkey string for display
  * Converts "CommandOrControl+Shift+D" to "⌘⇧D" on Mac or "Ctrl+Shift+D" on others
@@ -257,9 +180,8 @@ export function formatHotkeyForDisplay(hotkey) {
 
 /**
  * Initialize global lookup service
- * @param {Function} onLookup - Callback when hotkey is triggered, receives clipboard text
  */
-export async function initialize(onLookup) {
+export async function initialize() {
   if (!isSupported()) {
     console.log('Global lookup not supported on this platform');
     return { success: false, error: 'not_supported' };
@@ -270,7 +192,13 @@ export async function initialize(onLookup) {
     return { success: true };
   }
 
-  onLookupCallback = onLookup;
+  // The tray's Global Lookup item (src-tauri/src/tray.rs) switches it here,
+  // so that the setting and the permission check stay in one place
+  const { listen } = await import('@tauri-apps/api/event');
+  unlistenTrayToggle = await listen('tray-toggle-global-lookup', async ({ payload }) => {
+    const result = await toggle(payload);
+    if (result.needsPermission) await requestAccessibilityPermission();
+  });
 
   if (isEnabled()) {
     const result = await registerShortcut(getHotkey());
@@ -310,39 +238,8 @@ export async function registerShortcut(hotkey) {
       }
     }
 
-    const { register, isRegistered } = await import('@tauri-apps/plugin-global-shortcut');
-    const { readText } = await import('@tauri-apps/plugin-clipboard-manager');
-
-    // Check if already registered by another app
-    const alreadyRegistered = await isRegistered(hotkey);
-    if (alreadyRegistered) {
-      console.log('Hotkey already registered:', hotkey);
-      // It might be registered by us from a previous session, try to unregister and re-register
-      try {
-        const { unregister } = await import('@tauri-apps/plugin-global-shortcut');
-        await unregister(hotkey);
-      } catch (e) {
-        // Ignore unregister errors
-      }
-    }
-
-    await register(hotkey, async (event) => {
-      console.log('Global lookup hotkey event:', event);
-      // Only trigger on key press, not release
-      if (event.state !== 'Pressed') return;
-
-      console.log('Global lookup hotkey triggered');
-      try {
-        const text = await readText();
-        console.log('Clipboard text:', text);
-        if (text) {
-          // Show popup window with the clipboard text
-          await showPopupWindow(text);
-        }
-      } catch (err) {
-        console.error('Error reading clipboard:', err);
-      }
-    });
+    const { invoke } = await import('@tauri-apps/api/core');
+    await invoke('set_global_lookup_hotkey', { hotkey });
 
     currentShortcut = hotkey;
     console.log('Global shortcut registered:', hotkey);
@@ -386,8 +283,8 @@ export async function unregisterShortcut() {
   if (!currentShortcut) return;
 
   try {
-    const { unregister } = await import('@tauri-apps/plugin-global-shortcut');
-    await unregister(currentShortcut);
+    const { invoke } = await import('@tauri-apps/api/core');
+    await invoke('set_global_lookup_hotkey', { hotkey: null });
     currentShortcut = null;
     console.log('Global shortcut unregistered');
   } catch (err) {
@@ -427,12 +324,11 @@ export async function toggle(enabled) {
  * Close the popup window if open
  */
 export async function closePopup() {
-  if (popupWindow) {
-    try {
-      await popupWindow.hide();
-    } catch (e) {
-      // Window might already be closed or hidden
-    }
+  try {
+    const { invoke } = await import('@tauri-apps/api/core');
+    await invoke('hide_lookup_panel');
+  } catch (e) {
+    // Window might already be closed or hidden
   }
 }
 
@@ -442,6 +338,10 @@ export async function closePopup() {
 export async function cleanup() {
   await closePopup();
   await unregisterShortcut();
+  if (unlistenTrayToggle) {
+    unlistenTrayToggle();
+    unlistenTrayToggle = null;
+  }
   isInitialized = false;
 }
 
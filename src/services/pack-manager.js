@@ -152,7 +152,10 @@ export const PackManager = {
       await listen('pack-download-progress', (event) => {
         const progress = event.payload;
 
-        if (progress.status === 'complete') {
+        if (progress.status === 'failed') {
+          // The download_pack call rejects too; whichever comes first clears it
+          delete state.downloadingPacks[progress.packId];
+        } else if (progress.status === 'complete') {
           // Remove from downloading, add to installed
           delete state.downloadingPacks[progress.packId];
           if (!state.installedPacks.includes(progress.packId)) {
@@ -173,13 +176,21 @@ export const PackManager = {
       await listen('pack-update-progress', (event) => {
         const progress = event.payload;
 
-        if (progress.status === 'complete') {
+        if (progress.status === 'complete' || progress.status === 'failed') {
           delete state.updatingPacks[progress.packId];
         } else {
           state.updatingPacks[progress.packId] = progress;
         }
       });
 
+      // Custom packs upgraded from an older schema on startup have empty
+      // phonetic columns
+      await listen('custom-packs-migrated', (event) => {
+        for (const packId of event.payload || []) {
+          PackManager.fillMissingPhonetics(packId);
+        }
+      });
+
       // Fetch manifest, installed packs, and custom packs in parallel
       const [manifest, installed, customPacks] = await Promise.all([
         invoke('fetch_pack_manifest').catch((e) => {
@@ -197,6 +208,9 @@ export const PackManager = {
       state.installedPacks = installed;
       state.customPacks = customPacks || [];
       state.initialized = true;
+
+      // Packs imported or migrated without phonetics (see pack-phonetics.js)
+      PackManager.fillMissingPhonetics();
     } catch (error) {
       console.error('Pack manager init failed:', error);
       state.error = error.message || String(error);
@@ -588,6 +602,7 @@ export const PackManager = {
     if (result.status === 'installed') {
       state.customPacks = await invoke('list_custom_packs');
       await refreshDictionariesAndTerms();
+      PackManager.fillMissingPhonetics(result.pack.id);
     }
     return result;
   },
@@ -602,6 +617,7 @@ export const PackManager = {
     if (result.status === 'installed') {
       state.customPacks = await invoke('list_custom_packs');
       await refreshDictionariesAndTerms();
+      PackManager.fillMissingPhonetics(result.pack.id);
     }
     return result;
   },
@@ -616,6 +632,24 @@ export const PackManager = {
     await refreshDictionariesAndTerms();
   },
 
+  /**
+   * Compute the phonetics missing from a custom pack, or from every custom
+   * pack when no id is given, so they show up in phonetic searches.
+   * Runs in the background: failures are only logged.
+   */
+  async fillMissingPhonetics(packId = null) {
+    const { fillMissingPhonetics } = await import('./pack-phonetics');
+    const packIds = packId ? [packId] : state.customPacks.map((p) => p.id);
+    for (const id of packIds) {
+      try {
+        const updated = await fillMissingPhonetics(id);
+        if (updated > 0) console.log(`[PackManager] Computed phonetics for ${updated} entries of ${id}`);
+      } catch (e) {
+        console.warn(`[PackManager] Failed to compute phonetics for ${id}:`, e);
+      }
+    }
+  },
+
   /**
    * Refresh the custom pack list (useful after external changes).
    */
@@ -0,0 +1,59 @@
+/**
+ * Phonetics for dictionary entries, computed the same way as the official
+ * packs' build scripts.
+ *
+ * Packs converted or imported by the backend (glossaries, Anki decks,
+ * StarDict, MDict...) come without phonetics, since those are only computed
+ * here. fillMissingPhonetics pages through an installed custom pack's
+ * entries that lack them (see src-tauri/src/pack_phonetics.rs) and saves
+ * them back, so the pack can be found by phonetic search.
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import { strictAndLoosePhoneticsFor } from '../utils';
+
+const PAGE_SIZE = 500;
+
+export function phoneticsFor(entry) {
+  const [termPhoneticsStrict, termPhoneticsLoose] = strictAndLoosePhoneticsFor(entry.term || '');
+  const [definitionPhoneticsWordsStrict, definitionPhoneticsWordsLoose] =
+    strictAndLoosePhoneticsFor(entry.definition || '');
+  return {
+    termPhoneticsStrict,
+    termPhoneticsLoose,
+    definitionPhoneticsWordsStrict,
+    definitionPhoneticsWordsLoose,
+  };
+}
+
+/** Rows as given, with the phonetics they don't have yet */
+export function withPhonetics(entries) {
+  return entries.map((entry) => {
+    const hasPhonetics =
+      entry.termPhoneticsStrict ||
+      entry.termPhoneticsLoose ||
+      entry.definitionPhoneticsWordsStrict ||
+      entry.definitionPhoneticsWordsLoose;
+    return hasPhonetics ? entry : { ...entry, ...phoneticsFor(entry) };
+  });
+}
+
+/** Compute and save the missing phonetics of an installed custom pack.
+ *  Returns the number of entries updated. */
+export async function fillMissingPhonetics(packId) {
+  let afterId = 0;
+  let updated = 0;
+  for (;;) {
+    const entries = await invoke('list_entries_missing_phonetics', {
+      packId,
+      afterId,
+      limit: PAGE_SIZE,
+    });
+    if (!entries.length) return updated;
+    const phonetics = entries.map((entry) => ({ id: entry.id, ...phoneticsFor(entry) }));
+    updated += await invoke('save_entry_phonetics', { packId, phonetics });
+    afterId = entries[entries.length - 1].id;
+  }
+}
+
+export default { phoneticsFor, withPhonetics, fillMissingPhonetics };
@@ -0,0 +1,31 @@
+/**
+ * RecentLookups — terms looked up in any window, for the tray's "Recent
+ * Lookups" menu (src-tauri/src/tray.rs keeps and saves the list). Each
+ * lookup is sent as a "term-looked-up" event once the user has stayed on
+ * the term for a moment, so that stepping through results or typing past a
+ * term doesn't fill the menu.
+ */
+
+import { isTauri, isMobile } from '../config/platform';
+
+// How long a term has to stay selected to count as looked up
+const RECORD_DELAY_MS = 1500;
+
+let pendingTimer = null;
+
+export const RecentLookups = {
+  record(term) {
+    if (!term || !isTauri() || isMobile()) return;
+    clearTimeout(pendingTimer);
+    pendingTimer = setTimeout(async () => {
+      try {
+        const { emit } = await import('@tauri-apps/api/event');
+        await emit('term-looked-up', term);
+      } catch (e) {
+        console.error('[RecentLookups] Failed to record lookup:', e);
+      }
+    }, RECORD_DELAY_MS);
+  },
+};
+
+export default RecentLookups;
@@ -18,6 +18,10 @@ let invoke = null;
 // 'web' = web browser with sql.js
 let _initMode = null;
 
+// Which storage the native lookup commands read (see src-tauri/src/repository.rs)
+const BACKEND_PACKS = "packs";
+const BACKEND_SINGLE_DATABASE = "singleDatabase";
+
 async function determineInitMode() {
   if (_initMode !== null) return _initMode;
 
@@ -84,11 +88,11 @@ const database = {
       console.log('[SqlDatabase.init] Using native SQLite for packs...');
       const inv = await getInvoke();
       // Load dictionaries into localStorage
-      const dictionaries = await inv("pack_get_dictionaries");
+      const dictionaries = await inv("get_dictionaries", { backend: BACKEND_PACKS });
       this.loadDictionariesFromNative(dictionaries);
       // Load all terms for autocomplete
       console.log('[SqlDatabase.init] Loading all terms via native SQLite...');
-      this.allTerms = await inv("pack_get_all_terms");
+      this.allTerms = await inv("get_all_terms", { backend: BACKEND_PACKS });
       console.log('[SqlDatabase.init] allTerms.length:', this.allTerms.length);
     } else if (mode === "tauri-packs") {
       // Desktop: Use multi-database system with sql.js workers per pack
@@ -102,7 +106,7 @@ const database = {
       const inv = await getInvoke();
       await inv("init_database");
       await this.loadDictionariesIntoLocalStorage();
-      this.allTerms = await inv("get_all_terms");
+      this.allTerms = await inv("get_all_terms", { backend: BACKEND_SINGLE_DATABASE });
     } else {
       // Web: Use sql.js WebAssembly with full database
       console.log("[Database] Using sql.js WebAssembly");
@@ -132,7 +136,7 @@ const database = {
       return;
     } else if (mode === "tauri-native") {
       const inv = await getInvoke();
-      databaseDictionaries = await inv("get_dictionaries");
+      databaseDictionaries = await inv("get_dictionaries", { backend: BACKEND_SINGLE_DATABASE });
     } else {
       databaseDictionaries = await this.exec("SELECT * FROM dictionaries");
     }
@@ -185,14 +189,14 @@ const database = {
       // Mobile: Use native SQLite pack query command
       const inv = await getInvoke();
       const paramsJson = JSON.stringify(params || []);
-      return await inv("pack_execute_query", { sql: query, paramsJson });
+      return await inv("execute_query", { sql: query, paramsJson, backend: BACKEND_PACKS });
     } else if (mode === "tauri-packs") {
       // Query all pack databases and merge results
       return MultiDatabase.execAll(query, params);
     } else if (mode === "tauri-native") {
       const inv = await getInvoke();
       const paramsJson = JSON.stringify(params || []);
-      return await inv("execute_query", { sql: query, paramsJson });
+      return await inv("execute_query", { sql: query, paramsJson, backend: BACKEND_SINGLE_DATABASE });
     } else {
       return postMessageAsync({
         action: "exec",
@@ -202,6 +206,16 @@ const database = {
     }
   },
 
+  // Pick up a replaced TibetanTranslator.sqlite (native desktop mode only)
+  async reload() {
+    const mode = await determineInitMode();
+    if (mode !== "tauri-native") return;
+
+    const inv = await getInvoke();
+    await inv("reload_database");
+    await this.setAllTermsVariable();
+  },
+
   async setAllTermsVariable() {
     try {
       const mode = await determineInitMode();
@@ -209,7 +223,7 @@ const database = {
       if (mode === "tauri-packs-native") {
         // Mobile: Use native SQLite
         const inv = await getInvoke();
-        this.allTerms = await inv("pack_get_all_terms");
+        this.allTerms = await inv("get_all_terms", { backend: BACKEND_PACKS });
         console.log('[Database] Loaded', this.allTerms.length, 'terms via native SQLite');
       } else if (mode === "tauri-packs") {
         const terms = await MultiDatabase.execAll(
@@ -220,7 +234,7 @@ const database = {
         console.log('[Database] Loaded', this.allTerms.length, 'terms');
       } else if (mode === "tauri-native") {
         const inv = await getInvoke();
-        this.allTerms = await inv("get_all_terms");
+        this.allTerms = await inv("get_all_terms", { backend: BACKEND_SINGLE_DATABASE });
       } else {
         const terms = await this.exec("SELECT DISTINCT term FROM entries ORDER BY term");
         this.allTerms = terms.map((row) => row.term);
@@ -237,7 +251,7 @@ const database = {
     if (mode === "tauri-packs-native") {
       // Mobile: Use native SQLite for fast queries
       const inv = await getInvoke();
-      return await inv("pack_get_entries_for_term", { term });
+      return await inv("get_entries_for_term", { term, backend: BACKEND_PACKS });
     } else if (mode === "tauri-packs") {
       // Use exact term matching (not FTS) for Define page
       // Escape single quotes for SQL safety
@@ -255,7 +269,7 @@ const database = {
       return results.sort((a, b) => (a.dictionaryPosition || 0) - (b.dictionaryPosition || 0));
     } else if (mode === "tauri-native") {
       const inv = await getInvoke();
-      return await inv("get_entries_for_term", { term });
+      return await inv("get_entries_for_term", { term, backend: BACKEND_SINGLE_DATABASE });
     } else {
       return this.exec(
         `
@@ -276,7 +290,7 @@ const database = {
     if (mode === "tauri-packs-native") {
       // Mobile: Use native SQLite for fast FTS queries
       const inv = await getInvoke();
-      return await inv("pack_search_entries", { query, searchType });
+      return await inv("search_entries", { query, searchType, backend: BACKEND_PACKS });
     } else if (mode === "tauri-packs") {
       // Search across all packs
       // Note: We interpolate the query directly instead of using parameterized queries
@@ -295,7 +309,7 @@ const database = {
       return results;
     } else if (mode === "tauri-native") {
       const inv = await getInvoke();
-      return await inv("search_entries", { query, searchType });
+      return await inv("search_entries", { query, searchType, backend: BACKEND_SINGLE_DATABASE });
     } else {
       // Fall back to standard exec for web
       return this.exec(
@@ -331,7 +345,7 @@ const database = {
     if (_initMode === "tauri-packs-native") {
       // Mobile: Reload dictionaries and terms from native SQLite
       const inv = await getInvoke();
-      const dictionaries = await inv("pack_get_dictionaries");
+      const dictionaries = await inv("get_dictionaries", { backend: BACKEND_PACKS });
       this.loadDictionariesFromNative(dictionaries);
       await this.setAllTermsVariable();
     } else if (_initMode === "tauri-packs") {
@@ -347,7 +361,7 @@ const database = {
     if (_initMode === "tauri-packs-native") {
       // Mobile: Reload dictionaries and terms after pack removal
       const inv = await getInvoke();
-      const dictionaries = await inv("pack_get_dictionaries");
+      const dictionaries = await inv("get_dictionaries", { backend: BACKEND_PACKS });
       this.loadDictionariesFromNative(dictionaries);
       await this.setAllTermsVariable();
     } else if (_initMode === "tauri-packs") {
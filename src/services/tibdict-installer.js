@@ -1,5 +1,6 @@
 /**
- * TibdictInstaller — shared install orchestration for .tibdict files.
+ * TibdictInstaller — shared install orchestration for .tibdict files and
+ * Anki .apkg decks.
  *
  * Both the "Import…" button (in CustomPackSection) and the window-wide
  * drag-and-drop handler (in App.vue) go through this module, so they share:
@@ -7,11 +8,16 @@
  *   - the same snackbar messages
  *   - the same error classification
  *
- * The modal is rendered once at the App level; the button and drop handler
+ * An .apkg first opens the field picker, where the user chooses which field
+ * is the term and which the definition; decks dropped together are asked
+ * for one after the other.
+ *
+ * The modals are rendered once at the App level; the button and drop handler
  * simply call `install(filePath)` / `installMany([...])`.
  */
 
 import { reactive } from 'vue';
+import CustomPackImporter from './custom-pack-importer';
 import PackManager from './pack-manager';
 
 const state = reactive({
@@ -19,10 +25,19 @@ const state = reactive({
   /** The source passed to install on conflict — either an OS path (string)
    *  for the Import button, or { bytes, name } for HTML5 drag-drop. */
   pendingSource: null,
+  /** Install options to retry with ({ apkg } for Anki decks) */
+  pendingOptions: {},
   incomingManifest: null,
   existingManifest: null,
+  fieldsOpen: false,
+  /** inspect_apkg's result for the deck whose fields are being picked */
+  apkgInfo: null,
+  apkgSource: null,
 });
 
+/** Anki decks waiting for the field picker */
+const apkgQueue = [];
+
 let snackbarOpen = null;
 
 function notify(text) {
@@ -37,6 +52,11 @@ function messageForError(result) {
   return "This file isn't a valid dictionary.";
 }
 
+function isApkg(source) {
+  const name = typeof source === 'string' ? source : source?.name || '';
+  return name.toLowerCase().endsWith('.apkg');
+}
+
 async function runInstall(source, options = {}) {
   // source is either a string (OS path) or { bytes, name } (in-memory file).
   if (source && typeof source === 'object' && source.bytes) {
@@ -45,8 +65,9 @@ async function runInstall(source, options = {}) {
   return PackManager.installCustomPack(source, options);
 }
 
-function openConflictModal(source, result) {
+function openConflictModal(source, result, options = {}) {
   state.pendingSource = source;
+  state.pendingOptions = options;
   state.incomingManifest = result.incomingManifest || null;
   state.existingManifest = result.existingManifest || null;
   state.conflictOpen = true;
@@ -55,10 +76,49 @@ function openConflictModal(source, result) {
 function closeConflictModal() {
   state.conflictOpen = false;
   state.pendingSource = null;
+  state.pendingOptions = {};
   state.incomingManifest = null;
   state.existingManifest = null;
 }
 
+/** Install one source, opening the conflict modal if needed */
+async function installOne(source, options = {}) {
+  const result = await runInstall(source, options);
+  if (result.status === 'installed') {
+    notify(`${result.pack.manifest.name} installed`);
+    return;
+  }
+  if (result.status === 'conflict') {
+    openConflictModal(source, result, options);
+    return;
+  }
+  notify(messageForError(result));
+}
+
+/** Open the field picker for the next queued deck, unless it's open */
+async function pickNextApkgFields() {
+  while (!state.fieldsOpen && apkgQueue.length) {
+    const source = apkgQueue.shift();
+    try {
+      state.apkgInfo =
+        typeof source === 'string'
+          ? await CustomPackImporter.inspectApkg(source)
+          : await CustomPackImporter.inspectApkgFromBytes(source.bytes);
+      state.apkgSource = source;
+      state.fieldsOpen = true;
+    } catch (e) {
+      console.error('[TibdictInstaller] inspect failed:', e);
+      notify(e?.code === 'limits' ? 'This deck is too large.' : "This file isn't a valid Anki deck.");
+    }
+  }
+}
+
+function closeFieldPicker() {
+  state.fieldsOpen = false;
+  state.apkgInfo = null;
+  state.apkgSource = null;
+}
+
 export const TibdictInstaller = {
   state,
 
@@ -67,58 +127,73 @@ export const TibdictInstaller = {
     snackbarOpen = openFn;
   },
 
-  /** Install a single .tibdict file. `source` is either an OS path (string)
-   *  or { bytes, name } for drag-dropped File content. Opens the conflict
-   *  modal if needed. */
+  /** Install a single .tibdict file or .apkg deck. `source` is either an OS
+   *  path (string) or { bytes, name } for drag-dropped File content. Opens
+   *  the field picker or the conflict modal if needed. */
   async install(source) {
-    const result = await runInstall(source);
-    if (result.status === 'installed') {
-      notify(`${result.pack.manifest.name} installed`);
-      return;
-    }
-    if (result.status === 'conflict') {
-      openConflictModal(source, result);
+    if (isApkg(source)) {
+      apkgQueue.push(source);
+      await pickNextApkgFields();
       return;
     }
-    notify(messageForError(result));
+    await installOne(source);
   },
 
-  /** Install multiple sources sequentially, with a recap snackbar. */
+  /** Install multiple sources sequentially, with a recap snackbar. Decks
+   *  go through the field picker afterwards. */
   async installMany(sources) {
-    if (sources.length === 1) {
-      await this.install(sources[0]);
-      return;
-    }
-    let installed = 0;
-    let cancelled = 0;
-    for (const source of sources) {
-      const result = await runInstall(source);
-      if (result.status === 'installed') {
-        installed++;
-        continue;
-      }
-      if (result.status === 'conflict') {
-        // Queue the first conflict for user confirmation; skip subsequent ones silently.
-        if (!state.conflictOpen) {
-          openConflictModal(source, result);
+    apkgQueue.push(...sources.filter(isApkg));
+    const tibdicts = sources.filter((source) => !isApkg(source));
+    if (tibdicts.length === 1) {
+      await installOne(tibdicts[0]);
+    } else if (tibdicts.length > 1) {
+      let installed = 0;
+      let cancelled = 0;
+      for (const source of tibdicts) {
+        const result = await runInstall(source);
+        if (result.status === 'installed') {
+          installed++;
+          continue;
+        }
+        if (result.status === 'conflict') {
+          // Queue the first conflict for user confirmation; skip subsequent ones silently.
+          if (!state.conflictOpen) {
+            openConflictModal(source, result);
+          }
+          cancelled++;
+          continue;
         }
         cancelled++;
-        continue;
       }
-      cancelled++;
+      const parts = [];
+      if (installed > 0) parts.push(`${installed} installed`);
+      if (cancelled > 0) parts.push(`${cancelled} skipped`);
+      if (parts.length) notify(parts.join(', '));
     }
-    const parts = [];
-    if (installed > 0) parts.push(`${installed} installed`);
-    if (cancelled > 0) parts.push(`${cancelled} skipped`);
-    if (parts.length) notify(parts.join(', '));
+    await pickNextApkgFields();
+  },
+
+  /** Called by the field picker with { termField, definitionField }. */
+  async confirmFields(fields) {
+    const source = state.apkgSource;
+    closeFieldPicker();
+    await installOne(source, { apkg: fields });
+    await pickNextApkgFields();
+  },
+
+  /** Called by the field picker when user cancels. */
+  async cancelFields() {
+    closeFieldPicker();
+    await pickNextApkgFields();
   },
 
   /** Called by the conflict modal when user confirms the replacement. */
   async confirmReplace() {
     const source = state.pendingSource;
+    const options = state.pendingOptions;
     const name = state.incomingManifest?.name || '';
     closeConflictModal();
-    const result = await runInstall(source, { force: true });
+    const result = await runInstall(source, { ...options, force: true });
     if (result.status === 'installed') {
       notify(`${result.pack.manifest.name || name} installed`);
     } else {
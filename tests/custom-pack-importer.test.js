@@ -62,4 +62,21 @@ describe('CustomPackImporter', () => {
       force: true,
     });
   });
+
+  it('passes the chosen fields of an Anki deck', async () => {
+    invokeMock.mockResolvedValue({ id: 'custom-deck', manifest: { name: 'Deck' } });
+    const apkg = { termField: 'Front', definitionField: 'Back' };
+    await CustomPackImporter.install('/path/to/deck.apkg', { apkg });
+    expect(invokeMock).toHaveBeenCalledWith('install_custom_pack', {
+      filePath: '/path/to/deck.apkg',
+      force: false,
+      apkg,
+    });
+  });
+
+  it('returns status=needsFields for a deck without chosen fields', async () => {
+    invokeMock.mockRejectedValue({ code: 'fields', message: 'choose the term and definition fields' });
+    const result = await CustomPackImporter.install('/path/to/deck.apkg');
+    expect(result.status).toBe('needsFields');
+  });
 });